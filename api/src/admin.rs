@@ -1,4 +1,6 @@
-use crate::{put_with_token, Error, HouseflowAPI};
+use crate::{
+    delete_with_token, get_with_token, post_with_token, put_with_token, Error, HouseflowAPI,
+};
 use houseflow_types::admin;
 use houseflow_types::token::AccessToken;
 
@@ -6,39 +8,302 @@ use houseflow_types::token::AccessToken;
 pub enum AdminError {}
 
 impl HouseflowAPI {
+    pub async fn admin_apply(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::apply::Request,
+    ) -> Result<admin::apply::Response, Error> {
+        let url = self.admin_url.join("apply").unwrap();
+        post_with_token(url, request, access_token).await
+    }
+
+    pub async fn admin_export_backup(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::backup::export::Request,
+    ) -> Result<admin::backup::export::Response, Error> {
+        let url = self.admin_url.join("backup/export").unwrap();
+        get_with_token(url, request, access_token).await
+    }
+
+    pub async fn admin_import_backup(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::backup::import::Request,
+    ) -> Result<admin::backup::import::Response, Error> {
+        let url = self.admin_url.join("backup/import").unwrap();
+        post_with_token(url, request, access_token).await
+    }
+
     pub async fn admin_add_device(
         &self,
         access_token: &AccessToken,
         request: &admin::device::add::Request,
     ) -> Result<admin::device::add::Response, Error> {
-        let url = self.admin_url.join("device").unwrap();
+        let url = self.admin_url.join("device/add").unwrap();
         put_with_token(url, request, access_token).await
     }
 
+    pub async fn admin_connected_devices(
+        &self,
+        access_token: &AccessToken,
+    ) -> Result<admin::device::connected::Response, Error> {
+        let url = self.admin_url.join("device/connected").unwrap();
+        get_with_token(url, &admin::device::connected::Request {}, access_token).await
+    }
+
+    pub async fn admin_rotate_device_token(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::device::rotate_token::Request,
+    ) -> Result<admin::device::rotate_token::Response, Error> {
+        let url = self.admin_url.join("device/rotate_token").unwrap();
+        post_with_token(url, request, access_token).await
+    }
+
+    pub async fn admin_rotate_device_password(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::device::rotate_password::Request,
+    ) -> Result<admin::device::rotate_password::Response, Error> {
+        let url = self.admin_url.join("device/rotate_password").unwrap();
+        post_with_token(url, request, access_token).await
+    }
+
+    pub async fn admin_revoke_device(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::device::revoke::Request,
+    ) -> Result<admin::device::revoke::Response, Error> {
+        let url = self.admin_url.join("device/revoke").unwrap();
+        post_with_token(url, request, access_token).await
+    }
+
+    pub async fn admin_list_devices(
+        &self,
+        access_token: &AccessToken,
+    ) -> Result<admin::device::list::Response, Error> {
+        let url = self.admin_url.join("device/list").unwrap();
+        get_with_token(url, &admin::device::list::Request {}, access_token).await
+    }
+
+    pub async fn admin_get_device(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::device::get::Request,
+    ) -> Result<admin::device::get::Response, Error> {
+        let url = self.admin_url.join("device/get").unwrap();
+        get_with_token(url, request, access_token).await
+    }
+
+    pub async fn admin_update_device(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::device::update::Request,
+    ) -> Result<admin::device::update::Response, Error> {
+        let url = self.admin_url.join("device/update").unwrap();
+        post_with_token(url, request, access_token).await
+    }
+
+    pub async fn admin_delete_device(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::device::delete::Request,
+    ) -> Result<admin::device::delete::Response, Error> {
+        let url = self.admin_url.join("device/delete").unwrap();
+        delete_with_token(url, request, access_token).await
+    }
+
     pub async fn admin_add_structure(
         &self,
         access_token: &AccessToken,
         request: &admin::structure::add::Request,
     ) -> Result<admin::structure::add::Response, Error> {
-        let url = self.admin_url.join("structure").unwrap();
+        let url = self.admin_url.join("structure/add").unwrap();
         put_with_token(url, request, access_token).await
     }
 
+    pub async fn admin_list_structures(
+        &self,
+        access_token: &AccessToken,
+    ) -> Result<admin::structure::list::Response, Error> {
+        let url = self.admin_url.join("structure/list").unwrap();
+        get_with_token(url, &admin::structure::list::Request {}, access_token).await
+    }
+
+    pub async fn admin_get_structure(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::structure::get::Request,
+    ) -> Result<admin::structure::get::Response, Error> {
+        let url = self.admin_url.join("structure/get").unwrap();
+        get_with_token(url, request, access_token).await
+    }
+
+    pub async fn admin_update_structure(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::structure::update::Request,
+    ) -> Result<admin::structure::update::Response, Error> {
+        let url = self.admin_url.join("structure/update").unwrap();
+        post_with_token(url, request, access_token).await
+    }
+
+    pub async fn admin_delete_structure(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::structure::delete::Request,
+    ) -> Result<admin::structure::delete::Response, Error> {
+        let url = self.admin_url.join("structure/delete").unwrap();
+        delete_with_token(url, request, access_token).await
+    }
+
     pub async fn admin_add_room(
         &self,
         access_token: &AccessToken,
         request: &admin::room::add::Request,
     ) -> Result<admin::room::add::Response, Error> {
-        let url = self.admin_url.join("room").unwrap();
+        let url = self.admin_url.join("room/add").unwrap();
         put_with_token(url, request, access_token).await
     }
 
+    pub async fn admin_list_rooms(
+        &self,
+        access_token: &AccessToken,
+    ) -> Result<admin::room::list::Response, Error> {
+        let url = self.admin_url.join("room/list").unwrap();
+        get_with_token(url, &admin::room::list::Request {}, access_token).await
+    }
+
+    pub async fn admin_get_room(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::room::get::Request,
+    ) -> Result<admin::room::get::Response, Error> {
+        let url = self.admin_url.join("room/get").unwrap();
+        get_with_token(url, request, access_token).await
+    }
+
+    pub async fn admin_update_room(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::room::update::Request,
+    ) -> Result<admin::room::update::Response, Error> {
+        let url = self.admin_url.join("room/update").unwrap();
+        post_with_token(url, request, access_token).await
+    }
+
+    pub async fn admin_delete_room(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::room::delete::Request,
+    ) -> Result<admin::room::delete::Response, Error> {
+        let url = self.admin_url.join("room/delete").unwrap();
+        delete_with_token(url, request, access_token).await
+    }
+
+    pub async fn admin_add_scoped_role(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::role::add::Request,
+    ) -> Result<admin::role::add::Response, Error> {
+        let url = self.admin_url.join("role/add").unwrap();
+        put_with_token(url, request, access_token).await
+    }
+
+    pub async fn admin_list_scoped_roles(
+        &self,
+        access_token: &AccessToken,
+    ) -> Result<admin::role::list::Response, Error> {
+        let url = self.admin_url.join("role/list").unwrap();
+        get_with_token(url, &admin::role::list::Request {}, access_token).await
+    }
+
+    pub async fn admin_delete_scoped_role(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::role::delete::Request,
+    ) -> Result<admin::role::delete::Response, Error> {
+        let url = self.admin_url.join("role/delete").unwrap();
+        delete_with_token(url, request, access_token).await
+    }
+
     pub async fn admin_add_user_structure(
         &self,
         access_token: &AccessToken,
         request: &admin::user_structure::add::Request,
     ) -> Result<admin::user_structure::add::Response, Error> {
-        let url = self.admin_url.join("user_structure").unwrap();
+        let url = self.admin_url.join("user_structure/add").unwrap();
         put_with_token(url, request, access_token).await
     }
+
+    pub async fn admin_list_user_structures(
+        &self,
+        access_token: &AccessToken,
+    ) -> Result<admin::user_structure::list::Response, Error> {
+        let url = self.admin_url.join("user_structure/list").unwrap();
+        get_with_token(url, &admin::user_structure::list::Request {}, access_token).await
+    }
+
+    pub async fn admin_get_user_structure(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::user_structure::get::Request,
+    ) -> Result<admin::user_structure::get::Response, Error> {
+        let url = self.admin_url.join("user_structure/get").unwrap();
+        get_with_token(url, request, access_token).await
+    }
+
+    pub async fn admin_update_user_structure(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::user_structure::update::Request,
+    ) -> Result<admin::user_structure::update::Response, Error> {
+        let url = self.admin_url.join("user_structure/update").unwrap();
+        post_with_token(url, request, access_token).await
+    }
+
+    pub async fn admin_delete_user_structure(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::user_structure::delete::Request,
+    ) -> Result<admin::user_structure::delete::Response, Error> {
+        let url = self.admin_url.join("user_structure/delete").unwrap();
+        delete_with_token(url, request, access_token).await
+    }
+
+    pub async fn admin_list_users(
+        &self,
+        access_token: &AccessToken,
+    ) -> Result<admin::user::list::Response, Error> {
+        let url = self.admin_url.join("user/list").unwrap();
+        get_with_token(url, &admin::user::list::Request {}, access_token).await
+    }
+
+    pub async fn admin_get_user(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::user::get::Request,
+    ) -> Result<admin::user::get::Response, Error> {
+        let url = self.admin_url.join("user/get").unwrap();
+        get_with_token(url, request, access_token).await
+    }
+
+    pub async fn admin_update_user(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::user::update::Request,
+    ) -> Result<admin::user::update::Response, Error> {
+        let url = self.admin_url.join("user/update").unwrap();
+        post_with_token(url, request, access_token).await
+    }
+
+    pub async fn admin_delete_user(
+        &self,
+        access_token: &AccessToken,
+        request: &admin::user::delete::Request,
+    ) -> Result<admin::user::delete::Response, Error> {
+        let url = self.admin_url.join("user/delete").unwrap();
+        delete_with_token(url, request, access_token).await
+    }
 }
@@ -4,6 +4,45 @@ use houseflow_types::{fulfillment, token::AccessToken};
 #[derive(Debug, thiserror::Error)]
 pub enum FulfillmentError {}
 
+/// Stream of state updates, received as Server-Sent Events
+pub struct WatchStream {
+    response: reqwest::Response,
+    buffer: Vec<u8>,
+}
+
+impl WatchStream {
+    /// Waits for the next state update, returns None if the server closed the stream
+    pub async fn next(&mut self) -> Result<Option<fulfillment::watch::Event>, Error> {
+        loop {
+            if let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+                let message = self.buffer.drain(..end + 2).collect::<Vec<_>>();
+                let message = String::from_utf8_lossy(&message);
+                let data = message
+                    .lines()
+                    .filter_map(|line| line.strip_prefix("data:"))
+                    .map(str::trim_start)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                if data.is_empty() {
+                    continue;
+                }
+                let event =
+                    serde_json::from_str(&data).map_err(|err| Error::InvalidResponseBody {
+                        error: Box::new(err),
+                        status_code: self.response.status(),
+                        body: data.clone(),
+                    })?;
+                return Ok(Some(event));
+            }
+
+            match self.response.chunk().await? {
+                Some(chunk) => self.buffer.extend_from_slice(&chunk),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
 impl HouseflowAPI {
     pub async fn sync(
         &self,
@@ -30,4 +69,42 @@ impl HouseflowAPI {
         let url = self.fulfillment_url.join("query").unwrap();
         get_with_token(url, request, access_token).await
     }
+
+    pub async fn history(
+        &self,
+        access_token: &AccessToken,
+        request: &fulfillment::history::Request,
+    ) -> Result<fulfillment::history::Response, Error> {
+        let url = self.fulfillment_url.join("history").unwrap();
+        get_with_token(url, request, access_token).await
+    }
+
+    pub async fn watch(
+        &self,
+        access_token: &AccessToken,
+    ) -> Result<Result<WatchStream, fulfillment::watch::ResponseError>, Error> {
+        let url = self.fulfillment_url.join("watch").unwrap();
+        let response = reqwest::Client::new()
+            .get(url)
+            .json(&fulfillment::watch::Request {})
+            .bearer_auth(access_token)
+            .send()
+            .await?;
+        let status_code = response.status();
+        if status_code.is_success() {
+            Ok(Ok(WatchStream {
+                response,
+                buffer: Vec::new(),
+            }))
+        } else {
+            let bytes = response.bytes().await?;
+            let parsed =
+                serde_json::from_slice(&bytes).map_err(|err| Error::InvalidResponseBody {
+                    error: Box::new(err),
+                    status_code,
+                    body: String::from_utf8(bytes.to_vec()).unwrap(),
+                })?;
+            Ok(Err(parsed))
+        }
+    }
 }
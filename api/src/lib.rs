@@ -8,7 +8,7 @@ mod auth;
 mod fulfillment;
 
 #[cfg(feature = "auth")]
-pub use crate::fulfillment::FulfillmentError;
+pub use crate::fulfillment::{FulfillmentError, WatchStream};
 
 #[cfg(any(feature = "auth", feature = "fulfillment", feature = "admin"))]
 use url::Url;
@@ -64,7 +64,7 @@ impl HouseflowAPI {
             fulfillment_url: base_url.join("fulfillment/internal/").unwrap(),
 
             #[cfg(feature = "admin")]
-            admin_url: base_url.join("fulfillment/admin/").unwrap(),
+            admin_url: base_url.join("admin/").unwrap(),
         }
     }
 }
@@ -169,6 +169,21 @@ mod utils {
         let request = client.get(url).json(body).bearer_auth(token);
         send_request(request).await
     }
+
+    pub(crate) async fn delete_with_token<TP, B, E>(
+        url: Url,
+        body: &impl Serialize,
+        token: &Token<TP>,
+    ) -> Result<Result<B, E>, Error>
+    where
+        TP: Serialize + DeserializeOwned,
+        B: DeserializeOwned,
+        E: DeserializeOwned,
+    {
+        let client = Client::new();
+        let request = client.delete(url).json(body).bearer_auth(token);
+        send_request(request).await
+    }
 }
 
 #[cfg(any(feature = "auth", feature = "fulfillment", feature = "admin"))]
@@ -35,3 +35,7 @@ pub fn database_path() -> std::path::PathBuf {
 pub fn token_store_path() -> std::path::PathBuf {
     data_home().join("token_store.sled")
 }
+
+pub fn homegraph_url() -> url::Url {
+    url::Url::parse("https://homegraph.googleapis.com/").unwrap()
+}
@@ -1,5 +1,5 @@
 use crate::defaults;
-use houseflow_types::{DeviceID, DevicePassword};
+use houseflow_types::{lighthouse::proto::Encoding, DeviceID, DevicePassword};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -10,12 +10,24 @@ pub struct Config {
     /// Password of the device in plain-text
     pub device_password: DevicePassword,
 
+    /// Token issued by the server, used instead of the password if set
+    #[serde(default)]
+    pub device_token: Option<String>,
+
     /// Host of the server
     #[serde(default = "defaults::server_hostname", with = "crate::serde_hostname")]
     pub server_hostname: url::Host,
 
+    /// Port of the server, defaults to the standard port for either plain or TLS connections
+    #[serde(default)]
+    pub server_port: Option<u16>,
+
     #[serde(default)]
     pub use_tls: bool,
+
+    /// Preferred encoding of the frames, JSON is used if the server does not support it
+    #[serde(default)]
+    pub encoding: Encoding,
 }
 
 impl Config {
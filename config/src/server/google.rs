@@ -10,4 +10,13 @@ pub struct Config {
 
     /// Google Project ID
     pub project_id: String,
+
+    /// Path to the JSON key of a service account with access to the HomeGraph API.
+    /// Report State and Request Sync are disabled if it's not set.
+    #[serde(default)]
+    pub service_account_key_path: Option<std::path::PathBuf>,
+
+    /// URL of the HomeGraph API
+    #[serde(default = "crate::defaults::homegraph_url")]
+    pub homegraph_url: url::Url,
 }
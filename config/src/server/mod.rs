@@ -1,6 +1,7 @@
 use crate::defaults;
 use serde::{Deserialize, Serialize};
 
+pub mod database;
 pub mod google;
 pub mod tls;
 
@@ -10,6 +11,10 @@ pub struct Config {
     #[serde(default = "defaults::server_hostname", with = "crate::serde_hostname")]
     pub hostname: url::Host,
 
+    /// Database backend, SQLite is used by default
+    #[serde(default)]
+    pub database: database::Config,
+
     /// Path to the SQLite database
     #[serde(default = "defaults::database_path")]
     pub database_path: std::path::PathBuf,
@@ -37,10 +42,11 @@ impl Config {
 
         format!(
             include_str!("default.toml"),
-            defaults::server_port(),
             defaults::server_hostname(),
             rand.next().unwrap(),
             rand.next().unwrap(),
+            rand.next().unwrap(),
+            rand.next().unwrap(),
         )
     }
 }
@@ -67,6 +73,10 @@ pub struct Secrets {
 
     /// Key used to sign authorization codes. Must be secret and should be farily random.
     pub authorization_code_key: String,
+
+    /// Key used to sign device tokens. Must be secret and should be farily random.
+    /// Device tokens are disabled if it's not set, devices can still authenticate with passwords.
+    pub device_key: Option<String>,
 }
 
 impl rand::distributions::Distribution<Secrets> for rand::distributions::Standard {
@@ -80,6 +90,7 @@ impl rand::distributions::Distribution<Secrets> for rand::distributions::Standar
             refresh_key: gen_secret(),
             access_key: gen_secret(),
             authorization_code_key: gen_secret(),
+            device_key: Some(gen_secret()),
         }
     }
 }
@@ -94,4 +105,40 @@ mod tests {
         dbg!(&config);
         let _: Config = toml::from_str(&config).unwrap();
     }
+
+    #[test]
+    fn without_device_key() {
+        let config = Config::default_toml()
+            .lines()
+            .filter(|line| !line.starts_with("device_key"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let config: Config = toml::from_str(&config).unwrap();
+        assert_eq!(config.secrets.device_key, None);
+    }
+
+    #[test]
+    fn database_backend() {
+        let config = Config::default_toml();
+        let config: Config = toml::from_str(&config).unwrap();
+        assert_eq!(config.database, super::database::Config::Sqlite);
+
+        let config = format!(
+            "{}
+{}",
+            Config::default_toml(),
+            r#"
+            [database]
+            backend = "postgres"
+            url = "postgres://houseflow@localhost/houseflow"
+            "#
+        );
+        let config: Config = toml::from_str(&config).unwrap();
+        assert_eq!(
+            config.database,
+            super::database::Config::Postgres {
+                url: String::from("postgres://houseflow@localhost/houseflow")
+            }
+        );
+    }
 }
@@ -0,0 +1,65 @@
+use crate::{ClientCommandState, Command};
+use anyhow::Context;
+use async_trait::async_trait;
+use houseflow_types::{admin, Home, HomeChange, HomeItem};
+use std::path::PathBuf;
+
+use clap::Clap;
+
+#[derive(Clap)]
+pub struct ApplyCommand {
+    /// File describing the home, usually `home.toml`
+    path: PathBuf,
+
+    /// Delete the structures, rooms, devices and members which aren't declared in the file
+    #[clap(long)]
+    prune: bool,
+
+    /// Only print the plan, without applying it
+    #[clap(long)]
+    dry_run: bool,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for ApplyCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let home: Home = houseflow_config::read_file(self.path.clone())
+            .await
+            .with_context(|| format!("read home from {}", self.path.display()))?;
+        let access_token = state.access_token().await?;
+        let request = admin::apply::Request {
+            home,
+            prune: self.prune,
+            dry_run: self.dry_run,
+        };
+
+        let plan = state
+            .houseflow_api
+            .admin_apply(&access_token, &request)
+            .await??
+            .plan;
+
+        if plan.is_empty() {
+            tracing::info!("✔ Database already matches the home, nothing to do");
+            return Ok(());
+        }
+        println!("{}", plan);
+        if self.dry_run {
+            return Ok(());
+        }
+        tracing::info!("✔ Succesfully applied the plan");
+        let created_devices = plan.changes.iter().any(|change| {
+            matches!(
+                change,
+                HomeChange::Create {
+                    item: HomeItem::Device(_)
+                }
+            )
+        });
+        if created_devices {
+            tracing::info!("Created devices must get passwords with `houseflow admin device rotate-password` before connecting");
+        }
+
+        Ok(())
+    }
+}
@@ -6,11 +6,13 @@ use clap::Clap;
 use houseflow_types::{admin, DeviceTrait, DeviceType, RoomID};
 use semver::Version;
 
-fn from_json<'de, T: serde::de::Deserialize<'de>>(v: &'de str) -> Result<T, serde_json::Error> {
+pub(super) fn from_json<'de, T: serde::de::Deserialize<'de>>(
+    v: &'de str,
+) -> Result<T, serde_json::Error> {
     serde_json::from_str(v)
 }
 
-struct Traits {
+pub(super) struct Traits {
     inner: Vec<DeviceTrait>,
 }
 
@@ -0,0 +1,35 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+#[derive(Clap)]
+pub struct ConnectedDevicesCommand {}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for ConnectedDevicesCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let access_token = state.access_token().await?;
+        let response = state
+            .houseflow_api
+            .admin_connected_devices(&access_token)
+            .await??;
+
+        println!("{} devices connected", response.devices.len());
+        for device in response.devices {
+            let uptime = chrono::Duration::seconds(device.uptime as i64);
+            println!(
+                "Device ID: {}, Address: {}, Protocol: {}, Connected at: {}, Uptime: {}h {}m {}s",
+                device.device_id,
+                device.address,
+                device.protocol_version,
+                device.connected_at,
+                uptime.num_hours(),
+                uptime.num_minutes() % 60,
+                uptime.num_seconds() % 60,
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, DeviceID};
+
+#[derive(Clap)]
+pub struct DeleteDeviceCommand {
+    /// ID of the device
+    device_id: DeviceID,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for DeleteDeviceCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let request = admin::device::delete::Request {
+            device_id: self.device_id,
+        };
+
+        let access_token = state.access_token().await?;
+        state
+            .houseflow_api
+            .admin_delete_device(&access_token, &request)
+            .await??;
+
+        tracing::info!("✔ Succesfully deleted device");
+
+        Ok(())
+    }
+}
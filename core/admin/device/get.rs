@@ -0,0 +1,31 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, DeviceID};
+
+#[derive(Clap)]
+pub struct GetDeviceCommand {
+    /// ID of the device
+    device_id: DeviceID,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for GetDeviceCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let request = admin::device::get::Request {
+            device_id: self.device_id,
+        };
+
+        let access_token = state.access_token().await?;
+        let response = state
+            .houseflow_api
+            .admin_get_device(&access_token, &request)
+            .await??;
+
+        println!("{}", serde_json::to_string_pretty(&response.device)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+#[derive(Clap)]
+pub struct ListDevicesCommand {}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for ListDevicesCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let access_token = state.access_token().await?;
+        let response = state
+            .houseflow_api
+            .admin_list_devices(&access_token)
+            .await??;
+
+        println!("{} devices", response.devices.len());
+        for device in response.devices {
+            println!(
+                "ID: {}, Room ID: {}, Type: {}, Name: {}",
+                device.id, device.room_id, device.device_type, device.name
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -1,5 +1,21 @@
 mod add;
+mod connected;
+mod delete;
+mod get;
+mod list;
+mod revoke;
+mod rotate_password;
+mod rotate_token;
+mod update;
 use add::AddDeviceCommand;
+use connected::ConnectedDevicesCommand;
+use delete::DeleteDeviceCommand;
+use get::GetDeviceCommand;
+use list::ListDevicesCommand;
+use revoke::RevokeDeviceCommand;
+use rotate_password::RotateDevicePasswordCommand;
+use rotate_token::RotateDeviceTokenCommand;
+use update::UpdateDeviceCommand;
 
 use crate::{ClientCommandState, Command};
 use async_trait::async_trait;
@@ -14,7 +30,32 @@ pub struct DeviceCommand {
 
 #[derive(Clap)]
 pub enum DeviceSubCommand {
-    Add(AddDeviceCommand)
+    /// Add new device
+    Add(Box<AddDeviceCommand>),
+
+    /// List devices connected to the server
+    Connected(ConnectedDevicesCommand),
+
+    /// Issue a new token for the device, revoking the previous one
+    RotateToken(RotateDeviceTokenCommand),
+
+    /// Change password of the device, disconnecting it if it's connected
+    RotatePassword(RotateDevicePasswordCommand),
+
+    /// Revoke password and token of the device, disconnecting it if it's connected
+    Revoke(RevokeDeviceCommand),
+
+    /// List all devices
+    List(ListDevicesCommand),
+
+    /// Get device by ID
+    Get(GetDeviceCommand),
+
+    /// Update device
+    Update(UpdateDeviceCommand),
+
+    /// Delete device
+    Delete(DeleteDeviceCommand),
 }
 
 #[async_trait(?Send)]
@@ -22,6 +63,14 @@ impl Command<ClientCommandState> for DeviceCommand {
     async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
         match self.subcommand {
             DeviceSubCommand::Add(cmd) => cmd.run(state).await,
+            DeviceSubCommand::Connected(cmd) => cmd.run(state).await,
+            DeviceSubCommand::RotateToken(cmd) => cmd.run(state).await,
+            DeviceSubCommand::RotatePassword(cmd) => cmd.run(state).await,
+            DeviceSubCommand::Revoke(cmd) => cmd.run(state).await,
+            DeviceSubCommand::List(cmd) => cmd.run(state).await,
+            DeviceSubCommand::Get(cmd) => cmd.run(state).await,
+            DeviceSubCommand::Update(cmd) => cmd.run(state).await,
+            DeviceSubCommand::Delete(cmd) => cmd.run(state).await,
         }
     }
 }
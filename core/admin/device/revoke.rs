@@ -0,0 +1,34 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, DeviceID};
+
+#[derive(Clap)]
+pub struct RevokeDeviceCommand {
+    /// ID of the device
+    device_id: DeviceID,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for RevokeDeviceCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let access_token = state.access_token().await?;
+        let request = admin::device::revoke::Request {
+            device_id: self.device_id,
+        };
+
+        let response = state
+            .houseflow_api
+            .admin_revoke_device(&access_token, &request)
+            .await??;
+
+        tracing::info!("✔ Succesfully revoked device password and token");
+        if response.disconnected {
+            tracing::info!("Device has been disconnected");
+        }
+
+        Ok(())
+    }
+}
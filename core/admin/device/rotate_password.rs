@@ -0,0 +1,38 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, DeviceID};
+
+#[derive(Clap)]
+pub struct RotateDevicePasswordCommand {
+    /// ID of the device
+    device_id: DeviceID,
+
+    /// New password used to authenticate the device
+    password: String,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for RotateDevicePasswordCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let access_token = state.access_token().await?;
+        let request = admin::device::rotate_password::Request {
+            device_id: self.device_id,
+            password: self.password,
+        };
+
+        let response = state
+            .houseflow_api
+            .admin_rotate_device_password(&access_token, &request)
+            .await??;
+
+        tracing::info!("✔ Succesfully rotated device password");
+        if response.disconnected {
+            tracing::info!("Device has been disconnected, it must reconnect with the new password");
+        }
+
+        Ok(())
+    }
+}
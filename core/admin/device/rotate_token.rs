@@ -0,0 +1,34 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, DeviceID};
+
+#[derive(Clap)]
+pub struct RotateDeviceTokenCommand {
+    /// ID of the device
+    device_id: DeviceID,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for RotateDeviceTokenCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let access_token = state.access_token().await?;
+        let request = admin::device::rotate_token::Request {
+            device_id: self.device_id,
+        };
+
+        let response = state
+            .houseflow_api
+            .admin_rotate_device_token(&access_token, &request)
+            .await??;
+
+        tracing::info!(
+            "✔ Succesfully issued device token, set it as `device_token` in the device configuration: {}",
+            response.device_token
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use super::add::{from_json, Traits};
+use houseflow_types::{admin, DeviceID, DeviceType, RoomID};
+use semver::Version;
+
+#[derive(Clap)]
+pub struct UpdateDeviceCommand {
+    /// ID of the device
+    device_id: DeviceID,
+
+    /// ID of the room to which the device belongs
+    room_id: RoomID,
+
+    /// Type of the device, e.g light
+    device_type: DeviceType,
+
+    /// List of traits that the device has
+    traits: Traits,
+
+    /// Name of the device
+    name: String,
+
+    /// True if the device will push state, false if use polling model
+    #[clap(parse(try_from_str))]
+    will_push_state: bool,
+
+    /// Model of the device
+    model: String,
+
+    /// Hardware version of the device
+    hw_version: Version,
+
+    /// Software version of the device
+    sw_version: Version,
+
+    /// Additional attributes of the device
+    #[clap(parse(try_from_str = from_json))]
+    attributes: serde_json::Map<String, serde_json::Value>,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for UpdateDeviceCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let request = admin::device::update::Request {
+            device_id: self.device_id,
+            room_id: self.room_id,
+            device_type: self.device_type,
+            traits: self.traits.into(),
+            name: self.name,
+            will_push_state: self.will_push_state,
+            model: self.model,
+            hw_version: self.hw_version,
+            sw_version: self.sw_version,
+            attributes: self.attributes,
+        };
+
+        let access_token = state.access_token().await?;
+        state
+            .houseflow_api
+            .admin_update_device(&access_token, &request)
+            .await??;
+
+        tracing::info!("✔ Succesfully updated device");
+
+        Ok(())
+    }
+}
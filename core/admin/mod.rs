@@ -1,11 +1,17 @@
+mod apply;
 mod device;
+mod role;
 mod room;
 mod structure;
+mod user;
 mod user_structure;
 
+use apply::ApplyCommand;
 use device::DeviceCommand;
+use role::ScopedRoleCommand;
 use room::RoomCommand;
 use structure::StructureCommand;
+use user::UserCommand;
 use user_structure::UserStructureCommand;
 
 use crate::{ClientCommandState, Command};
@@ -20,15 +26,24 @@ pub struct AdminCommand {
 
 #[derive(Clap)]
 pub enum AdminSubcommand {
+    /// Create, update and optionally delete structures, rooms, devices and members to match the home file
+    Apply(ApplyCommand),
+
     /// Add/Delete/Update devices
     Device(DeviceCommand),
 
+    /// Add/Delete roles of users in rooms and devices
+    Role(ScopedRoleCommand),
+
     /// Add/Delete/Update rooms
     Room(RoomCommand),
 
     /// Add/Delete/Update structures
     Structure(StructureCommand),
 
+    /// Delete/Update users
+    User(UserCommand),
+
     /// Add/Delete/Update user structures
     UserStructure(UserStructureCommand),
 }
@@ -37,9 +52,12 @@ pub enum AdminSubcommand {
 impl Command<ClientCommandState> for AdminCommand {
     async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
         match self.subcommand {
+            AdminSubcommand::Apply(cmd) => cmd.run(state).await,
             AdminSubcommand::Device(cmd) => cmd.run(state).await,
+            AdminSubcommand::Role(cmd) => cmd.run(state).await,
             AdminSubcommand::Room(cmd) => cmd.run(state).await,
             AdminSubcommand::Structure(cmd) => cmd.run(state).await,
+            AdminSubcommand::User(cmd) => cmd.run(state).await,
             AdminSubcommand::UserStructure(cmd) => cmd.run(state).await,
         }
     }
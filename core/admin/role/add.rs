@@ -0,0 +1,40 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, Role, RoleScope, UserID};
+
+#[derive(Clap)]
+pub struct AddScopedRoleCommand {
+    /// ID of the user
+    user_id: UserID,
+
+    /// Room or device which the role applies to, `room:<room-id>` or `device:<device-id>`
+    #[clap(parse(try_from_str = super::parse_scope))]
+    scope: RoleScope,
+
+    /// Role of the user, one of viewer, operator or manager
+    role: Role,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for AddScopedRoleCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let request = admin::role::add::Request {
+            user_id: self.user_id,
+            scope: self.scope,
+            role: self.role,
+        };
+
+        let access_token = state.access_token().await?;
+        state
+            .houseflow_api
+            .admin_add_scoped_role(&access_token, &request)
+            .await??;
+
+        tracing::info!("✔ Succesfully added scoped role");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, RoleScope, UserID};
+
+#[derive(Clap)]
+pub struct DeleteScopedRoleCommand {
+    /// ID of the user
+    user_id: UserID,
+
+    /// Room or device which the role applies to, `room:<room-id>` or `device:<device-id>`
+    #[clap(parse(try_from_str = super::parse_scope))]
+    scope: RoleScope,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for DeleteScopedRoleCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let request = admin::role::delete::Request {
+            user_id: self.user_id,
+            scope: self.scope,
+        };
+
+        let access_token = state.access_token().await?;
+        state
+            .houseflow_api
+            .admin_delete_scoped_role(&access_token, &request)
+            .await??;
+
+        tracing::info!("✔ Succesfully deleted scoped role");
+
+        Ok(())
+    }
+}
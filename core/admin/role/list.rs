@@ -0,0 +1,33 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+use houseflow_types::RoleScope;
+
+use clap::Clap;
+
+#[derive(Clap)]
+pub struct ListScopedRolesCommand {}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for ListScopedRolesCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let access_token = state.access_token().await?;
+        let response = state
+            .houseflow_api
+            .admin_list_scoped_roles(&access_token)
+            .await??;
+
+        println!("{} scoped roles", response.roles.len());
+        for scoped_role in response.roles {
+            let scope = match scoped_role.scope {
+                RoleScope::Room(room_id) => format!("room:{}", room_id),
+                RoleScope::Device(device_id) => format!("device:{}", device_id),
+            };
+            println!(
+                "User ID: {}, Scope: {}, Role: {}",
+                scoped_role.user_id, scope, scoped_role.role
+            );
+        }
+
+        Ok(())
+    }
+}
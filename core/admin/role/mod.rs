@@ -0,0 +1,53 @@
+mod add;
+mod delete;
+mod list;
+use add::AddScopedRoleCommand;
+use delete::DeleteScopedRoleCommand;
+use list::ListScopedRolesCommand;
+
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+use houseflow_types::RoleScope;
+
+use clap::Clap;
+
+/// Parses scope in form of `room:<room-id>` or `device:<device-id>`
+fn parse_scope(s: &str) -> anyhow::Result<RoleScope> {
+    let (kind, id) = s
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("expected `room:<id>` or `device:<id>`"))?;
+    match kind {
+        "room" => Ok(RoleScope::Room(id.parse()?)),
+        "device" => Ok(RoleScope::Device(id.parse()?)),
+        _ => Err(anyhow::anyhow!("unknown scope kind: {}", kind)),
+    }
+}
+
+#[derive(Clap)]
+pub struct ScopedRoleCommand {
+    #[clap(subcommand)]
+    subcommand: ScopedRoleSubCommand,
+}
+
+#[derive(Clap)]
+pub enum ScopedRoleSubCommand {
+    /// Give user a role in room or device
+    Add(AddScopedRoleCommand),
+
+    /// List all scoped roles
+    List(ListScopedRolesCommand),
+
+    /// Delete role of user in room or device
+    Delete(DeleteScopedRoleCommand),
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for ScopedRoleCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        match self.subcommand {
+            ScopedRoleSubCommand::Add(cmd) => cmd.run(state).await,
+            ScopedRoleSubCommand::List(cmd) => cmd.run(state).await,
+            ScopedRoleSubCommand::Delete(cmd) => cmd.run(state).await,
+        }
+    }
+}
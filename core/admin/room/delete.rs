@@ -0,0 +1,31 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, RoomID};
+
+#[derive(Clap)]
+pub struct DeleteRoomCommand {
+    /// ID of the room
+    room_id: RoomID,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for DeleteRoomCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let request = admin::room::delete::Request {
+            room_id: self.room_id,
+        };
+
+        let access_token = state.access_token().await?;
+        state
+            .houseflow_api
+            .admin_delete_room(&access_token, &request)
+            .await??;
+
+        tracing::info!("✔ Succesfully deleted room");
+
+        Ok(())
+    }
+}
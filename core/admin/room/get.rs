@@ -0,0 +1,31 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, RoomID};
+
+#[derive(Clap)]
+pub struct GetRoomCommand {
+    /// ID of the room
+    room_id: RoomID,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for GetRoomCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let request = admin::room::get::Request {
+            room_id: self.room_id,
+        };
+
+        let access_token = state.access_token().await?;
+        let response = state
+            .houseflow_api
+            .admin_get_room(&access_token, &request)
+            .await??;
+
+        println!("{}", serde_json::to_string_pretty(&response.room)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+#[derive(Clap)]
+pub struct ListRoomsCommand {}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for ListRoomsCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let access_token = state.access_token().await?;
+        let response = state
+            .houseflow_api
+            .admin_list_rooms(&access_token)
+            .await??;
+
+        println!("{} rooms", response.rooms.len());
+        for room in response.rooms {
+            println!(
+                "ID: {}, Structure ID: {}, Name: {}",
+                room.id, room.structure_id, room.name
+            );
+        }
+
+        Ok(())
+    }
+}
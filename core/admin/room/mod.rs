@@ -1,5 +1,13 @@
 mod add;
+mod delete;
+mod get;
+mod list;
+mod update;
 use add::AddRoomCommand;
+use delete::DeleteRoomCommand;
+use get::GetRoomCommand;
+use list::ListRoomsCommand;
+use update::UpdateRoomCommand;
 
 use crate::{ClientCommandState, Command};
 use async_trait::async_trait;
@@ -14,7 +22,20 @@ pub struct RoomCommand {
 
 #[derive(Clap)]
 pub enum RoomSubCommand {
-    Add(AddRoomCommand)
+    /// Add new room
+    Add(AddRoomCommand),
+
+    /// List all rooms
+    List(ListRoomsCommand),
+
+    /// Get room by ID
+    Get(GetRoomCommand),
+
+    /// Update room
+    Update(UpdateRoomCommand),
+
+    /// Delete room
+    Delete(DeleteRoomCommand),
 }
 
 #[async_trait(?Send)]
@@ -22,6 +43,10 @@ impl Command<ClientCommandState> for RoomCommand {
     async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
         match self.subcommand {
             RoomSubCommand::Add(cmd) => cmd.run(state).await,
+            RoomSubCommand::List(cmd) => cmd.run(state).await,
+            RoomSubCommand::Get(cmd) => cmd.run(state).await,
+            RoomSubCommand::Update(cmd) => cmd.run(state).await,
+            RoomSubCommand::Delete(cmd) => cmd.run(state).await,
         }
     }
 }
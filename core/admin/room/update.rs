@@ -0,0 +1,39 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, RoomID, StructureID};
+
+#[derive(Clap)]
+pub struct UpdateRoomCommand {
+    /// ID of the room
+    room_id: RoomID,
+
+    /// ID of the structure to which the room belongs
+    structure_id: StructureID,
+
+    /// New name of the room
+    name: String,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for UpdateRoomCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let request = admin::room::update::Request {
+            room_id: self.room_id,
+            structure_id: self.structure_id,
+            room_name: self.name,
+        };
+
+        let access_token = state.access_token().await?;
+        state
+            .houseflow_api
+            .admin_update_room(&access_token, &request)
+            .await??;
+
+        tracing::info!("✔ Succesfully updated room");
+
+        Ok(())
+    }
+}
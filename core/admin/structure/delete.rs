@@ -0,0 +1,31 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, StructureID};
+
+#[derive(Clap)]
+pub struct DeleteStructureCommand {
+    /// ID of the structure
+    structure_id: StructureID,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for DeleteStructureCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let request = admin::structure::delete::Request {
+            structure_id: self.structure_id,
+        };
+
+        let access_token = state.access_token().await?;
+        state
+            .houseflow_api
+            .admin_delete_structure(&access_token, &request)
+            .await??;
+
+        tracing::info!("✔ Succesfully deleted structure");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, StructureID};
+
+#[derive(Clap)]
+pub struct GetStructureCommand {
+    /// ID of the structure
+    structure_id: StructureID,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for GetStructureCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let request = admin::structure::get::Request {
+            structure_id: self.structure_id,
+        };
+
+        let access_token = state.access_token().await?;
+        let response = state
+            .houseflow_api
+            .admin_get_structure(&access_token, &request)
+            .await??;
+
+        println!("{}", serde_json::to_string_pretty(&response.structure)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,25 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+#[derive(Clap)]
+pub struct ListStructuresCommand {}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for ListStructuresCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let access_token = state.access_token().await?;
+        let response = state
+            .houseflow_api
+            .admin_list_structures(&access_token)
+            .await??;
+
+        println!("{} structures", response.structures.len());
+        for structure in response.structures {
+            println!("ID: {}, Name: {}", structure.id, structure.name);
+        }
+
+        Ok(())
+    }
+}
@@ -1,5 +1,13 @@
 mod add;
+mod delete;
+mod get;
+mod list;
+mod update;
 use add::AddStructureCommand;
+use delete::DeleteStructureCommand;
+use get::GetStructureCommand;
+use list::ListStructuresCommand;
+use update::UpdateStructureCommand;
 
 use crate::{ClientCommandState, Command};
 use async_trait::async_trait;
@@ -14,7 +22,20 @@ pub struct StructureCommand {
 
 #[derive(Clap)]
 pub enum StructureSubCommand {
-    Add(AddStructureCommand)
+    /// Add new structure
+    Add(AddStructureCommand),
+
+    /// List all structures
+    List(ListStructuresCommand),
+
+    /// Get structure by ID
+    Get(GetStructureCommand),
+
+    /// Update structure
+    Update(UpdateStructureCommand),
+
+    /// Delete structure
+    Delete(DeleteStructureCommand),
 }
 
 #[async_trait(?Send)]
@@ -22,6 +43,10 @@ impl Command<ClientCommandState> for StructureCommand {
     async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
         match self.subcommand {
             StructureSubCommand::Add(cmd) => cmd.run(state).await,
+            StructureSubCommand::List(cmd) => cmd.run(state).await,
+            StructureSubCommand::Get(cmd) => cmd.run(state).await,
+            StructureSubCommand::Update(cmd) => cmd.run(state).await,
+            StructureSubCommand::Delete(cmd) => cmd.run(state).await,
         }
     }
 }
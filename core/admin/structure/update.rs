@@ -0,0 +1,35 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, StructureID};
+
+#[derive(Clap)]
+pub struct UpdateStructureCommand {
+    /// ID of the structure
+    structure_id: StructureID,
+
+    /// New name of the structure
+    name: String,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for UpdateStructureCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let request = admin::structure::update::Request {
+            structure_id: self.structure_id,
+            structure_name: self.name,
+        };
+
+        let access_token = state.access_token().await?;
+        state
+            .houseflow_api
+            .admin_update_structure(&access_token, &request)
+            .await??;
+
+        tracing::info!("✔ Succesfully updated structure");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, UserID};
+
+#[derive(Clap)]
+pub struct DeleteUserCommand {
+    /// ID of the user
+    user_id: UserID,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for DeleteUserCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let request = admin::user::delete::Request {
+            user_id: self.user_id,
+        };
+
+        let access_token = state.access_token().await?;
+        state
+            .houseflow_api
+            .admin_delete_user(&access_token, &request)
+            .await??;
+
+        tracing::info!("✔ Succesfully deleted user");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, UserID};
+
+#[derive(Clap)]
+pub struct GetUserCommand {
+    /// ID of the user
+    user_id: UserID,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for GetUserCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let request = admin::user::get::Request {
+            user_id: self.user_id,
+        };
+
+        let access_token = state.access_token().await?;
+        let response = state
+            .houseflow_api
+            .admin_get_user(&access_token, &request)
+            .await??;
+
+        println!("{}", serde_json::to_string_pretty(&response.user)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+#[derive(Clap)]
+pub struct ListUsersCommand {}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for ListUsersCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let access_token = state.access_token().await?;
+        let response = state
+            .houseflow_api
+            .admin_list_users(&access_token)
+            .await??;
+
+        println!("{} users", response.users.len());
+        for user in response.users {
+            println!(
+                "ID: {}, Username: {}, Email: {}",
+                user.id, user.username, user.email
+            );
+        }
+
+        Ok(())
+    }
+}
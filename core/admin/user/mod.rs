@@ -0,0 +1,46 @@
+mod delete;
+mod get;
+mod list;
+mod update;
+use delete::DeleteUserCommand;
+use get::GetUserCommand;
+use list::ListUsersCommand;
+use update::UpdateUserCommand;
+
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+#[derive(Clap)]
+pub struct UserCommand {
+    #[clap(subcommand)]
+    subcommand: UserSubCommand,
+}
+
+#[derive(Clap)]
+pub enum UserSubCommand {
+    /// List all users
+    List(ListUsersCommand),
+
+    /// Get user by ID
+    Get(GetUserCommand),
+
+    /// Update user
+    Update(UpdateUserCommand),
+
+    /// Delete user
+    Delete(DeleteUserCommand),
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for UserCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        match self.subcommand {
+            UserSubCommand::List(cmd) => cmd.run(state).await,
+            UserSubCommand::Get(cmd) => cmd.run(state).await,
+            UserSubCommand::Update(cmd) => cmd.run(state).await,
+            UserSubCommand::Delete(cmd) => cmd.run(state).await,
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, UserID};
+
+#[derive(Clap)]
+pub struct UpdateUserCommand {
+    /// ID of the user
+    user_id: UserID,
+
+    /// New name of the user
+    username: String,
+
+    /// New email of the user
+    email: String,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for UpdateUserCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let request = admin::user::update::Request {
+            user_id: self.user_id,
+            username: self.username,
+            email: self.email,
+        };
+
+        let access_token = state.access_token().await?;
+        state
+            .houseflow_api
+            .admin_update_user(&access_token, &request)
+            .await??;
+
+        tracing::info!("✔ Succesfully updated user");
+
+        Ok(())
+    }
+}
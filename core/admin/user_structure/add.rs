@@ -3,7 +3,7 @@ use async_trait::async_trait;
 
 use clap::Clap;
 
-use houseflow_types::{admin, StructureID, UserID};
+use houseflow_types::{admin, Role, StructureID, UserID};
 
 #[derive(Clap)]
 pub struct AddUserStructureCommand {
@@ -13,9 +13,8 @@ pub struct AddUserStructureCommand {
     /// ID of the user
     user_id: UserID,
 
-    /// True if user is manager in relation to the structure
-    #[clap(parse(try_from_str))]
-    is_manager: bool,
+    /// Role of the user in the structure, one of viewer, operator or manager
+    role: Role,
 }
 
 #[async_trait(?Send)]
@@ -24,7 +23,7 @@ impl Command<ClientCommandState> for AddUserStructureCommand {
         let request = admin::user_structure::add::Request {
             structure_id: self.structure_id,
             user_id: self.user_id,
-            is_manager: self.is_manager,
+            role: self.role,
         };
 
         let access_token = state.access_token().await?;
@@ -0,0 +1,35 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, StructureID, UserID};
+
+#[derive(Clap)]
+pub struct DeleteUserStructureCommand {
+    /// ID of the structure
+    structure_id: StructureID,
+
+    /// ID of the user
+    user_id: UserID,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for DeleteUserStructureCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let request = admin::user_structure::delete::Request {
+            structure_id: self.structure_id,
+            user_id: self.user_id,
+        };
+
+        let access_token = state.access_token().await?;
+        state
+            .houseflow_api
+            .admin_delete_user_structure(&access_token, &request)
+            .await??;
+
+        tracing::info!("✔ Succesfully deleted user structure");
+
+        Ok(())
+    }
+}
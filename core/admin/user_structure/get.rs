@@ -0,0 +1,38 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, StructureID, UserID};
+
+#[derive(Clap)]
+pub struct GetUserStructureCommand {
+    /// ID of the structure
+    structure_id: StructureID,
+
+    /// ID of the user
+    user_id: UserID,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for GetUserStructureCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let request = admin::user_structure::get::Request {
+            structure_id: self.structure_id,
+            user_id: self.user_id,
+        };
+
+        let access_token = state.access_token().await?;
+        let response = state
+            .houseflow_api
+            .admin_get_user_structure(&access_token, &request)
+            .await??;
+
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&response.user_structure)?
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+#[derive(Clap)]
+pub struct ListUserStructuresCommand {}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for ListUserStructuresCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let access_token = state.access_token().await?;
+        let response = state
+            .houseflow_api
+            .admin_list_user_structures(&access_token)
+            .await??;
+
+        println!("{} user structures", response.user_structures.len());
+        for user_structure in response.user_structures {
+            println!(
+                "Structure ID: {}, User ID: {}, Role: {}",
+                user_structure.structure_id, user_structure.user_id, user_structure.role
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -1,5 +1,13 @@
 mod add;
+mod delete;
+mod get;
+mod list;
+mod update;
 use add::AddUserStructureCommand;
+use delete::DeleteUserStructureCommand;
+use get::GetUserStructureCommand;
+use list::ListUserStructuresCommand;
+use update::UpdateUserStructureCommand;
 
 use crate::{ClientCommandState, Command};
 use async_trait::async_trait;
@@ -14,7 +22,20 @@ pub struct UserStructureCommand {
 
 #[derive(Clap)]
 pub enum UserStructureSubCommand {
-    Add(AddUserStructureCommand)
+    /// Add new user structure
+    Add(AddUserStructureCommand),
+
+    /// List all user structures
+    List(ListUserStructuresCommand),
+
+    /// Get user structure by ID
+    Get(GetUserStructureCommand),
+
+    /// Update user structure
+    Update(UpdateUserStructureCommand),
+
+    /// Delete user structure
+    Delete(DeleteUserStructureCommand),
 }
 
 #[async_trait(?Send)]
@@ -22,6 +43,10 @@ impl Command<ClientCommandState> for UserStructureCommand {
     async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
         match self.subcommand {
             UserStructureSubCommand::Add(cmd) => cmd.run(state).await,
+            UserStructureSubCommand::List(cmd) => cmd.run(state).await,
+            UserStructureSubCommand::Get(cmd) => cmd.run(state).await,
+            UserStructureSubCommand::Update(cmd) => cmd.run(state).await,
+            UserStructureSubCommand::Delete(cmd) => cmd.run(state).await,
         }
     }
 }
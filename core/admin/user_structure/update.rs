@@ -0,0 +1,39 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+use houseflow_types::{admin, Role, StructureID, UserID};
+
+#[derive(Clap)]
+pub struct UpdateUserStructureCommand {
+    /// ID of the structure
+    structure_id: StructureID,
+
+    /// ID of the user
+    user_id: UserID,
+
+    /// Role of the user in the structure, one of viewer, operator or manager
+    role: Role,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for UpdateUserStructureCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let request = admin::user_structure::update::Request {
+            structure_id: self.structure_id,
+            user_id: self.user_id,
+            role: self.role,
+        };
+
+        let access_token = state.access_token().await?;
+        state
+            .houseflow_api
+            .admin_update_user_structure(&access_token, &request)
+            .await??;
+
+        tracing::info!("✔ Succesfully updated user structure");
+
+        Ok(())
+    }
+}
@@ -19,17 +19,19 @@ impl Command<DeviceCommandState> for RunDeviceCommand {
             state.config.device_id
         );
 
-        async fn run_device<D: devices::Device<EP>, EP: devices::ExecuteParams>(
+        async fn run_device(
             state: DeviceCommandState,
-            device: D,
+            device: impl devices::Device,
         ) -> anyhow::Result<()> {
             houseflow_device::run(state.config, device).await
         }
 
         match self.device_type {
-            DeviceType::Light => run_device(state, devices::light::Device::default()),
+            DeviceType::Light => run_device(state, devices::light::Device::default()).await,
+            DeviceType::Thermostat => {
+                run_device(state, devices::thermostat::Device::default()).await
+            }
             _ => unimplemented!(),
         }
-        .await
     }
 }
@@ -1,7 +1,7 @@
 use crate::{ClientCommandState, Command};
 use async_trait::async_trait;
 use houseflow_types::{
-    fulfillment::execute, lighthouse::proto, DeviceCommand, DeviceID, DeviceStatus,
+    fulfillment::execute, traits::CommandParams, DeviceCommand, DeviceID, DeviceStatus,
 };
 
 use clap::Clap;
@@ -52,14 +52,17 @@ impl Command<ClientCommandState> for ExecuteCommand {
             )));
         }
 
-        let execute_frame = proto::execute::Frame {
-            id: rand::random(),
-            command: self.command.clone(),
-            params: self.params.clone(),
-        };
+        CommandParams::new(self.command.clone(), self.params.clone()).map_err(|err| {
+            anyhow::Error::msg(format!(
+                "invalid parameters for `{}` command: {}",
+                self.command, err
+            ))
+        })?;
+
         let request = execute::Request {
             device_id: self.device_id.clone(),
-            frame: execute_frame,
+            command: self.command,
+            params: self.params,
         };
         let response = state
             .houseflow_api
@@ -0,0 +1,58 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use houseflow_types::{fulfillment::history, DeviceID};
+
+use clap::Clap;
+
+#[derive(Clap)]
+pub struct HistoryCommand {
+    pub device_id: DeviceID,
+
+    /// Show only states reported at or after this time, in RFC 3339 format
+    #[clap(long)]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Show only states reported before this time, in RFC 3339 format
+    #[clap(long)]
+    pub until: Option<DateTime<Utc>>,
+
+    /// Maximum amount of states to show
+    #[clap(long)]
+    pub limit: Option<u32>,
+
+    /// Amount of states to skip
+    #[clap(long, default_value = "0")]
+    pub offset: u32,
+}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for HistoryCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let access_token = state.access_token().await?;
+        let request = history::Request {
+            device_id: self.device_id,
+            since: self.since,
+            until: self.until,
+            limit: self.limit.unwrap_or(history::DEFAULT_LIMIT),
+            offset: self.offset,
+        };
+        let response = state
+            .houseflow_api
+            .history(&access_token, &request)
+            .await??;
+
+        if response.states.is_empty() {
+            println!("No states found");
+        }
+        for snapshot in response.states {
+            println!(
+                "{}: {}",
+                snapshot.updated_at,
+                serde_json::to_string(&snapshot.state)?
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -1,13 +1,17 @@
 mod execute;
+mod history;
 mod sync;
 mod query;
+mod watch;
 
 use crate::{ClientCommandState, Command};
 use async_trait::async_trait;
 
 use execute::ExecuteCommand;
+use history::HistoryCommand;
 use sync::SyncCommand;
 use query::QueryCommand;
+use watch::WatchCommand;
 
 use clap::Clap;
 
@@ -27,6 +31,12 @@ pub enum FulfillmentSubcommand {
 
     /// Query state of the device
     Query(QueryCommand),
+
+    /// Show history of the device state
+    History(HistoryCommand),
+
+    /// Stream state updates of all devices
+    Watch(WatchCommand),
 }
 
 #[async_trait(?Send)]
@@ -36,6 +46,8 @@ impl Command<ClientCommandState> for FulfillmentCommand {
             FulfillmentSubcommand::Sync(cmd) => cmd.run(state).await,
             FulfillmentSubcommand::Execute(cmd) => cmd.run(state).await,
             FulfillmentSubcommand::Query(cmd) => cmd.run(state).await,
+            FulfillmentSubcommand::History(cmd) => cmd.run(state).await,
+            FulfillmentSubcommand::Watch(cmd) => cmd.run(state).await,
         }
     }
 }
@@ -35,7 +35,14 @@ impl Command<ClientCommandState> for QueryCommand {
             .query(&access_token, &request)
             .await??;
 
-        println!("Device responded with state: {:#?}", response.frame.state);
+        if response.online {
+            println!("Device responded with state: {:#?}", response.frame.state);
+        } else {
+            println!(
+                "Device is offline, last known state from {}: {:#?}",
+                response.updated_at, response.frame.state
+            );
+        }
 
         Ok(())
     }
@@ -18,10 +18,16 @@ impl Command<ClientCommandState> for SyncCommand {
 
         println!("Synced {} devices", response.devices.len());
         response.devices.iter().for_each(|device| {
+            let last_seen = response
+                .last_seen
+                .get(&device.id)
+                .map(|last_seen| last_seen.to_string())
+                .unwrap_or_else(|| String::from("never"));
             println!(
-                "Device ID: {}, Name: {}",
+                "Device ID: {}, Name: {}, Last seen: {}",
                 device.id.to_string(),
-                device.name
+                device.name,
+                last_seen
             )
         });
         state
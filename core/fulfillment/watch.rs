@@ -0,0 +1,35 @@
+use crate::{ClientCommandState, Command};
+use async_trait::async_trait;
+
+use clap::Clap;
+
+#[derive(Clap)]
+pub struct WatchCommand {}
+
+#[async_trait(?Send)]
+impl Command<ClientCommandState> for WatchCommand {
+    async fn run(self, state: ClientCommandState) -> anyhow::Result<()> {
+        let access_token = state.access_token().await?;
+        let devices = state.devices.get().await.unwrap_or_default();
+        let mut stream = state.houseflow_api.watch(&access_token).await??;
+
+        println!("Watching for state updates, press Ctrl+C to stop");
+        while let Some(event) = stream.next().await? {
+            let device_name = devices
+                .iter()
+                .find(|device| device.id == event.device_id)
+                .map(|device| device.name.as_str())
+                .unwrap_or("unknown");
+            println!(
+                "{} {} ({}): {}",
+                event.snapshot.updated_at,
+                event.device_id,
+                device_name,
+                serde_json::to_string(&event.snapshot.state)?
+            );
+        }
+        println!("Server closed the stream");
+
+        Ok(())
+    }
+}
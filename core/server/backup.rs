@@ -0,0 +1,46 @@
+use crate::{Command, ServerCommandState};
+use anyhow::Context;
+use async_trait::async_trait;
+use std::path::PathBuf;
+
+use clap::Clap;
+
+#[derive(Clap)]
+pub struct BackupServerCommand {
+    /// File to save the backup to, it's saved as TOML if the file has `.toml` extension, otherwise as JSON
+    path: PathBuf,
+
+    /// Leave out the password hashes, only the existing users can log in and devices must get new passwords after restoring
+    #[clap(long)]
+    without_secrets: bool,
+}
+
+#[async_trait(?Send)]
+impl Command<ServerCommandState> for BackupServerCommand {
+    async fn run(self, state: ServerCommandState) -> anyhow::Result<()> {
+        let database = houseflow_db::open(&state.config).await?;
+        let backup = database.backup().await?;
+        let backup = match self.without_secrets {
+            true => backup.without_secrets(),
+            false => backup,
+        };
+        let contents = match super::is_toml(&self.path) {
+            true => toml::to_string_pretty(&backup)?,
+            false => serde_json::to_string_pretty(&backup)?,
+        };
+        tokio::fs::write(&self.path, contents)
+            .await
+            .with_context(|| format!("write backup to {}", self.path.display()))?;
+
+        tracing::info!(
+            "✔ Succesfully saved backup of {} structures, {} rooms, {} devices and {} users to {}",
+            backup.structures.len(),
+            backup.rooms.len(),
+            backup.devices.len(),
+            backup.users.len(),
+            self.path.display()
+        );
+
+        Ok(())
+    }
+}
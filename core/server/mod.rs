@@ -1,7 +1,12 @@
 use crate::{Command, ServerCommandState};
 use async_trait::async_trait;
+use backup::BackupServerCommand;
+use restore::RestoreServerCommand;
 use run::RunServerCommand;
+use std::path::Path;
 
+mod backup;
+mod restore;
 mod run;
 
 use clap::Clap;
@@ -17,6 +22,12 @@ pub enum Service {
 pub enum ServerSubcommand {
     /// Run specific service
     Run(RunServerCommand),
+
+    /// Save structures, rooms, devices, users and their roles to a file
+    Backup(BackupServerCommand),
+
+    /// Replace structures, rooms, devices, users and their roles with the ones from the backup
+    Restore(RestoreServerCommand),
 }
 
 #[derive(Clap)]
@@ -30,6 +41,13 @@ impl Command<ServerCommandState> for ServerCommand {
     async fn run(self, state: ServerCommandState) -> anyhow::Result<()> {
         match self.subcommand {
             ServerSubcommand::Run(cmd) => cmd.run(state).await,
+            ServerSubcommand::Backup(cmd) => cmd.run(state).await,
+            ServerSubcommand::Restore(cmd) => cmd.run(state).await,
         }
     }
 }
+
+/// Backups are stored as TOML if the file has `.toml` extension, otherwise as JSON
+fn is_toml(path: &Path) -> bool {
+    path.extension() == Some("toml".as_ref())
+}
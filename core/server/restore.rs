@@ -0,0 +1,42 @@
+use crate::{Command, ServerCommandState};
+use anyhow::Context;
+use async_trait::async_trait;
+use houseflow_types::Backup;
+use std::path::PathBuf;
+
+use clap::Clap;
+
+#[derive(Clap)]
+pub struct RestoreServerCommand {
+    /// File with the backup, created by `houseflow server backup`
+    path: PathBuf,
+}
+
+#[async_trait(?Send)]
+impl Command<ServerCommandState> for RestoreServerCommand {
+    async fn run(self, state: ServerCommandState) -> anyhow::Result<()> {
+        let contents = tokio::fs::read_to_string(&self.path)
+            .await
+            .with_context(|| format!("read backup from {}", self.path.display()))?;
+        let backup: Backup = match super::is_toml(&self.path) {
+            true => toml::from_str(&contents)?,
+            false => serde_json::from_str(&contents)?,
+        };
+        let database = houseflow_db::open(&state.config).await?;
+        database.restore(&backup).await?;
+
+        tracing::info!(
+            "✔ Succesfully restored {} structures, {} rooms, {} devices and {} users from backup created at {}",
+            backup.structures.len(),
+            backup.rooms.len(),
+            backup.devices.len(),
+            backup.users.len(),
+            backup.created_at
+        );
+        if !backup.with_secrets {
+            tracing::warn!("Backup doesn't contain secrets, only the users which already existed can log in and devices must get new passwords with `houseflow admin device rotate-password` before connecting");
+        }
+
+        Ok(())
+    }
+}
@@ -1,14 +1,23 @@
 use crate::{Command, ServerCommandState};
 use actix_web::{web::Data, App, HttpServer};
+use anyhow::Context;
 use async_trait::async_trait;
-use houseflow_db::{sqlite::Database as SqliteDatabase, Database};
 use houseflow_server::{SledTokenStore, TokenStore};
-use std::sync::Arc;
+use houseflow_types::Home;
+use std::{path::PathBuf, sync::Arc};
 
 use clap::Clap;
 
 #[derive(Clap)]
-pub struct RunServerCommand {}
+pub struct RunServerCommand {
+    /// File describing the home, applied to the database before the server starts
+    #[clap(long)]
+    home: Option<PathBuf>,
+
+    /// Delete the structures, rooms, devices and members which aren't declared in the home file
+    #[clap(long, requires = "home")]
+    prune: bool,
+}
 
 #[async_trait(?Send)]
 impl Command<ServerCommandState> for RunServerCommand {
@@ -16,8 +25,30 @@ impl Command<ServerCommandState> for RunServerCommand {
         let token_store = SledTokenStore::new(&state.config.tokens_path)?;
         let token_store = Data::from(Arc::new(token_store) as Arc<dyn TokenStore>);
 
-        let database = SqliteDatabase::new(&state.config.database_path)?;
-        let database = Data::from(Arc::new(database) as Arc<dyn Database>);
+        let database = Data::from(houseflow_db::open(&state.config).await?);
+        let homegraph = match &state.config.google {
+            Some(google) => {
+                houseflow_server::homegraph::HomeGraph::from_config(google)?.map(Data::new)
+            }
+            None => None,
+        };
+        if let Some(path) = &self.home {
+            let home: Home = houseflow_config::read_file(path.clone())
+                .await
+                .with_context(|| format!("read home from {}", path.display()))?;
+            let plan = houseflow_server::home::plan(&**database, &home, self.prune).await?;
+            if !plan.is_empty() {
+                tracing::info!("Applying home from {}:\n{}", path.display(), plan);
+                houseflow_server::home::apply(&**database, &plan).await?;
+                let users = database.get_users().await?;
+                houseflow_server::homegraph::request_sync(
+                    homegraph.as_ref().map(|homegraph| homegraph.get_ref()),
+                    &**database,
+                    &users,
+                )
+                .await;
+            }
+        }
 
         let address = (
             state.config.hostname.to_string(),
@@ -25,6 +56,14 @@ impl Command<ServerCommandState> for RunServerCommand {
         );
         let config = Data::new(state.config);
         let sessions = Data::new(houseflow_server::Sessions::default());
+        let state_updates = Data::new(houseflow_server::StateUpdates::default());
+        if let Some(homegraph) = &homegraph {
+            actix_rt::spawn(houseflow_server::homegraph::report_state_updates(
+                homegraph.clone(),
+                database.clone(),
+                state_updates.subscribe(),
+            ));
+        }
         let server = HttpServer::new(move || {
             App::new()
                 .wrap(actix_web::middleware::Logger::default())
@@ -35,6 +74,8 @@ impl Command<ServerCommandState> for RunServerCommand {
                         database.clone(),
                         config.clone(),
                         sessions.clone(),
+                        state_updates.clone(),
+                        homegraph.clone(),
                     )
                 })
         })
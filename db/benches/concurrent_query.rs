@@ -0,0 +1,228 @@
+//! Concurrent QUERY requests made from a single-threaded runtime, like the one of each actix worker.
+//! `blocking` makes the queries on the runtime thread, as the handlers used to, while `async` awaits them.
+//!
+//! `concurrent_query` measures the time it takes to handle all of the requests, and `worker_latency` the time
+//! it takes before another task of the worker, e.g an unrelated request or a heartbeat, gets to run meanwhile.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use houseflow_db::sqlite::Database;
+use houseflow_types::{
+    traits::{on_off, DeviceState, DeviceStateSnapshot},
+    Device, DeviceID, DeviceTrait, DeviceType, Role, Room, Structure, User, UserID, UserStructure,
+};
+use rand::random;
+use semver::Version;
+use std::time::{Duration, Instant};
+
+const CONCURRENCY: [usize; 3] = [1, 16, 64];
+
+struct Fixture {
+    database: Database,
+    user_id: UserID,
+    device_id: DeviceID,
+    _directory: tempfile::TempDir,
+}
+
+/// Database stored in a file, so the pooled connections share it, with a user which can query a device
+fn fixture() -> Fixture {
+    use houseflow_db::blocking::Database as _;
+
+    let directory = tempfile::tempdir().unwrap();
+    let database = Database::new(directory.path().join("houseflow.sqlite")).unwrap();
+    let structure = Structure {
+        id: random(),
+        name: "SomeStructure".to_string(),
+    };
+    let room = Room {
+        id: random(),
+        structure_id: structure.id.clone(),
+        name: "SomeRoom".to_string(),
+    };
+    let device = Device {
+        id: random(),
+        room_id: room.id.clone(),
+        password_hash: "SomePasswordHash".to_string(),
+        device_type: DeviceType::Light,
+        traits: vec![DeviceTrait::OnOff],
+        name: "SomeLight".to_string(),
+        will_push_state: true,
+        model: "testing-light".to_string(),
+        hw_version: Version::new(1, 0, 0),
+        sw_version: Version::new(1, 0, 0),
+        attributes: Default::default(),
+    };
+    let user = User {
+        id: random(),
+        username: "SomeUser".to_string(),
+        email: "user@example.com".to_string(),
+        password_hash: "SomePasswordHash".to_string(),
+    };
+    database.add_structure(&structure).unwrap();
+    database.add_room(&room).unwrap();
+    database.add_device(&device).unwrap();
+    database.add_user(&user).unwrap();
+    database
+        .add_user_structure(&UserStructure {
+            structure_id: structure.id,
+            user_id: user.id.clone(),
+            role: Role::Operator,
+        })
+        .unwrap();
+    database
+        .set_device_state(
+            &device.id,
+            &DeviceStateSnapshot {
+                state: DeviceState {
+                    on_off: Some(on_off::State { on: true }),
+                    ..Default::default()
+                },
+                updated_at: chrono::Utc::now(),
+            },
+        )
+        .unwrap();
+
+    Fixture {
+        database,
+        user_id: user.id,
+        device_id: device.id,
+        _directory: directory,
+    }
+}
+
+/// Queries made by the QUERY handler, blocking the runtime thread
+async fn query_blocking(fixture: &Fixture) {
+    use houseflow_db::blocking::Database as _;
+
+    let database = &fixture.database;
+    database
+        .get_user_device_role(&fixture.user_id, &fixture.device_id)
+        .unwrap()
+        .unwrap();
+    database.get_device(&fixture.device_id).unwrap().unwrap();
+    database
+        .get_device_state(&fixture.device_id)
+        .unwrap()
+        .unwrap();
+}
+
+/// Queries made by the QUERY handler, awaiting them
+async fn query_async(fixture: &Fixture) {
+    use houseflow_db::Database as _;
+
+    let database = &fixture.database;
+    database
+        .get_user_device_role(&fixture.user_id, &fixture.device_id)
+        .await
+        .unwrap()
+        .unwrap();
+    database
+        .get_device(&fixture.device_id)
+        .await
+        .unwrap()
+        .unwrap();
+    database
+        .get_device_state(&fixture.device_id)
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+fn concurrent_query(c: &mut Criterion) {
+    let fixture = fixture();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let mut group = c.benchmark_group("concurrent_query");
+    for concurrency in CONCURRENCY.iter() {
+        group.bench_with_input(
+            BenchmarkId::new("blocking", concurrency),
+            concurrency,
+            |b, &concurrency| {
+                b.iter(|| {
+                    runtime.block_on(futures::future::join_all(
+                        (0..concurrency).map(|_| query_blocking(&fixture)),
+                    ))
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("async", concurrency),
+            concurrency,
+            |b, &concurrency| {
+                b.iter(|| {
+                    runtime.block_on(futures::future::join_all(
+                        (0..concurrency).map(|_| query_async(&fixture)),
+                    ))
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Spawns the requests as separate tasks, then returns how long it took for the task spawned after them to run
+async fn worker_latency_once<F, Fut>(
+    fixture: &'static Fixture,
+    concurrency: usize,
+    query: F,
+) -> Duration
+where
+    F: Fn(&'static Fixture) -> Fut,
+    Fut: std::future::Future<Output = ()> + Send + 'static,
+{
+    let requests = (0..concurrency)
+        .map(|_| tokio::spawn(query(fixture)))
+        .collect::<Vec<_>>();
+    let spawned_at = Instant::now();
+    let latency = tokio::spawn(async move { spawned_at.elapsed() })
+        .await
+        .unwrap();
+    for request in requests {
+        request.await.unwrap();
+    }
+    latency
+}
+
+fn worker_latency(c: &mut Criterion) {
+    let fixture: &'static Fixture = Box::leak(Box::new(fixture()));
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let mut group = c.benchmark_group("worker_latency");
+    for concurrency in CONCURRENCY.iter() {
+        group.bench_with_input(
+            BenchmarkId::new("blocking", concurrency),
+            concurrency,
+            |b, &concurrency| {
+                b.iter_custom(|iters| {
+                    (0..iters)
+                        .map(|_| {
+                            runtime.block_on(worker_latency_once(
+                                fixture,
+                                concurrency,
+                                query_blocking,
+                            ))
+                        })
+                        .sum()
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("async", concurrency),
+            concurrency,
+            |b, &concurrency| {
+                b.iter_custom(|iters| {
+                    (0..iters)
+                        .map(|_| {
+                            runtime.block_on(worker_latency_once(fixture, concurrency, query_async))
+                        })
+                        .sum()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, concurrent_query, worker_latency);
+criterion_main!(benches);
@@ -0,0 +1,66 @@
+-- Keep in sync with the SQLite migration of the same version
+
+CREATE TABLE users (
+  id            CHAR(32) NOT NULL,
+  username      VARCHAR  NOT NULL,
+  email         VARCHAR  NOT NULL UNIQUE,
+  password_hash VARCHAR  NOT NULL,
+
+  CHECK( length(id) = 32 ),
+
+  PRIMARY KEY( id )
+);
+
+CREATE TABLE admins (
+  user_id CHAR(32) NOT NULL REFERENCES users( id ),
+
+  PRIMARY KEY( user_id )
+);
+
+CREATE TABLE structures (
+  id          CHAR(32)   NOT NULL UNIQUE,
+  name        VARCHAR    NOT NULL,
+
+  CHECK( length(id) = 32 ),
+
+  PRIMARY KEY( id )
+);
+
+CREATE TABLE user_structures (
+  structure_id  CHAR(32) NOT NULL REFERENCES structures(id) ON DELETE CASCADE,
+  user_id       CHAR(32) NOT NULL REFERENCES users     (id) ON DELETE CASCADE,
+  is_manager    BOOLEAN  NOT NULL,
+
+  PRIMARY KEY( structure_id, user_id )
+);
+
+
+CREATE TABLE rooms (
+  id           CHAR(32) NOT NULL,
+  structure_id CHAR(32) NOT NULL REFERENCES structures(id) ON DELETE CASCADE,
+  name         VARCHAR  NOT NULL,
+
+  PRIMARY KEY( id )
+);
+
+CREATE TABLE devices (
+  id              CHAR(32)    NOT NULL,
+  room_id         CHAR(32)    NOT NULL REFERENCES rooms(id) ON DELETE CASCADE,
+  password_hash   VARCHAR     NOT NULL, -- password hash used to verify
+  type            VARCHAR     NOT NULL, -- type of the device
+  name            VARCHAR     NOT NULL, -- name of the device
+  will_push_state BOOLEAN     NOT NULL, -- true if device will push state on it's own
+  model           VARCHAR     NOT NULL, -- model name of the device
+  hw_version      VARCHAR     NOT NULL, -- hardware version, must follow semver
+  sw_version      VARCHAR     NOT NULL, -- software version, must follow semver
+  attributes      VARCHAR     NOT NULL, -- device attributes in JSON format
+
+  PRIMARY KEY( id )
+);
+
+CREATE TABLE device_traits (
+  device_id  CHAR(32) NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+  trait_name VARCHAR  NOT NULL,
+
+  PRIMARY KEY(device_id, trait_name)
+);
@@ -0,0 +1,7 @@
+CREATE TABLE device_state_history (
+  device_id  CHAR(32)    NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+  state      VARCHAR     NOT NULL, -- reported state in JSON format
+  updated_at TIMESTAMPTZ NOT NULL  -- time when the state was reported
+);
+
+CREATE INDEX device_state_history_device_id_updated_at ON device_state_history( device_id, updated_at );
@@ -0,0 +1,6 @@
+CREATE TABLE google_home_unlinks (
+  user_id     CHAR(32)    NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  unlinked_at TIMESTAMPTZ NOT NULL, -- time when the user unlinked Google Home account
+
+  PRIMARY KEY( user_id )
+);
@@ -0,0 +1,7 @@
+CREATE TABLE device_tokens (
+  device_id CHAR(32)    NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+  token_id  CHAR(32)    NOT NULL, -- ID of the only valid token of the device
+  issued_at TIMESTAMPTZ NOT NULL,
+
+  PRIMARY KEY( device_id )
+);
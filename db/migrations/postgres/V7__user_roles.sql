@@ -0,0 +1,26 @@
+-- is_manager is replaced with the role, previously every user of the structure could execute commands
+ALTER TABLE user_structures ADD COLUMN role VARCHAR;
+UPDATE user_structures SET role = CASE WHEN is_manager THEN 'manager' ELSE 'operator' END;
+ALTER TABLE user_structures ALTER COLUMN role SET NOT NULL;
+ALTER TABLE user_structures ADD CHECK( role in ('viewer', 'operator', 'manager') );
+ALTER TABLE user_structures DROP COLUMN is_manager;
+
+CREATE TABLE user_room_roles (
+  user_id  CHAR(32) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  room_id  CHAR(32) NOT NULL REFERENCES rooms(id) ON DELETE CASCADE,
+  role     VARCHAR  NOT NULL,
+
+  CHECK( role in ('viewer', 'operator', 'manager') ),
+
+  PRIMARY KEY( user_id, room_id )
+);
+
+CREATE TABLE user_device_roles (
+  user_id   CHAR(32) NOT NULL REFERENCES users  (id) ON DELETE CASCADE,
+  device_id CHAR(32) NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+  role      VARCHAR  NOT NULL,
+
+  CHECK( role in ('viewer', 'operator', 'manager') ),
+
+  PRIMARY KEY( user_id, device_id )
+);
@@ -0,0 +1,7 @@
+CREATE TABLE device_states (
+  device_id  CHAR(32) NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+  state      VARCHAR  NOT NULL, -- last known state in JSON format
+  updated_at DATETIME NOT NULL, -- time when the state was reported
+
+  PRIMARY KEY( device_id )
+);
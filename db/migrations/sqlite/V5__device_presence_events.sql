@@ -0,0 +1,8 @@
+CREATE TABLE device_presence_events (
+  device_id   CHAR(32) NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+  connected   BOOLEAN  NOT NULL, -- true if the device has connected, false if it has disconnected
+  address     VARCHAR  NOT NULL, -- address the device has connected from
+  occurred_at DATETIME NOT NULL
+);
+
+CREATE INDEX device_presence_events_device_id_occurred_at ON device_presence_events( device_id, occurred_at );
@@ -0,0 +1,37 @@
+-- is_manager is replaced with the role, previously every user of the structure could execute commands
+CREATE TABLE user_structures_roles (
+  structure_id  CHAR(32) NOT NULL REFERENCES structures(id) ON DELETE CASCADE,
+  user_id       CHAR(32) NOT NULL REFERENCES users     (id) ON DELETE CASCADE,
+  role          VARCHAR  NOT NULL,
+
+  CHECK( role in ('viewer', 'operator', 'manager') )
+
+  PRIMARY KEY( structure_id, user_id )
+);
+
+INSERT INTO user_structures_roles(structure_id, user_id, role)
+  SELECT structure_id, user_id, CASE is_manager WHEN 1 THEN 'manager' ELSE 'operator' END
+  FROM user_structures;
+
+DROP TABLE user_structures;
+ALTER TABLE user_structures_roles RENAME TO user_structures;
+
+CREATE TABLE user_room_roles (
+  user_id  CHAR(32) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  room_id  CHAR(32) NOT NULL REFERENCES rooms(id) ON DELETE CASCADE,
+  role     VARCHAR  NOT NULL,
+
+  CHECK( role in ('viewer', 'operator', 'manager') )
+
+  PRIMARY KEY( user_id, room_id )
+);
+
+CREATE TABLE user_device_roles (
+  user_id   CHAR(32) NOT NULL REFERENCES users  (id) ON DELETE CASCADE,
+  device_id CHAR(32) NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+  role      VARCHAR  NOT NULL,
+
+  CHECK( role in ('viewer', 'operator', 'manager') )
+
+  PRIMARY KEY( user_id, device_id )
+);
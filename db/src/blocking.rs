@@ -0,0 +1,501 @@
+//! Synchronous interface of the database backends, each of the queries blocks the calling thread until it's done.
+//!
+//! [crate::Database] is implemented for all of them by running the queries on the blocking thread pool,
+//! so they don't stall the async executor.
+
+use crate::Error;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use houseflow_types::{
+    traits::DeviceStateSnapshot, Backup, Device, DeviceID, DevicePresenceEvent, DeviceTokenID,
+    HomePlan, Role, RoleScope, Room, RoomID, ScopedRole, Structure, StructureID, User, UserID,
+    UserStructure,
+};
+
+/// Blocking counterpart of [crate::Database], see it for the description of the methods
+pub trait Database: Clone + Send + Sync + 'static {
+    fn add_structure(&self, structure: &Structure) -> Result<(), Error>;
+    fn add_room(&self, room: &Room) -> Result<(), Error>;
+    fn add_device(&self, device: &Device) -> Result<(), Error>;
+    fn add_user(&self, user: &User) -> Result<(), Error>;
+    fn add_admin(&self, user_id: &UserID) -> Result<(), Error>;
+    fn add_user_structure(&self, user_structure: &UserStructure) -> Result<(), Error>;
+
+    fn get_structure(&self, structure_id: &StructureID) -> Result<Option<Structure>, Error>;
+    fn get_room(&self, room_id: &RoomID) -> Result<Option<Room>, Error>;
+    fn get_device(&self, device_id: &DeviceID) -> Result<Option<Device>, Error>;
+    fn get_user(&self, user_id: &UserID) -> Result<Option<User>, Error>;
+    fn get_user_by_email(&self, email: &str) -> Result<Option<User>, Error>;
+    fn get_user_devices(&self, user_id: &UserID) -> Result<Vec<Device>, Error>;
+    fn get_device_users(&self, device_id: &DeviceID) -> Result<Vec<User>, Error>;
+    fn get_user_structure(
+        &self,
+        structure_id: &StructureID,
+        user_id: &UserID,
+    ) -> Result<Option<UserStructure>, Error>;
+
+    fn get_structures(&self) -> Result<Vec<Structure>, Error>;
+    fn get_rooms(&self) -> Result<Vec<Room>, Error>;
+    fn get_devices(&self) -> Result<Vec<Device>, Error>;
+    fn get_users(&self) -> Result<Vec<User>, Error>;
+    fn get_user_structures(&self) -> Result<Vec<UserStructure>, Error>;
+    fn update_structure(&self, structure: &Structure) -> Result<bool, Error>;
+    fn update_room(&self, room: &Room) -> Result<bool, Error>;
+    fn update_device(&self, device: &Device) -> Result<bool, Error>;
+    fn update_user(&self, user: &User) -> Result<bool, Error>;
+    fn update_user_structure(&self, user_structure: &UserStructure) -> Result<bool, Error>;
+    fn delete_structure(&self, structure_id: &StructureID) -> Result<bool, Error>;
+    fn delete_room(&self, room_id: &RoomID) -> Result<bool, Error>;
+    fn delete_device(&self, device_id: &DeviceID) -> Result<bool, Error>;
+    fn delete_user(&self, user_id: &UserID) -> Result<bool, Error>;
+    fn delete_user_structure(
+        &self,
+        structure_id: &StructureID,
+        user_id: &UserID,
+    ) -> Result<bool, Error>;
+
+    fn set_device_state(
+        &self,
+        device_id: &DeviceID,
+        snapshot: &DeviceStateSnapshot,
+    ) -> Result<(), Error>;
+    fn get_device_state(&self, device_id: &DeviceID) -> Result<Option<DeviceStateSnapshot>, Error>;
+
+    fn add_device_state_history(
+        &self,
+        device_id: &DeviceID,
+        snapshot: &DeviceStateSnapshot,
+    ) -> Result<(), Error>;
+    fn get_device_state_history(
+        &self,
+        device_id: &DeviceID,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<DeviceStateSnapshot>, Error>;
+
+    fn add_device_presence_event(
+        &self,
+        device_id: &DeviceID,
+        event: &DevicePresenceEvent,
+    ) -> Result<(), Error>;
+    fn get_last_device_presence_event(
+        &self,
+        device_id: &DeviceID,
+    ) -> Result<Option<DevicePresenceEvent>, Error>;
+    fn set_device_token_id(
+        &self,
+        device_id: &DeviceID,
+        token_id: &DeviceTokenID,
+        issued_at: &DateTime<Utc>,
+    ) -> Result<(), Error>;
+    fn get_device_token_id(&self, device_id: &DeviceID) -> Result<Option<DeviceTokenID>, Error>;
+    fn remove_device_token_id(&self, device_id: &DeviceID) -> Result<bool, Error>;
+    fn set_device_password_hash(
+        &self,
+        device_id: &DeviceID,
+        password_hash: &str,
+    ) -> Result<bool, Error>;
+    fn get_user_device_role(
+        &self,
+        user_id: &UserID,
+        device_id: &DeviceID,
+    ) -> Result<Option<Role>, Error>;
+    fn get_user_room_role(&self, user_id: &UserID, room_id: &RoomID)
+        -> Result<Option<Role>, Error>;
+    fn add_scoped_role(&self, scoped_role: &ScopedRole) -> Result<(), Error>;
+    fn get_scoped_roles(&self) -> Result<Vec<ScopedRole>, Error>;
+    fn delete_scoped_role(&self, user_id: &UserID, scope: &RoleScope) -> Result<bool, Error>;
+
+    fn check_user_admin(&self, user_id: &UserID) -> Result<bool, Error>;
+    fn get_admins(&self) -> Result<Vec<UserID>, Error>;
+    fn check_user_structure_manager(
+        &self,
+        user_id: &UserID,
+        structure_id: &StructureID,
+    ) -> Result<bool, Error>;
+    fn add_google_home_unlink(
+        &self,
+        user_id: &UserID,
+        unlinked_at: &DateTime<Utc>,
+    ) -> Result<(), Error>;
+    fn remove_google_home_unlink(&self, user_id: &UserID) -> Result<bool, Error>;
+    fn check_google_home_unlinked(&self, user_id: &UserID) -> Result<bool, Error>;
+
+    fn restore(&self, backup: &Backup) -> Result<(), Error>;
+    fn backup(&self) -> Result<Backup, Error>;
+    fn apply_home(&self, plan: &HomePlan) -> Result<(), Error>;
+}
+
+/// Runs the closure on the blocking thread pool, panic of the closure is propagated to the caller
+pub(crate) async fn spawn_blocking<T, F>(f: F) -> Result<T, Error>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, Error> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))
+}
+
+#[async_trait]
+impl<T: Database> crate::Database for T {
+    async fn add_structure(&self, structure: &Structure) -> Result<(), Error> {
+        let (database, structure) = (self.clone(), structure.to_owned());
+        spawn_blocking(move || Database::add_structure(&database, &structure)).await
+    }
+
+    async fn add_room(&self, room: &Room) -> Result<(), Error> {
+        let (database, room) = (self.clone(), room.to_owned());
+        spawn_blocking(move || Database::add_room(&database, &room)).await
+    }
+
+    async fn add_device(&self, device: &Device) -> Result<(), Error> {
+        let (database, device) = (self.clone(), device.to_owned());
+        spawn_blocking(move || Database::add_device(&database, &device)).await
+    }
+
+    async fn add_user(&self, user: &User) -> Result<(), Error> {
+        let (database, user) = (self.clone(), user.to_owned());
+        spawn_blocking(move || Database::add_user(&database, &user)).await
+    }
+
+    async fn add_admin(&self, user_id: &UserID) -> Result<(), Error> {
+        let (database, user_id) = (self.clone(), user_id.to_owned());
+        spawn_blocking(move || Database::add_admin(&database, &user_id)).await
+    }
+
+    async fn add_user_structure(&self, user_structure: &UserStructure) -> Result<(), Error> {
+        let (database, user_structure) = (self.clone(), user_structure.to_owned());
+        spawn_blocking(move || Database::add_user_structure(&database, &user_structure)).await
+    }
+
+    async fn get_structure(&self, structure_id: &StructureID) -> Result<Option<Structure>, Error> {
+        let (database, structure_id) = (self.clone(), structure_id.to_owned());
+        spawn_blocking(move || Database::get_structure(&database, &structure_id)).await
+    }
+
+    async fn get_room(&self, room_id: &RoomID) -> Result<Option<Room>, Error> {
+        let (database, room_id) = (self.clone(), room_id.to_owned());
+        spawn_blocking(move || Database::get_room(&database, &room_id)).await
+    }
+
+    async fn get_device(&self, device_id: &DeviceID) -> Result<Option<Device>, Error> {
+        let (database, device_id) = (self.clone(), device_id.to_owned());
+        spawn_blocking(move || Database::get_device(&database, &device_id)).await
+    }
+
+    async fn get_user(&self, user_id: &UserID) -> Result<Option<User>, Error> {
+        let (database, user_id) = (self.clone(), user_id.to_owned());
+        spawn_blocking(move || Database::get_user(&database, &user_id)).await
+    }
+
+    async fn get_user_by_email(&self, email: &str) -> Result<Option<User>, Error> {
+        let (database, email) = (self.clone(), email.to_owned());
+        spawn_blocking(move || Database::get_user_by_email(&database, &email)).await
+    }
+
+    async fn get_user_devices(&self, user_id: &UserID) -> Result<Vec<Device>, Error> {
+        let (database, user_id) = (self.clone(), user_id.to_owned());
+        spawn_blocking(move || Database::get_user_devices(&database, &user_id)).await
+    }
+
+    async fn get_device_users(&self, device_id: &DeviceID) -> Result<Vec<User>, Error> {
+        let (database, device_id) = (self.clone(), device_id.to_owned());
+        spawn_blocking(move || Database::get_device_users(&database, &device_id)).await
+    }
+
+    async fn get_user_structure(
+        &self,
+        structure_id: &StructureID,
+        user_id: &UserID,
+    ) -> Result<Option<UserStructure>, Error> {
+        let (database, structure_id, user_id) =
+            (self.clone(), structure_id.to_owned(), user_id.to_owned());
+        spawn_blocking(move || Database::get_user_structure(&database, &structure_id, &user_id))
+            .await
+    }
+
+    async fn get_structures(&self) -> Result<Vec<Structure>, Error> {
+        let database = self.clone();
+        spawn_blocking(move || Database::get_structures(&database)).await
+    }
+
+    async fn get_rooms(&self) -> Result<Vec<Room>, Error> {
+        let database = self.clone();
+        spawn_blocking(move || Database::get_rooms(&database)).await
+    }
+
+    async fn get_devices(&self) -> Result<Vec<Device>, Error> {
+        let database = self.clone();
+        spawn_blocking(move || Database::get_devices(&database)).await
+    }
+
+    async fn get_users(&self) -> Result<Vec<User>, Error> {
+        let database = self.clone();
+        spawn_blocking(move || Database::get_users(&database)).await
+    }
+
+    async fn get_user_structures(&self) -> Result<Vec<UserStructure>, Error> {
+        let database = self.clone();
+        spawn_blocking(move || Database::get_user_structures(&database)).await
+    }
+
+    async fn update_structure(&self, structure: &Structure) -> Result<bool, Error> {
+        let (database, structure) = (self.clone(), structure.to_owned());
+        spawn_blocking(move || Database::update_structure(&database, &structure)).await
+    }
+
+    async fn update_room(&self, room: &Room) -> Result<bool, Error> {
+        let (database, room) = (self.clone(), room.to_owned());
+        spawn_blocking(move || Database::update_room(&database, &room)).await
+    }
+
+    async fn update_device(&self, device: &Device) -> Result<bool, Error> {
+        let (database, device) = (self.clone(), device.to_owned());
+        spawn_blocking(move || Database::update_device(&database, &device)).await
+    }
+
+    async fn update_user(&self, user: &User) -> Result<bool, Error> {
+        let (database, user) = (self.clone(), user.to_owned());
+        spawn_blocking(move || Database::update_user(&database, &user)).await
+    }
+
+    async fn update_user_structure(&self, user_structure: &UserStructure) -> Result<bool, Error> {
+        let (database, user_structure) = (self.clone(), user_structure.to_owned());
+        spawn_blocking(move || Database::update_user_structure(&database, &user_structure)).await
+    }
+
+    async fn delete_structure(&self, structure_id: &StructureID) -> Result<bool, Error> {
+        let (database, structure_id) = (self.clone(), structure_id.to_owned());
+        spawn_blocking(move || Database::delete_structure(&database, &structure_id)).await
+    }
+
+    async fn delete_room(&self, room_id: &RoomID) -> Result<bool, Error> {
+        let (database, room_id) = (self.clone(), room_id.to_owned());
+        spawn_blocking(move || Database::delete_room(&database, &room_id)).await
+    }
+
+    async fn delete_device(&self, device_id: &DeviceID) -> Result<bool, Error> {
+        let (database, device_id) = (self.clone(), device_id.to_owned());
+        spawn_blocking(move || Database::delete_device(&database, &device_id)).await
+    }
+
+    async fn delete_user(&self, user_id: &UserID) -> Result<bool, Error> {
+        let (database, user_id) = (self.clone(), user_id.to_owned());
+        spawn_blocking(move || Database::delete_user(&database, &user_id)).await
+    }
+
+    async fn delete_user_structure(
+        &self,
+        structure_id: &StructureID,
+        user_id: &UserID,
+    ) -> Result<bool, Error> {
+        let (database, structure_id, user_id) =
+            (self.clone(), structure_id.to_owned(), user_id.to_owned());
+        spawn_blocking(move || Database::delete_user_structure(&database, &structure_id, &user_id))
+            .await
+    }
+
+    async fn set_device_state(
+        &self,
+        device_id: &DeviceID,
+        snapshot: &DeviceStateSnapshot,
+    ) -> Result<(), Error> {
+        let (database, device_id, snapshot) =
+            (self.clone(), device_id.to_owned(), snapshot.to_owned());
+        spawn_blocking(move || Database::set_device_state(&database, &device_id, &snapshot)).await
+    }
+
+    async fn get_device_state(
+        &self,
+        device_id: &DeviceID,
+    ) -> Result<Option<DeviceStateSnapshot>, Error> {
+        let (database, device_id) = (self.clone(), device_id.to_owned());
+        spawn_blocking(move || Database::get_device_state(&database, &device_id)).await
+    }
+
+    async fn add_device_state_history(
+        &self,
+        device_id: &DeviceID,
+        snapshot: &DeviceStateSnapshot,
+    ) -> Result<(), Error> {
+        let (database, device_id, snapshot) =
+            (self.clone(), device_id.to_owned(), snapshot.to_owned());
+        spawn_blocking(move || Database::add_device_state_history(&database, &device_id, &snapshot))
+            .await
+    }
+
+    async fn get_device_state_history(
+        &self,
+        device_id: &DeviceID,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<DeviceStateSnapshot>, Error> {
+        let (database, device_id) = (self.clone(), device_id.to_owned());
+        spawn_blocking(move || {
+            Database::get_device_state_history(&database, &device_id, since, until, limit, offset)
+        })
+        .await
+    }
+
+    async fn add_device_presence_event(
+        &self,
+        device_id: &DeviceID,
+        event: &DevicePresenceEvent,
+    ) -> Result<(), Error> {
+        let (database, device_id, event) = (self.clone(), device_id.to_owned(), event.to_owned());
+        spawn_blocking(move || Database::add_device_presence_event(&database, &device_id, &event))
+            .await
+    }
+
+    async fn get_last_device_presence_event(
+        &self,
+        device_id: &DeviceID,
+    ) -> Result<Option<DevicePresenceEvent>, Error> {
+        let (database, device_id) = (self.clone(), device_id.to_owned());
+        spawn_blocking(move || Database::get_last_device_presence_event(&database, &device_id))
+            .await
+    }
+
+    async fn set_device_token_id(
+        &self,
+        device_id: &DeviceID,
+        token_id: &DeviceTokenID,
+        issued_at: &DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let (database, device_id, token_id, issued_at) = (
+            self.clone(),
+            device_id.to_owned(),
+            token_id.to_owned(),
+            issued_at.to_owned(),
+        );
+        spawn_blocking(move || {
+            Database::set_device_token_id(&database, &device_id, &token_id, &issued_at)
+        })
+        .await
+    }
+
+    async fn get_device_token_id(
+        &self,
+        device_id: &DeviceID,
+    ) -> Result<Option<DeviceTokenID>, Error> {
+        let (database, device_id) = (self.clone(), device_id.to_owned());
+        spawn_blocking(move || Database::get_device_token_id(&database, &device_id)).await
+    }
+
+    async fn remove_device_token_id(&self, device_id: &DeviceID) -> Result<bool, Error> {
+        let (database, device_id) = (self.clone(), device_id.to_owned());
+        spawn_blocking(move || Database::remove_device_token_id(&database, &device_id)).await
+    }
+
+    async fn set_device_password_hash(
+        &self,
+        device_id: &DeviceID,
+        password_hash: &str,
+    ) -> Result<bool, Error> {
+        let (database, device_id, password_hash) =
+            (self.clone(), device_id.to_owned(), password_hash.to_owned());
+        spawn_blocking(move || {
+            Database::set_device_password_hash(&database, &device_id, &password_hash)
+        })
+        .await
+    }
+
+    async fn get_user_device_role(
+        &self,
+        user_id: &UserID,
+        device_id: &DeviceID,
+    ) -> Result<Option<Role>, Error> {
+        let (database, user_id, device_id) =
+            (self.clone(), user_id.to_owned(), device_id.to_owned());
+        spawn_blocking(move || Database::get_user_device_role(&database, &user_id, &device_id))
+            .await
+    }
+
+    async fn get_user_room_role(
+        &self,
+        user_id: &UserID,
+        room_id: &RoomID,
+    ) -> Result<Option<Role>, Error> {
+        let (database, user_id, room_id) = (self.clone(), user_id.to_owned(), room_id.to_owned());
+        spawn_blocking(move || Database::get_user_room_role(&database, &user_id, &room_id)).await
+    }
+
+    async fn add_scoped_role(&self, scoped_role: &ScopedRole) -> Result<(), Error> {
+        let (database, scoped_role) = (self.clone(), scoped_role.to_owned());
+        spawn_blocking(move || Database::add_scoped_role(&database, &scoped_role)).await
+    }
+
+    async fn get_scoped_roles(&self) -> Result<Vec<ScopedRole>, Error> {
+        let database = self.clone();
+        spawn_blocking(move || Database::get_scoped_roles(&database)).await
+    }
+
+    async fn delete_scoped_role(&self, user_id: &UserID, scope: &RoleScope) -> Result<bool, Error> {
+        let (database, user_id, scope) = (self.clone(), user_id.to_owned(), scope.to_owned());
+        spawn_blocking(move || Database::delete_scoped_role(&database, &user_id, &scope)).await
+    }
+
+    async fn check_user_admin(&self, user_id: &UserID) -> Result<bool, Error> {
+        let (database, user_id) = (self.clone(), user_id.to_owned());
+        spawn_blocking(move || Database::check_user_admin(&database, &user_id)).await
+    }
+
+    async fn get_admins(&self) -> Result<Vec<UserID>, Error> {
+        let database = self.clone();
+        spawn_blocking(move || Database::get_admins(&database)).await
+    }
+
+    async fn check_user_structure_manager(
+        &self,
+        user_id: &UserID,
+        structure_id: &StructureID,
+    ) -> Result<bool, Error> {
+        let (database, user_id, structure_id) =
+            (self.clone(), user_id.to_owned(), structure_id.to_owned());
+        spawn_blocking(move || {
+            Database::check_user_structure_manager(&database, &user_id, &structure_id)
+        })
+        .await
+    }
+
+    async fn add_google_home_unlink(
+        &self,
+        user_id: &UserID,
+        unlinked_at: &DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let (database, user_id, unlinked_at) =
+            (self.clone(), user_id.to_owned(), unlinked_at.to_owned());
+        spawn_blocking(move || Database::add_google_home_unlink(&database, &user_id, &unlinked_at))
+            .await
+    }
+
+    async fn remove_google_home_unlink(&self, user_id: &UserID) -> Result<bool, Error> {
+        let (database, user_id) = (self.clone(), user_id.to_owned());
+        spawn_blocking(move || Database::remove_google_home_unlink(&database, &user_id)).await
+    }
+
+    async fn check_google_home_unlinked(&self, user_id: &UserID) -> Result<bool, Error> {
+        let (database, user_id) = (self.clone(), user_id.to_owned());
+        spawn_blocking(move || Database::check_google_home_unlinked(&database, &user_id)).await
+    }
+
+    async fn restore(&self, backup: &Backup) -> Result<(), Error> {
+        let (database, backup) = (self.clone(), backup.to_owned());
+        spawn_blocking(move || Database::restore(&database, &backup)).await
+    }
+
+    async fn backup(&self) -> Result<Backup, Error> {
+        let database = self.clone();
+        spawn_blocking(move || Database::backup(&database)).await
+    }
+
+    async fn apply_home(&self, plan: &HomePlan) -> Result<(), Error> {
+        let (database, plan) = (self.clone(), plan.to_owned());
+        spawn_blocking(move || Database::apply_home(&database, &plan)).await
+    }
+}
@@ -1,16 +1,32 @@
+pub mod blocking;
+
 #[cfg(feature = "sqlite")]
 pub mod sqlite;
 
+#[cfg(feature = "postgres")]
+pub mod postgres;
+
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
 pub enum Error {
+    /// Constraint violations are converted to `AlreadyExists` and `ReferenceNotFound`
     #[cfg(feature = "sqlite")]
     #[error("sqlite error: {0}")]
-    Sqlite(#[from] rusqlite::Error),
+    Sqlite(rusqlite::Error),
+
+    /// Constraint violations are converted to `AlreadyExists` and `ReferenceNotFound`
+    #[cfg(feature = "postgres")]
+    #[error("postgres error: {0}")]
+    Postgres(::postgres::Error),
 
+    #[cfg(feature = "postgres")]
+    #[error("invalid column value: {0}")]
+    InvalidColumn(String),
+
+    /// Boxed, because it's much larger than the other errors
     #[cfg(feature = "refinery")]
     #[error("sqlite error: {0}")]
-    Refinery(#[from] refinery::Error),
+    Refinery(Box<refinery::Error>),
 
     #[cfg(feature = "refinery")]
     #[error("sqlite error: {0}")]
@@ -22,36 +38,266 @@ pub enum Error {
     #[error("Query did not modify anything")]
     NotModified,
 
+    /// Row with the same primary key or unique column already exists
     #[error("Row already exists")]
     AlreadyExists,
+
+    /// Row referred by the foreign key doesn't exist
+    #[error("Referenced row doesn't exist")]
+    ReferenceNotFound,
+
+    #[error("{0} backend is not enabled in this build")]
+    BackendNotEnabled(&'static str),
+
+    #[error("backup version {0} is not supported, expected {}", Backup::VERSION)]
+    UnsupportedBackupVersion(u32),
+
+    /// Backup without secrets would leave all of the admins without a password
+    #[error("none of the admins would be able to log in after restoring the backup")]
+    NoAdminPassword,
 }
 
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use houseflow_types::{
-    Device, DeviceID, Room, RoomID, Structure, StructureID, User, UserID, UserStructure,
+    traits::DeviceStateSnapshot, Backup, Device, DeviceID, DevicePresenceEvent, DeviceTokenID,
+    HomePlan, Role, RoleScope, Room, RoomID, ScopedRole, Structure, StructureID, User, UserID,
+    UserStructure,
 };
 
+#[async_trait]
 pub trait Database: Send + Sync {
-    fn add_structure(&self, structure: &Structure) -> Result<(), Error>;
-    fn add_room(&self, room: &Room) -> Result<(), Error>;
-    fn add_device(&self, device: &Device) -> Result<(), Error>;
-    fn add_user(&self, user: &User) -> Result<(), Error>;
-    fn add_admin(&self, user_id: &UserID) -> Result<(), Error>;
-    fn add_user_structure(&self, user_structure: &UserStructure) -> Result<(), Error>;
-
-    fn get_structure(&self, structure_id: &StructureID) -> Result<Option<Structure>, Error>;
-    fn get_room(&self, room_id: &RoomID) -> Result<Option<Room>, Error>;
-    fn get_device(&self, device_id: &DeviceID) -> Result<Option<Device>, Error>;
-    fn get_user(&self, user_id: &UserID) -> Result<Option<User>, Error>;
-    fn get_user_by_email(&self, email: &str) -> Result<Option<User>, Error>;
-    fn get_user_devices(&self, user_id: &UserID) -> Result<Vec<Device>, Error>;
-
-    fn check_user_device_access(
+    async fn add_structure(&self, structure: &Structure) -> Result<(), Error>;
+    async fn add_room(&self, room: &Room) -> Result<(), Error>;
+    async fn add_device(&self, device: &Device) -> Result<(), Error>;
+    async fn add_user(&self, user: &User) -> Result<(), Error>;
+    async fn add_admin(&self, user_id: &UserID) -> Result<(), Error>;
+    async fn add_user_structure(&self, user_structure: &UserStructure) -> Result<(), Error>;
+
+    async fn get_structure(&self, structure_id: &StructureID) -> Result<Option<Structure>, Error>;
+    async fn get_room(&self, room_id: &RoomID) -> Result<Option<Room>, Error>;
+    async fn get_device(&self, device_id: &DeviceID) -> Result<Option<Device>, Error>;
+    async fn get_user(&self, user_id: &UserID) -> Result<Option<User>, Error>;
+    async fn get_user_by_email(&self, email: &str) -> Result<Option<User>, Error>;
+    /// Returns devices which the user has any role for
+    async fn get_user_devices(&self, user_id: &UserID) -> Result<Vec<Device>, Error>;
+    /// Returns users which have any role for the device
+    async fn get_device_users(&self, device_id: &DeviceID) -> Result<Vec<User>, Error>;
+    async fn get_user_structure(
         &self,
+        structure_id: &StructureID,
         user_id: &UserID,
+    ) -> Result<Option<UserStructure>, Error>;
+
+    async fn get_structures(&self) -> Result<Vec<Structure>, Error>;
+    async fn get_rooms(&self) -> Result<Vec<Room>, Error>;
+    async fn get_devices(&self) -> Result<Vec<Device>, Error>;
+    async fn get_users(&self) -> Result<Vec<User>, Error>;
+    async fn get_user_structures(&self) -> Result<Vec<UserStructure>, Error>;
+
+    /// Update functions return false if the row doesn't exist
+    async fn update_structure(&self, structure: &Structure) -> Result<bool, Error>;
+    async fn update_room(&self, room: &Room) -> Result<bool, Error>;
+    /// Replaces everything except the password hash, which is changed with `set_device_password_hash`
+    async fn update_device(&self, device: &Device) -> Result<bool, Error>;
+    /// Replaces everything except the password hash
+    async fn update_user(&self, user: &User) -> Result<bool, Error>;
+    async fn update_user_structure(&self, user_structure: &UserStructure) -> Result<bool, Error>;
+
+    /// Delete functions return false if the row doesn't exist, rows which refer to the deleted one are deleted too
+    async fn delete_structure(&self, structure_id: &StructureID) -> Result<bool, Error>;
+    async fn delete_room(&self, room_id: &RoomID) -> Result<bool, Error>;
+    async fn delete_device(&self, device_id: &DeviceID) -> Result<bool, Error>;
+    async fn delete_user(&self, user_id: &UserID) -> Result<bool, Error>;
+    async fn delete_user_structure(
+        &self,
+        structure_id: &StructureID,
+        user_id: &UserID,
+    ) -> Result<bool, Error>;
+
+    async fn set_device_state(
+        &self,
         device_id: &DeviceID,
+        snapshot: &DeviceStateSnapshot,
+    ) -> Result<(), Error>;
+    async fn get_device_state(
+        &self,
+        device_id: &DeviceID,
+    ) -> Result<Option<DeviceStateSnapshot>, Error>;
+
+    async fn add_device_state_history(
+        &self,
+        device_id: &DeviceID,
+        snapshot: &DeviceStateSnapshot,
+    ) -> Result<(), Error>;
+    /// Returns states reported in the `since..until` range, from the oldest to the newest
+    async fn get_device_state_history(
+        &self,
+        device_id: &DeviceID,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<DeviceStateSnapshot>, Error>;
+
+    async fn add_device_presence_event(
+        &self,
+        device_id: &DeviceID,
+        event: &DevicePresenceEvent,
+    ) -> Result<(), Error>;
+    async fn get_last_device_presence_event(
+        &self,
+        device_id: &DeviceID,
+    ) -> Result<Option<DevicePresenceEvent>, Error>;
+
+    /// Sets the only valid token of the device, which revokes the previously issued one
+    async fn set_device_token_id(
+        &self,
+        device_id: &DeviceID,
+        token_id: &DeviceTokenID,
+        issued_at: &DateTime<Utc>,
+    ) -> Result<(), Error>;
+    async fn get_device_token_id(
+        &self,
+        device_id: &DeviceID,
+    ) -> Result<Option<DeviceTokenID>, Error>;
+    /// Removes the token of the device, so no token issued before will be accepted
+    async fn remove_device_token_id(&self, device_id: &DeviceID) -> Result<bool, Error>;
+
+    /// Replaces password hash of the device, empty hash disables password authentication
+    async fn set_device_password_hash(
+        &self,
+        device_id: &DeviceID,
+        password_hash: &str,
     ) -> Result<bool, Error>;
 
-    fn check_user_admin(&self, user_id: &UserID) -> Result<bool, Error>;
+    /// Returns the role of the user for the device, device role takes precedence over the room role,
+    /// which takes precedence over the structure role. None means that the user has no access to the device.
+    async fn get_user_device_role(
+        &self,
+        user_id: &UserID,
+        device_id: &DeviceID,
+    ) -> Result<Option<Role>, Error>;
+    /// Returns the role of the user for the room, room role takes precedence over the structure role
+    async fn get_user_room_role(
+        &self,
+        user_id: &UserID,
+        room_id: &RoomID,
+    ) -> Result<Option<Role>, Error>;
+
+    /// Adds the scoped role, or replaces the role if the user has one for that scope already
+    async fn add_scoped_role(&self, scoped_role: &ScopedRole) -> Result<(), Error>;
+    async fn get_scoped_roles(&self) -> Result<Vec<ScopedRole>, Error>;
+    async fn delete_scoped_role(&self, user_id: &UserID, scope: &RoleScope) -> Result<bool, Error>;
+
+    async fn check_user_admin(&self, user_id: &UserID) -> Result<bool, Error>;
+    async fn get_admins(&self) -> Result<Vec<UserID>, Error>;
+    /// Returns true if the user has the manager role in the structure
+    async fn check_user_structure_manager(
+        &self,
+        user_id: &UserID,
+        structure_id: &StructureID,
+    ) -> Result<bool, Error>;
+
+    /// Records that the user unlinked their Google Home account
+    async fn add_google_home_unlink(
+        &self,
+        user_id: &UserID,
+        unlinked_at: &DateTime<Utc>,
+    ) -> Result<(), Error>;
+    /// Removes the unlink record after the user links their Google Home account again
+    async fn remove_google_home_unlink(&self, user_id: &UserID) -> Result<bool, Error>;
+    async fn check_google_home_unlinked(&self, user_id: &UserID) -> Result<bool, Error>;
+
+    /// Replaces the whole home configuration with the backup in a single transaction.
+    /// States and history of the devices aren't part of the backup, so they're removed too.
+    /// Users keep their current passwords if the backup is without secrets, see [Error::NoAdminPassword].
+    async fn restore(&self, backup: &Backup) -> Result<(), Error>;
+
+    /// Exports the home configuration including the password hashes and IDs of the device tokens,
+    /// all of the tables are read within a single transaction so the backup is a consistent snapshot
+    async fn backup(&self) -> Result<Backup, Error>;
+
+    /// Applies the changes of the plan in order within a single transaction, nothing is changed if any of them fails.
+    /// [Error::NotModified] is returned if an updated item doesn't exist anymore, deleting a missing item isn't an error.
+    async fn apply_home(&self, plan: &HomePlan) -> Result<(), Error>;
+}
+
+/// Opens the database backend selected in the server configuration,
+/// connecting to it and running the migrations happens on the blocking thread pool
+pub async fn open(
+    config: &houseflow_config::server::Config,
+) -> Result<std::sync::Arc<dyn Database>, Error> {
+    use houseflow_config::server::database::Config as DatabaseConfig;
+
+    match &config.database {
+        #[cfg(feature = "sqlite")]
+        DatabaseConfig::Sqlite => {
+            let path = config.database_path.clone();
+            let database = blocking::spawn_blocking(move || sqlite::Database::new(path)).await?;
+            Ok(std::sync::Arc::new(database))
+        }
+        #[cfg(feature = "postgres")]
+        DatabaseConfig::Postgres { url } => {
+            let url = url.clone();
+            let database = blocking::spawn_blocking(move || postgres::Database::new(&url)).await?;
+            Ok(std::sync::Arc::new(database))
+        }
+        #[allow(unreachable_patterns)]
+        DatabaseConfig::Sqlite => Err(Error::BackendNotEnabled("sqlite")),
+        #[allow(unreachable_patterns)]
+        DatabaseConfig::Postgres { .. } => Err(Error::BackendNotEnabled("postgres")),
+    }
+}
+
+/// Backups of the other versions must be converted before restoring them
+pub(crate) fn check_backup_version(backup: &Backup) -> Result<(), Error> {
+    match backup.version {
+        Backup::VERSION => Ok(()),
+        version => Err(Error::UnsupportedBackupVersion(version)),
+    }
+}
+
+/// Users to insert on restore. Backups without secrets keep the password hashes of the users which already exist,
+/// the other ones can't log in, so at least one of the admins must already exist.
+pub(crate) fn restored_users(backup: &Backup, existing: Vec<User>) -> Result<Vec<User>, Error> {
+    if backup.with_secrets {
+        return Ok(backup.users.clone());
+    }
+
+    let mut password_hashes = existing
+        .into_iter()
+        .map(|user| (user.id, user.password_hash))
+        .collect::<std::collections::HashMap<_, _>>();
+    let users = backup
+        .users
+        .iter()
+        .cloned()
+        .map(|mut user| {
+            match password_hashes.remove(&user.id) {
+                Some(password_hash) if user.password_hash.is_empty() => {
+                    user.password_hash = password_hash
+                }
+                _ => (),
+            };
+            user
+        })
+        .collect::<Vec<_>>();
+    if !users
+        .iter()
+        .any(|user| backup.admins.contains(&user.id) && !user.password_hash.is_empty())
+    {
+        return Err(Error::NoAdminPassword);
+    }
+
+    Ok(users)
+}
+
+#[cfg(feature = "refinery")]
+impl From<refinery::Error> for Error {
+    fn from(err: refinery::Error) -> Self {
+        Self::Refinery(Box::new(err))
+    }
 }
 
 impl From<Error> for houseflow_types::InternalServerError {
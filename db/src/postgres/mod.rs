@@ -0,0 +1,1256 @@
+use chrono::{DateTime, Utc};
+use houseflow_types::{
+    traits::DeviceStateSnapshot, Backup, Device, DeviceID, DevicePresenceEvent, DeviceTokenID,
+    DeviceTrait, GoogleHomeUnlink, HomeChange, HomeItem, HomePlan, IssuedDeviceToken, Role,
+    RoleScope, Room, RoomID, ScopedRole, Structure, StructureID, User, UserID, UserStructure,
+};
+use postgres::{Client, GenericClient, IsolationLevel, NoTls, Row};
+use r2d2_postgres::PostgresConnectionManager;
+use semver::Version;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct Database {
+    pool: Arc<Pool>,
+}
+
+/// Closing connections of the synchronous client blocks on its own runtime, which panics within async code,
+/// so the last reference to the pool drops it on a separate thread
+struct Pool(Option<r2d2::Pool<PostgresConnectionManager<NoTls>>>);
+
+impl std::ops::Deref for Pool {
+    type Target = r2d2::Pool<PostgresConnectionManager<NoTls>>;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref().unwrap()
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        if let Some(pool) = self.0.take() {
+            if let Err(err) = std::thread::spawn(move || drop(pool)).join() {
+                std::panic::resume_unwind(err)
+            }
+        }
+    }
+}
+
+use crate::Error;
+
+impl From<postgres::Error> for Error {
+    fn from(err: postgres::Error) -> Self {
+        use postgres::error::SqlState;
+
+        match err.code() {
+            Some(&SqlState::UNIQUE_VIOLATION) => Self::AlreadyExists,
+            Some(&SqlState::FOREIGN_KEY_VIOLATION) => Self::ReferenceNotFound,
+            _ => Self::Postgres(err),
+        }
+    }
+}
+
+mod embedded {
+    use refinery::embed_migrations;
+    embed_migrations!("./migrations/postgres");
+}
+
+impl Database {
+    /// Connects to the database using connection string, e.g `postgres://houseflow@localhost/houseflow`.
+    /// Synchronous client blocks on its own runtime, so it must not be called from async code, use [crate::open] there.
+    pub fn new(url: &str) -> Result<Self, Error> {
+        Self::with_config(url.parse()?)
+    }
+
+    pub fn with_config(config: postgres::Config) -> Result<Self, Error> {
+        let manager = PostgresConnectionManager::new(config, NoTls);
+        // Connections are opened when needed instead of taking all slots of the pool at startup
+        let pool = r2d2::Pool::builder().min_idle(Some(1)).build(manager)?;
+        let mut connection = pool.get()?;
+        embedded::migrations::runner().run(&mut *connection)?;
+        Ok(Self {
+            pool: Arc::new(Pool(Some(pool))),
+        })
+    }
+
+    fn with_client<T>(&self, f: impl FnOnce(&mut Client) -> Result<T, Error>) -> Result<T, Error> {
+        let mut connection = self.pool.get()?;
+        f(&mut connection)
+    }
+}
+
+fn get_traits(
+    client: &mut impl GenericClient,
+    device_id: &DeviceID,
+) -> Result<Vec<DeviceTrait>, Error> {
+    const SQL: &str = "SELECT trait_name FROM device_traits WHERE device_id = $1";
+    client
+        .query(SQL, &[device_id])?
+        .iter()
+        .map(|row| Ok(row.try_get("trait_name")?))
+        .collect()
+}
+
+fn device_from_row(row: &Row, traits: Vec<DeviceTrait>) -> Result<Device, Error> {
+    Ok(Device {
+        id: row.try_get("id")?,
+        room_id: row.try_get("room_id")?,
+        password_hash: row.try_get("password_hash")?,
+        device_type: row.try_get("type")?,
+        traits,
+        name: row.try_get("name")?,
+        will_push_state: row.try_get("will_push_state")?,
+        model: row.try_get("model")?,
+        hw_version: Version::parse(row.try_get("hw_version")?)
+            .map_err(|err| Error::InvalidColumn(err.to_string()))?,
+        sw_version: Version::parse(row.try_get("sw_version")?)
+            .map_err(|err| Error::InvalidColumn(err.to_string()))?,
+        attributes: serde_json::from_str(row.try_get("attributes")?)?,
+    })
+}
+
+fn devices_from_rows(
+    client: &mut impl GenericClient,
+    rows: Vec<Row>,
+) -> Result<Vec<Device>, Error> {
+    rows.iter()
+        .map(|row| {
+            let traits = get_traits(client, &row.try_get("id")?)?;
+            device_from_row(row, traits)
+        })
+        .collect()
+}
+
+fn user_from_row(row: &Row) -> Result<User, Error> {
+    Ok(User {
+        id: row.try_get("id")?,
+        username: row.try_get("username")?,
+        email: row.try_get("email")?,
+        password_hash: row.try_get("password_hash")?,
+    })
+}
+
+fn structure_from_row(row: &Row) -> Result<Structure, Error> {
+    Ok(Structure {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+    })
+}
+
+fn room_from_row(row: &Row) -> Result<Room, Error> {
+    Ok(Room {
+        id: row.try_get("id")?,
+        structure_id: row.try_get("structure_id")?,
+        name: row.try_get("name")?,
+    })
+}
+
+fn user_structure_from_row(row: &Row) -> Result<UserStructure, Error> {
+    Ok(UserStructure {
+        structure_id: row.try_get("structure_id")?,
+        user_id: row.try_get("user_id")?,
+        role: row.try_get("role")?,
+    })
+}
+
+fn snapshot_from_row(row: &Row) -> Result<DeviceStateSnapshot, Error> {
+    Ok(DeviceStateSnapshot {
+        state: serde_json::from_str(row.try_get("state")?)?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+fn insert_structure(client: &mut impl GenericClient, structure: &Structure) -> Result<(), Error> {
+    const SQL: &str = "INSERT INTO structures(id, name) VALUES($1, $2)";
+    let n = client.execute(SQL, &[&structure.id, &structure.name])?;
+    match n {
+        0 => Err(Error::NotModified),
+        1 => Ok(()),
+        _ => unreachable!(),
+    }
+}
+
+fn insert_room(client: &mut impl GenericClient, room: &Room) -> Result<(), Error> {
+    const SQL: &str = "INSERT INTO rooms(id, structure_id, name) VALUES($1, $2, $3)";
+    let n = client.execute(SQL, &[&room.id, &room.structure_id, &room.name])?;
+    match n {
+        0 => Err(Error::NotModified),
+        1 => Ok(()),
+        _ => unreachable!(),
+    }
+}
+
+/// Inserts the device with its traits, it should be called within a transaction
+fn insert_device(client: &mut impl GenericClient, device: &Device) -> Result<(), Error> {
+    const INSERT_DEVICE_SQL: &str = "INSERT INTO
+        devices(id, room_id, password_hash, type, name, will_push_state, model, hw_version, sw_version, attributes)
+        VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)";
+    const INSERT_TRAIT_SQL: &str =
+        "INSERT INTO device_traits(device_id, trait_name) VALUES($1, $2)";
+
+    let n = client.execute(
+        INSERT_DEVICE_SQL,
+        &[
+            &device.id,
+            &device.room_id,
+            &device.password_hash,
+            &device.device_type,
+            &device.name,
+            &device.will_push_state,
+            &device.model,
+            &device.hw_version.to_string(),
+            &device.sw_version.to_string(),
+            &serde_json::to_string(&device.attributes)?,
+        ],
+    )?;
+    if n == 0 {
+        return Err(Error::NotModified);
+    }
+    for device_trait in &device.traits {
+        let n = client.execute(INSERT_TRAIT_SQL, &[&device.id, device_trait])?;
+        if n == 0 {
+            return Err(Error::NotModified);
+        }
+    }
+    Ok(())
+}
+
+fn insert_user(client: &mut impl GenericClient, user: &User) -> Result<(), Error> {
+    const SQL: &str =
+        "INSERT INTO users(id, username, email, password_hash) VALUES($1, $2, $3, $4)";
+    let n = client.execute(
+        SQL,
+        &[&user.id, &user.username, &user.email, &user.password_hash],
+    )?;
+    match n {
+        0 => Err(Error::NotModified),
+        1 => Ok(()),
+        _ => unreachable!(),
+    }
+}
+
+fn insert_admin(client: &mut impl GenericClient, user_id: &UserID) -> Result<(), Error> {
+    const SQL: &str = "INSERT INTO admins(user_id) VALUES($1)";
+    let n = client.execute(SQL, &[user_id])?;
+    match n {
+        0 => Err(Error::NotModified),
+        1 => Ok(()),
+        _ => unreachable!(),
+    }
+}
+
+fn insert_user_structure(
+    client: &mut impl GenericClient,
+    user_structure: &UserStructure,
+) -> Result<(), Error> {
+    const SQL: &str = "INSERT INTO user_structures(structure_id, user_id, role) VALUES($1, $2, $3)";
+    let n = client.execute(
+        SQL,
+        &[
+            &user_structure.structure_id,
+            &user_structure.user_id,
+            &user_structure.role,
+        ],
+    )?;
+    match n {
+        0 => Err(Error::NotModified),
+        1 => Ok(()),
+        _ => unreachable!(),
+    }
+}
+
+fn insert_scoped_role(
+    client: &mut impl GenericClient,
+    scoped_role: &ScopedRole,
+) -> Result<(), Error> {
+    const ROOM_SQL: &str = "
+        INSERT INTO user_room_roles(user_id, room_id, role) VALUES($1, $2, $3)
+        ON CONFLICT (user_id, room_id) DO UPDATE SET role = EXCLUDED.role
+        ";
+    const DEVICE_SQL: &str = "
+        INSERT INTO user_device_roles(user_id, device_id, role) VALUES($1, $2, $3)
+        ON CONFLICT (user_id, device_id) DO UPDATE SET role = EXCLUDED.role
+        ";
+    let n = match &scoped_role.scope {
+        RoleScope::Room(room_id) => client.execute(
+            ROOM_SQL,
+            &[&scoped_role.user_id, room_id, &scoped_role.role],
+        )?,
+        RoleScope::Device(device_id) => client.execute(
+            DEVICE_SQL,
+            &[&scoped_role.user_id, device_id, &scoped_role.role],
+        )?,
+    };
+    match n {
+        0 => Err(Error::NotModified),
+        _ => Ok(()),
+    }
+}
+
+fn insert_device_token(
+    client: &mut impl GenericClient,
+    device_token: &IssuedDeviceToken,
+) -> Result<(), Error> {
+    const SQL: &str =
+        "INSERT INTO device_tokens(device_id, token_id, issued_at) VALUES($1, $2, $3)";
+    client.execute(
+        SQL,
+        &[
+            &device_token.device_id,
+            &device_token.token_id,
+            &device_token.issued_at,
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_google_home_unlink(
+    client: &mut impl GenericClient,
+    unlink: &GoogleHomeUnlink,
+) -> Result<(), Error> {
+    const SQL: &str = "INSERT INTO google_home_unlinks(user_id, unlinked_at) VALUES($1, $2)";
+    client.execute(SQL, &[&unlink.user_id, &unlink.unlinked_at])?;
+    Ok(())
+}
+
+fn update_structure(client: &mut impl GenericClient, structure: &Structure) -> Result<bool, Error> {
+    const SQL: &str = "UPDATE structures SET name = $1 WHERE id = $2";
+    let n = client.execute(SQL, &[&structure.name, &structure.id])?;
+    Ok(n > 0)
+}
+
+fn update_room(client: &mut impl GenericClient, room: &Room) -> Result<bool, Error> {
+    const SQL: &str = "UPDATE rooms SET structure_id = $1, name = $2 WHERE id = $3";
+    let n = client.execute(SQL, &[&room.structure_id, &room.name, &room.id])?;
+    Ok(n > 0)
+}
+
+/// Traits are replaced with separate statements, so it should be called within a transaction
+fn update_device(client: &mut impl GenericClient, device: &Device) -> Result<bool, Error> {
+    const UPDATE_DEVICE_SQL: &str = "UPDATE devices
+        SET room_id = $1, type = $2, name = $3, will_push_state = $4, model = $5, hw_version = $6, sw_version = $7, attributes = $8
+        WHERE id = $9";
+    const DELETE_TRAITS_SQL: &str = "DELETE FROM device_traits WHERE device_id = $1";
+    const INSERT_TRAIT_SQL: &str =
+        "INSERT INTO device_traits(device_id, trait_name) VALUES($1, $2)";
+
+    let n = client.execute(
+        UPDATE_DEVICE_SQL,
+        &[
+            &device.room_id,
+            &device.device_type,
+            &device.name,
+            &device.will_push_state,
+            &device.model,
+            &device.hw_version.to_string(),
+            &device.sw_version.to_string(),
+            &serde_json::to_string(&device.attributes)?,
+            &device.id,
+        ],
+    )?;
+    if n == 0 {
+        return Ok(false);
+    }
+    client.execute(DELETE_TRAITS_SQL, &[&device.id])?;
+    for device_trait in &device.traits {
+        let n = client.execute(INSERT_TRAIT_SQL, &[&device.id, device_trait])?;
+        if n == 0 {
+            return Err(Error::NotModified);
+        }
+    }
+    Ok(true)
+}
+
+fn update_user_structure(
+    client: &mut impl GenericClient,
+    user_structure: &UserStructure,
+) -> Result<bool, Error> {
+    const SQL: &str =
+        "UPDATE user_structures SET role = $1 WHERE structure_id = $2 AND user_id = $3";
+    let n = client.execute(
+        SQL,
+        &[
+            &user_structure.role,
+            &user_structure.structure_id,
+            &user_structure.user_id,
+        ],
+    )?;
+    Ok(n > 0)
+}
+
+fn delete_structure(
+    client: &mut impl GenericClient,
+    structure_id: &StructureID,
+) -> Result<bool, Error> {
+    const SQL: &str = "DELETE FROM structures WHERE id = $1";
+    let n = client.execute(SQL, &[structure_id])?;
+    Ok(n > 0)
+}
+
+fn delete_room(client: &mut impl GenericClient, room_id: &RoomID) -> Result<bool, Error> {
+    const SQL: &str = "DELETE FROM rooms WHERE id = $1";
+    let n = client.execute(SQL, &[room_id])?;
+    Ok(n > 0)
+}
+
+fn delete_device(client: &mut impl GenericClient, device_id: &DeviceID) -> Result<bool, Error> {
+    const SQL: &str = "DELETE FROM devices WHERE id = $1";
+    let n = client.execute(SQL, &[device_id])?;
+    Ok(n > 0)
+}
+
+fn delete_user_structure(
+    client: &mut impl GenericClient,
+    structure_id: &StructureID,
+    user_id: &UserID,
+) -> Result<bool, Error> {
+    const SQL: &str = "DELETE FROM user_structures WHERE structure_id = $1 AND user_id = $2";
+    let n = client.execute(SQL, &[structure_id, user_id])?;
+    Ok(n > 0)
+}
+
+fn apply_home_change(client: &mut impl GenericClient, change: &HomeChange) -> Result<(), Error> {
+    match change {
+        HomeChange::Create { item } => match item {
+            HomeItem::Structure(structure) => insert_structure(client, structure),
+            HomeItem::Room(room) => insert_room(client, room),
+            HomeItem::Device(device) => insert_device(client, device),
+            HomeItem::Member { user_structure, .. } => {
+                insert_user_structure(client, user_structure)
+            }
+        },
+        HomeChange::Update { after, .. } => {
+            let updated = match after {
+                HomeItem::Structure(structure) => update_structure(client, structure)?,
+                HomeItem::Room(room) => update_room(client, room)?,
+                HomeItem::Device(device) => update_device(client, device)?,
+                HomeItem::Member { user_structure, .. } => {
+                    update_user_structure(client, user_structure)?
+                }
+            };
+            // Item has been deleted since the plan was made
+            match updated {
+                true => Ok(()),
+                false => Err(Error::NotModified),
+            }
+        }
+        // Item could have been deleted already, which is fine
+        HomeChange::Delete { item } => {
+            match item {
+                HomeItem::Structure(structure) => delete_structure(client, &structure.id)?,
+                HomeItem::Room(room) => delete_room(client, &room.id)?,
+                HomeItem::Device(device) => delete_device(client, &device.id)?,
+                HomeItem::Member { user_structure, .. } => delete_user_structure(
+                    client,
+                    &user_structure.structure_id,
+                    &user_structure.user_id,
+                )?,
+            };
+            Ok(())
+        }
+    }
+}
+
+fn select_structures(client: &mut impl GenericClient) -> Result<Vec<Structure>, Error> {
+    const SQL: &str = "SELECT * FROM structures";
+    client
+        .query(SQL, &[])?
+        .iter()
+        .map(structure_from_row)
+        .collect()
+}
+
+fn select_rooms(client: &mut impl GenericClient) -> Result<Vec<Room>, Error> {
+    const SQL: &str = "SELECT * FROM rooms";
+    client.query(SQL, &[])?.iter().map(room_from_row).collect()
+}
+
+fn select_devices(client: &mut impl GenericClient) -> Result<Vec<Device>, Error> {
+    const SQL: &str = "SELECT * FROM devices";
+    let rows = client.query(SQL, &[])?;
+    devices_from_rows(client, rows)
+}
+
+fn select_users(client: &mut impl GenericClient) -> Result<Vec<User>, Error> {
+    const SQL: &str = "SELECT * FROM users";
+    client.query(SQL, &[])?.iter().map(user_from_row).collect()
+}
+
+fn select_user_structures(client: &mut impl GenericClient) -> Result<Vec<UserStructure>, Error> {
+    const SQL: &str = "SELECT * FROM user_structures";
+    client
+        .query(SQL, &[])?
+        .iter()
+        .map(user_structure_from_row)
+        .collect()
+}
+
+fn select_scoped_roles(client: &mut impl GenericClient) -> Result<Vec<ScopedRole>, Error> {
+    const ROOM_SQL: &str = "SELECT * FROM user_room_roles";
+    const DEVICE_SQL: &str = "SELECT * FROM user_device_roles";
+    let room_roles = client.query(ROOM_SQL, &[])?;
+    let device_roles = client.query(DEVICE_SQL, &[])?;
+    let room_roles = room_roles.iter().map(|row| {
+        Ok(ScopedRole {
+            user_id: row.try_get("user_id")?,
+            scope: RoleScope::Room(row.try_get("room_id")?),
+            role: row.try_get("role")?,
+        })
+    });
+    let device_roles = device_roles.iter().map(|row| {
+        Ok(ScopedRole {
+            user_id: row.try_get("user_id")?,
+            scope: RoleScope::Device(row.try_get("device_id")?),
+            role: row.try_get("role")?,
+        })
+    });
+    room_roles.chain(device_roles).collect()
+}
+
+fn select_admins(client: &mut impl GenericClient) -> Result<Vec<UserID>, Error> {
+    const SQL: &str = "SELECT user_id FROM admins";
+    client
+        .query(SQL, &[])?
+        .iter()
+        .map(|row| Ok(row.try_get("user_id")?))
+        .collect()
+}
+
+fn select_device_tokens(client: &mut impl GenericClient) -> Result<Vec<IssuedDeviceToken>, Error> {
+    const SQL: &str = "SELECT * FROM device_tokens";
+    client
+        .query(SQL, &[])?
+        .iter()
+        .map(|row| {
+            Ok(IssuedDeviceToken {
+                device_id: row.try_get("device_id")?,
+                token_id: row.try_get("token_id")?,
+                issued_at: row.try_get("issued_at")?,
+            })
+        })
+        .collect()
+}
+
+fn select_google_home_unlinks(
+    client: &mut impl GenericClient,
+) -> Result<Vec<GoogleHomeUnlink>, Error> {
+    const SQL: &str = "SELECT * FROM google_home_unlinks";
+    client
+        .query(SQL, &[])?
+        .iter()
+        .map(|row| {
+            Ok(GoogleHomeUnlink {
+                user_id: row.try_get("user_id")?,
+                unlinked_at: row.try_get("unlinked_at")?,
+            })
+        })
+        .collect()
+}
+
+impl crate::blocking::Database for Database {
+    fn add_structure(&self, structure: &Structure) -> Result<(), Error> {
+        self.with_client(|client| insert_structure(client, structure))
+    }
+
+    fn add_room(&self, room: &Room) -> Result<(), Error> {
+        self.with_client(|client| insert_room(client, room))
+    }
+
+    fn add_device(&self, device: &Device) -> Result<(), Error> {
+        self.with_client(|client| {
+            let mut tx = client.transaction()?;
+            insert_device(&mut tx, device)?;
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    fn add_user(&self, user: &User) -> Result<(), Error> {
+        self.with_client(|client| insert_user(client, user))
+    }
+
+    fn add_admin(&self, user_id: &UserID) -> Result<(), Error> {
+        self.with_client(|client| insert_admin(client, user_id))
+    }
+
+    fn add_user_structure(&self, user_structure: &UserStructure) -> Result<(), Error> {
+        self.with_client(|client| insert_user_structure(client, user_structure))
+    }
+
+    fn get_structure(&self, structure_id: &StructureID) -> Result<Option<Structure>, Error> {
+        const SQL: &str = "SELECT * FROM structures WHERE id = $1";
+        self.with_client(|client| {
+            client
+                .query_opt(SQL, &[structure_id])?
+                .map(|row| structure_from_row(&row))
+                .transpose()
+        })
+    }
+
+    fn get_room(&self, room_id: &RoomID) -> Result<Option<Room>, Error> {
+        const SQL: &str = "SELECT * FROM rooms WHERE id = $1";
+        self.with_client(|client| {
+            client
+                .query_opt(SQL, &[room_id])?
+                .map(|row| room_from_row(&row))
+                .transpose()
+        })
+    }
+
+    fn get_device(&self, device_id: &DeviceID) -> Result<Option<Device>, Error> {
+        const SQL: &str = "SELECT * FROM devices WHERE id = $1";
+        self.with_client(|client| {
+            let row = match client.query_opt(SQL, &[device_id])? {
+                Some(row) => row,
+                None => return Ok(None),
+            };
+            let traits = get_traits(client, device_id)?;
+            device_from_row(&row, traits).map(Some)
+        })
+    }
+
+    fn get_user(&self, user_id: &UserID) -> Result<Option<User>, Error> {
+        const SQL: &str = "SELECT * FROM users WHERE id = $1";
+        self.with_client(|client| {
+            client
+                .query_opt(SQL, &[user_id])?
+                .map(|row| user_from_row(&row))
+                .transpose()
+        })
+    }
+
+    fn get_user_by_email(&self, email: &str) -> Result<Option<User>, Error> {
+        const SQL: &str = "SELECT * FROM users WHERE email = $1";
+        self.with_client(|client| {
+            client
+                .query_opt(SQL, &[&email])?
+                .map(|row| user_from_row(&row))
+                .transpose()
+        })
+    }
+
+    fn get_user_devices(&self, user_id: &UserID) -> Result<Vec<Device>, Error> {
+        const SQL: &str = "
+            SELECT *
+            FROM devices
+            WHERE id IN (
+                SELECT devices.id
+                FROM devices
+                JOIN rooms ON rooms.id = devices.room_id
+                JOIN user_structures ON user_structures.structure_id = rooms.structure_id
+                WHERE user_structures.user_id = $1
+                UNION
+                SELECT devices.id
+                FROM devices
+                JOIN user_room_roles ON user_room_roles.room_id = devices.room_id
+                WHERE user_room_roles.user_id = $1
+                UNION
+                SELECT device_id
+                FROM user_device_roles
+                WHERE user_id = $1
+            )";
+        self.with_client(|client| {
+            let rows = client.query(SQL, &[user_id])?;
+            devices_from_rows(client, rows)
+        })
+    }
+
+    fn get_device_users(&self, device_id: &DeviceID) -> Result<Vec<User>, Error> {
+        const SQL: &str = "
+            SELECT *
+            FROM users
+            WHERE id IN (
+                SELECT user_structures.user_id
+                FROM user_structures
+                JOIN rooms ON rooms.structure_id = user_structures.structure_id
+                JOIN devices ON devices.room_id = rooms.id
+                WHERE devices.id = $1
+                UNION
+                SELECT user_room_roles.user_id
+                FROM user_room_roles
+                JOIN devices ON devices.room_id = user_room_roles.room_id
+                WHERE devices.id = $1
+                UNION
+                SELECT user_id
+                FROM user_device_roles
+                WHERE device_id = $1
+            )";
+        self.with_client(|client| {
+            client
+                .query(SQL, &[device_id])?
+                .iter()
+                .map(user_from_row)
+                .collect()
+        })
+    }
+
+    fn get_user_structure(
+        &self,
+        structure_id: &StructureID,
+        user_id: &UserID,
+    ) -> Result<Option<UserStructure>, Error> {
+        const SQL: &str = "SELECT * FROM user_structures WHERE structure_id = $1 AND user_id = $2";
+        self.with_client(|client| {
+            client
+                .query_opt(SQL, &[structure_id, user_id])?
+                .map(|row| user_structure_from_row(&row))
+                .transpose()
+        })
+    }
+
+    fn get_structures(&self) -> Result<Vec<Structure>, Error> {
+        self.with_client(select_structures)
+    }
+
+    fn get_rooms(&self) -> Result<Vec<Room>, Error> {
+        self.with_client(select_rooms)
+    }
+
+    fn get_devices(&self) -> Result<Vec<Device>, Error> {
+        self.with_client(select_devices)
+    }
+
+    fn get_users(&self) -> Result<Vec<User>, Error> {
+        self.with_client(select_users)
+    }
+
+    fn get_user_structures(&self) -> Result<Vec<UserStructure>, Error> {
+        self.with_client(select_user_structures)
+    }
+
+    fn update_structure(&self, structure: &Structure) -> Result<bool, Error> {
+        self.with_client(|client| update_structure(client, structure))
+    }
+
+    fn update_room(&self, room: &Room) -> Result<bool, Error> {
+        self.with_client(|client| update_room(client, room))
+    }
+
+    fn update_device(&self, device: &Device) -> Result<bool, Error> {
+        self.with_client(|client| {
+            let mut tx = client.transaction()?;
+            let updated = update_device(&mut tx, device)?;
+            tx.commit()?;
+            Ok(updated)
+        })
+    }
+
+    fn update_user(&self, user: &User) -> Result<bool, Error> {
+        const SQL: &str = "UPDATE users SET username = $1, email = $2 WHERE id = $3";
+        self.with_client(|client| {
+            let n = client.execute(SQL, &[&user.username, &user.email, &user.id])?;
+            Ok(n > 0)
+        })
+    }
+
+    fn update_user_structure(&self, user_structure: &UserStructure) -> Result<bool, Error> {
+        self.with_client(|client| update_user_structure(client, user_structure))
+    }
+
+    fn delete_structure(&self, structure_id: &StructureID) -> Result<bool, Error> {
+        self.with_client(|client| delete_structure(client, structure_id))
+    }
+
+    fn delete_room(&self, room_id: &RoomID) -> Result<bool, Error> {
+        self.with_client(|client| delete_room(client, room_id))
+    }
+
+    fn delete_device(&self, device_id: &DeviceID) -> Result<bool, Error> {
+        self.with_client(|client| delete_device(client, device_id))
+    }
+
+    fn delete_user(&self, user_id: &UserID) -> Result<bool, Error> {
+        // admins table doesn't cascade deletes of the user
+        const DELETE_ADMIN_SQL: &str = "DELETE FROM admins WHERE user_id = $1";
+        const DELETE_USER_SQL: &str = "DELETE FROM users WHERE id = $1";
+
+        self.with_client(|client| {
+            let mut tx = client.transaction()?;
+            tx.execute(DELETE_ADMIN_SQL, &[user_id])?;
+            let n = tx.execute(DELETE_USER_SQL, &[user_id])?;
+            tx.commit()?;
+            Ok(n > 0)
+        })
+    }
+
+    fn delete_user_structure(
+        &self,
+        structure_id: &StructureID,
+        user_id: &UserID,
+    ) -> Result<bool, Error> {
+        self.with_client(|client| delete_user_structure(client, structure_id, user_id))
+    }
+
+    fn set_device_state(
+        &self,
+        device_id: &DeviceID,
+        snapshot: &DeviceStateSnapshot,
+    ) -> Result<(), Error> {
+        const SQL: &str = "
+            INSERT INTO device_states(device_id, state, updated_at) VALUES($1, $2, $3)
+            ON CONFLICT (device_id) DO UPDATE SET state = EXCLUDED.state, updated_at = EXCLUDED.updated_at
+            ";
+        self.with_client(|client| {
+            let n = client.execute(
+                SQL,
+                &[
+                    device_id,
+                    &serde_json::to_string(&snapshot.state)?,
+                    &snapshot.updated_at,
+                ],
+            )?;
+            match n {
+                0 => Err(Error::NotModified),
+                1 => Ok(()),
+                _ => unreachable!(),
+            }
+        })
+    }
+
+    fn get_device_state(&self, device_id: &DeviceID) -> Result<Option<DeviceStateSnapshot>, Error> {
+        const SQL: &str = "SELECT * FROM device_states WHERE device_id = $1";
+        self.with_client(|client| {
+            client
+                .query_opt(SQL, &[device_id])?
+                .map(|row| snapshot_from_row(&row))
+                .transpose()
+        })
+    }
+
+    fn add_device_state_history(
+        &self,
+        device_id: &DeviceID,
+        snapshot: &DeviceStateSnapshot,
+    ) -> Result<(), Error> {
+        const SQL: &str =
+            "INSERT INTO device_state_history(device_id, state, updated_at) VALUES($1, $2, $3)";
+        self.with_client(|client| {
+            let n = client.execute(
+                SQL,
+                &[
+                    device_id,
+                    &serde_json::to_string(&snapshot.state)?,
+                    &snapshot.updated_at,
+                ],
+            )?;
+            match n {
+                0 => Err(Error::NotModified),
+                1 => Ok(()),
+                _ => unreachable!(),
+            }
+        })
+    }
+
+    fn get_device_state_history(
+        &self,
+        device_id: &DeviceID,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<DeviceStateSnapshot>, Error> {
+        const SQL: &str = "
+            SELECT state, updated_at
+            FROM device_state_history
+            WHERE device_id = $1
+            AND ($2::TIMESTAMPTZ IS NULL OR updated_at >= $2)
+            AND ($3::TIMESTAMPTZ IS NULL OR updated_at < $3)
+            ORDER BY updated_at ASC
+            LIMIT $4 OFFSET $5
+            ";
+        self.with_client(|client| {
+            client
+                .query(
+                    SQL,
+                    &[
+                        device_id,
+                        &since,
+                        &until,
+                        &i64::from(limit),
+                        &i64::from(offset),
+                    ],
+                )?
+                .iter()
+                .map(snapshot_from_row)
+                .collect()
+        })
+    }
+
+    fn add_device_presence_event(
+        &self,
+        device_id: &DeviceID,
+        event: &DevicePresenceEvent,
+    ) -> Result<(), Error> {
+        const SQL: &str = "INSERT INTO device_presence_events(device_id, connected, address, occurred_at) VALUES($1, $2, $3, $4)";
+        self.with_client(|client| {
+            client.execute(
+                SQL,
+                &[
+                    device_id,
+                    &event.connected,
+                    &event.address.to_string(),
+                    &event.occurred_at,
+                ],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn get_last_device_presence_event(
+        &self,
+        device_id: &DeviceID,
+    ) -> Result<Option<DevicePresenceEvent>, Error> {
+        const SQL: &str = "
+            SELECT *
+            FROM device_presence_events
+            WHERE device_id = $1
+            ORDER BY occurred_at DESC
+            LIMIT 1
+            ";
+        self.with_client(|client| {
+            client
+                .query_opt(SQL, &[device_id])?
+                .map(|row| {
+                    Ok(DevicePresenceEvent {
+                        connected: row.try_get("connected")?,
+                        address: row.try_get::<_, &str>("address")?.parse().map_err(
+                            |err: std::net::AddrParseError| Error::InvalidColumn(err.to_string()),
+                        )?,
+                        occurred_at: row.try_get("occurred_at")?,
+                    })
+                })
+                .transpose()
+        })
+    }
+
+    fn set_device_token_id(
+        &self,
+        device_id: &DeviceID,
+        token_id: &DeviceTokenID,
+        issued_at: &DateTime<Utc>,
+    ) -> Result<(), Error> {
+        const SQL: &str = "
+            INSERT INTO device_tokens(device_id, token_id, issued_at) VALUES($1, $2, $3)
+            ON CONFLICT (device_id) DO UPDATE SET token_id = EXCLUDED.token_id, issued_at = EXCLUDED.issued_at
+            ";
+        self.with_client(|client| {
+            client.execute(SQL, &[device_id, token_id, issued_at])?;
+            Ok(())
+        })
+    }
+
+    fn get_device_token_id(&self, device_id: &DeviceID) -> Result<Option<DeviceTokenID>, Error> {
+        const SQL: &str = "SELECT token_id FROM device_tokens WHERE device_id = $1";
+        self.with_client(|client| {
+            client
+                .query_opt(SQL, &[device_id])?
+                .map(|row| Ok(row.try_get("token_id")?))
+                .transpose()
+        })
+    }
+
+    fn remove_device_token_id(&self, device_id: &DeviceID) -> Result<bool, Error> {
+        const SQL: &str = "DELETE FROM device_tokens WHERE device_id = $1";
+        self.with_client(|client| {
+            let n = client.execute(SQL, &[device_id])?;
+            Ok(n > 0)
+        })
+    }
+
+    fn set_device_password_hash(
+        &self,
+        device_id: &DeviceID,
+        password_hash: &str,
+    ) -> Result<bool, Error> {
+        const SQL: &str = "UPDATE devices SET password_hash = $1 WHERE id = $2";
+        self.with_client(|client| {
+            let n = client.execute(SQL, &[&password_hash, device_id])?;
+            Ok(n > 0)
+        })
+    }
+
+    fn get_user_device_role(
+        &self,
+        user_id: &UserID,
+        device_id: &DeviceID,
+    ) -> Result<Option<Role>, Error> {
+        const SQL: &str = "
+            SELECT COALESCE(
+                (
+                    SELECT role
+                    FROM user_device_roles
+                    WHERE user_id = $1 AND device_id = $2
+                ),
+                (
+                    SELECT user_room_roles.role
+                    FROM user_room_roles
+                    JOIN devices ON devices.room_id = user_room_roles.room_id
+                    WHERE user_room_roles.user_id = $1 AND devices.id = $2
+                ),
+                (
+                    SELECT user_structures.role
+                    FROM user_structures
+                    JOIN rooms ON rooms.structure_id = user_structures.structure_id
+                    JOIN devices ON devices.room_id = rooms.id
+                    WHERE user_structures.user_id = $1 AND devices.id = $2
+                )
+            ) AS role
+            ";
+        self.with_client(|client| {
+            let row = client.query_one(SQL, &[user_id, device_id])?;
+            Ok(row.try_get("role")?)
+        })
+    }
+
+    fn get_user_room_role(
+        &self,
+        user_id: &UserID,
+        room_id: &RoomID,
+    ) -> Result<Option<Role>, Error> {
+        const SQL: &str = "
+            SELECT COALESCE(
+                (
+                    SELECT role
+                    FROM user_room_roles
+                    WHERE user_id = $1 AND room_id = $2
+                ),
+                (
+                    SELECT user_structures.role
+                    FROM user_structures
+                    JOIN rooms ON rooms.structure_id = user_structures.structure_id
+                    WHERE user_structures.user_id = $1 AND rooms.id = $2
+                )
+            ) AS role
+            ";
+        self.with_client(|client| {
+            let row = client.query_one(SQL, &[user_id, room_id])?;
+            Ok(row.try_get("role")?)
+        })
+    }
+
+    fn add_scoped_role(&self, scoped_role: &ScopedRole) -> Result<(), Error> {
+        self.with_client(|client| insert_scoped_role(client, scoped_role))
+    }
+
+    fn get_scoped_roles(&self) -> Result<Vec<ScopedRole>, Error> {
+        self.with_client(select_scoped_roles)
+    }
+
+    fn delete_scoped_role(&self, user_id: &UserID, scope: &RoleScope) -> Result<bool, Error> {
+        const ROOM_SQL: &str = "DELETE FROM user_room_roles WHERE user_id = $1 AND room_id = $2";
+        const DEVICE_SQL: &str =
+            "DELETE FROM user_device_roles WHERE user_id = $1 AND device_id = $2";
+        self.with_client(|client| {
+            let n = match scope {
+                RoleScope::Room(room_id) => client.execute(ROOM_SQL, &[user_id, room_id])?,
+                RoleScope::Device(device_id) => {
+                    client.execute(DEVICE_SQL, &[user_id, device_id])?
+                }
+            };
+            Ok(n > 0)
+        })
+    }
+
+    fn check_user_admin(&self, user_id: &UserID) -> Result<bool, Error> {
+        const SQL: &str = "SELECT 1 FROM admins WHERE user_id = $1";
+        self.with_client(|client| Ok(client.query_opt(SQL, &[user_id])?.is_some()))
+    }
+
+    fn get_admins(&self) -> Result<Vec<UserID>, Error> {
+        self.with_client(select_admins)
+    }
+
+    fn check_user_structure_manager(
+        &self,
+        user_id: &UserID,
+        structure_id: &StructureID,
+    ) -> Result<bool, Error> {
+        const SQL: &str = "
+            SELECT 1
+            FROM user_structures
+            WHERE user_id = $1
+            AND structure_id = $2
+            AND role = 'manager'
+            ";
+        self.with_client(|client| Ok(client.query_opt(SQL, &[user_id, structure_id])?.is_some()))
+    }
+
+    fn add_google_home_unlink(
+        &self,
+        user_id: &UserID,
+        unlinked_at: &DateTime<Utc>,
+    ) -> Result<(), Error> {
+        const SQL: &str = "
+            INSERT INTO google_home_unlinks(user_id, unlinked_at) VALUES($1, $2)
+            ON CONFLICT (user_id) DO UPDATE SET unlinked_at = EXCLUDED.unlinked_at
+            ";
+        self.with_client(|client| {
+            client.execute(SQL, &[user_id, unlinked_at])?;
+            Ok(())
+        })
+    }
+
+    fn remove_google_home_unlink(&self, user_id: &UserID) -> Result<bool, Error> {
+        const SQL: &str = "DELETE FROM google_home_unlinks WHERE user_id = $1";
+        self.with_client(|client| {
+            let n = client.execute(SQL, &[user_id])?;
+            Ok(n > 0)
+        })
+    }
+
+    fn check_google_home_unlinked(&self, user_id: &UserID) -> Result<bool, Error> {
+        const SQL: &str = "SELECT 1 FROM google_home_unlinks WHERE user_id = $1";
+        self.with_client(|client| Ok(client.query_opt(SQL, &[user_id])?.is_some()))
+    }
+
+    fn restore(&self, backup: &Backup) -> Result<(), Error> {
+        // Everything else refers to the users or structures, apart from the admins table which doesn't cascade
+        const DELETE_SQL: &str = "
+            DELETE FROM admins;
+            DELETE FROM users;
+            DELETE FROM structures;
+            ";
+
+        crate::check_backup_version(backup)?;
+        self.with_client(|client| {
+            let mut tx = client.transaction()?;
+            let users = crate::restored_users(backup, select_users(&mut tx)?)?;
+            tx.batch_execute(DELETE_SQL)?;
+            for structure in &backup.structures {
+                insert_structure(&mut tx, structure)?;
+            }
+            for room in &backup.rooms {
+                insert_room(&mut tx, room)?;
+            }
+            for device in &backup.devices {
+                insert_device(&mut tx, device)?;
+            }
+            for user in &users {
+                insert_user(&mut tx, user)?;
+            }
+            for user_id in &backup.admins {
+                insert_admin(&mut tx, user_id)?;
+            }
+            for user_structure in &backup.user_structures {
+                insert_user_structure(&mut tx, user_structure)?;
+            }
+            for scoped_role in &backup.scoped_roles {
+                insert_scoped_role(&mut tx, scoped_role)?;
+            }
+            for device_token in &backup.device_tokens {
+                insert_device_token(&mut tx, device_token)?;
+            }
+            for unlink in &backup.google_home_unlinks {
+                insert_google_home_unlink(&mut tx, unlink)?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    fn apply_home(&self, plan: &HomePlan) -> Result<(), Error> {
+        self.with_client(|client| {
+            let mut tx = client.transaction()?;
+            for change in &plan.changes {
+                apply_home_change(&mut tx, change)?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    fn backup(&self) -> Result<Backup, Error> {
+        self.with_client(|client| {
+            // Repeatable read makes all of the queries see the same snapshot of the database
+            let mut tx = client
+                .build_transaction()
+                .isolation_level(IsolationLevel::RepeatableRead)
+                .read_only(true)
+                .start()?;
+            let backup = Backup {
+                version: Backup::VERSION,
+                created_at: Utc::now(),
+                with_secrets: true,
+                admins: select_admins(&mut tx)?,
+                structures: select_structures(&mut tx)?,
+                rooms: select_rooms(&mut tx)?,
+                devices: select_devices(&mut tx)?,
+                users: select_users(&mut tx)?,
+                user_structures: select_user_structures(&mut tx)?,
+                scoped_roles: select_scoped_roles(&mut tx)?,
+                device_tokens: select_device_tokens(&mut tx)?,
+                google_home_unlinks: select_google_home_unlinks(&mut tx)?,
+            };
+            tx.commit()?;
+            Ok(backup)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use postgres::NoTls;
+
+    /// Local PostgreSQL instance, can be replaced by setting `HOUSEFLOW_TEST_POSTGRES_URL`
+    const DEFAULT_URL: &str = "postgres://postgres@localhost/houseflow_test";
+
+    /// Each test gets its own schema, so the tests running in parallel don't see each other rows
+    struct TestDatabase {
+        database: super::Database,
+        config: postgres::Config,
+        schema: String,
+    }
+
+    impl std::ops::Deref for TestDatabase {
+        type Target = super::Database;
+
+        fn deref(&self) -> &Self::Target {
+            &self.database
+        }
+    }
+
+    impl Drop for TestDatabase {
+        fn drop(&mut self) {
+            // Synchronous client can't be used from the runtime of the test
+            let sql = format!("DROP SCHEMA {} CASCADE", self.schema);
+            let config = self.config.clone();
+            let result = std::thread::spawn(move || {
+                config
+                    .connect(NoTls)
+                    .and_then(|mut client| client.batch_execute(&sql))
+            })
+            .join()
+            .unwrap();
+            if let Err(err) = result {
+                eprintln!("dropping schema {} failed: {}", self.schema, err);
+            }
+        }
+    }
+
+    async fn get_database() -> TestDatabase {
+        let url = std::env::var("HOUSEFLOW_TEST_POSTGRES_URL")
+            .unwrap_or_else(|_| DEFAULT_URL.to_string());
+        let config: postgres::Config = url.parse().unwrap();
+        let schema = format!("test_{}", hex::encode(rand::random::<[u8; 8]>()));
+        let mut schema_config = config.clone();
+        schema_config.options(&format!("-c search_path={}", schema));
+        let sql = format!("CREATE SCHEMA {}", schema);
+        let database = crate::blocking::spawn_blocking({
+            let config = config.clone();
+            move || {
+                config.connect(NoTls)?.batch_execute(&sql)?;
+                super::Database::with_config(schema_config)
+            }
+        })
+        .await
+        .unwrap();
+
+        TestDatabase {
+            database,
+            config,
+            schema,
+        }
+    }
+
+    include!("../tests.rs");
+}
@@ -1,5 +1,8 @@
+use chrono::{DateTime, Utc};
 use houseflow_types::{
-    Device, DeviceID, DeviceTrait, Room, RoomID, Structure, StructureID, User, UserID,
+    traits::DeviceStateSnapshot, Backup, Device, DeviceID, DevicePresenceEvent, DeviceTokenID,
+    DeviceTrait, GoogleHomeUnlink, HomeChange, HomeItem, HomePlan, IssuedDeviceToken, Role,
+    RoleScope, Room, RoomID, ScopedRole, Structure, StructureID, User, UserID, UserStructure,
 };
 use r2d2_sqlite::SqliteConnectionManager;
 use semver::Version;
@@ -12,18 +15,43 @@ pub struct Database {
 
 use crate::Error;
 
+// Extended result codes of the constraint violations, see https://sqlite.org/rescode.html
+const SQLITE_CONSTRAINT_FOREIGNKEY: i32 = rusqlite::ffi::SQLITE_CONSTRAINT | (3 << 8);
+const SQLITE_CONSTRAINT_PRIMARYKEY: i32 = rusqlite::ffi::SQLITE_CONSTRAINT | (6 << 8);
+const SQLITE_CONSTRAINT_UNIQUE: i32 = rusqlite::ffi::SQLITE_CONSTRAINT | (8 << 8);
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        match err {
+            rusqlite::Error::SqliteFailure(rusqlite::ffi::Error { extended_code, .. }, _) => {
+                match extended_code {
+                    SQLITE_CONSTRAINT_PRIMARYKEY | SQLITE_CONSTRAINT_UNIQUE => Self::AlreadyExists,
+                    SQLITE_CONSTRAINT_FOREIGNKEY => Self::ReferenceNotFound,
+                    _ => Self::Sqlite(err),
+                }
+            }
+            err => Self::Sqlite(err),
+        }
+    }
+}
+
 mod embedded {
     use refinery::embed_migrations;
-    embed_migrations!("./migrations");
+    embed_migrations!("./migrations/sqlite");
 }
 
 impl Database {
-    fn init(manager: SqliteConnectionManager) -> Result<Self, Error> {
+    fn init(
+        manager: SqliteConnectionManager,
+        pool: r2d2::Builder<SqliteConnectionManager>,
+    ) -> Result<Self, Error> {
         use std::ops::DerefMut;
 
-        let pool = r2d2::Pool::new(manager)?;
+        // Foreign keys are enforced per connection, so each of the pooled connections must enable them
+        let manager =
+            manager.with_init(|connection| connection.execute_batch("PRAGMA foreign_keys = ON"));
+        let pool = pool.build(manager)?;
         let mut connection = pool.get()?;
-        connection.execute("PRAGMA foreign_keys = ON", params!())?;
         embedded::migrations::runner().run(connection.deref_mut())?;
         Ok(Self { pool })
     }
@@ -31,126 +59,541 @@ impl Database {
     pub fn new(path: impl AsRef<Path>) -> Result<Self, Error> {
         let manager = SqliteConnectionManager::file(path);
 
-        Self::init(manager)
+        Self::init(manager, r2d2::Pool::builder())
     }
 
     pub fn new_in_memory() -> Result<Self, Error> {
         let manager = SqliteConnectionManager::memory();
 
-        Self::init(manager)
+        // Each connection opens a separate in-memory database, so all of the queries must share one
+        Self::init(manager, r2d2::Pool::builder().max_size(1))
     }
 }
 
-use rusqlite::{params, OptionalExtension};
+use rusqlite::{params, Connection, OptionalExtension};
 
-impl crate::Database for Database {
-    fn add_structure(&self, structure: &Structure) -> Result<(), Error> {
-        const SQL: &str = "INSERT INTO structures(id,name) VALUES(?, ?)";
-        let connection = self.pool.get()?;
-        let n = connection.execute(SQL, params![&structure.id, &structure.name])?;
-        match n {
-            0 => Err(Error::NotModified),
-            1 => Ok(()),
-            _ => unreachable!(),
-        }
+fn insert_structure(connection: &Connection, structure: &Structure) -> Result<(), Error> {
+    const SQL: &str = "INSERT INTO structures(id,name) VALUES(?, ?)";
+    let n = connection.execute(SQL, params![&structure.id, &structure.name])?;
+    match n {
+        0 => Err(Error::NotModified),
+        1 => Ok(()),
+        _ => unreachable!(),
     }
+}
 
-    fn add_room(&self, room: &Room) -> Result<(), Error> {
-        const SQL: &str = "INSERT INTO rooms(id, structure_id, name) VALUES(?, ?, ?)";
-        let connection = self.pool.get()?;
-        let n = connection.execute(SQL, params![&room.id, &room.structure_id, &room.name])?;
-        match n {
-            0 => Err(Error::NotModified),
-            1 => Ok(()),
-            _ => unreachable!(),
+fn insert_room(connection: &Connection, room: &Room) -> Result<(), Error> {
+    const SQL: &str = "INSERT INTO rooms(id, structure_id, name) VALUES(?, ?, ?)";
+    let n = connection.execute(SQL, params![&room.id, &room.structure_id, &room.name])?;
+    match n {
+        0 => Err(Error::NotModified),
+        1 => Ok(()),
+        _ => unreachable!(),
+    }
+}
+
+/// Inserts the device with its traits, it should be called within a transaction
+fn insert_device(connection: &Connection, device: &Device) -> Result<(), Error> {
+    const INSERT_DEVICE_SQL: &str = "INSERT INTO 
+        devices(id, room_id, password_hash, type, name, will_push_state, model, hw_version, sw_version, attributes) 
+        VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+    const INSERT_TRAIT_SQL: &str = "INSERT INTO device_traits(device_id, trait_name) 
+        VALUES(?, ?)";
+
+    let n = connection.execute(
+        INSERT_DEVICE_SQL,
+        params![
+            device.id,
+            device.room_id,
+            device.password_hash,
+            device.device_type,
+            device.name,
+            device.will_push_state,
+            device.model,
+            device.hw_version.to_string(),
+            device.sw_version.to_string(),
+            serde_json::to_string(&device.attributes)?
+        ],
+    )?;
+    if n == 0 {
+        return Err(Error::NotModified);
+    }
+    for device_trait in &device.traits {
+        let n = connection.execute(INSERT_TRAIT_SQL, params!(&device.id, &device_trait))?;
+        if n == 0 {
+            return Err(Error::NotModified);
         }
     }
+    Ok(())
+}
 
-    fn add_device(&self, device: &Device) -> Result<(), Error> {
-        const INSERT_DEVICE_SQL: &str = "INSERT INTO 
-            devices(id, room_id, password_hash, type, name, will_push_state, model, hw_version, sw_version, attributes) 
-            VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
-        const INSERT_TRAIT_SQL: &str = "INSERT INTO device_traits(device_id, trait_name) 
-            VALUES(?, ?)";
+fn insert_user(connection: &Connection, user: &User) -> Result<(), Error> {
+    const SQL: &str = "INSERT INTO users(id, username, email, password_hash) VALUES(?, ?, ?, ?)";
+    let n = connection.execute(
+        SQL,
+        params![&user.id, &user.username, &user.email, &user.password_hash],
+    )?;
+
+    match n {
+        0 => Err(Error::NotModified),
+        1 => Ok(()),
+        _ => unreachable!(),
+    }
+}
 
-        let mut connection = self.pool.get()?;
-        let tx = connection.transaction()?;
-        let n = tx.execute(
-            INSERT_DEVICE_SQL,
-            params![
-                device.id,
-                device.room_id,
-                device.password_hash,
-                device.device_type,
-                device.name,
-                device.will_push_state,
-                device.model,
-                device.hw_version.to_string(),
-                device.sw_version.to_string(),
-                serde_json::to_string(&device.attributes)?
-            ],
-        )?;
+fn insert_admin(connection: &Connection, user_id: &UserID) -> Result<(), Error> {
+    const SQL: &str = "INSERT INTO admins(user_id) VALUES(?)";
+    let n = connection.execute(SQL, params![user_id])?;
+
+    match n {
+        0 => Err(Error::NotModified),
+        1 => Ok(()),
+        _ => unreachable!(),
+    }
+}
+
+fn insert_user_structure(
+    connection: &Connection,
+    user_structure: &UserStructure,
+) -> Result<(), Error> {
+    const SQL: &str = "INSERT INTO user_structures(structure_id, user_id, role) VALUES(?, ?, ?)";
+    let n = connection.execute(
+        SQL,
+        params![
+            &user_structure.structure_id,
+            &user_structure.user_id,
+            &user_structure.role
+        ],
+    )?;
+    match n {
+        0 => Err(Error::NotModified),
+        1 => Ok(()),
+        _ => unreachable!(),
+    }
+}
+
+fn insert_scoped_role(connection: &Connection, scoped_role: &ScopedRole) -> Result<(), Error> {
+    const ROOM_SQL: &str =
+        "INSERT OR REPLACE INTO user_room_roles(user_id, room_id, role) VALUES(?, ?, ?)";
+    const DEVICE_SQL: &str =
+        "INSERT OR REPLACE INTO user_device_roles(user_id, device_id, role) VALUES(?, ?, ?)";
+    let n = match &scoped_role.scope {
+        RoleScope::Room(room_id) => connection.execute(
+            ROOM_SQL,
+            params![&scoped_role.user_id, room_id, &scoped_role.role],
+        )?,
+        RoleScope::Device(device_id) => connection.execute(
+            DEVICE_SQL,
+            params![&scoped_role.user_id, device_id, &scoped_role.role],
+        )?,
+    };
+    match n {
+        0 => Err(Error::NotModified),
+        _ => Ok(()),
+    }
+}
+
+fn insert_device_token(
+    connection: &Connection,
+    device_token: &IssuedDeviceToken,
+) -> Result<(), Error> {
+    const SQL: &str = "INSERT INTO device_tokens(device_id, token_id, issued_at) VALUES(?, ?, ?)";
+    connection.execute(
+        SQL,
+        params![
+            device_token.device_id,
+            device_token.token_id,
+            device_token.issued_at
+        ],
+    )?;
+
+    Ok(())
+}
+
+fn insert_google_home_unlink(
+    connection: &Connection,
+    unlink: &GoogleHomeUnlink,
+) -> Result<(), Error> {
+    const SQL: &str = "INSERT INTO google_home_unlinks(user_id, unlinked_at) VALUES(?, ?)";
+    connection.execute(SQL, params![unlink.user_id, unlink.unlinked_at])?;
+
+    Ok(())
+}
+
+fn update_structure(connection: &Connection, structure: &Structure) -> Result<bool, Error> {
+    const SQL: &str = "UPDATE structures SET name = ? WHERE id = ?";
+    let n = connection.execute(SQL, params![&structure.name, &structure.id])?;
+
+    Ok(n > 0)
+}
+
+fn update_room(connection: &Connection, room: &Room) -> Result<bool, Error> {
+    const SQL: &str = "UPDATE rooms SET structure_id = ?, name = ? WHERE id = ?";
+    let n = connection.execute(SQL, params![&room.structure_id, &room.name, &room.id])?;
+
+    Ok(n > 0)
+}
+
+/// Traits are replaced with separate statements, so it should be called within a transaction
+fn update_device(connection: &Connection, device: &Device) -> Result<bool, Error> {
+    const UPDATE_DEVICE_SQL: &str = "UPDATE devices
+        SET room_id = ?, type = ?, name = ?, will_push_state = ?, model = ?, hw_version = ?, sw_version = ?, attributes = ?
+        WHERE id = ?";
+    const DELETE_TRAITS_SQL: &str = "DELETE FROM device_traits WHERE device_id = ?";
+    const INSERT_TRAIT_SQL: &str = "INSERT INTO device_traits(device_id, trait_name) 
+        VALUES(?, ?)";
+
+    let n = connection.execute(
+        UPDATE_DEVICE_SQL,
+        params![
+            device.room_id,
+            device.device_type,
+            device.name,
+            device.will_push_state,
+            device.model,
+            device.hw_version.to_string(),
+            device.sw_version.to_string(),
+            serde_json::to_string(&device.attributes)?,
+            device.id,
+        ],
+    )?;
+    if n == 0 {
+        return Ok(false);
+    }
+    connection.execute(DELETE_TRAITS_SQL, params![&device.id])?;
+    for device_trait in &device.traits {
+        let n = connection.execute(INSERT_TRAIT_SQL, params!(&device.id, &device_trait))?;
         if n == 0 {
             return Err(Error::NotModified);
         }
-        for device_trait in &device.traits {
-            let n = tx.execute(INSERT_TRAIT_SQL, params!(&device.id, &device_trait))?;
-            if n == 0 {
-                return Err(Error::NotModified);
+    }
+    Ok(true)
+}
+
+fn update_user_structure(
+    connection: &Connection,
+    user_structure: &UserStructure,
+) -> Result<bool, Error> {
+    const SQL: &str = "UPDATE user_structures SET role = ? WHERE structure_id = ? AND user_id = ?";
+    let n = connection.execute(
+        SQL,
+        params![
+            &user_structure.role,
+            &user_structure.structure_id,
+            &user_structure.user_id
+        ],
+    )?;
+
+    Ok(n > 0)
+}
+
+fn delete_structure(connection: &Connection, structure_id: &StructureID) -> Result<bool, Error> {
+    const SQL: &str = "DELETE FROM structures WHERE id = ?";
+    let n = connection.execute(SQL, params![structure_id])?;
+
+    Ok(n > 0)
+}
+
+fn delete_room(connection: &Connection, room_id: &RoomID) -> Result<bool, Error> {
+    const SQL: &str = "DELETE FROM rooms WHERE id = ?";
+    let n = connection.execute(SQL, params![room_id])?;
+
+    Ok(n > 0)
+}
+
+fn delete_device(connection: &Connection, device_id: &DeviceID) -> Result<bool, Error> {
+    const SQL: &str = "DELETE FROM devices WHERE id = ?";
+    let n = connection.execute(SQL, params![device_id])?;
+
+    Ok(n > 0)
+}
+
+fn delete_user_structure(
+    connection: &Connection,
+    structure_id: &StructureID,
+    user_id: &UserID,
+) -> Result<bool, Error> {
+    const SQL: &str = "DELETE FROM user_structures WHERE structure_id = ? AND user_id = ?";
+    let n = connection.execute(SQL, params![structure_id, user_id])?;
+
+    Ok(n > 0)
+}
+
+fn apply_home_change(connection: &Connection, change: &HomeChange) -> Result<(), Error> {
+    match change {
+        HomeChange::Create { item } => match item {
+            HomeItem::Structure(structure) => insert_structure(connection, structure),
+            HomeItem::Room(room) => insert_room(connection, room),
+            HomeItem::Device(device) => insert_device(connection, device),
+            HomeItem::Member { user_structure, .. } => {
+                insert_user_structure(connection, user_structure)
+            }
+        },
+        HomeChange::Update { after, .. } => {
+            let updated = match after {
+                HomeItem::Structure(structure) => update_structure(connection, structure)?,
+                HomeItem::Room(room) => update_room(connection, room)?,
+                HomeItem::Device(device) => update_device(connection, device)?,
+                HomeItem::Member { user_structure, .. } => {
+                    update_user_structure(connection, user_structure)?
+                }
+            };
+            // Item has been deleted since the plan was made
+            match updated {
+                true => Ok(()),
+                false => Err(Error::NotModified),
             }
         }
+        // Item could have been deleted already, which is fine
+        HomeChange::Delete { item } => {
+            match item {
+                HomeItem::Structure(structure) => delete_structure(connection, &structure.id)?,
+                HomeItem::Room(room) => delete_room(connection, &room.id)?,
+                HomeItem::Device(device) => delete_device(connection, &device.id)?,
+                HomeItem::Member { user_structure, .. } => delete_user_structure(
+                    connection,
+                    &user_structure.structure_id,
+                    &user_structure.user_id,
+                )?,
+            };
+            Ok(())
+        }
+    }
+}
+
+fn select_structures(connection: &Connection) -> Result<Vec<Structure>, Error> {
+    const SQL: &str = "SELECT * FROM structures";
+
+    use fallible_iterator::FallibleIterator;
+
+    let mut statement = connection.prepare(SQL)?;
+    let structures = statement
+        .query(params![])?
+        .map(|row| {
+            Ok(Structure {
+                id: row.get("id")?,
+                name: row.get("name")?,
+            })
+        })
+        .collect()?;
+
+    Ok(structures)
+}
+
+fn select_rooms(connection: &Connection) -> Result<Vec<Room>, Error> {
+    const SQL: &str = "SELECT * FROM rooms";
+
+    use fallible_iterator::FallibleIterator;
+
+    let mut statement = connection.prepare(SQL)?;
+    let rooms = statement
+        .query(params![])?
+        .map(|row| {
+            Ok(Room {
+                id: row.get("id")?,
+                name: row.get("name")?,
+                structure_id: row.get("structure_id")?,
+            })
+        })
+        .collect()?;
+
+    Ok(rooms)
+}
+
+fn select_devices(connection: &Connection) -> Result<Vec<Device>, Error> {
+    const SELECT_TRAITS_SQL: &str = "SELECT trait_name FROM device_traits WHERE device_id = ?";
+    const SELECT_DEVICES_SQL: &str = "SELECT * FROM devices";
+
+    use fallible_iterator::FallibleIterator;
+
+    let mut select_traits_sql = connection.prepare(SELECT_TRAITS_SQL)?;
+    let mut statement = connection.prepare(SELECT_DEVICES_SQL)?;
+    let devices = statement
+        .query(params![])?
+        .map(|row| {
+            let device_id = row.get("id")?;
+            let traits: Vec<DeviceTrait> = select_traits_sql
+                .query(params![device_id])?
+                .map(|row| {
+                    DeviceTrait::from_str(row.get::<_, String>("trait_name")?.as_str())
+                        .map_err(|err| rusqlite::types::FromSqlError::Other(Box::new(err)).into())
+                })
+                .collect()?;
+            Ok(Device {
+                id: device_id,
+                room_id: row.get("room_id")?,
+                password_hash: row.get("password_hash")?,
+                device_type: row.get("type")?,
+                traits,
+                name: row.get("name")?,
+                will_push_state: row.get("will_push_state")?,
+                model: row.get("model")?,
+                hw_version: Version::parse(row.get::<_, String>("hw_version")?.as_str())
+                    .map_err(|err| rusqlite::types::FromSqlError::Other(Box::new(err)))?,
+                sw_version: Version::parse(row.get::<_, String>("sw_version")?.as_str())
+                    .map_err(|err| rusqlite::types::FromSqlError::Other(Box::new(err)))?,
+                attributes: serde_json::from_str(row.get::<_, String>("attributes")?.as_str())
+                    .map_err(|err| rusqlite::types::FromSqlError::Other(Box::new(err)))?,
+            })
+        })
+        .collect()?;
+
+    Ok(devices)
+}
+
+fn select_users(connection: &Connection) -> Result<Vec<User>, Error> {
+    const SQL: &str = "SELECT * FROM users";
+
+    use fallible_iterator::FallibleIterator;
+
+    let mut statement = connection.prepare(SQL)?;
+    let users = statement
+        .query(params![])?
+        .map(|row| {
+            Ok(User {
+                id: row.get("id")?,
+                username: row.get("username")?,
+                email: row.get("email")?,
+                password_hash: row.get("password_hash")?,
+            })
+        })
+        .collect()?;
+
+    Ok(users)
+}
+
+fn select_user_structures(connection: &Connection) -> Result<Vec<UserStructure>, Error> {
+    const SQL: &str = "SELECT * FROM user_structures";
+
+    use fallible_iterator::FallibleIterator;
+
+    let mut statement = connection.prepare(SQL)?;
+    let user_structures = statement
+        .query(params![])?
+        .map(|row| {
+            Ok(UserStructure {
+                structure_id: row.get("structure_id")?,
+                user_id: row.get("user_id")?,
+                role: row.get("role")?,
+            })
+        })
+        .collect()?;
+
+    Ok(user_structures)
+}
+
+fn select_scoped_roles(connection: &Connection) -> Result<Vec<ScopedRole>, Error> {
+    const ROOM_SQL: &str = "SELECT * FROM user_room_roles";
+    const DEVICE_SQL: &str = "SELECT * FROM user_device_roles";
+
+    use fallible_iterator::FallibleIterator;
+
+    let mut room_statement = connection.prepare(ROOM_SQL)?;
+    let mut device_statement = connection.prepare(DEVICE_SQL)?;
+    let room_roles = room_statement.query(params![])?.map(|row| {
+        Ok(ScopedRole {
+            user_id: row.get("user_id")?,
+            scope: RoleScope::Room(row.get("room_id")?),
+            role: row.get("role")?,
+        })
+    });
+    let device_roles = device_statement.query(params![])?.map(|row| {
+        Ok(ScopedRole {
+            user_id: row.get("user_id")?,
+            scope: RoleScope::Device(row.get("device_id")?),
+            role: row.get("role")?,
+        })
+    });
+    let scoped_roles = room_roles.chain(device_roles).collect()?;
+
+    Ok(scoped_roles)
+}
+
+fn select_admins(connection: &Connection) -> Result<Vec<UserID>, Error> {
+    const SQL: &str = "SELECT user_id FROM admins";
+
+    use fallible_iterator::FallibleIterator;
+
+    let mut statement = connection.prepare(SQL)?;
+    let admins = statement
+        .query(params![])?
+        .map(|row| row.get("user_id"))
+        .collect()?;
+
+    Ok(admins)
+}
+
+fn select_device_tokens(connection: &Connection) -> Result<Vec<IssuedDeviceToken>, Error> {
+    const SQL: &str = "SELECT * FROM device_tokens";
+
+    use fallible_iterator::FallibleIterator;
+
+    let mut statement = connection.prepare(SQL)?;
+    let device_tokens = statement
+        .query(params![])?
+        .map(|row| {
+            Ok(IssuedDeviceToken {
+                device_id: row.get("device_id")?,
+                token_id: row.get("token_id")?,
+                issued_at: row.get("issued_at")?,
+            })
+        })
+        .collect()?;
+
+    Ok(device_tokens)
+}
+
+fn select_google_home_unlinks(connection: &Connection) -> Result<Vec<GoogleHomeUnlink>, Error> {
+    const SQL: &str = "SELECT * FROM google_home_unlinks";
+
+    use fallible_iterator::FallibleIterator;
+
+    let mut statement = connection.prepare(SQL)?;
+    let unlinks = statement
+        .query(params![])?
+        .map(|row| {
+            Ok(GoogleHomeUnlink {
+                user_id: row.get("user_id")?,
+                unlinked_at: row.get("unlinked_at")?,
+            })
+        })
+        .collect()?;
+
+    Ok(unlinks)
+}
+
+impl crate::blocking::Database for Database {
+    fn add_structure(&self, structure: &Structure) -> Result<(), Error> {
+        let connection = self.pool.get()?;
+        insert_structure(&connection, structure)
+    }
+
+    fn add_room(&self, room: &Room) -> Result<(), Error> {
+        let connection = self.pool.get()?;
+        insert_room(&connection, room)
+    }
+
+    fn add_device(&self, device: &Device) -> Result<(), Error> {
+        let mut connection = self.pool.get()?;
+        let tx = connection.transaction()?;
+        insert_device(&tx, device)?;
         tx.commit()?;
         Ok(())
     }
 
     fn add_user(&self, user: &User) -> Result<(), Error> {
-        const SQL: &str =
-            "INSERT INTO users(id, username, email, password_hash) VALUES(?, ?, ?, ?)";
         let connection = self.pool.get()?;
-        let n = connection.execute(
-            SQL,
-            params![&user.id, &user.username, &user.email, &user.password_hash],
-        )?;
-
-        match n {
-            0 => Err(Error::NotModified),
-            1 => Ok(()),
-            _ => unreachable!(),
-        }
+        insert_user(&connection, user)
     }
 
     fn add_admin(&self, user_id: &UserID) -> Result<(), Error> {
-        const SQL: &str = "INSERT INTO admins(user_id) VALUES(?)";
         let connection = self.pool.get()?;
-        let n = connection.execute(SQL, params![user_id])?;
-
-        match n {
-            0 => Err(Error::NotModified),
-            1 => Ok(()),
-            _ => unreachable!(),
-        }
+        insert_admin(&connection, user_id)
     }
 
-    fn add_user_structure(
-        &self,
-        user_structure: &houseflow_types::UserStructure,
-    ) -> Result<(), Error> {
-        const SQL: &str =
-            "INSERT INTO user_structures(structure_id, user_id, is_manager) VALUES(?, ?, ?)";
+    fn add_user_structure(&self, user_structure: &UserStructure) -> Result<(), Error> {
         let connection = self.pool.get()?;
-        let n = connection.execute(
-            SQL,
-            params![
-                &user_structure.structure_id,
-                &user_structure.user_id,
-                &user_structure.is_manager
-            ],
-        )?;
-        match n {
-            0 => Err(Error::NotModified),
-            1 => Ok(()),
-            _ => unreachable!(),
-        }
+        insert_user_structure(&connection, user_structure)
     }
 
     fn get_structure(&self, structure_id: &StructureID) -> Result<Option<Structure>, Error> {
@@ -261,17 +704,25 @@ impl crate::Database for Database {
     fn get_user_devices(&self, user_id: &UserID) -> Result<Vec<Device>, Error> {
         const SELECT_TRAITS_SQL: &str = "SELECT trait_name FROM device_traits WHERE device_id = ?";
         const SELECT_USER_DEVICES_SQL: &str = "
-            SELECT * 
-            FROM devices 
-            WHERE room_id = (
-                SELECT id
-                FROM rooms
-                WHERE structure_id = (
-                    SELECT structure_id
-                    FROM user_structures
-                    WHERE user_id = ?
-                )
-            )";
+            SELECT *
+            FROM devices
+            WHERE id IN (
+                SELECT devices.id
+                FROM devices
+                JOIN rooms ON rooms.id = devices.room_id
+                JOIN user_structures ON user_structures.structure_id = rooms.structure_id
+                WHERE user_structures.user_id = ?1
+                UNION
+                SELECT devices.id
+                FROM devices
+                JOIN user_room_roles ON user_room_roles.room_id = devices.room_id
+                WHERE user_room_roles.user_id = ?1
+                UNION
+                SELECT device_id
+                FROM user_device_roles
+                WHERE user_id = ?1
+            )
+            ORDER BY rowid";
 
         use fallible_iterator::FallibleIterator;
 
@@ -312,31 +763,434 @@ impl crate::Database for Database {
         Ok(devices)
     }
 
-    fn check_user_device_access(
+    fn get_device_users(&self, device_id: &DeviceID) -> Result<Vec<User>, Error> {
+        const SQL: &str = "
+            SELECT *
+            FROM users
+            WHERE id IN (
+                SELECT user_structures.user_id
+                FROM user_structures
+                JOIN rooms ON rooms.structure_id = user_structures.structure_id
+                JOIN devices ON devices.room_id = rooms.id
+                WHERE devices.id = ?1
+                UNION
+                SELECT user_room_roles.user_id
+                FROM user_room_roles
+                JOIN devices ON devices.room_id = user_room_roles.room_id
+                WHERE devices.id = ?1
+                UNION
+                SELECT user_id
+                FROM user_device_roles
+                WHERE device_id = ?1
+            )
+            ORDER BY rowid";
+
+        use fallible_iterator::FallibleIterator;
+
+        let connection = self.pool.get()?;
+        let mut statement = connection.prepare(SQL)?;
+        let users = statement
+            .query(params![device_id])?
+            .map(|row| {
+                Ok(User {
+                    id: row.get("id")?,
+                    username: row.get("username")?,
+                    email: row.get("email")?,
+                    password_hash: row.get("password_hash")?,
+                })
+            })
+            .collect()?;
+
+        Ok(users)
+    }
+
+    fn get_user_structure(
         &self,
+        structure_id: &StructureID,
         user_id: &UserID,
+    ) -> Result<Option<UserStructure>, Error> {
+        const SQL: &str = "SELECT * FROM user_structures WHERE structure_id = ? AND user_id = ?";
+        let connection = self.pool.get()?;
+        let user_structure = connection
+            .query_row(SQL, params![structure_id, user_id], |row| {
+                Ok(UserStructure {
+                    structure_id: row.get("structure_id")?,
+                    user_id: row.get("user_id")?,
+                    role: row.get("role")?,
+                })
+            })
+            .optional()?;
+
+        Ok(user_structure)
+    }
+
+    fn get_structures(&self) -> Result<Vec<Structure>, Error> {
+        let connection = self.pool.get()?;
+        select_structures(&connection)
+    }
+
+    fn get_rooms(&self) -> Result<Vec<Room>, Error> {
+        let connection = self.pool.get()?;
+        select_rooms(&connection)
+    }
+
+    fn get_devices(&self) -> Result<Vec<Device>, Error> {
+        let connection = self.pool.get()?;
+        select_devices(&connection)
+    }
+
+    fn get_users(&self) -> Result<Vec<User>, Error> {
+        let connection = self.pool.get()?;
+        select_users(&connection)
+    }
+
+    fn get_user_structures(&self) -> Result<Vec<UserStructure>, Error> {
+        let connection = self.pool.get()?;
+        select_user_structures(&connection)
+    }
+
+    fn update_structure(&self, structure: &Structure) -> Result<bool, Error> {
+        let connection = self.pool.get()?;
+        update_structure(&connection, structure)
+    }
+
+    fn update_room(&self, room: &Room) -> Result<bool, Error> {
+        let connection = self.pool.get()?;
+        update_room(&connection, room)
+    }
+
+    fn update_device(&self, device: &Device) -> Result<bool, Error> {
+        let mut connection = self.pool.get()?;
+        let tx = connection.transaction()?;
+        let updated = update_device(&tx, device)?;
+        tx.commit()?;
+
+        Ok(updated)
+    }
+
+    fn update_user(&self, user: &User) -> Result<bool, Error> {
+        const SQL: &str = "UPDATE users SET username = ?, email = ? WHERE id = ?";
+        let connection = self.pool.get()?;
+        let n = connection.execute(SQL, params![&user.username, &user.email, &user.id])?;
+
+        Ok(n > 0)
+    }
+
+    fn update_user_structure(&self, user_structure: &UserStructure) -> Result<bool, Error> {
+        let connection = self.pool.get()?;
+        update_user_structure(&connection, user_structure)
+    }
+
+    fn delete_structure(&self, structure_id: &StructureID) -> Result<bool, Error> {
+        let connection = self.pool.get()?;
+        delete_structure(&connection, structure_id)
+    }
+
+    fn delete_room(&self, room_id: &RoomID) -> Result<bool, Error> {
+        let connection = self.pool.get()?;
+        delete_room(&connection, room_id)
+    }
+
+    fn delete_device(&self, device_id: &DeviceID) -> Result<bool, Error> {
+        let connection = self.pool.get()?;
+        delete_device(&connection, device_id)
+    }
+
+    fn delete_user(&self, user_id: &UserID) -> Result<bool, Error> {
+        // admins table doesn't cascade deletes of the user
+        const DELETE_ADMIN_SQL: &str = "DELETE FROM admins WHERE user_id = ?";
+        const DELETE_USER_SQL: &str = "DELETE FROM users WHERE id = ?";
+
+        let mut connection = self.pool.get()?;
+        let tx = connection.transaction()?;
+        tx.execute(DELETE_ADMIN_SQL, params![user_id])?;
+        let n = tx.execute(DELETE_USER_SQL, params![user_id])?;
+        tx.commit()?;
+
+        Ok(n > 0)
+    }
+
+    fn delete_user_structure(
+        &self,
+        structure_id: &StructureID,
+        user_id: &UserID,
+    ) -> Result<bool, Error> {
+        let connection = self.pool.get()?;
+        delete_user_structure(&connection, structure_id, user_id)
+    }
+
+    fn set_device_state(
+        &self,
+        device_id: &DeviceID,
+        snapshot: &DeviceStateSnapshot,
+    ) -> Result<(), Error> {
+        const SQL: &str =
+            "INSERT OR REPLACE INTO device_states(device_id, state, updated_at) VALUES(?, ?, ?)";
+        let connection = self.pool.get()?;
+        let n = connection.execute(
+            SQL,
+            params![
+                device_id,
+                serde_json::to_string(&snapshot.state)?,
+                snapshot.updated_at
+            ],
+        )?;
+        match n {
+            0 => Err(Error::NotModified),
+            1 => Ok(()),
+            _ => unreachable!(),
+        }
+    }
+
+    fn get_device_state(&self, device_id: &DeviceID) -> Result<Option<DeviceStateSnapshot>, Error> {
+        const SQL: &str = "SELECT * FROM device_states WHERE device_id = ?";
+        let connection = self.pool.get()?;
+        let snapshot = connection
+            .query_row(SQL, params![device_id], |row| {
+                Ok(DeviceStateSnapshot {
+                    state: serde_json::from_str(row.get::<_, String>("state")?.as_str())
+                        .map_err(|err| rusqlite::types::FromSqlError::Other(Box::new(err)))?,
+                    updated_at: row.get("updated_at")?,
+                })
+            })
+            .optional()?;
+
+        Ok(snapshot)
+    }
+
+    fn add_device_state_history(
+        &self,
+        device_id: &DeviceID,
+        snapshot: &DeviceStateSnapshot,
+    ) -> Result<(), Error> {
+        const SQL: &str =
+            "INSERT INTO device_state_history(device_id, state, updated_at) VALUES(?, ?, ?)";
+        let connection = self.pool.get()?;
+        let n = connection.execute(
+            SQL,
+            params![
+                device_id,
+                serde_json::to_string(&snapshot.state)?,
+                snapshot.updated_at
+            ],
+        )?;
+        match n {
+            0 => Err(Error::NotModified),
+            1 => Ok(()),
+            _ => unreachable!(),
+        }
+    }
+
+    fn get_device_state_history(
+        &self,
+        device_id: &DeviceID,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<DeviceStateSnapshot>, Error> {
+        const SQL: &str = "
+            SELECT state, updated_at
+            FROM device_state_history
+            WHERE device_id = ?1
+            AND (?2 IS NULL OR updated_at >= ?2)
+            AND (?3 IS NULL OR updated_at < ?3)
+            ORDER BY updated_at ASC
+            LIMIT ?4 OFFSET ?5
+            ";
+
+        use fallible_iterator::FallibleIterator;
+
+        let connection = self.pool.get()?;
+        let mut statement = connection.prepare(SQL)?;
+        let snapshots = statement
+            .query(params![device_id, since, until, limit, offset])?
+            .map(|row| {
+                Ok(DeviceStateSnapshot {
+                    state: serde_json::from_str(row.get::<_, String>("state")?.as_str())
+                        .map_err(|err| rusqlite::types::FromSqlError::Other(Box::new(err)))?,
+                    updated_at: row.get("updated_at")?,
+                })
+            })
+            .collect()?;
+
+        Ok(snapshots)
+    }
+
+    fn add_device_presence_event(
+        &self,
+        device_id: &DeviceID,
+        event: &DevicePresenceEvent,
+    ) -> Result<(), Error> {
+        const SQL: &str = "INSERT INTO device_presence_events(device_id, connected, address, occurred_at) VALUES(?, ?, ?, ?)";
+        let connection = self.pool.get()?;
+        connection.execute(
+            SQL,
+            params![
+                device_id,
+                event.connected,
+                event.address.to_string(),
+                event.occurred_at
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn get_last_device_presence_event(
+        &self,
+        device_id: &DeviceID,
+    ) -> Result<Option<DevicePresenceEvent>, Error> {
+        const SQL: &str = "
+            SELECT *
+            FROM device_presence_events
+            WHERE device_id = ?
+            ORDER BY occurred_at DESC
+            LIMIT 1
+            ";
+        let connection = self.pool.get()?;
+        let event = connection
+            .query_row(SQL, params![device_id], |row| {
+                Ok(DevicePresenceEvent {
+                    connected: row.get("connected")?,
+                    address: row
+                        .get::<_, String>("address")?
+                        .parse()
+                        .map_err(|err| rusqlite::types::FromSqlError::Other(Box::new(err)))?,
+                    occurred_at: row.get("occurred_at")?,
+                })
+            })
+            .optional()?;
+
+        Ok(event)
+    }
+
+    fn set_device_token_id(
+        &self,
+        device_id: &DeviceID,
+        token_id: &DeviceTokenID,
+        issued_at: &DateTime<Utc>,
+    ) -> Result<(), Error> {
+        const SQL: &str =
+            "INSERT OR REPLACE INTO device_tokens(device_id, token_id, issued_at) VALUES(?, ?, ?)";
+        let connection = self.pool.get()?;
+        connection.execute(SQL, params![device_id, token_id, issued_at])?;
+
+        Ok(())
+    }
+
+    fn get_device_token_id(&self, device_id: &DeviceID) -> Result<Option<DeviceTokenID>, Error> {
+        const SQL: &str = "SELECT token_id FROM device_tokens WHERE device_id = ?";
+        let connection = self.pool.get()?;
+        let token_id = connection
+            .query_row(SQL, params![device_id], |row| row.get("token_id"))
+            .optional()?;
+
+        Ok(token_id)
+    }
+
+    fn remove_device_token_id(&self, device_id: &DeviceID) -> Result<bool, Error> {
+        const SQL: &str = "DELETE FROM device_tokens WHERE device_id = ?";
+        let connection = self.pool.get()?;
+        let n = connection.execute(SQL, params![device_id])?;
+
+        Ok(n > 0)
+    }
+
+    fn set_device_password_hash(
+        &self,
         device_id: &DeviceID,
+        password_hash: &str,
     ) -> Result<bool, Error> {
+        const SQL: &str = "UPDATE devices SET password_hash = ? WHERE id = ?";
+        let connection = self.pool.get()?;
+        let n = connection.execute(SQL, params![password_hash, device_id])?;
+
+        Ok(n > 0)
+    }
+
+    fn get_user_device_role(
+        &self,
+        user_id: &UserID,
+        device_id: &DeviceID,
+    ) -> Result<Option<Role>, Error> {
         const SQL: &str = "
-            SELECT 1
-            FROM devices 
-            WHERE id = ? 
-            AND room_id = (
-                SELECT id
-                FROM rooms
-                WHERE structure_id = (
-                    SELECT structure_id
+            SELECT COALESCE(
+                (
+                    SELECT role
+                    FROM user_device_roles
+                    WHERE user_id = ?1 AND device_id = ?2
+                ),
+                (
+                    SELECT user_room_roles.role
+                    FROM user_room_roles
+                    JOIN devices ON devices.room_id = user_room_roles.room_id
+                    WHERE user_room_roles.user_id = ?1 AND devices.id = ?2
+                ),
+                (
+                    SELECT user_structures.role
                     FROM user_structures
-                    WHERE user_id = ?
+                    JOIN rooms ON rooms.structure_id = user_structures.structure_id
+                    JOIN devices ON devices.room_id = rooms.id
+                    WHERE user_structures.user_id = ?1 AND devices.id = ?2
                 )
-            )
+            ) AS role
             ";
         let connection = self.pool.get()?;
-        let result = connection
-            .query_row(SQL, params![device_id, user_id], |_| Ok(()))
-            .optional()?;
+        let role = connection.query_row(SQL, params![user_id, device_id], |row| row.get("role"))?;
 
-        Ok(result.is_some())
+        Ok(role)
+    }
+
+    fn get_user_room_role(
+        &self,
+        user_id: &UserID,
+        room_id: &RoomID,
+    ) -> Result<Option<Role>, Error> {
+        const SQL: &str = "
+            SELECT COALESCE(
+                (
+                    SELECT role
+                    FROM user_room_roles
+                    WHERE user_id = ?1 AND room_id = ?2
+                ),
+                (
+                    SELECT user_structures.role
+                    FROM user_structures
+                    JOIN rooms ON rooms.structure_id = user_structures.structure_id
+                    WHERE user_structures.user_id = ?1 AND rooms.id = ?2
+                )
+            ) AS role
+            ";
+        let connection = self.pool.get()?;
+        let role = connection.query_row(SQL, params![user_id, room_id], |row| row.get("role"))?;
+
+        Ok(role)
+    }
+
+    fn add_scoped_role(&self, scoped_role: &ScopedRole) -> Result<(), Error> {
+        let connection = self.pool.get()?;
+        insert_scoped_role(&connection, scoped_role)
+    }
+
+    fn get_scoped_roles(&self) -> Result<Vec<ScopedRole>, Error> {
+        let connection = self.pool.get()?;
+        select_scoped_roles(&connection)
+    }
+
+    fn delete_scoped_role(&self, user_id: &UserID, scope: &RoleScope) -> Result<bool, Error> {
+        const ROOM_SQL: &str = "DELETE FROM user_room_roles WHERE user_id = ? AND room_id = ?";
+        const DEVICE_SQL: &str =
+            "DELETE FROM user_device_roles WHERE user_id = ? AND device_id = ?";
+        let connection = self.pool.get()?;
+        let n = match scope {
+            RoleScope::Room(room_id) => connection.execute(ROOM_SQL, params![user_id, room_id])?,
+            RoleScope::Device(device_id) => {
+                connection.execute(DEVICE_SQL, params![user_id, device_id])?
+            }
+        };
+
+        Ok(n > 0)
     }
 
     fn check_user_admin(&self, user_id: &UserID) -> Result<bool, Error> {
@@ -353,247 +1207,154 @@ impl crate::Database for Database {
 
         Ok(result.is_some())
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::Database as SqliteDatabase;
-    use crate::Database;
-    use houseflow_types::{
-        Device, DeviceTrait, DeviceType, Room, RoomID, Structure, StructureID, User, UserID,
-        UserStructure,
-    };
-    use rand::random;
-    use semver::Version;
 
-    fn get_database() -> SqliteDatabase {
-        SqliteDatabase::new_in_memory().unwrap()
+    fn get_admins(&self) -> Result<Vec<UserID>, Error> {
+        let connection = self.pool.get()?;
+        select_admins(&connection)
     }
 
-    mod structure {
-        use super::*;
+    fn check_user_structure_manager(
+        &self,
+        user_id: &UserID,
+        structure_id: &StructureID,
+    ) -> Result<bool, Error> {
+        const SQL: &str = "
+            SELECT 1
+            FROM user_structures
+            WHERE user_id = ?
+            AND structure_id = ?
+            AND role = 'manager'
+            ";
 
-        pub fn gen() -> Structure {
-            Structure {
-                id: random(),
-                name: "SomeStructure".to_string(),
-            }
-        }
+        let connection = self.pool.get()?;
+        let result = connection
+            .query_row(SQL, params![user_id, structure_id], |_| Ok(()))
+            .optional()?;
 
-        #[test]
-        fn add() {
-            let db = get_database();
-            let structure = gen();
-            db.add_structure(&structure).unwrap();
-            assert_eq!(db.get_structure(&structure.id).unwrap().unwrap(), structure)
-        }
+        Ok(result.is_some())
+    }
 
-        #[test]
-        fn add_duplicate() {
-            let db = get_database();
-            let structure = gen();
-            db.add_structure(&structure).unwrap();
-            db.add_structure(&structure).unwrap_err();
-        }
+    fn add_google_home_unlink(
+        &self,
+        user_id: &UserID,
+        unlinked_at: &DateTime<Utc>,
+    ) -> Result<(), Error> {
+        const SQL: &str =
+            "INSERT OR REPLACE INTO google_home_unlinks(user_id, unlinked_at) VALUES(?, ?)";
+        let connection = self.pool.get()?;
+        connection.execute(SQL, params![user_id, unlinked_at])?;
+
+        Ok(())
     }
 
-    mod room {
-        use super::*;
+    fn remove_google_home_unlink(&self, user_id: &UserID) -> Result<bool, Error> {
+        const SQL: &str = "DELETE FROM google_home_unlinks WHERE user_id = ?";
+        let connection = self.pool.get()?;
+        let n = connection.execute(SQL, params![user_id])?;
 
-        pub fn gen(structure_id: StructureID) -> Room {
-            Room {
-                id: random(),
-                name: "SomeRoom".to_string(),
-                structure_id: structure_id.clone(),
-            }
-        }
+        Ok(n > 0)
+    }
 
-        #[test]
-        fn add_get() {
-            let db = get_database();
-            let structure = super::structure::gen();
-            let room = gen(structure.id.clone());
-            db.add_structure(&structure).unwrap();
-            db.add_room(&room).unwrap();
-            assert_eq!(db.get_room(&room.id).unwrap().unwrap(), room)
-        }
+    fn check_google_home_unlinked(&self, user_id: &UserID) -> Result<bool, Error> {
+        const SQL: &str = "
+            SELECT 1
+            FROM google_home_unlinks
+            WHERE user_id = ?
+            ";
 
-        #[test]
-        fn add_duplicate() {
-            let db = get_database();
-            let structure = super::structure::gen();
-            let room = gen(structure.id.clone());
-            db.add_structure(&structure).unwrap();
-            db.add_room(&room).unwrap();
-            db.add_room(&room).unwrap_err();
-        }
+        let connection = self.pool.get()?;
+        let result = connection
+            .query_row(SQL, params![user_id], |_| Ok(()))
+            .optional()?;
 
-        #[test]
-        fn add_no_structure() {
-            let db = get_database();
-            let structure = super::structure::gen();
-            let room = gen(structure.id.clone());
-            db.add_room(&room).unwrap_err();
-        }
+        Ok(result.is_some())
     }
 
-    mod device {
-        use super::*;
-
-        pub fn gen(room_id: RoomID) -> Device {
-            Device {
-                id: random(),
-                name: "SomeRoom".to_string(),
-                room_id: room_id.clone(),
-                password_hash: "SomePasswordHash".to_string(),
-                device_type: DeviceType::Garage,
-                traits: vec![DeviceTrait::OnOff, DeviceTrait::OpenClose],
-                will_push_state: true,
-                model: "testing-garage".to_string(),
-                hw_version: Version::new(1, 0, 0),
-                sw_version: Version::new(1, 0, 0),
-                attributes: Default::default(),
-            }
-        }
+    fn restore(&self, backup: &Backup) -> Result<(), Error> {
+        // Everything else refers to the users or structures, apart from the admins table which doesn't cascade
+        const DELETE_SQL: &str = "
+            DELETE FROM admins;
+            DELETE FROM users;
+            DELETE FROM structures;
+            ";
 
-        #[test]
-        fn add_get() {
-            let db = get_database();
-            let structure = super::structure::gen();
-            let room = super::room::gen(structure.id.clone());
-            let device = gen(room.id.clone());
-            db.add_structure(&structure).unwrap();
-            db.add_room(&room).unwrap();
-            db.add_device(&device).unwrap();
-            assert_eq!(db.get_device(&device.id).unwrap().unwrap(), device)
+        crate::check_backup_version(backup)?;
+        let mut connection = self.pool.get()?;
+        let tx = connection.transaction()?;
+        let users = crate::restored_users(backup, select_users(&tx)?)?;
+        tx.execute_batch(DELETE_SQL)?;
+        for structure in &backup.structures {
+            insert_structure(&tx, structure)?;
         }
-
-        #[test]
-        fn add_duplicate() {
-            let db = get_database();
-            let structure = super::structure::gen();
-            let room = super::room::gen(structure.id.clone());
-            let device = gen(room.id.clone());
-            db.add_structure(&structure).unwrap();
-            db.add_room(&room).unwrap();
-            db.add_device(&device).unwrap();
-            db.add_device(&device).unwrap_err();
+        for room in &backup.rooms {
+            insert_room(&tx, room)?;
         }
-
-        #[test]
-        fn add_no_room() {
-            let db = get_database();
-            let device = gen(random());
-            db.add_device(&device).unwrap_err();
+        for device in &backup.devices {
+            insert_device(&tx, device)?;
         }
-    }
-
-    mod user {
-        use super::*;
-
-        pub fn gen() -> User {
-            User {
-                id: random(),
-                username: "gbaranski".to_string(),
-                email: "root@gbaranski.com".to_string(),
-                password_hash: "super-secret".to_string(),
-            }
+        for user in &users {
+            insert_user(&tx, user)?;
         }
-
-        #[test]
-        fn add_get() {
-            let db = get_database();
-            let user = gen();
-            db.add_user(&user).unwrap();
-            assert_eq!(db.get_user(&user.id).unwrap().unwrap(), user);
-            assert_eq!(db.get_user_by_email(&user.email).unwrap().unwrap(), user);
-            assert_eq!(db.check_user_admin(&user.id).unwrap(), false);
-            db.add_admin(&user.id).unwrap();
-            assert_eq!(db.check_user_admin(&user.id).unwrap(), true);
+        for user_id in &backup.admins {
+            insert_admin(&tx, user_id)?;
         }
-
-        #[test]
-        fn add_duplicate() {
-            let db = get_database();
-            let user = gen();
-            db.add_user(&user).unwrap();
-            db.add_user(&user).unwrap_err();
+        for user_structure in &backup.user_structures {
+            insert_user_structure(&tx, user_structure)?;
         }
-    }
+        for scoped_role in &backup.scoped_roles {
+            insert_scoped_role(&tx, scoped_role)?;
+        }
+        for device_token in &backup.device_tokens {
+            insert_device_token(&tx, device_token)?;
+        }
+        for unlink in &backup.google_home_unlinks {
+            insert_google_home_unlink(&tx, unlink)?;
+        }
+        tx.commit()?;
 
-    mod user_structure {
-        use super::*;
+        Ok(())
+    }
 
-        pub fn gen(user_id: UserID, structure_id: StructureID, is_manager: bool) -> UserStructure {
-            UserStructure {
-                structure_id,
-                user_id,
-                is_manager,
-            }
+    fn apply_home(&self, plan: &HomePlan) -> Result<(), Error> {
+        let mut connection = self.pool.get()?;
+        let tx = connection.transaction()?;
+        for change in &plan.changes {
+            apply_home_change(&tx, change)?;
         }
+        tx.commit()?;
 
-        #[test]
-        fn add_get() {
-            let db = get_database();
-            let structure_allow = super::structure::gen();
-            let structure_deny = super::structure::gen();
-            let room_allow = super::room::gen(structure_allow.id.clone());
-            let room_deny = super::room::gen(structure_deny.id.clone());
-            let devices_allow =
-                std::iter::repeat_with(|| super::device::gen(room_allow.id.clone()))
-                    .take(5)
-                    .collect::<Vec<_>>();
-            let devices_deny = std::iter::repeat_with(|| super::device::gen(room_deny.id.clone()))
-                .take(5)
-                .collect::<Vec<_>>();
-            let user = super::user::gen();
-            let user_structure = gen(user.id.clone(), structure_allow.id.clone(), false);
-            db.add_user(&user).unwrap();
-            db.add_structure(&structure_allow).unwrap();
-            db.add_structure(&structure_deny).unwrap();
-            db.add_room(&room_allow).unwrap();
-            db.add_room(&room_deny).unwrap();
-            devices_allow
-                .iter()
-                .chain(devices_deny.iter())
-                .for_each(|device| db.add_device(&device).unwrap());
-
-            db.add_user_structure(&user_structure).unwrap();
-
-            devices_allow.iter().for_each(|device| {
-                assert_eq!(
-                    db.check_user_device_access(&user.id, &device.id).unwrap(),
-                    true
-                )
-            });
+        Ok(())
+    }
 
-            devices_deny.iter().for_each(|device| {
-                assert_eq!(
-                    db.check_user_device_access(&user.id, &device.id).unwrap(),
-                    false
-                )
-            });
+    fn backup(&self) -> Result<Backup, Error> {
+        let mut connection = self.pool.get()?;
+        // Reading all of the tables in one transaction keeps the backup consistent with concurrent changes
+        let tx = connection.transaction()?;
+        let backup = Backup {
+            version: Backup::VERSION,
+            created_at: Utc::now(),
+            with_secrets: true,
+            admins: select_admins(&tx)?,
+            structures: select_structures(&tx)?,
+            rooms: select_rooms(&tx)?,
+            devices: select_devices(&tx)?,
+            users: select_users(&tx)?,
+            user_structures: select_user_structures(&tx)?,
+            scoped_roles: select_scoped_roles(&tx)?,
+            device_tokens: select_device_tokens(&tx)?,
+            google_home_unlinks: select_google_home_unlinks(&tx)?,
+        };
+        tx.commit()?;
 
-            let sort_devices = |devices: Vec<Device>| {
-                devices.clone().sort_by(|a, b| a.id.cmp(&b.id));
-                devices
-            };
-            assert_eq!(
-                sort_devices(db.get_user_devices(&user.id).unwrap()),
-                sort_devices(devices_allow)
-            );
-        }
+        Ok(backup)
+    }
+}
 
-        #[test]
-        fn add_duplicate() {
-            let db = get_database();
-            let structure = super::structure::gen();
-            let user = super::user::gen();
-            let user_structure = gen(user.id.clone(), structure.id.clone(), false);
-            db.add_user(&user).unwrap();
-            db.add_structure(&structure).unwrap();
-            db.add_user_structure(&user_structure).unwrap();
-            db.add_user_structure(&user_structure).unwrap_err();
-        }
+#[cfg(test)]
+mod tests {
+    async fn get_database() -> super::Database {
+        super::Database::new_in_memory().unwrap()
     }
+
+    include!("../tests.rs");
 }
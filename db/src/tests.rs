@@ -0,0 +1,1045 @@
+// Tests shared by all of the database backends, they're included into the `tests` module of each backend,
+// which defines `get_database().await` returning a fresh database.
+
+use crate::{Database, Error};
+use chrono::{DateTime, Duration, SubsecRound, Utc};
+use houseflow_types::{
+    traits::{on_off, DeviceState, DeviceStateSnapshot},
+    Device, DevicePresenceEvent, DeviceTrait, DeviceType, Role, RoleScope, Room, RoomID,
+    ScopedRole, Structure, StructureID, User, UserID, UserStructure,
+};
+use rand::random;
+use semver::Version;
+
+/// Timestamps are truncated to microseconds, which is the precision of PostgreSQL
+fn now() -> DateTime<Utc> {
+    Utc::now().trunc_subsecs(6)
+}
+
+mod structure {
+    use super::*;
+
+    pub fn gen() -> Structure {
+        Structure {
+            id: random(),
+            name: "SomeStructure".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn add() {
+        let db = get_database().await;
+        let structure = gen();
+        db.add_structure(&structure).await.unwrap();
+        assert_eq!(
+            db.get_structure(&structure.id).await.unwrap().unwrap(),
+            structure
+        )
+    }
+
+    #[tokio::test]
+    async fn add_duplicate() {
+        let db = get_database().await;
+        let structure = gen();
+        db.add_structure(&structure).await.unwrap();
+        assert!(matches!(
+            db.add_structure(&structure).await,
+            Err(Error::AlreadyExists)
+        ));
+    }
+
+    #[tokio::test]
+    async fn list_update_delete() {
+        let db = get_database().await;
+        let structure = gen();
+        let room = super::room::gen(structure.id.clone());
+        let device = super::device::gen(room.id.clone());
+        db.add_structure(&structure).await.unwrap();
+        db.add_room(&room).await.unwrap();
+        db.add_device(&device).await.unwrap();
+        assert_eq!(db.get_structures().await.unwrap(), vec![structure.clone()]);
+
+        let structure = Structure {
+            name: "OtherStructure".to_string(),
+            ..structure
+        };
+        assert!(db.update_structure(&structure).await.unwrap());
+        assert_eq!(
+            db.get_structure(&structure.id).await.unwrap().unwrap(),
+            structure
+        );
+        assert!(!db.update_structure(&gen()).await.unwrap());
+
+        assert!(db.delete_structure(&structure.id).await.unwrap());
+        assert_eq!(db.get_structure(&structure.id).await.unwrap(), None);
+        assert_eq!(db.get_room(&room.id).await.unwrap(), None);
+        assert_eq!(db.get_device(&device.id).await.unwrap(), None);
+        assert!(!db.delete_structure(&structure.id).await.unwrap());
+    }
+}
+
+mod room {
+    use super::*;
+
+    pub fn gen(structure_id: StructureID) -> Room {
+        Room {
+            id: random(),
+            name: "SomeRoom".to_string(),
+            structure_id: structure_id.clone(),
+        }
+    }
+
+    #[tokio::test]
+    async fn add_get() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let room = gen(structure.id.clone());
+        db.add_structure(&structure).await.unwrap();
+        db.add_room(&room).await.unwrap();
+        assert_eq!(db.get_room(&room.id).await.unwrap().unwrap(), room)
+    }
+
+    #[tokio::test]
+    async fn add_duplicate() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let room = gen(structure.id.clone());
+        db.add_structure(&structure).await.unwrap();
+        db.add_room(&room).await.unwrap();
+        assert!(matches!(
+            db.add_room(&room).await,
+            Err(Error::AlreadyExists)
+        ));
+    }
+
+    #[tokio::test]
+    async fn add_no_structure() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let room = gen(structure.id.clone());
+        assert!(matches!(
+            db.add_room(&room).await,
+            Err(Error::ReferenceNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn list_update_delete() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let other_structure = super::structure::gen();
+        let room = gen(structure.id.clone());
+        let device = super::device::gen(room.id.clone());
+        db.add_structure(&structure).await.unwrap();
+        db.add_structure(&other_structure).await.unwrap();
+        db.add_room(&room).await.unwrap();
+        db.add_device(&device).await.unwrap();
+        assert_eq!(db.get_rooms().await.unwrap(), vec![room.clone()]);
+
+        let room = Room {
+            name: "OtherRoom".to_string(),
+            structure_id: other_structure.id.clone(),
+            ..room
+        };
+        assert!(db.update_room(&room).await.unwrap());
+        assert_eq!(db.get_room(&room.id).await.unwrap().unwrap(), room);
+        assert!(!db.update_room(&gen(structure.id.clone())).await.unwrap());
+        let result = db
+            .update_room(&Room {
+                structure_id: random(),
+                ..room.clone()
+            })
+            .await;
+        assert!(matches!(result, Err(Error::ReferenceNotFound)));
+
+        assert!(db.delete_room(&room.id).await.unwrap());
+        assert_eq!(db.get_room(&room.id).await.unwrap(), None);
+        assert_eq!(db.get_device(&device.id).await.unwrap(), None);
+        assert!(!db.delete_room(&room.id).await.unwrap());
+    }
+}
+
+mod device {
+    use super::*;
+
+    pub fn gen(room_id: RoomID) -> Device {
+        Device {
+            id: random(),
+            name: "SomeRoom".to_string(),
+            room_id: room_id.clone(),
+            password_hash: "SomePasswordHash".to_string(),
+            device_type: DeviceType::Garage,
+            traits: vec![DeviceTrait::OnOff, DeviceTrait::OpenClose],
+            will_push_state: true,
+            model: "testing-garage".to_string(),
+            hw_version: Version::new(1, 0, 0),
+            sw_version: Version::new(1, 0, 0),
+            attributes: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn add_get() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let room = super::room::gen(structure.id.clone());
+        let device = gen(room.id.clone());
+        db.add_structure(&structure).await.unwrap();
+        db.add_room(&room).await.unwrap();
+        db.add_device(&device).await.unwrap();
+        assert_eq!(db.get_device(&device.id).await.unwrap().unwrap(), device)
+    }
+
+    #[tokio::test]
+    async fn add_duplicate() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let room = super::room::gen(structure.id.clone());
+        let device = gen(room.id.clone());
+        db.add_structure(&structure).await.unwrap();
+        db.add_room(&room).await.unwrap();
+        db.add_device(&device).await.unwrap();
+        assert!(matches!(
+            db.add_device(&device).await,
+            Err(Error::AlreadyExists)
+        ));
+    }
+
+    #[tokio::test]
+    async fn add_no_room() {
+        let db = get_database().await;
+        let device = gen(random());
+        assert!(matches!(
+            db.add_device(&device).await,
+            Err(Error::ReferenceNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn set_get_state() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let room = super::room::gen(structure.id.clone());
+        let device = gen(room.id.clone());
+        db.add_structure(&structure).await.unwrap();
+        db.add_room(&room).await.unwrap();
+        db.add_device(&device).await.unwrap();
+        assert_eq!(db.get_device_state(&device.id).await.unwrap(), None);
+
+        let mut snapshot = DeviceStateSnapshot {
+            state: DeviceState {
+                on_off: Some(on_off::State { on: true }),
+                ..Default::default()
+            },
+            updated_at: now(),
+        };
+        db.set_device_state(&device.id, &snapshot).await.unwrap();
+        assert_eq!(
+            db.get_device_state(&device.id).await.unwrap().unwrap(),
+            snapshot
+        );
+
+        snapshot.state.on_off = Some(on_off::State { on: false });
+        snapshot.updated_at = now();
+        db.set_device_state(&device.id, &snapshot).await.unwrap();
+        assert_eq!(
+            db.get_device_state(&device.id).await.unwrap().unwrap(),
+            snapshot
+        );
+    }
+
+    #[tokio::test]
+    async fn add_get_state_history() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let room = super::room::gen(structure.id.clone());
+        let device = gen(room.id.clone());
+        db.add_structure(&structure).await.unwrap();
+        db.add_room(&room).await.unwrap();
+        db.add_device(&device).await.unwrap();
+
+        let start = now();
+        let snapshots = (0..10)
+            .map(|i| DeviceStateSnapshot {
+                state: DeviceState {
+                    on_off: Some(on_off::State { on: i % 2 == 0 }),
+                    ..Default::default()
+                },
+                updated_at: start + Duration::minutes(i),
+            })
+            .collect::<Vec<_>>();
+        for snapshot in snapshots.iter().rev() {
+            db.add_device_state_history(&device.id, snapshot)
+                .await
+                .unwrap();
+        }
+
+        let history = |since, until, limit, offset| {
+            db.get_device_state_history(&device.id, since, until, limit, offset)
+        };
+        assert_eq!(history(None, None, 100, 0).await.unwrap(), snapshots);
+        assert_eq!(history(None, None, 3, 2).await.unwrap(), snapshots[2..5]);
+        assert_eq!(
+            history(
+                Some(start + Duration::minutes(4)),
+                Some(start + Duration::minutes(7)),
+                100,
+                0
+            )
+            .await
+            .unwrap(),
+            snapshots[4..7]
+        );
+        assert_eq!(
+            db.get_device_state_history(&random(), None, None, 100, 0)
+                .await
+                .unwrap(),
+            vec![]
+        );
+    }
+
+    #[tokio::test]
+    async fn add_get_presence_event() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let room = super::room::gen(structure.id.clone());
+        let device = gen(room.id.clone());
+        db.add_structure(&structure).await.unwrap();
+        db.add_room(&room).await.unwrap();
+        db.add_device(&device).await.unwrap();
+        assert_eq!(
+            db.get_last_device_presence_event(&device.id).await.unwrap(),
+            None
+        );
+
+        let connected = DevicePresenceEvent {
+            connected: true,
+            address: "127.0.0.1:1234".parse().unwrap(),
+            occurred_at: now(),
+        };
+        let disconnected = DevicePresenceEvent {
+            connected: false,
+            occurred_at: connected.occurred_at + Duration::minutes(5),
+            ..connected.clone()
+        };
+        db.add_device_presence_event(&device.id, &connected)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_last_device_presence_event(&device.id).await.unwrap(),
+            Some(connected)
+        );
+        db.add_device_presence_event(&device.id, &disconnected)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_last_device_presence_event(&device.id).await.unwrap(),
+            Some(disconnected)
+        );
+    }
+
+    #[tokio::test]
+    async fn set_get_token_id() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let room = super::room::gen(structure.id.clone());
+        let device = gen(room.id.clone());
+        db.add_structure(&structure).await.unwrap();
+        db.add_room(&room).await.unwrap();
+        db.add_device(&device).await.unwrap();
+        assert_eq!(db.get_device_token_id(&device.id).await.unwrap(), None);
+
+        let (first, second) = (random(), random());
+        db.set_device_token_id(&device.id, &first, &now())
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_device_token_id(&device.id).await.unwrap(),
+            Some(first)
+        );
+        db.set_device_token_id(&device.id, &second, &now())
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_device_token_id(&device.id).await.unwrap(),
+            Some(second)
+        );
+        assert!(db.remove_device_token_id(&device.id).await.unwrap());
+        assert_eq!(db.get_device_token_id(&device.id).await.unwrap(), None);
+        assert!(!db.remove_device_token_id(&device.id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn set_password_hash() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let room = super::room::gen(structure.id.clone());
+        let device = gen(room.id.clone());
+        db.add_structure(&structure).await.unwrap();
+        db.add_room(&room).await.unwrap();
+        db.add_device(&device).await.unwrap();
+
+        assert!(db
+            .set_device_password_hash(&device.id, "new-hash")
+            .await
+            .unwrap());
+        assert_eq!(
+            db.get_device(&device.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .password_hash,
+            "new-hash"
+        );
+        assert!(!db
+            .set_device_password_hash(&random(), "new-hash")
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn list_update_delete() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let room = super::room::gen(structure.id.clone());
+        let device = gen(room.id.clone());
+        db.add_structure(&structure).await.unwrap();
+        db.add_room(&room).await.unwrap();
+        db.add_device(&device).await.unwrap();
+        db.set_device_token_id(&device.id, &random(), &now())
+            .await
+            .unwrap();
+        assert_eq!(db.get_devices().await.unwrap(), vec![device.clone()]);
+
+        let updated = Device {
+            name: "OtherDevice".to_string(),
+            password_hash: "ignored".to_string(),
+            traits: vec![DeviceTrait::OnOff],
+            sw_version: Version::new(1, 1, 0),
+            ..device.clone()
+        };
+        assert!(db.update_device(&updated).await.unwrap());
+        assert_eq!(
+            db.get_device(&device.id).await.unwrap().unwrap(),
+            Device {
+                password_hash: device.password_hash.clone(),
+                ..updated
+            }
+        );
+        assert!(!db.update_device(&gen(room.id.clone())).await.unwrap());
+
+        assert!(db.delete_device(&device.id).await.unwrap());
+        assert_eq!(db.get_device(&device.id).await.unwrap(), None);
+        assert_eq!(db.get_device_token_id(&device.id).await.unwrap(), None);
+        assert!(!db.delete_device(&device.id).await.unwrap());
+    }
+}
+
+mod user {
+    use super::*;
+
+    pub fn gen() -> User {
+        User {
+            id: random(),
+            username: "gbaranski".to_string(),
+            email: "root@gbaranski.com".to_string(),
+            password_hash: "super-secret".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn add_get() {
+        let db = get_database().await;
+        let user = gen();
+        db.add_user(&user).await.unwrap();
+        assert_eq!(db.get_user(&user.id).await.unwrap().unwrap(), user);
+        assert_eq!(
+            db.get_user_by_email(&user.email).await.unwrap().unwrap(),
+            user
+        );
+        assert!(!db.check_user_admin(&user.id).await.unwrap());
+        db.add_admin(&user.id).await.unwrap();
+        assert!(db.check_user_admin(&user.id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn add_duplicate() {
+        let db = get_database().await;
+        let user = gen();
+        db.add_user(&user).await.unwrap();
+        assert!(matches!(
+            db.add_user(&user).await,
+            Err(Error::AlreadyExists)
+        ));
+        let other_user = User {
+            email: user.email.clone(),
+            ..gen()
+        };
+        assert!(matches!(
+            db.add_user(&other_user).await,
+            Err(Error::AlreadyExists)
+        ));
+    }
+
+    #[tokio::test]
+    async fn google_home_unlink() {
+        let db = get_database().await;
+        let user = gen();
+        db.add_user(&user).await.unwrap();
+        assert!(!db.check_google_home_unlinked(&user.id).await.unwrap());
+        db.add_google_home_unlink(&user.id, &now()).await.unwrap();
+        db.add_google_home_unlink(&user.id, &now()).await.unwrap();
+        assert!(db.check_google_home_unlinked(&user.id).await.unwrap());
+        assert!(db.remove_google_home_unlink(&user.id).await.unwrap());
+        assert!(!db.check_google_home_unlinked(&user.id).await.unwrap());
+        assert!(!db.remove_google_home_unlink(&user.id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn list_update_delete() {
+        let db = get_database().await;
+        let user = gen();
+        let structure = super::structure::gen();
+        db.add_user(&user).await.unwrap();
+        db.add_admin(&user.id).await.unwrap();
+        db.add_structure(&structure).await.unwrap();
+        db.add_user_structure(&super::user_structure::gen(
+            user.id.clone(),
+            structure.id.clone(),
+            Role::Manager,
+        ))
+        .await
+        .unwrap();
+        assert_eq!(db.get_users().await.unwrap(), vec![user.clone()]);
+
+        let user = User {
+            username: "other".to_string(),
+            email: "other@gbaranski.com".to_string(),
+            ..user
+        };
+        assert!(db.update_user(&user).await.unwrap());
+        assert_eq!(db.get_user(&user.id).await.unwrap().unwrap(), user);
+        assert!(!db.update_user(&gen()).await.unwrap());
+
+        assert!(db.delete_user(&user.id).await.unwrap());
+        assert_eq!(db.get_user(&user.id).await.unwrap(), None);
+        assert!(!db.check_user_admin(&user.id).await.unwrap());
+        assert_eq!(db.get_user_structures().await.unwrap(), vec![]);
+        assert!(!db.delete_user(&user.id).await.unwrap());
+    }
+}
+
+mod user_structure {
+    use super::*;
+
+    pub fn gen(user_id: UserID, structure_id: StructureID, role: Role) -> UserStructure {
+        UserStructure {
+            structure_id,
+            user_id,
+            role,
+        }
+    }
+
+    #[tokio::test]
+    async fn add_get() {
+        let db = get_database().await;
+        let structure_allow = super::structure::gen();
+        let structure_deny = super::structure::gen();
+        let room_allow = super::room::gen(structure_allow.id.clone());
+        let room_deny = super::room::gen(structure_deny.id.clone());
+        let devices_allow = std::iter::repeat_with(|| super::device::gen(room_allow.id.clone()))
+            .take(5)
+            .collect::<Vec<_>>();
+        let devices_deny = std::iter::repeat_with(|| super::device::gen(room_deny.id.clone()))
+            .take(5)
+            .collect::<Vec<_>>();
+        let user = super::user::gen();
+        let user_structure = gen(user.id.clone(), structure_allow.id.clone(), Role::Operator);
+        db.add_user(&user).await.unwrap();
+        db.add_structure(&structure_allow).await.unwrap();
+        db.add_structure(&structure_deny).await.unwrap();
+        db.add_room(&room_allow).await.unwrap();
+        db.add_room(&room_deny).await.unwrap();
+        for device in devices_allow.iter().chain(devices_deny.iter()) {
+            db.add_device(device).await.unwrap();
+        }
+
+        db.add_user_structure(&user_structure).await.unwrap();
+
+        for device in &devices_allow {
+            assert_eq!(
+                db.get_user_device_role(&user.id, &device.id).await.unwrap(),
+                Some(Role::Operator)
+            )
+        }
+
+        for device in &devices_deny {
+            assert_eq!(
+                db.get_user_device_role(&user.id, &device.id).await.unwrap(),
+                None
+            )
+        }
+
+        let sort_devices = |mut devices: Vec<Device>| {
+            devices.sort_by(|a, b| a.id.cmp(&b.id));
+            devices
+        };
+        assert_eq!(
+            sort_devices(db.get_user_devices(&user.id).await.unwrap()),
+            sort_devices(devices_allow)
+        );
+    }
+
+    #[tokio::test]
+    async fn get_device_users() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let room = super::room::gen(structure.id.clone());
+        let device = super::device::gen(room.id.clone());
+        let user = super::user::gen();
+        let other_user = User {
+            id: random(),
+            email: "other@gbaranski.com".to_string(),
+            ..super::user::gen()
+        };
+        db.add_user(&user).await.unwrap();
+        db.add_user(&other_user).await.unwrap();
+        db.add_structure(&structure).await.unwrap();
+        db.add_room(&room).await.unwrap();
+        db.add_device(&device).await.unwrap();
+        assert_eq!(db.get_device_users(&device.id).await.unwrap(), vec![]);
+
+        db.add_user_structure(&gen(user.id.clone(), structure.id.clone(), Role::Operator))
+            .await
+            .unwrap();
+        assert_eq!(db.get_device_users(&device.id).await.unwrap(), vec![user]);
+    }
+
+    #[tokio::test]
+    async fn add_duplicate() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let user = super::user::gen();
+        let user_structure = gen(user.id.clone(), structure.id.clone(), Role::Operator);
+        db.add_user(&user).await.unwrap();
+        db.add_structure(&structure).await.unwrap();
+        db.add_user_structure(&user_structure).await.unwrap();
+        assert!(matches!(
+            db.add_user_structure(&user_structure).await,
+            Err(Error::AlreadyExists)
+        ));
+    }
+
+    #[tokio::test]
+    async fn scoped_roles() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let living_room = super::room::gen(structure.id.clone());
+        let garden = super::room::gen(structure.id.clone());
+        let light = super::device::gen(living_room.id.clone());
+        let gate = super::device::gen(garden.id.clone());
+        let parent = super::user::gen();
+        let guest = User {
+            id: random(),
+            email: "guest@gbaranski.com".to_string(),
+            ..super::user::gen()
+        };
+        db.add_user(&parent).await.unwrap();
+        db.add_user(&guest).await.unwrap();
+        db.add_structure(&structure).await.unwrap();
+        db.add_room(&living_room).await.unwrap();
+        db.add_room(&garden).await.unwrap();
+        db.add_device(&light).await.unwrap();
+        db.add_device(&gate).await.unwrap();
+        db.add_user_structure(&gen(
+            parent.id.clone(),
+            structure.id.clone(),
+            Role::Operator,
+        ))
+        .await
+        .unwrap();
+
+        let scoped_roles = vec![
+            ScopedRole {
+                user_id: parent.id.clone(),
+                scope: RoleScope::Room(garden.id.clone()),
+                role: Role::Manager,
+            },
+            ScopedRole {
+                user_id: parent.id.clone(),
+                scope: RoleScope::Device(gate.id.clone()),
+                role: Role::Viewer,
+            },
+            ScopedRole {
+                user_id: guest.id.clone(),
+                scope: RoleScope::Device(light.id.clone()),
+                role: Role::Operator,
+            },
+        ];
+        for scoped_role in &scoped_roles {
+            db.add_scoped_role(scoped_role).await.unwrap();
+        }
+        assert_eq!(db.get_scoped_roles().await.unwrap(), scoped_roles);
+
+        // The most specific role takes precedence
+        let role = |user: &User, device: &Device| {
+            let (db, user_id, device_id) = (&db, user.id.clone(), device.id.clone());
+            async move { db.get_user_device_role(&user_id, &device_id).await.unwrap() }
+        };
+        assert_eq!(role(&parent, &light).await, Some(Role::Operator));
+        assert_eq!(role(&parent, &gate).await, Some(Role::Viewer));
+        assert_eq!(
+            db.get_user_room_role(&parent.id, &garden.id).await.unwrap(),
+            Some(Role::Manager)
+        );
+        assert_eq!(role(&guest, &light).await, Some(Role::Operator));
+        assert_eq!(role(&guest, &gate).await, None);
+        assert_eq!(
+            db.get_user_room_role(&guest.id, &living_room.id)
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            db.get_user_devices(&guest.id).await.unwrap(),
+            vec![light.clone()]
+        );
+        assert_eq!(
+            db.get_device_users(&gate.id).await.unwrap(),
+            vec![parent.clone()]
+        );
+
+        assert!(db
+            .delete_scoped_role(&parent.id, &RoleScope::Device(gate.id.clone()))
+            .await
+            .unwrap());
+        assert_eq!(role(&parent, &gate).await, Some(Role::Manager));
+        assert!(!db
+            .delete_scoped_role(&parent.id, &RoleScope::Device(gate.id.clone()))
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn check_manager() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let other_structure = super::structure::gen();
+        let manager = super::user::gen();
+        let member = User {
+            id: random(),
+            email: "other@gbaranski.com".to_string(),
+            ..super::user::gen()
+        };
+        db.add_user(&manager).await.unwrap();
+        db.add_user(&member).await.unwrap();
+        db.add_structure(&structure).await.unwrap();
+        db.add_structure(&other_structure).await.unwrap();
+        db.add_user_structure(&gen(
+            manager.id.clone(),
+            structure.id.clone(),
+            Role::Manager,
+        ))
+        .await
+        .unwrap();
+        db.add_user_structure(&gen(
+            member.id.clone(),
+            structure.id.clone(),
+            Role::Operator,
+        ))
+        .await
+        .unwrap();
+
+        assert!(db
+            .check_user_structure_manager(&manager.id, &structure.id)
+            .await
+            .unwrap());
+        assert!(!db
+            .check_user_structure_manager(&manager.id, &other_structure.id)
+            .await
+            .unwrap());
+        assert!(!db
+            .check_user_structure_manager(&member.id, &structure.id)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn list_update_delete() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let user = super::user::gen();
+        let user_structure = gen(user.id.clone(), structure.id.clone(), Role::Operator);
+        db.add_user(&user).await.unwrap();
+        db.add_structure(&structure).await.unwrap();
+        db.add_user_structure(&user_structure).await.unwrap();
+        assert_eq!(
+            db.get_user_structures().await.unwrap(),
+            vec![user_structure.clone()]
+        );
+
+        let user_structure = UserStructure {
+            role: Role::Manager,
+            ..user_structure
+        };
+        assert!(db.update_user_structure(&user_structure).await.unwrap());
+        assert_eq!(
+            db.get_user_structure(&structure.id, &user.id)
+                .await
+                .unwrap()
+                .unwrap(),
+            user_structure
+        );
+        assert!(!db
+            .update_user_structure(&gen(random(), structure.id.clone(), Role::Manager))
+            .await
+            .unwrap());
+
+        assert!(db
+            .delete_user_structure(&structure.id, &user.id)
+            .await
+            .unwrap());
+        assert_eq!(
+            db.get_user_structure(&structure.id, &user.id)
+                .await
+                .unwrap(),
+            None
+        );
+        assert!(!db
+            .delete_user_structure(&structure.id, &user.id)
+            .await
+            .unwrap());
+    }
+}
+
+mod backup {
+    use super::*;
+    use houseflow_types::{Backup, GoogleHomeUnlink, IssuedDeviceToken};
+
+    /// Structure with a room and device, and an admin which is a member of the structure.
+    /// The device has a token issued and the admin has unlinked their Google Home account
+    fn gen() -> Backup {
+        let structure = super::structure::gen();
+        let room = super::room::gen(structure.id.clone());
+        let device = super::device::gen(room.id.clone());
+        let user = super::user::gen();
+        Backup {
+            version: Backup::VERSION,
+            created_at: now(),
+            with_secrets: true,
+            admins: vec![user.id.clone()],
+            user_structures: vec![super::user_structure::gen(
+                user.id.clone(),
+                structure.id.clone(),
+                Role::Viewer,
+            )],
+            scoped_roles: vec![
+                ScopedRole {
+                    user_id: user.id.clone(),
+                    scope: RoleScope::Room(room.id.clone()),
+                    role: Role::Operator,
+                },
+                ScopedRole {
+                    user_id: user.id.clone(),
+                    scope: RoleScope::Device(device.id.clone()),
+                    role: Role::Manager,
+                },
+            ],
+            device_tokens: vec![IssuedDeviceToken {
+                device_id: device.id.clone(),
+                token_id: random(),
+                issued_at: now(),
+            }],
+            google_home_unlinks: vec![GoogleHomeUnlink {
+                user_id: user.id.clone(),
+                unlinked_at: now(),
+            }],
+            structures: vec![structure],
+            rooms: vec![room],
+            devices: vec![device],
+            users: vec![user],
+        }
+    }
+
+    fn snapshot() -> DeviceStateSnapshot {
+        DeviceStateSnapshot {
+            state: Default::default(),
+            updated_at: now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn backup_restore() {
+        let db = get_database().await;
+        let backup = gen();
+        db.restore(&backup).await.unwrap();
+        let restored = db.backup().await.unwrap();
+        assert_eq!(
+            Backup {
+                created_at: backup.created_at,
+                ..restored
+            },
+            backup
+        );
+        let device_id = &backup.devices[0].id;
+        let user_id = &backup.users[0].id;
+        assert!(db.check_user_admin(user_id).await.unwrap());
+        assert_eq!(
+            db.get_user_device_role(user_id, device_id).await.unwrap(),
+            Some(Role::Manager)
+        );
+        assert_eq!(
+            db.get_device_token_id(device_id).await.unwrap(),
+            Some(backup.device_tokens[0].token_id.clone())
+        );
+        assert!(db.check_google_home_unlinked(user_id).await.unwrap());
+
+        // Restoring replaces everything that was there before, including states of the devices
+        db.set_device_state(device_id, &snapshot()).await.unwrap();
+        let other = gen();
+        db.restore(&other).await.unwrap();
+        let restored = db.backup().await.unwrap();
+        assert_eq!(
+            Backup {
+                created_at: other.created_at,
+                ..restored
+            },
+            other
+        );
+        assert_eq!(db.get_device(device_id).await.unwrap(), None);
+        assert_eq!(db.get_device_state(device_id).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn restore_invalid() {
+        let db = get_database().await;
+        let before = gen();
+        db.restore(&before).await.unwrap();
+        let device_id = &before.devices[0].id;
+        db.set_device_state(device_id, &snapshot()).await.unwrap();
+
+        let mut backup = gen();
+        backup.rooms.push(super::room::gen(random()));
+        assert!(matches!(
+            db.restore(&backup).await,
+            Err(Error::ReferenceNotFound)
+        ));
+
+        let backup = Backup {
+            version: Backup::VERSION + 1,
+            ..gen()
+        };
+        assert!(matches!(
+            db.restore(&backup).await,
+            Err(Error::UnsupportedBackupVersion(_))
+        ));
+
+        // Nothing is changed if the restore fails
+        let after = db.backup().await.unwrap();
+        assert_eq!(
+            Backup {
+                created_at: before.created_at,
+                ..after
+            },
+            before
+        );
+        assert!(db.get_device_state(device_id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn restore_without_secrets() {
+        let db = get_database().await;
+        let before = gen();
+        db.restore(&before).await.unwrap();
+        let admin = before.users[0].clone();
+
+        // Users which already exist keep their passwords, the new ones can't log in
+        let new_user = User {
+            email: String::from("other@gbaranski.com"),
+            ..super::user::gen()
+        };
+        let backup = Backup {
+            users: vec![admin.clone(), new_user.clone()],
+            ..before.clone()
+        }
+        .without_secrets();
+        db.restore(&backup).await.unwrap();
+        assert_eq!(db.get_user(&admin.id).await.unwrap(), Some(admin.clone()));
+        assert!(db
+            .get_user(&new_user.id)
+            .await
+            .unwrap()
+            .unwrap()
+            .password_hash
+            .is_empty());
+
+        // None of the admins would have a password
+        assert!(matches!(
+            db.restore(&gen().without_secrets()).await,
+            Err(Error::NoAdminPassword)
+        ));
+        assert_eq!(db.get_user(&admin.id).await.unwrap(), Some(admin));
+    }
+}
+
+mod home {
+    use super::*;
+    use houseflow_types::{HomeChange, HomeItem, HomePlan};
+
+    #[tokio::test]
+    async fn apply_home() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let room = super::room::gen(structure.id.clone());
+        let mut device = super::device::gen(room.id.clone());
+        db.add_structure(&structure).await.unwrap();
+        db.add_room(&room).await.unwrap();
+        db.add_device(&device).await.unwrap();
+
+        let created = super::structure::gen();
+        let before = HomeItem::Device(Box::new(device.clone()));
+        device.name = String::from("Other name");
+        let plan = HomePlan {
+            changes: vec![
+                HomeChange::Create {
+                    item: HomeItem::Structure(created.clone()),
+                },
+                HomeChange::Update {
+                    before,
+                    after: HomeItem::Device(Box::new(device.clone())),
+                },
+                HomeChange::Delete {
+                    item: HomeItem::Room(super::room::gen(structure.id.clone())),
+                },
+            ],
+        };
+        db.apply_home(&plan).await.unwrap();
+        assert_eq!(db.get_structure(&created.id).await.unwrap(), Some(created));
+        assert_eq!(db.get_device(&device.id).await.unwrap(), Some(device));
+    }
+
+    #[tokio::test]
+    async fn apply_home_failed() {
+        let db = get_database().await;
+        let structure = super::structure::gen();
+        let missing = super::room::gen(structure.id.clone());
+        let plan = HomePlan {
+            changes: vec![
+                HomeChange::Create {
+                    item: HomeItem::Structure(structure.clone()),
+                },
+                HomeChange::Update {
+                    before: HomeItem::Room(missing.clone()),
+                    after: HomeItem::Room(missing),
+                },
+            ],
+        };
+        assert!(matches!(
+            db.apply_home(&plan).await,
+            Err(Error::NotModified)
+        ));
+
+        // Changes made before the failed one are rolled back
+        assert_eq!(db.get_structure(&structure.id).await.unwrap(), None);
+    }
+}
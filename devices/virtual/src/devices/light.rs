@@ -1,16 +1,13 @@
 use async_trait::async_trait;
-use houseflow_types::{DeviceCommand, DeviceError, DeviceStatus};
+use houseflow_types::traits::{
+    brightness::{self, BrightnessAbsoluteParams, BrightnessRelativeParams},
+    color_setting::{self, Color},
+    on_off::{self, OnOffParams},
+    CommandParams, DeviceState,
+};
+use houseflow_types::{DeviceError, DeviceStatus};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(untagged)]
-pub enum ExecuteParams {
-    NoOperation(()),
-    OnOff { on: bool },
-}
-
-impl super::ExecuteParams for ExecuteParams {}
-
 pub trait OnOffHook: Fn(&mut State, bool) -> DeviceStatus + Send {}
 
 impl std::fmt::Debug for dyn OnOffHook {
@@ -20,14 +17,49 @@ impl std::fmt::Debug for dyn OnOffHook {
 }
 impl<T> OnOffHook for T where T: Fn(&mut State, bool) -> DeviceStatus + Send {}
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub trait BrightnessHook: Fn(&mut State, u8) -> DeviceStatus + Send {}
+
+impl std::fmt::Debug for dyn BrightnessHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("[Function]")
+    }
+}
+impl<T> BrightnessHook for T where T: Fn(&mut State, u8) -> DeviceStatus + Send {}
+
+pub trait ColorHook: Fn(&mut State, Color) -> DeviceStatus + Send {}
+
+impl std::fmt::Debug for dyn ColorHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("[Function]")
+    }
+}
+impl<T> ColorHook for T where T: Fn(&mut State, Color) -> DeviceStatus + Send {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct State {
     pub on: bool,
+    pub brightness: u8,
+    pub color: Color,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            on: false,
+            brightness: 100,
+            color: Color::default(),
+        }
+    }
 }
 
+/// Brightness change applied for each unit of `brightnessRelativeWeight`
+const BRIGHTNESS_WEIGHT_STEP: i16 = 10;
+
 #[derive(Debug)]
 pub struct Hooks {
     on_off: Box<dyn OnOffHook>,
+    brightness: Box<dyn BrightnessHook>,
+    color: Box<dyn ColorHook>,
 }
 
 impl Default for Hooks {
@@ -37,6 +69,18 @@ impl Default for Hooks {
                 tracing::info!("Changing `on` to {0}", on);
                 state.on = on;
 
+                DeviceStatus::Success
+            }),
+            brightness: Box::new(|state, brightness| {
+                tracing::info!("Changing `brightness` to {0}", brightness);
+                state.brightness = brightness;
+
+                DeviceStatus::Success
+            }),
+            color: Box::new(|state, color| {
+                tracing::info!("Changing `color` to {:?}", color);
+                state.color = color;
+
                 DeviceStatus::Success
             }),
         }
@@ -52,36 +96,134 @@ pub struct Device {
 impl Device {
     pub fn new(on_off_hook: impl OnOffHook + 'static) -> Self {
         Self {
-            state: State { on: false },
+            state: State::default(),
             hooks: Hooks {
                 on_off: Box::new(on_off_hook),
+                ..Default::default()
             },
         }
     }
+
+    pub fn with_brightness_hook(mut self, hook: impl BrightnessHook + 'static) -> Self {
+        self.hooks.brightness = Box::new(hook);
+        self
+    }
+
+    pub fn with_color_hook(mut self, hook: impl ColorHook + 'static) -> Self {
+        self.hooks.color = Box::new(hook);
+        self
+    }
 }
 
 #[async_trait]
-impl super::Device<ExecuteParams> for Device {
-    async fn on_execute(
-        &mut self,
-        command: DeviceCommand,
-        params: ExecuteParams,
-    ) -> anyhow::Result<DeviceStatus> {
-        let result = match command {
-            DeviceCommand::OnOff => match params {
-                ExecuteParams::OnOff { on } => (self.hooks.on_off)(&mut self.state, on),
-                _ => DeviceStatus::Error(DeviceError::InvalidParameters),
-            },
+impl super::Device for Device {
+    async fn on_execute(&mut self, params: CommandParams) -> anyhow::Result<DeviceStatus> {
+        let result = match params {
+            CommandParams::OnOff(OnOffParams { on }) => (self.hooks.on_off)(&mut self.state, on),
+            CommandParams::BrightnessAbsolute(BrightnessAbsoluteParams { brightness })
+                if brightness <= 100 =>
+            {
+                (self.hooks.brightness)(&mut self.state, brightness)
+            }
+            CommandParams::BrightnessAbsolute(_) => {
+                DeviceStatus::Error(DeviceError::InvalidParameters)
+            }
+            CommandParams::BrightnessRelative(params) => {
+                let change = match params {
+                    BrightnessRelativeParams::Percent {
+                        brightness_relative_percent,
+                    } => brightness_relative_percent as i16,
+                    BrightnessRelativeParams::Weight {
+                        brightness_relative_weight,
+                    } => brightness_relative_weight as i16 * BRIGHTNESS_WEIGHT_STEP,
+                };
+                let brightness = (self.state.brightness as i16 + change).clamp(0, 100) as u8;
+                (self.hooks.brightness)(&mut self.state, brightness)
+            }
+            CommandParams::ColorAbsolute(params) => {
+                (self.hooks.color)(&mut self.state, params.color.value.into())
+            }
             _ => DeviceStatus::Error(DeviceError::FunctionNotSupported),
         };
         Ok(result)
     }
 
-    fn state(&self) -> serde_json::Map<String, serde_json::Value> {
-        serde_json::to_value(&self.state)
-            .unwrap()
-            .as_object()
-            .unwrap()
-            .clone()
+    fn state(&self) -> DeviceState {
+        DeviceState {
+            on_off: Some(on_off::State { on: self.state.on }),
+            brightness: Some(brightness::State {
+                brightness: self.state.brightness,
+            }),
+            color_setting: Some(color_setting::State {
+                color: self.state.color.clone(),
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::Device as _;
+
+    use houseflow_types::DeviceCommand;
+
+    fn params(command: DeviceCommand, json: serde_json::Value) -> CommandParams {
+        let value = serde_json::json!({ "command": command, "params": json });
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn brightness() {
+        let mut device = Device::default();
+        let status = device
+            .on_execute(params(
+                DeviceCommand::BrightnessAbsolute,
+                serde_json::json!({ "brightness": 40 }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(status, DeviceStatus::Success);
+        let status = device
+            .on_execute(params(
+                DeviceCommand::BrightnessRelative,
+                serde_json::json!({ "brightnessRelativeWeight": -1 }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(status, DeviceStatus::Success);
+        assert_eq!(
+            device.state().brightness,
+            Some(brightness::State { brightness: 30 })
+        );
+
+        let status = device
+            .on_execute(params(
+                DeviceCommand::BrightnessAbsolute,
+                serde_json::json!({ "brightness": 120 }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(status, DeviceStatus::Error(DeviceError::InvalidParameters));
+    }
+
+    #[tokio::test]
+    async fn color() {
+        let mut device = Device::default();
+        let status = device
+            .on_execute(params(
+                DeviceCommand::ColorAbsolute,
+                serde_json::json!({ "color": { "temperature": 2700 } }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(status, DeviceStatus::Success);
+        assert_eq!(
+            device.state().color_setting,
+            Some(color_setting::State {
+                color: Color::TemperatureK(2700)
+            })
+        );
     }
 }
@@ -1,20 +1,18 @@
 pub mod light;
+pub mod thermostat;
 
 use async_trait::async_trait;
-use houseflow_types::{DeviceCommand, DeviceStatus};
-
-pub trait ExecuteParams: serde::de::DeserializeOwned {}
+use houseflow_types::{
+    traits::{CommandParams, DeviceState},
+    DeviceStatus,
+};
 
 #[async_trait]
-pub trait Device<EP>
-where
-    EP: ExecuteParams,
-{
-    async fn on_execute(
-        &mut self,
-        command: DeviceCommand,
-        params: EP,
-    ) -> anyhow::Result<DeviceStatus>;
+pub trait Device {
+    async fn on_execute(&mut self, params: CommandParams) -> anyhow::Result<DeviceStatus>;
+
+    fn state(&self) -> DeviceState;
 
-    fn state(&self) -> serde_json::Map<String, serde_json::Value>;
+    /// Called when the connection with the server is established or lost
+    fn on_connectivity_change(&mut self, _connected: bool) {}
 }
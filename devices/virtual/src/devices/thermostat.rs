@@ -0,0 +1,105 @@
+use async_trait::async_trait;
+use houseflow_types::traits::{
+    temperature_setting::{self, ThermostatMode},
+    CommandParams, DeviceState,
+};
+use houseflow_types::{DeviceError, DeviceStatus};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct State {
+    pub thermostat_mode: ThermostatMode,
+    pub thermostat_temperature_ambient: f64,
+    pub thermostat_temperature_setpoint: f64,
+    pub thermostat_temperature_setpoint_high: f64,
+    pub thermostat_temperature_setpoint_low: f64,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            thermostat_mode: ThermostatMode::Off,
+            thermostat_temperature_ambient: 21.0,
+            thermostat_temperature_setpoint: 21.0,
+            thermostat_temperature_setpoint_high: 24.0,
+            thermostat_temperature_setpoint_low: 18.0,
+        }
+    }
+}
+
+/// Modes which can be set using the ThermostatSetMode command
+pub const AVAILABLE_MODES: &[ThermostatMode] = &[
+    ThermostatMode::Off,
+    ThermostatMode::Heat,
+    ThermostatMode::Cool,
+    ThermostatMode::Heatcool,
+];
+
+#[derive(Default, Debug)]
+pub struct Device {
+    state: State,
+}
+
+#[async_trait]
+impl super::Device for Device {
+    async fn on_execute(&mut self, params: CommandParams) -> anyhow::Result<DeviceStatus> {
+        let result = match params {
+            CommandParams::ThermostatTemperatureSetpoint(params) => {
+                tracing::info!(
+                    "Changing `thermostatTemperatureSetpoint` to {}",
+                    params.thermostat_temperature_setpoint
+                );
+                self.state.thermostat_temperature_setpoint = params.thermostat_temperature_setpoint;
+                DeviceStatus::Success
+            }
+            CommandParams::ThermostatTemperatureSetRange(params)
+                if params.thermostat_temperature_setpoint_low
+                    <= params.thermostat_temperature_setpoint_high =>
+            {
+                tracing::info!(
+                    "Changing temperature range to {}-{}",
+                    params.thermostat_temperature_setpoint_low,
+                    params.thermostat_temperature_setpoint_high
+                );
+                self.state.thermostat_temperature_setpoint_low =
+                    params.thermostat_temperature_setpoint_low;
+                self.state.thermostat_temperature_setpoint_high =
+                    params.thermostat_temperature_setpoint_high;
+                DeviceStatus::Success
+            }
+            CommandParams::ThermostatSetMode(params)
+                if AVAILABLE_MODES.contains(&params.thermostat_mode) =>
+            {
+                tracing::info!("Changing `thermostatMode` to {}", params.thermostat_mode);
+                self.state.thermostat_mode = params.thermostat_mode;
+                DeviceStatus::Success
+            }
+            CommandParams::ThermostatTemperatureSetRange(_)
+            | CommandParams::ThermostatSetMode(_) => {
+                DeviceStatus::Error(DeviceError::InvalidParameters)
+            }
+            _ => DeviceStatus::Error(DeviceError::FunctionNotSupported),
+        };
+        Ok(result)
+    }
+
+    fn state(&self) -> DeviceState {
+        DeviceState {
+            temperature_setting: Some(temperature_setting::State {
+                active_thermostat_mode: None,
+                thermostat_mode: self.state.thermostat_mode,
+                thermostat_temperature_ambient: Some(self.state.thermostat_temperature_ambient),
+                thermostat_temperature_setpoint: Some(self.state.thermostat_temperature_setpoint),
+                thermostat_temperature_setpoint_high: Some(
+                    self.state.thermostat_temperature_setpoint_high,
+                ),
+                thermostat_temperature_setpoint_low: Some(
+                    self.state.thermostat_temperature_setpoint_low,
+                ),
+                thermostat_humidity_ambient: None,
+            }),
+            ..Default::default()
+        }
+    }
+}
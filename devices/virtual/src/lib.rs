@@ -1,13 +1,11 @@
 use houseflow_config::device::Config;
-use session::Session;
 
 pub mod devices;
 mod session;
 
-pub async fn run<D: devices::Device<EP>, EP: devices::ExecuteParams>(
-    cfg: Config,
-    device: D,
-) -> anyhow::Result<()> {
+pub use session::{Session, StatePusher};
+
+pub async fn run(cfg: Config, device: impl devices::Device) -> anyhow::Result<()> {
     let session = Session::new(cfg);
     session.run(device).await?;
 
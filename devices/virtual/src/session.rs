@@ -1,7 +1,9 @@
 use crate::devices;
-use futures_util::{Sink, SinkExt, StreamExt};
+use futures_util::{FutureExt, Sink, SinkExt, StreamExt};
 use houseflow_config::device::Config;
-use houseflow_types::lighthouse::proto::{execute_response, state, Frame};
+use houseflow_types::lighthouse::proto::{self, error, execute_response, state, Encoding, Frame};
+use houseflow_types::traits::DeviceState;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tungstenite::Message as WebsocketMessage;
 use url::Url;
@@ -18,62 +20,213 @@ pub enum Event {
 pub type EventSender = mpsc::Sender<Event>;
 pub type EventReceiver = mpsc::Receiver<Event>;
 
+/// Delay before the first reconnection attempt
+const BACKOFF_INITIAL_DELAY: Duration = Duration::from_millis(500);
+
+/// Upper bound of the delay between reconnection attempts
+const BACKOFF_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Time the connection must stay up before the backoff is reset,
+/// so a server closing connections right after accepting them isn't reconnected to in a tight loop
+const BACKOFF_RESET_UPTIME: Duration = Duration::from_secs(30);
+
+/// Exponential backoff with jitter, so devices don't reconnect all at once after the server restarts
+#[derive(Debug, Default)]
+pub struct Backoff {
+    attempt: u32,
+}
+
+impl Backoff {
+    /// Returns delay before the next attempt, which is between half and the whole of the exponential delay
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = BACKOFF_INITIAL_DELAY
+            .checked_mul(1 << self.attempt.min(16))
+            .map_or(BACKOFF_MAX_DELAY, |delay| delay.min(BACKOFF_MAX_DELAY));
+        self.attempt = self.attempt.saturating_add(1);
+
+        delay / 2 + delay.mul_f64(rand::random::<f64>() / 2.0)
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+
+    /// Resets the backoff if the connection has been up for long enough
+    pub fn on_disconnected(&mut self, uptime: Duration) {
+        if uptime >= BACKOFF_RESET_UPTIME {
+            self.reset();
+        }
+    }
+}
+
+/// Handle that lets device implementations push their state to the server at any time
+#[derive(Debug, Clone)]
+pub struct StatePusher {
+    sender: mpsc::UnboundedSender<DeviceState>,
+}
+
+impl StatePusher {
+    /// Sends the state to the server, states pushed while disconnected are superseded by the state sent on reconnect
+    pub fn push(&self, state: DeviceState) -> Result<(), anyhow::Error> {
+        self.sender
+            .send(state)
+            .map_err(|_| anyhow::anyhow!("session is not running"))
+    }
+}
+
 pub struct Session {
     config: Config,
+    state_sender: mpsc::UnboundedSender<DeviceState>,
+    state_receiver: Option<mpsc::UnboundedReceiver<DeviceState>>,
 }
 
 impl Session {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        let (state_sender, state_receiver) = mpsc::unbounded_channel();
+        Self {
+            config,
+            state_sender,
+            state_receiver: Some(state_receiver),
+        }
     }
 
-    pub async fn run<D: devices::Device<EP>, EP: devices::ExecuteParams>(
-        self,
-        device: D,
-    ) -> Result<(), anyhow::Error> {
+    pub fn state_pusher(&self) -> StatePusher {
+        StatePusher {
+            sender: self.state_sender.clone(),
+        }
+    }
+
+    fn http_request(&self) -> http::Request<()> {
         use houseflow_config::defaults;
 
         let url = format!(
             "ws{}://{}:{}/lighthouse/ws",
             if self.config.use_tls { "s" } else { "" },
             self.config.server_hostname,
-            if self.config.use_tls {
-                defaults::server_port_tls()
-            } else {
-                defaults::server_port()
-            },
+            self.config
+                .server_port
+                .unwrap_or_else(|| if self.config.use_tls {
+                    defaults::server_port_tls()
+                } else {
+                    defaults::server_port()
+                }),
         );
-        tracing::info!("`{}` will be used the as Server URL", url);
         let url = Url::parse(&url).unwrap();
 
         tracing::debug!("will use {} as websocket endpoint", url);
-        let http_request = http::Request::builder()
+        http::Request::builder()
             .uri(url.to_string())
+            .header(proto::VERSION_HEADER, proto::VERSION.to_string())
             .header(
-                http::header::AUTHORIZATION,
-                format!(
-                    "Basic {}:{}",
-                    self.config.device_id, self.config.device_password
-                ),
+                http::header::SEC_WEBSOCKET_PROTOCOL,
+                match self.config.encoding {
+                    Encoding::Json => Encoding::Json.protocol().to_string(),
+                    // Fallback to JSON if server doesn't support the preferred encoding
+                    encoding => format!("{}, {}", encoding.protocol(), Encoding::Json.protocol()),
+                },
             )
+            .header(http::header::AUTHORIZATION, self.authorization())
             .body(())
-            .unwrap();
+            .unwrap()
+    }
+
+    fn authorization(&self) -> String {
+        match self.config.device_token {
+            Some(ref token) => format!("Bearer {}", token),
+            None => {
+                let credentials =
+                    format!("{}:{}", self.config.device_id, self.config.device_password);
+                format!("Basic {}", base64::encode(credentials))
+            }
+        }
+    }
+
+    /// Keeps the device connected to the server, reconnecting with a backoff whenever the connection is lost
+    pub async fn run(mut self, mut device: impl devices::Device) -> Result<(), anyhow::Error> {
+        let mut states = self
+            .state_receiver
+            .take()
+            .expect("session must not be run twice");
+        let mut backoff = Backoff::default();
+        loop {
+            match tokio_tungstenite::connect_async(self.http_request()).await {
+                Ok((stream, response)) => {
+                    let encoding = response
+                        .headers()
+                        .get(http::header::SEC_WEBSOCKET_PROTOCOL)
+                        .and_then(|protocol| protocol.to_str().ok())
+                        .map(Encoding::negotiate)
+                        .unwrap_or_default();
+                    let version = response
+                        .headers()
+                        .get(proto::VERSION_HEADER)
+                        .and_then(|version| version.to_str().ok())
+                        .and_then(|version| semver::Version::parse(version).ok())
+                        .unwrap_or(proto::LEGACY_VERSION);
+                    tracing::info!(
+                        "Connected to the server, using protocol version {} with {:?} encoding",
+                        version,
+                        encoding
+                    );
+                    let connected_at = Instant::now();
+                    device.on_connectivity_change(true);
+                    let result = self
+                        .run_connection(stream, encoding, &version, &mut device, &mut states)
+                        .await;
+                    device.on_connectivity_change(false);
+                    backoff.on_disconnected(connected_at.elapsed());
+                    match result {
+                        Ok(()) => tracing::warn!("Connection closed"),
+                        Err(err) => tracing::error!("Connection failed: {}", err),
+                    }
+                }
+                Err(err) => tracing::error!("Connecting to the server failed: {}", err),
+            }
 
-        let (stream, _) = tokio_tungstenite::connect_async(http_request).await?;
+            let delay = backoff.next_delay();
+            tracing::info!("Reconnecting in {}ms", delay.as_millis());
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    async fn run_connection<S, D: devices::Device>(
+        &self,
+        stream: S,
+        encoding: Encoding,
+        version: &semver::Version,
+        device: &mut D,
+        states: &mut mpsc::UnboundedReceiver<DeviceState>,
+    ) -> Result<(), anyhow::Error>
+    where
+        S: futures_util::Stream<Item = Result<WebsocketMessage, tungstenite::Error>>
+            + Sink<WebsocketMessage, Error = tungstenite::Error>
+            + Unpin,
+    {
         let (event_sender, event_receiver) = mpsc::channel::<Event>(8);
         let (stream_sender, stream_receiver) = stream.split();
 
+        // State might have changed while the device was offline, pushes from that time are outdated
+        while let Some(Some(_)) = states.recv().now_or_never() {}
+        let state_frame = Frame::State(state::Frame {
+            state: device.state(),
+        });
+        event_sender
+            .send(Event::LighthouseFrame(state_frame))
+            .await
+            .expect("failed sending event");
+
         tokio::select! {
-            v = self.stream_read(stream_receiver, event_sender, device) => { v }
-            v = self.stream_write(stream_sender, event_receiver) => { v }
+            v = self.stream_read(stream_receiver, version, event_sender, device) => { v }
+            v = self.stream_write(stream_sender, encoding, event_receiver, states) => { v }
         }
     }
 
-    async fn stream_read<S, D: devices::Device<EP>, EP: devices::ExecuteParams>(
+    async fn stream_read<S, D: devices::Device>(
         &self,
         mut stream: S,
+        version: &semver::Version,
         events: EventSender,
-        mut device: D,
+        device: &mut D,
     ) -> anyhow::Result<()>
     where
         S: futures_util::Stream<Item = Result<WebsocketMessage, tungstenite::Error>> + Unpin,
@@ -83,43 +236,13 @@ impl Session {
             match message {
                 WebsocketMessage::Text(text) => {
                     tracing::debug!("Raw frame: `{}`", text);
-                    let frame: Frame = serde_json::from_str(&text)?;
-                    tracing::debug!("Parsed frame: {:?}", frame);
-                    match frame {
-                        Frame::Execute(frame) => {
-                            let params: EP =
-                                serde_json::from_value(serde_json::Value::Object(frame.params))?;
-                            let status = device.on_execute(frame.command, params).await?;
-                            let response_frame = execute_response::Frame {
-                                id: frame.id,
-                                status,
-                                state: device.state(),
-                            };
-                            let response_frame = Frame::ExecuteResponse(response_frame);
-                            let response_event = Event::LighthouseFrame(response_frame);
-                            events
-                                .send(response_event)
-                                .await
-                                .expect("failed sending event");
-                        }
-                        Frame::Query(_) => {
-                            let response_frame = state::Frame {
-                                state: device.state(),
-                            };
-                            let response_frame = Frame::State(response_frame);
-                            let response_event = Event::LighthouseFrame(response_frame);
-                            events
-                                .send(response_event)
-                                .await
-                                .expect("failed sending event");
-                        }
-                        _ => {
-                            panic!("Unexpected frame received")
-                        }
-                    }
+                    self.handle_message(Encoding::Json, text.as_bytes(), version, &events, device)
+                        .await?;
                 }
                 WebsocketMessage::Binary(bytes) => {
-                    tracing::debug!("received binary: {:?}", bytes);
+                    tracing::debug!("Raw frame: {:?}", bytes);
+                    self.handle_message(Encoding::Cbor, &bytes, version, &events, device)
+                        .await?;
                 }
                 WebsocketMessage::Ping(payload) => {
                     events
@@ -140,15 +263,104 @@ impl Session {
         Ok(())
     }
 
+    /// Decodes and handles the frame, errors are reported back to the server if it supports it
+    async fn handle_message<D: devices::Device>(
+        &self,
+        encoding: Encoding,
+        bytes: &[u8],
+        version: &semver::Version,
+        events: &EventSender,
+        device: &mut D,
+    ) -> anyhow::Result<()> {
+        let error = match encoding.decode(bytes) {
+            Ok(frame) => match self.handle_frame(frame, events, device).await? {
+                Some(error) => error,
+                None => return Ok(()),
+            },
+            Err(err) => error::Error::InvalidFrame(err.to_string()),
+        };
+        tracing::warn!("Invalid frame received: {}", error);
+
+        // Older servers would close the connection when receiving unknown frame
+        if *version >= proto::ERROR_FRAME_VERSION {
+            let frame = Frame::Error(error::Frame {
+                id: encoding.decode_id(bytes),
+                error,
+            });
+            events
+                .send(Event::LighthouseFrame(frame))
+                .await
+                .expect("failed sending event");
+        }
+        Ok(())
+    }
+
+    /// Returns error if the frame is not expected by the device
+    async fn handle_frame<D: devices::Device>(
+        &self,
+        frame: Frame,
+        events: &EventSender,
+        device: &mut D,
+    ) -> anyhow::Result<Option<error::Error>> {
+        tracing::debug!("Parsed frame: {:?}", frame);
+        match frame {
+            Frame::Execute(frame) => {
+                let status = device.on_execute(frame.params).await?;
+                let response_frame = execute_response::Frame {
+                    id: frame.id,
+                    status,
+                    state: device.state(),
+                };
+                let response_frame = Frame::ExecuteResponse(response_frame);
+                let response_event = Event::LighthouseFrame(response_frame);
+                events
+                    .send(response_event)
+                    .await
+                    .expect("failed sending event");
+            }
+            Frame::Query(_) => {
+                let response_frame = state::Frame {
+                    state: device.state(),
+                };
+                let response_frame = Frame::State(response_frame);
+                let response_event = Event::LighthouseFrame(response_frame);
+                events
+                    .send(response_event)
+                    .await
+                    .expect("failed sending event");
+            }
+            Frame::Error(frame) => {
+                tracing::warn!("Server rejected frame {:?}: {}", frame.id, frame.error);
+            }
+            frame => {
+                return Ok(Some(error::Error::UnexpectedFrame(
+                    frame.name().to_string(),
+                )))
+            }
+        }
+        Ok(None)
+    }
+
     async fn stream_write<S>(
         &self,
         mut stream: S,
+        encoding: Encoding,
         mut events: EventReceiver,
+        states: &mut mpsc::UnboundedReceiver<DeviceState>,
     ) -> Result<(), anyhow::Error>
     where
         S: Sink<WebsocketMessage, Error = tungstenite::Error> + Unpin,
     {
-        while let Some(event) = events.recv().await {
+        loop {
+            let event = tokio::select! {
+                event = events.recv() => match event {
+                    Some(event) => event,
+                    None => break,
+                },
+                Some(state) = states.recv() => {
+                    Event::LighthouseFrame(Frame::State(state::Frame { state }))
+                }
+            };
             match event {
                 Event::Ping => {
                     tracing::info!("Sending Ping");
@@ -159,12 +371,121 @@ impl Session {
                     stream.send(WebsocketMessage::Pong(Vec::new())).await?;
                 }
                 Event::LighthouseFrame(frame) => {
-                    let json = serde_json::to_string(&frame).unwrap();
-                    tracing::debug!("sending text message: {}", json);
-                    stream.send(WebsocketMessage::Text(json)).await?;
+                    let bytes = encoding.encode(&frame)?;
+                    let message = if encoding.is_binary() {
+                        WebsocketMessage::Binary(bytes)
+                    } else {
+                        WebsocketMessage::Text(String::from_utf8(bytes)?)
+                    };
+                    tracing::debug!("sending message: {:?}", message);
+                    stream.send(message).await?;
                 }
             }
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use houseflow_types::DeviceStatus;
+
+    #[test]
+    fn backoff() {
+        let mut backoff = Backoff::default();
+        let bounds = |delay: Duration| delay / 2..=delay;
+
+        assert!(bounds(BACKOFF_INITIAL_DELAY).contains(&backoff.next_delay()));
+        assert!(bounds(BACKOFF_INITIAL_DELAY * 2).contains(&backoff.next_delay()));
+        assert!(bounds(BACKOFF_INITIAL_DELAY * 4).contains(&backoff.next_delay()));
+        for _ in 0..100 {
+            assert!(backoff.next_delay() <= BACKOFF_MAX_DELAY);
+        }
+        assert!(bounds(BACKOFF_MAX_DELAY).contains(&backoff.next_delay()));
+
+        backoff.reset();
+        assert!(bounds(BACKOFF_INITIAL_DELAY).contains(&backoff.next_delay()));
+
+        backoff.on_disconnected(Duration::from_millis(10));
+        assert!(bounds(BACKOFF_INITIAL_DELAY * 2).contains(&backoff.next_delay()));
+        backoff.on_disconnected(BACKOFF_RESET_UPTIME);
+        assert!(bounds(BACKOFF_INITIAL_DELAY).contains(&backoff.next_delay()));
+    }
+
+    #[tokio::test]
+    async fn error_frame() {
+        let session = Session::new(Config {
+            device_id: rand::random(),
+            device_password: String::from("password"),
+            device_token: None,
+            server_hostname: url::Host::Domain(String::from("localhost")),
+            server_port: None,
+            use_tls: false,
+            encoding: Encoding::Json,
+        });
+        let mut device = devices::light::Device::new(|_, _| DeviceStatus::Success);
+        let (events, mut receiver) = mpsc::channel(8);
+        let unexpected = Encoding::Json
+            .encode(&Frame::ExecuteResponse(execute_response::Frame {
+                id: 4,
+                status: DeviceStatus::Success,
+                state: DeviceState::default(),
+            }))
+            .unwrap();
+        let cases = [
+            (
+                br#"{"type": "Execute", "id": 3}"#.to_vec(),
+                Some(3),
+                "invalid_frame",
+            ),
+            (unexpected, Some(4), "unexpected_frame"),
+        ];
+
+        for (bytes, id, error) in cases.iter() {
+            session
+                .handle_message(Encoding::Json, bytes, &proto::VERSION, &events, &mut device)
+                .await
+                .unwrap();
+            match receiver.recv().now_or_never() {
+                Some(Some(Event::LighthouseFrame(Frame::Error(frame)))) => {
+                    assert_eq!(frame.id, *id);
+                    let json = serde_json::to_value(&frame.error).unwrap();
+                    assert_eq!(json["error"], *error);
+                }
+                event => panic!("expected error frame, received: {:?}", event),
+            }
+
+            // Older servers don't understand error frames
+            session
+                .handle_message(
+                    Encoding::Json,
+                    bytes,
+                    &proto::LEGACY_VERSION,
+                    &events,
+                    &mut device,
+                )
+                .await
+                .unwrap();
+            assert!(receiver.recv().now_or_never().is_none());
+        }
+
+        let rejected = Encoding::Json
+            .encode(&Frame::Error(error::Frame {
+                id: Some(5),
+                error: error::Error::ResponseWithoutRequest,
+            }))
+            .unwrap();
+        session
+            .handle_message(
+                Encoding::Json,
+                &rejected,
+                &proto::VERSION,
+                &events,
+                &mut device,
+            )
+            .await
+            .unwrap();
+        assert!(receiver.recv().now_or_never().is_none());
+    }
+}
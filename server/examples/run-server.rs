@@ -1,7 +1,6 @@
 use actix_web::{web, HttpServer};
 use houseflow_config::server::Config;
-use houseflow_db::{sqlite::Database as SqliteDatabase, Database};
-use houseflow_server::{Sessions, SledTokenStore, TokenStore};
+use houseflow_server::{homegraph::HomeGraph, Sessions, SledTokenStore, StateUpdates, TokenStore};
 use std::sync::Arc;
 
 #[actix_web::main]
@@ -14,9 +13,25 @@ async fn main() {
     let token_store = SledTokenStore::new(&config.tokens_path).expect("cannot open token store");
     let token_store = web::Data::from(Arc::new(token_store) as Arc<dyn TokenStore>);
 
-    let database = SqliteDatabase::new(&config.database_path).expect("cannot open database");
-    let database = web::Data::from(Arc::new(database) as Arc<dyn Database>);
+    let database = web::Data::from(
+        houseflow_db::open(&config)
+            .await
+            .expect("cannot open database"),
+    );
     let sessions = web::Data::new(Sessions::default());
+    let state_updates = web::Data::new(StateUpdates::default());
+    let homegraph = config
+        .google
+        .as_ref()
+        .and_then(|google| HomeGraph::from_config(google).expect("cannot load service account key"))
+        .map(web::Data::new);
+    if let Some(homegraph) = &homegraph {
+        actix_rt::spawn(houseflow_server::homegraph::report_state_updates(
+            homegraph.clone(),
+            database.clone(),
+            state_updates.subscribe(),
+        ));
+    }
     let config_cloned = config.clone();
     let server = HttpServer::new(move || {
         actix_web::App::new()
@@ -28,6 +43,8 @@ async fn main() {
                     database.clone(),
                     config_cloned.clone(),
                     sessions.clone(),
+                    state_updates.clone(),
+                    homegraph.clone(),
                 )
             })
     });
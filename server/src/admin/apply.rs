@@ -0,0 +1,202 @@
+use crate::{
+    home,
+    homegraph::{self, HomeGraph},
+};
+use actix_web::{
+    web::{Data, Json},
+    HttpRequest,
+};
+use houseflow_config::server::Config;
+use houseflow_db::Database;
+use houseflow_types::{admin::apply, token::AccessToken};
+
+impl From<home::Error> for apply::ResponseError {
+    fn from(err: home::Error) -> Self {
+        match err {
+            home::Error::DatabaseError(err) => err.into_internal_server_error().into(),
+            home::Error::StalePlan => Self::StalePlan,
+            err => Self::InvalidHome(err.to_string()),
+        }
+    }
+}
+
+pub async fn on_apply(
+    Json(request): Json<apply::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+    sessions: Data<crate::Sessions>,
+    homegraph: Option<Data<HomeGraph>>,
+) -> Result<Json<apply::ResponseBody>, apply::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(apply::ResponseError::UserNotAdmin);
+    }
+
+    let plan = home::plan(&**db, &request.home, request.prune).await?;
+    if request.dry_run || plan.is_empty() {
+        return Ok(Json(apply::ResponseBody { plan }));
+    }
+    home::apply(&**db, &plan).await?;
+
+    // Devices are also deleted along with their rooms and structures, so the remaining sessions are checked
+    let device_ids = sessions.lock().unwrap().keys().cloned().collect::<Vec<_>>();
+    for device_id in device_ids {
+        if db
+            .get_device(&device_id)
+            .await
+            .map_err(houseflow_db::Error::into_internal_server_error)?
+            .is_none()
+        {
+            super::device::disconnect(&sessions, &device_id, "device deleted").await;
+        }
+    }
+    let users = db
+        .get_users()
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?;
+    homegraph::request_sync(
+        homegraph.as_ref().map(|homegraph| homegraph.get_ref()),
+        &**db,
+        &users,
+    )
+    .await;
+
+    Ok(Json(apply::ResponseBody { plan }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use houseflow_types::{Home, HomeRoom, HomeStructure, Structure, User};
+
+    async fn apply(
+        state: &State,
+        user: &User,
+        home: Home,
+        dry_run: bool,
+    ) -> Result<Json<apply::ResponseBody>, apply::ResponseError> {
+        on_apply(
+            Json(apply::Request {
+                home,
+                prune: true,
+                dry_run,
+            }),
+            get_http_request(&state.config, user),
+            state.config.clone(),
+            state.database.clone(),
+            Data::new(crate::Sessions::default()),
+            None,
+        )
+        .await
+    }
+
+    #[actix_rt::test]
+    async fn dry_run_and_apply() {
+        let state = get_state();
+        let admin = get_user();
+        let structure = get_structure();
+        state.database.add_user(&admin).await.unwrap();
+        state.database.add_admin(&admin.id).await.unwrap();
+        state.database.add_structure(&structure).await.unwrap();
+        let home = Home {
+            structures: vec![HomeStructure {
+                id: rand::random(),
+                name: "Home".to_string(),
+                members: vec![],
+                rooms: vec![HomeRoom {
+                    id: rand::random(),
+                    name: "Kitchen".to_string(),
+                    devices: vec![],
+                }],
+            }],
+        };
+
+        let plan = apply(&state, &admin, home.clone(), true)
+            .await
+            .unwrap()
+            .into_inner()
+            .plan;
+        assert_eq!(plan.summary(), (2, 0, 1));
+        assert_eq!(
+            state.database.get_structures().await.unwrap(),
+            vec![structure]
+        );
+
+        let applied = apply(&state, &admin, home.clone(), false)
+            .await
+            .unwrap()
+            .into_inner()
+            .plan;
+        assert_eq!(applied, plan);
+        assert_eq!(
+            state.database.get_structures().await.unwrap(),
+            vec![home.structures[0].to_structure()]
+        );
+        let plan = apply(&state, &admin, home, true)
+            .await
+            .unwrap()
+            .into_inner()
+            .plan;
+        assert!(plan.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn stale_plan() {
+        let state = get_state();
+        let home = Home {
+            structures: vec![HomeStructure {
+                id: rand::random(),
+                name: "Home".to_string(),
+                members: vec![],
+                rooms: vec![],
+            }],
+        };
+        let structure = Structure {
+            name: "Old home".to_string(),
+            ..home.structures[0].to_structure()
+        };
+        state.database.add_structure(&structure).await.unwrap();
+        let plan = home::plan(&**state.database, &home, false).await.unwrap();
+        assert_eq!(plan.summary(), (0, 1, 0));
+
+        // Updated structure is deleted before the plan gets applied
+        state
+            .database
+            .delete_structure(&structure.id)
+            .await
+            .unwrap();
+        let err = home::apply(&**state.database, &plan).await.unwrap_err();
+        match apply::ResponseError::from(err) {
+            err @ apply::ResponseError::StalePlan => {
+                assert_eq!(
+                    actix_web::ResponseError::status_code(&err),
+                    actix_web::http::StatusCode::CONFLICT
+                )
+            }
+            err => panic!("expected stale plan, received: {:?}", err),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn not_admin() {
+        let state = get_state();
+        let user = get_user();
+        state.database.add_user(&user).await.unwrap();
+
+        match apply(&state, &user, Home::default(), true).await {
+            Err(apply::ResponseError::UserNotAdmin) => (),
+            result => panic!(
+                "expected user not admin, received: {:?}",
+                result.map(|_| ())
+            ),
+        }
+    }
+}
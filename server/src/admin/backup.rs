@@ -0,0 +1,244 @@
+use crate::homegraph::{self, HomeGraph};
+use actix_web::{
+    web::{Data, Json},
+    HttpRequest,
+};
+use houseflow_config::server::Config;
+use houseflow_db::Database;
+use houseflow_types::{
+    admin::backup::{export, import},
+    token::AccessToken,
+};
+
+pub async fn on_export(
+    Json(request): Json<export::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+) -> Result<Json<export::ResponseBody>, export::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(export::ResponseError::UserNotAdmin);
+    }
+
+    let backup = db
+        .backup()
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?;
+    let backup = match request.without_secrets {
+        true => backup.without_secrets(),
+        false => backup,
+    };
+
+    Ok(Json(export::ResponseBody { backup }))
+}
+
+pub async fn on_import(
+    Json(request): Json<import::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+    sessions: Data<crate::Sessions>,
+    homegraph: Option<Data<HomeGraph>>,
+) -> Result<Json<import::ResponseBody>, import::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(import::ResponseError::UserNotAdmin);
+    }
+
+    db.restore(&request.backup).await.map_err(|err| match err {
+        houseflow_db::Error::UnsupportedBackupVersion(version) => {
+            import::ResponseError::UnsupportedVersion(version)
+        }
+        houseflow_db::Error::AlreadyExists => import::ResponseError::AlreadyExists,
+        houseflow_db::Error::ReferenceNotFound => import::ResponseError::ReferenceNotFound,
+        houseflow_db::Error::NoAdminPassword => import::ResponseError::NoAdminPassword,
+        err => err.into_internal_server_error().into(),
+    })?;
+
+    // Devices could have been removed or got other passwords, so they must connect again
+    let sessions = sessions
+        .lock()
+        .unwrap()
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    for session in sessions {
+        // Fails only if the session has been stopped in the meantime
+        let _ = session
+            .send(crate::lighthouse::Disconnect {
+                reason: "configuration restored from backup".to_string(),
+            })
+            .await;
+    }
+    homegraph::request_sync(
+        homegraph.as_ref().map(|homegraph| homegraph.get_ref()),
+        &**db,
+        &request.backup.users,
+    )
+    .await;
+
+    Ok(Json(import::ResponseBody {}))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use houseflow_types::{Backup, User};
+
+    async fn import(
+        state: &State,
+        user: &User,
+        backup: Backup,
+    ) -> Result<Json<import::ResponseBody>, import::ResponseError> {
+        on_import(
+            Json(import::Request { backup }),
+            get_http_request(&state.config, user),
+            state.config.clone(),
+            state.database.clone(),
+            Data::new(crate::Sessions::default()),
+            None,
+        )
+        .await
+    }
+
+    #[actix_rt::test]
+    async fn export_import() {
+        let state = get_state();
+        let admin = get_user();
+        let structure = get_structure();
+        let room = get_room(&structure);
+        let device = get_device(&room);
+        state.database.add_user(&admin).await.unwrap();
+        state.database.add_admin(&admin.id).await.unwrap();
+        state.database.add_structure(&structure).await.unwrap();
+        state.database.add_room(&room).await.unwrap();
+        state.database.add_device(&device).await.unwrap();
+
+        let export = |without_secrets| {
+            on_export(
+                Json(export::Request { without_secrets }),
+                get_http_request(&state.config, &admin),
+                state.config.clone(),
+                state.database.clone(),
+            )
+        };
+        let backup = export(true).await.unwrap().into_inner().backup;
+        assert!(!backup.with_secrets);
+        assert_eq!(backup.devices[0].password_hash, "");
+        let backup = export(false).await.unwrap().into_inner().backup;
+        assert!(backup.with_secrets);
+        assert_eq!(backup.devices, vec![device]);
+
+        let other = get_state();
+        other.database.add_user(&admin).await.unwrap();
+        other.database.add_admin(&admin.id).await.unwrap();
+        other
+            .database
+            .add_structure(&get_structure())
+            .await
+            .unwrap();
+        import(&other, &admin, backup.clone()).await.unwrap();
+        let restored = other.database.backup().await.unwrap();
+        assert_eq!(restored.structures, backup.structures);
+        assert_eq!(restored.devices, backup.devices);
+        assert_eq!(restored.users, backup.users);
+
+        let unsupported = Backup {
+            version: Backup::VERSION + 1,
+            ..backup
+        };
+        match import(&other, &admin, unsupported).await {
+            Err(import::ResponseError::UnsupportedVersion(_)) => (),
+            result => panic!(
+                "expected unsupported version, received: {:?}",
+                result.map(|_| ())
+            ),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn import_without_secrets() {
+        let state = get_state();
+        let admin = get_user();
+        state.database.add_user(&admin).await.unwrap();
+        state.database.add_admin(&admin.id).await.unwrap();
+
+        let backup = state.database.backup().await.unwrap().without_secrets();
+        import(&state, &admin, backup.clone()).await.unwrap();
+        crate::auth::on_login(
+            Json(houseflow_types::auth::login::Request {
+                email: admin.email.clone(),
+                password: PASSWORD.into(),
+            }),
+            state.token_store.clone(),
+            state.config.clone(),
+            state.database.clone(),
+        )
+        .await
+        .unwrap();
+
+        let other_admin = User {
+            password_hash: String::new(),
+            ..get_user()
+        };
+        let backup = Backup {
+            admins: vec![other_admin.id.clone()],
+            users: vec![admin.clone(), other_admin],
+            ..backup
+        };
+        match import(&state, &admin, backup).await {
+            Err(import::ResponseError::NoAdminPassword) => (),
+            result => panic!(
+                "expected no admin password, received: {:?}",
+                result.map(|_| ())
+            ),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn not_admin() {
+        let state = get_state();
+        let user = get_user();
+        state.database.add_user(&user).await.unwrap();
+
+        let result = on_export(
+            Json(export::Request {
+                without_secrets: false,
+            }),
+            get_http_request(&state.config, &user),
+            state.config.clone(),
+            state.database.clone(),
+        )
+        .await;
+        match result {
+            Err(export::ResponseError::UserNotAdmin) => (),
+            result => panic!(
+                "expected user not admin, received: {:?}",
+                result.map(|_| ())
+            ),
+        }
+
+        let backup = state.database.backup().await.unwrap();
+        match import(&state, &user, backup).await {
+            Err(import::ResponseError::UserNotAdmin) => (),
+            result => panic!(
+                "expected user not admin, received: {:?}",
+                result.map(|_| ())
+            ),
+        }
+    }
+}
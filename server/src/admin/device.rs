@@ -1,3 +1,4 @@
+use crate::homegraph::{self, HomeGraph};
 use actix_web::{
     web::{Data, Json},
     HttpRequest,
@@ -5,9 +6,12 @@ use actix_web::{
 use houseflow_config::server::Config;
 use houseflow_db::Database;
 use houseflow_types::{
-    admin::device::add::{Request, ResponseBody, ResponseError},
-    token::AccessToken,
-    Device,
+    admin::device::{
+        add::{Request, ResponseBody, ResponseError},
+        connected, delete, get, list, revoke, rotate_password, rotate_token, update,
+    },
+    token::{AccessToken, DeviceToken, DeviceTokenPayload},
+    Device, DeviceID,
 };
 
 pub async fn on_add(
@@ -15,15 +19,13 @@ pub async fn on_add(
     http_request: HttpRequest,
     config: Data<Config>,
     db: Data<dyn Database>,
+    homegraph: Option<Data<HomeGraph>>,
 ) -> Result<Json<ResponseBody>, ResponseError> {
     let access_token =
         AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
 
-    if !db
-        .check_user_admin(&access_token.sub)
-        .map_err(houseflow_db::Error::into_internal_server_error)?
-    {
-        return Err(ResponseError::UserNotAdmin);
+    if !super::check_room_permission(&**db, &access_token.sub, &request.room_id).await? {
+        return Err(ResponseError::UserNotManager);
     }
 
     let device = Device {
@@ -46,9 +48,361 @@ pub async fn on_add(
     };
 
     db.add_device(&device)
+        .await
+        .map_err(super::add_response_error)?;
+
+    let users = db
+        .get_device_users(&device.id)
+        .await
         .map_err(houseflow_db::Error::into_internal_server_error)?;
+    homegraph::request_sync(
+        homegraph.as_ref().map(|homegraph| homegraph.get_ref()),
+        &**db,
+        &users,
+    )
+    .await;
 
     Ok(Json(ResponseBody {
         device_id: device.id,
     }))
 }
+
+pub async fn on_connected(
+    _request: Json<connected::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+    sessions: Data<crate::Sessions>,
+) -> Result<Json<connected::ResponseBody>, connected::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(connected::ResponseError::UserNotAdmin);
+    }
+
+    let sessions = sessions
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(device_id, session)| (device_id.clone(), session.clone()))
+        .collect::<Vec<_>>();
+    let now = chrono::Utc::now();
+    let devices = sessions.into_iter().map(|(device_id, session)| async move {
+        // Fails only if the session has been stopped in the meantime
+        let info = session
+            .send(crate::lighthouse::GetConnectionInfo)
+            .await
+            .ok()?;
+        Some(connected::ConnectedDevice {
+            device_id,
+            address: info.address,
+            connected_at: info.connected_at,
+            uptime: (now - info.connected_at).num_seconds().max(0) as u64,
+            protocol_version: info.protocol_version,
+        })
+    });
+    let devices = futures::future::join_all(devices)
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+
+    Ok(Json(connected::ResponseBody { devices }))
+}
+
+pub async fn on_rotate_token(
+    Json(request): Json<rotate_token::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+) -> Result<Json<rotate_token::ResponseBody>, rotate_token::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(rotate_token::ResponseError::UserNotAdmin);
+    }
+    let device_key = config
+        .secrets
+        .device_key
+        .as_ref()
+        .ok_or(rotate_token::ResponseError::DeviceKeyNotConfigured)?;
+
+    db.get_device(&request.device_id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+        .ok_or(rotate_token::ResponseError::DeviceNotFound)?;
+
+    let payload = DeviceTokenPayload {
+        tid: rand::random(),
+        sub: request.device_id,
+        exp: None,
+    };
+    db.set_device_token_id(&payload.sub, &payload.tid, &chrono::Utc::now())
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?;
+    let device_token = DeviceToken::new(device_key.as_bytes(), payload);
+
+    Ok(Json(rotate_token::ResponseBody {
+        device_token: device_token.encode(),
+    }))
+}
+
+/// Closes the live session of the device, returns false if the device wasn't connected
+pub(super) async fn disconnect(
+    sessions: &crate::Sessions,
+    device_id: &DeviceID,
+    reason: &str,
+) -> bool {
+    let session = sessions.lock().unwrap().get(device_id).cloned();
+    match session {
+        // Fails only if the session has been stopped in the meantime
+        Some(session) => session
+            .send(crate::lighthouse::Disconnect {
+                reason: reason.to_string(),
+            })
+            .await
+            .is_ok(),
+        None => false,
+    }
+}
+
+pub async fn on_rotate_password(
+    Json(request): Json<rotate_password::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+    sessions: Data<crate::Sessions>,
+) -> Result<Json<rotate_password::ResponseBody>, rotate_password::ResponseError> {
+    validator::Validate::validate(&request).map_err(houseflow_types::ValidationError::from)?;
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(rotate_password::ResponseError::UserNotAdmin);
+    }
+
+    let password_hash = argon2::hash_encoded(
+        request.password.as_bytes(),
+        &crate::get_password_salt(),
+        &argon2::Config::default(),
+    )
+    .unwrap();
+    if !db
+        .set_device_password_hash(&request.device_id, &password_hash)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(rotate_password::ResponseError::DeviceNotFound);
+    }
+
+    let disconnected = disconnect(&sessions, &request.device_id, "password rotated").await;
+
+    Ok(Json(rotate_password::ResponseBody { disconnected }))
+}
+
+pub async fn on_revoke(
+    Json(request): Json<revoke::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+    sessions: Data<crate::Sessions>,
+) -> Result<Json<revoke::ResponseBody>, revoke::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(revoke::ResponseError::UserNotAdmin);
+    }
+
+    // Empty hash disables password authentication until the password is rotated again
+    if !db
+        .set_device_password_hash(&request.device_id, "")
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(revoke::ResponseError::DeviceNotFound);
+    }
+    db.remove_device_token_id(&request.device_id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?;
+
+    let disconnected = disconnect(&sessions, &request.device_id, "credentials revoked").await;
+
+    Ok(Json(revoke::ResponseBody { disconnected }))
+}
+
+pub async fn on_list(
+    _request: Json<list::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+) -> Result<Json<list::ResponseBody>, list::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(list::ResponseError::UserNotAdmin);
+    }
+
+    let devices = db
+        .get_devices()
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?;
+
+    Ok(Json(list::ResponseBody { devices }))
+}
+
+pub async fn on_get(
+    Json(request): Json<get::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+) -> Result<Json<get::ResponseBody>, get::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(get::ResponseError::UserNotAdmin);
+    }
+
+    let device = db
+        .get_device(&request.device_id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+        .ok_or(get::ResponseError::NotFound)?;
+
+    Ok(Json(get::ResponseBody { device }))
+}
+
+pub async fn on_update(
+    Json(request): Json<update::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+    homegraph: Option<Data<HomeGraph>>,
+) -> Result<Json<update::ResponseBody>, update::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(update::ResponseError::UserNotAdmin);
+    }
+
+    let device = db
+        .get_device(&request.device_id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+        .ok_or(update::ResponseError::NotFound)?;
+    // Device could be moved to the room of other structure, so users of both are synced
+    let mut users = db
+        .get_device_users(&device.id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?;
+
+    let device = Device {
+        room_id: request.room_id,
+        device_type: request.device_type,
+        traits: request.traits,
+        name: request.name,
+        will_push_state: request.will_push_state,
+        model: request.model,
+        hw_version: request.hw_version,
+        sw_version: request.sw_version,
+        attributes: request.attributes,
+        ..device
+    };
+    if !db
+        .update_device(&device)
+        .await
+        .map_err(super::item_response_error)?
+    {
+        return Err(update::ResponseError::NotFound);
+    }
+
+    for user in db
+        .get_device_users(&device.id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        if !users.contains(&user) {
+            users.push(user);
+        }
+    }
+    homegraph::request_sync(
+        homegraph.as_ref().map(|homegraph| homegraph.get_ref()),
+        &**db,
+        &users,
+    )
+    .await;
+
+    Ok(Json(update::ResponseBody {}))
+}
+
+pub async fn on_delete(
+    Json(request): Json<delete::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+    sessions: Data<crate::Sessions>,
+    homegraph: Option<Data<HomeGraph>>,
+) -> Result<Json<delete::ResponseBody>, delete::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !super::check_device_permission(&**db, &access_token.sub, &request.device_id).await? {
+        return Err(delete::ResponseError::UserNotManager);
+    }
+
+    // Users must be collected before the device is deleted
+    let users = db
+        .get_device_users(&request.device_id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?;
+    if !db
+        .delete_device(&request.device_id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(delete::ResponseError::NotFound);
+    }
+
+    disconnect(&sessions, &request.device_id, "device deleted").await;
+    homegraph::request_sync(
+        homegraph.as_ref().map(|homegraph| homegraph.get_ref()),
+        &**db,
+        &users,
+    )
+    .await;
+
+    Ok(Json(delete::ResponseBody {}))
+}
@@ -1,4 +1,110 @@
+pub mod apply;
+pub mod backup;
 pub mod device;
+pub mod role;
 pub mod room;
 pub mod structure;
+pub mod user;
 pub mod user_structure;
+
+use crate::homegraph::{self, HomeGraph};
+use houseflow_db::Database;
+use houseflow_types::admin::{AddResponseError, ItemResponseError};
+use houseflow_types::{DeviceID, InternalServerError, RoomID, StructureID, User, UserID};
+
+/// Duplicates and missing references are caused by the request, so they're not reported as internal errors
+fn add_response_error(err: houseflow_db::Error) -> AddResponseError {
+    match err {
+        houseflow_db::Error::AlreadyExists => AddResponseError::AlreadyExists,
+        houseflow_db::Error::ReferenceNotFound => AddResponseError::ReferenceNotFound,
+        err => err.into_internal_server_error().into(),
+    }
+}
+
+/// Same as `add_response_error`, for the requests which modify an existing item
+fn item_response_error(err: houseflow_db::Error) -> ItemResponseError {
+    match err {
+        houseflow_db::Error::AlreadyExists => ItemResponseError::AlreadyExists,
+        houseflow_db::Error::ReferenceNotFound => ItemResponseError::ReferenceNotFound,
+        err => err.into_internal_server_error().into(),
+    }
+}
+
+/// Admins can manage every structure, while managers only the structures they manage
+async fn check_structure_permission(
+    db: &dyn Database,
+    user_id: &UserID,
+    structure_id: &StructureID,
+) -> Result<bool, InternalServerError> {
+    Ok(db.check_user_admin(user_id).await?
+        || db
+            .check_user_structure_manager(user_id, structure_id)
+            .await?)
+}
+
+/// Returns false for non-admins if the room doesn't exist, so its existence is not revealed
+async fn check_room_permission(
+    db: &dyn Database,
+    user_id: &UserID,
+    room_id: &RoomID,
+) -> Result<bool, InternalServerError> {
+    if db.check_user_admin(user_id).await? {
+        return Ok(true);
+    }
+    let role = db.get_user_room_role(user_id, room_id).await?;
+    Ok(matches!(role, Some(role) if role.can_manage()))
+}
+
+/// Returns false for non-admins if the device doesn't exist, so its existence is not revealed
+async fn check_device_permission(
+    db: &dyn Database,
+    user_id: &UserID,
+    device_id: &DeviceID,
+) -> Result<bool, InternalServerError> {
+    if db.check_user_admin(user_id).await? {
+        return Ok(true);
+    }
+    let role = db.get_user_device_role(user_id, device_id).await?;
+    Ok(matches!(role, Some(role) if role.can_manage()))
+}
+
+/// Devices visible to the user might have changed
+async fn request_sync(homegraph: Option<&HomeGraph>, db: &dyn Database, user_id: &UserID) {
+    match db.get_user(user_id).await {
+        Ok(user) => {
+            homegraph::request_sync(homegraph, db, &user.into_iter().collect::<Vec<_>>()).await
+        }
+        Err(err) => tracing::error!(user_id = %user_id, "fetching user failed: {}", err),
+    }
+}
+
+/// Users who can see any of the devices, they must be collected before the devices are deleted
+async fn get_devices_users(
+    db: &dyn Database,
+    device_ids: &[DeviceID],
+) -> Result<Vec<User>, InternalServerError> {
+    let mut users = Vec::new();
+    for device_id in device_ids {
+        for user in db.get_device_users(device_id).await? {
+            if !users.contains(&user) {
+                users.push(user);
+            }
+        }
+    }
+    Ok(users)
+}
+
+/// Disconnects the devices deleted along with their room or structure and lets their users sync
+async fn on_devices_deleted(
+    sessions: &crate::Sessions,
+    homegraph: Option<&HomeGraph>,
+    db: &dyn Database,
+    device_ids: &[DeviceID],
+    users: &[User],
+    reason: &str,
+) {
+    for device_id in device_ids {
+        device::disconnect(sessions, device_id, reason).await;
+    }
+    homegraph::request_sync(homegraph, db, users).await;
+}
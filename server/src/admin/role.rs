@@ -0,0 +1,117 @@
+use crate::homegraph::HomeGraph;
+use actix_web::{
+    web::{Data, Json},
+    HttpRequest,
+};
+use houseflow_config::server::Config;
+use houseflow_db::Database;
+use houseflow_types::{
+    admin::role::{add, delete, list},
+    token::AccessToken,
+    InternalServerError, RoleScope, ScopedRole, UserID,
+};
+
+/// Scoped roles can be given by admins and by managers of the room or device
+async fn check_scope_permission(
+    db: &dyn Database,
+    user_id: &UserID,
+    scope: &RoleScope,
+) -> Result<bool, InternalServerError> {
+    match scope {
+        RoleScope::Room(room_id) => super::check_room_permission(db, user_id, room_id).await,
+        RoleScope::Device(device_id) => {
+            super::check_device_permission(db, user_id, device_id).await
+        }
+    }
+}
+
+pub async fn on_add(
+    Json(request): Json<add::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+    homegraph: Option<Data<HomeGraph>>,
+) -> Result<Json<add::ResponseBody>, add::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !check_scope_permission(&**db, &access_token.sub, &request.scope).await? {
+        return Err(add::ResponseError::UserNotManager);
+    }
+
+    let scoped_role = ScopedRole {
+        user_id: request.user_id,
+        scope: request.scope,
+        role: request.role,
+    };
+
+    db.add_scoped_role(&scoped_role)
+        .await
+        .map_err(super::add_response_error)?;
+
+    super::request_sync(
+        homegraph.as_ref().map(|homegraph| homegraph.get_ref()),
+        &**db,
+        &scoped_role.user_id,
+    )
+    .await;
+
+    Ok(Json(add::ResponseBody {}))
+}
+
+pub async fn on_list(
+    _request: Json<list::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+) -> Result<Json<list::ResponseBody>, list::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(list::ResponseError::UserNotAdmin);
+    }
+
+    let roles = db
+        .get_scoped_roles()
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?;
+
+    Ok(Json(list::ResponseBody { roles }))
+}
+
+pub async fn on_delete(
+    Json(request): Json<delete::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+    homegraph: Option<Data<HomeGraph>>,
+) -> Result<Json<delete::ResponseBody>, delete::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !check_scope_permission(&**db, &access_token.sub, &request.scope).await? {
+        return Err(delete::ResponseError::UserNotManager);
+    }
+
+    if !db
+        .delete_scoped_role(&request.user_id, &request.scope)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(delete::ResponseError::NotFound);
+    }
+
+    super::request_sync(
+        homegraph.as_ref().map(|homegraph| homegraph.get_ref()),
+        &**db,
+        &request.user_id,
+    )
+    .await;
+
+    Ok(Json(delete::ResponseBody {}))
+}
@@ -1,3 +1,4 @@
+use crate::homegraph::HomeGraph;
 use actix_web::{
     web::{Data, Json},
     HttpRequest,
@@ -5,7 +6,10 @@ use actix_web::{
 use houseflow_config::server::Config;
 use houseflow_db::Database;
 use houseflow_types::{
-    admin::room::add::{Request, ResponseBody, ResponseError},
+    admin::room::{
+        add::{Request, ResponseBody, ResponseError},
+        delete, get, list, update,
+    },
     token::AccessToken,
     Room,
 };
@@ -19,21 +23,284 @@ pub async fn on_add(
     let access_token =
         AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
 
+    if !super::check_structure_permission(&**db, &access_token.sub, &request.structure_id).await? {
+        return Err(ResponseError::UserNotManager);
+    }
+
+    let room = Room {
+        id: rand::random(),
+        structure_id: request.structure_id,
+        name: request.room_name,
+    };
+
+    db.add_room(&room)
+        .await
+        .map_err(super::add_response_error)?;
+
+    Ok(Json(ResponseBody { room_id: room.id }))
+}
+
+pub async fn on_list(
+    _request: Json<list::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+) -> Result<Json<list::ResponseBody>, list::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(list::ResponseError::UserNotAdmin);
+    }
+
+    let rooms = db
+        .get_rooms()
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?;
+
+    Ok(Json(list::ResponseBody { rooms }))
+}
+
+pub async fn on_get(
+    Json(request): Json<get::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+) -> Result<Json<get::ResponseBody>, get::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(get::ResponseError::UserNotAdmin);
+    }
+
+    let room = db
+        .get_room(&request.room_id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+        .ok_or(get::ResponseError::NotFound)?;
+
+    Ok(Json(get::ResponseBody { room }))
+}
+
+pub async fn on_update(
+    Json(request): Json<update::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+) -> Result<Json<update::ResponseBody>, update::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
     if !db
         .check_user_admin(&access_token.sub)
+        .await
         .map_err(houseflow_db::Error::into_internal_server_error)?
     {
-        return Err(ResponseError::UserNotAdmin);
+        return Err(update::ResponseError::UserNotAdmin);
     }
 
     let room = Room {
-        id: rand::random(),
+        id: request.room_id,
         structure_id: request.structure_id,
         name: request.room_name,
     };
+    if !db
+        .update_room(&room)
+        .await
+        .map_err(super::item_response_error)?
+    {
+        return Err(update::ResponseError::NotFound);
+    }
 
-    db.add_room(&room)
-        .map_err(houseflow_db::Error::into_internal_server_error)?;
+    Ok(Json(update::ResponseBody {}))
+}
 
-    Ok(Json(ResponseBody { room_id: room.id }))
+pub async fn on_delete(
+    Json(request): Json<delete::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+    sessions: Data<crate::Sessions>,
+    homegraph: Option<Data<HomeGraph>>,
+) -> Result<Json<delete::ResponseBody>, delete::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !super::check_room_permission(&**db, &access_token.sub, &request.room_id).await? {
+        return Err(delete::ResponseError::UserNotManager);
+    }
+
+    // Devices are deleted along with the room
+    let device_ids = db
+        .get_devices()
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+        .into_iter()
+        .filter(|device| device.room_id == request.room_id)
+        .map(|device| device.id)
+        .collect::<Vec<_>>();
+    let users = super::get_devices_users(&**db, &device_ids).await?;
+    if !db
+        .delete_room(&request.room_id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(delete::ResponseError::NotFound);
+    }
+
+    super::on_devices_deleted(
+        &sessions,
+        homegraph.as_ref().map(|homegraph| homegraph.get_ref()),
+        &**db,
+        &device_ids,
+        &users,
+        "room deleted",
+    )
+    .await;
+
+    Ok(Json(delete::ResponseBody {}))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use houseflow_types::{Role, UserStructure};
+
+    #[actix_rt::test]
+    async fn manager_permissions() {
+        let state = get_state();
+        let manager = get_user();
+        let structure = get_structure();
+        let other_structure = get_structure();
+        state.database.add_user(&manager).await.unwrap();
+        state.database.add_structure(&structure).await.unwrap();
+        state
+            .database
+            .add_structure(&other_structure)
+            .await
+            .unwrap();
+        state
+            .database
+            .add_user_structure(&UserStructure {
+                structure_id: structure.id.clone(),
+                user_id: manager.id.clone(),
+                role: Role::Manager,
+            })
+            .await
+            .unwrap();
+        let http_request = || get_http_request(&state.config, &manager);
+        let add = |structure_id| {
+            on_add(
+                Json(Request {
+                    structure_id,
+                    room_name: String::from("kitchen"),
+                }),
+                http_request(),
+                state.config.clone(),
+                state.database.clone(),
+            )
+        };
+        let delete = |room_id| {
+            on_delete(
+                Json(delete::Request { room_id }),
+                http_request(),
+                state.config.clone(),
+                state.database.clone(),
+                Data::new(crate::Sessions::default()),
+                None,
+            )
+        };
+
+        let room_id = add(structure.id.clone())
+            .await
+            .unwrap()
+            .into_inner()
+            .room_id;
+        match add(other_structure.id.clone()).await {
+            Err(ResponseError::UserNotManager) => (),
+            result => panic!(
+                "expected user not manager, received: {:?}",
+                result.map(|_| ())
+            ),
+        }
+
+        let other_room = get_room(&other_structure);
+        state.database.add_room(&other_room).await.unwrap();
+        match delete(other_room.id.clone()).await {
+            Err(delete::ResponseError::UserNotManager) => (),
+            result => panic!(
+                "expected user not manager, received: {:?}",
+                result.map(|_| ())
+            ),
+        }
+        delete(room_id.clone()).await.unwrap();
+        assert_eq!(state.database.get_room(&room_id).await.unwrap(), None);
+        assert!(state
+            .database
+            .get_room(&other_room.id)
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[actix_rt::test]
+    async fn missing_structure() {
+        let state = get_state();
+        let admin = get_user();
+        let structure = get_structure();
+        let room = get_room(&structure);
+        state.database.add_user(&admin).await.unwrap();
+        state.database.add_admin(&admin.id).await.unwrap();
+        state.database.add_structure(&structure).await.unwrap();
+        state.database.add_room(&room).await.unwrap();
+        let http_request = || get_http_request(&state.config, &admin);
+
+        let result = on_add(
+            Json(Request {
+                structure_id: rand::random(),
+                room_name: String::from("kitchen"),
+            }),
+            http_request(),
+            state.config.clone(),
+            state.database.clone(),
+        )
+        .await;
+        match result {
+            Err(ResponseError::ReferenceNotFound) => (),
+            result => panic!(
+                "expected reference not found, received: {:?}",
+                result.map(|_| ())
+            ),
+        }
+
+        let result = on_update(
+            Json(update::Request {
+                room_id: room.id.clone(),
+                structure_id: rand::random(),
+                room_name: String::from("kitchen"),
+            }),
+            http_request(),
+            state.config.clone(),
+            state.database.clone(),
+        )
+        .await;
+        match result {
+            Err(update::ResponseError::ReferenceNotFound) => (),
+            result => panic!(
+                "expected reference not found, received: {:?}",
+                result.map(|_| ())
+            ),
+        }
+        assert_eq!(state.database.get_room(&room.id).await.unwrap(), Some(room));
+    }
 }
@@ -1,3 +1,4 @@
+use crate::homegraph::HomeGraph;
 use actix_web::{
     web::{Data, Json},
     HttpRequest,
@@ -5,7 +6,10 @@ use actix_web::{
 use houseflow_config::server::Config;
 use houseflow_db::Database;
 use houseflow_types::{
-    admin::structure::add::{Request, ResponseBody, ResponseError},
+    admin::structure::{
+        add::{Request, ResponseBody, ResponseError},
+        delete, get, list, update,
+    },
     token::AccessToken,
     Structure,
 };
@@ -21,6 +25,7 @@ pub async fn on_add(
 
     if !db
         .check_user_admin(&access_token.sub)
+        .await
         .map_err(houseflow_db::Error::into_internal_server_error)?
     {
         return Err(ResponseError::UserNotAdmin);
@@ -32,9 +37,151 @@ pub async fn on_add(
     };
 
     db.add_structure(&structure)
-        .map_err(houseflow_db::Error::into_internal_server_error)?;
+        .await
+        .map_err(super::add_response_error)?;
 
     Ok(Json(ResponseBody {
         structure_id: structure.id,
     }))
 }
+
+pub async fn on_list(
+    _request: Json<list::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+) -> Result<Json<list::ResponseBody>, list::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(list::ResponseError::UserNotAdmin);
+    }
+
+    let structures = db
+        .get_structures()
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?;
+
+    Ok(Json(list::ResponseBody { structures }))
+}
+
+pub async fn on_get(
+    Json(request): Json<get::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+) -> Result<Json<get::ResponseBody>, get::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(get::ResponseError::UserNotAdmin);
+    }
+
+    let structure = db
+        .get_structure(&request.structure_id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+        .ok_or(get::ResponseError::NotFound)?;
+
+    Ok(Json(get::ResponseBody { structure }))
+}
+
+pub async fn on_update(
+    Json(request): Json<update::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+) -> Result<Json<update::ResponseBody>, update::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(update::ResponseError::UserNotAdmin);
+    }
+
+    let structure = Structure {
+        id: request.structure_id,
+        name: request.structure_name,
+    };
+    if !db
+        .update_structure(&structure)
+        .await
+        .map_err(super::item_response_error)?
+    {
+        return Err(update::ResponseError::NotFound);
+    }
+
+    Ok(Json(update::ResponseBody {}))
+}
+
+pub async fn on_delete(
+    Json(request): Json<delete::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+    sessions: Data<crate::Sessions>,
+    homegraph: Option<Data<HomeGraph>>,
+) -> Result<Json<delete::ResponseBody>, delete::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(delete::ResponseError::UserNotAdmin);
+    }
+
+    // Rooms and devices are deleted along with the structure
+    let room_ids = db
+        .get_rooms()
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+        .into_iter()
+        .filter(|room| room.structure_id == request.structure_id)
+        .map(|room| room.id)
+        .collect::<Vec<_>>();
+    let device_ids = db
+        .get_devices()
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+        .into_iter()
+        .filter(|device| room_ids.contains(&device.room_id))
+        .map(|device| device.id)
+        .collect::<Vec<_>>();
+    let users = super::get_devices_users(&**db, &device_ids).await?;
+    if !db
+        .delete_structure(&request.structure_id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(delete::ResponseError::NotFound);
+    }
+
+    super::on_devices_deleted(
+        &sessions,
+        homegraph.as_ref().map(|homegraph| homegraph.get_ref()),
+        &**db,
+        &device_ids,
+        &users,
+        "structure deleted",
+    )
+    .await;
+
+    Ok(Json(delete::ResponseBody {}))
+}
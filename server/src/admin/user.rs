@@ -0,0 +1,129 @@
+use actix_web::{
+    web::{Data, Json},
+    HttpRequest,
+};
+use houseflow_config::server::Config;
+use houseflow_db::Database;
+use houseflow_types::{
+    admin::user::{delete, get, list, update},
+    token::AccessToken,
+    User,
+};
+
+pub async fn on_list(
+    _request: Json<list::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+) -> Result<Json<list::ResponseBody>, list::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(list::ResponseError::UserNotAdmin);
+    }
+
+    let users = db
+        .get_users()
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?;
+
+    Ok(Json(list::ResponseBody { users }))
+}
+
+pub async fn on_get(
+    Json(request): Json<get::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+) -> Result<Json<get::ResponseBody>, get::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(get::ResponseError::UserNotAdmin);
+    }
+
+    let user = db
+        .get_user(&request.user_id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+        .ok_or(get::ResponseError::NotFound)?;
+
+    Ok(Json(get::ResponseBody { user }))
+}
+
+pub async fn on_update(
+    Json(request): Json<update::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+) -> Result<Json<update::ResponseBody>, update::ResponseError> {
+    validator::Validate::validate(&request).map_err(houseflow_types::ValidationError::from)?;
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(update::ResponseError::UserNotAdmin);
+    }
+
+    let user = db
+        .get_user(&request.user_id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+        .ok_or(update::ResponseError::NotFound)?;
+    let user = User {
+        username: request.username,
+        email: request.email,
+        ..user
+    };
+    if !db
+        .update_user(&user)
+        .await
+        .map_err(super::item_response_error)?
+    {
+        return Err(update::ResponseError::NotFound);
+    }
+
+    Ok(Json(update::ResponseBody {}))
+}
+
+pub async fn on_delete(
+    Json(request): Json<delete::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+) -> Result<Json<delete::ResponseBody>, delete::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(delete::ResponseError::UserNotAdmin);
+    }
+
+    if !db
+        .delete_user(&request.user_id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(delete::ResponseError::NotFound);
+    }
+
+    Ok(Json(delete::ResponseBody {}))
+}
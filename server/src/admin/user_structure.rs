@@ -1,3 +1,4 @@
+use crate::homegraph::HomeGraph;
 use actix_web::{
     web::{Data, Json},
     HttpRequest,
@@ -5,7 +6,10 @@ use actix_web::{
 use houseflow_config::server::Config;
 use houseflow_db::Database;
 use houseflow_types::{
-    admin::user_structure::add::{Request, ResponseBody, ResponseError},
+    admin::user_structure::{
+        add::{Request, ResponseBody, ResponseError},
+        delete, get, list, update,
+    },
     token::AccessToken,
     UserStructure,
 };
@@ -15,25 +19,159 @@ pub async fn on_add(
     http_request: HttpRequest,
     config: Data<Config>,
     db: Data<dyn Database>,
+    homegraph: Option<Data<HomeGraph>>,
 ) -> Result<Json<ResponseBody>, ResponseError> {
     let access_token =
         AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
 
+    if !super::check_structure_permission(&**db, &access_token.sub, &request.structure_id).await? {
+        return Err(ResponseError::UserNotManager);
+    }
+
+    let user_structure = UserStructure {
+        structure_id: request.structure_id,
+        user_id: request.user_id,
+        role: request.role,
+    };
+
+    db.add_user_structure(&user_structure)
+        .await
+        .map_err(super::add_response_error)?;
+
+    super::request_sync(
+        homegraph.as_ref().map(|homegraph| homegraph.get_ref()),
+        &**db,
+        &user_structure.user_id,
+    )
+    .await;
+
+    Ok(Json(ResponseBody {}))
+}
+
+pub async fn on_list(
+    _request: Json<list::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+) -> Result<Json<list::ResponseBody>, list::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
     if !db
         .check_user_admin(&access_token.sub)
+        .await
         .map_err(houseflow_db::Error::into_internal_server_error)?
     {
-        return Err(ResponseError::UserNotAdmin);
+        return Err(list::ResponseError::UserNotAdmin);
+    }
+
+    let user_structures = db
+        .get_user_structures()
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?;
+
+    Ok(Json(list::ResponseBody { user_structures }))
+}
+
+pub async fn on_get(
+    Json(request): Json<get::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+) -> Result<Json<get::ResponseBody>, get::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(get::ResponseError::UserNotAdmin);
+    }
+
+    let user_structure = db
+        .get_user_structure(&request.structure_id, &request.user_id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+        .ok_or(get::ResponseError::NotFound)?;
+
+    Ok(Json(get::ResponseBody { user_structure }))
+}
+
+pub async fn on_update(
+    Json(request): Json<update::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+    homegraph: Option<Data<HomeGraph>>,
+) -> Result<Json<update::ResponseBody>, update::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(update::ResponseError::UserNotAdmin);
     }
 
     let user_structure = UserStructure {
         structure_id: request.structure_id,
         user_id: request.user_id,
-        is_manager: request.is_manager,
+        role: request.role,
     };
+    if !db
+        .update_user_structure(&user_structure)
+        .await
+        .map_err(super::item_response_error)?
+    {
+        return Err(update::ResponseError::NotFound);
+    }
 
-    db.add_user_structure(&user_structure)
-        .map_err(houseflow_db::Error::into_internal_server_error)?;
+    super::request_sync(
+        homegraph.as_ref().map(|homegraph| homegraph.get_ref()),
+        &**db,
+        &user_structure.user_id,
+    )
+    .await;
 
-    Ok(Json(ResponseBody {}))
+    Ok(Json(update::ResponseBody {}))
+}
+
+pub async fn on_delete(
+    Json(request): Json<delete::Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+    homegraph: Option<Data<HomeGraph>>,
+) -> Result<Json<delete::ResponseBody>, delete::ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+
+    if !db
+        .check_user_admin(&access_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(delete::ResponseError::UserNotAdmin);
+    }
+
+    if !db
+        .delete_user_structure(&request.structure_id, &request.user_id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+    {
+        return Err(delete::ResponseError::NotFound);
+    }
+
+    super::request_sync(
+        homegraph.as_ref().map(|homegraph| homegraph.get_ref()),
+        &**db,
+        &request.user_id,
+    )
+    .await;
+
+    Ok(Json(delete::ResponseBody {}))
 }
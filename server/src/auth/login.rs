@@ -9,9 +9,11 @@ use houseflow_types::{
 };
 
 fn verify_password(hash: &str, password: &str) -> Result<(), ResponseError> {
-    match argon2::verify_encoded(hash, password.as_bytes()).unwrap() {
-        true => Ok(()),
-        false => Err(ResponseError::InvalidPassword),
+    // Hash is empty for the users which didn't exist before a backup without secrets was restored,
+    // login is disabled for them
+    match argon2::verify_encoded(hash, password.as_bytes()) {
+        Ok(true) => Ok(()),
+        Ok(false) | Err(_) => Err(ResponseError::InvalidPassword),
     }
 }
 
@@ -31,6 +33,7 @@ pub async fn on_login(
     validator::Validate::validate(&request).map_err(houseflow_types::ValidationError::from)?;
     let user = db
         .get_user_by_email(&request.email)
+        .await
         .map_err(houseflow_db::Error::into_internal_server_error)?
         .ok_or(ResponseError::UserNotFound)?;
 
@@ -65,12 +68,13 @@ pub async fn on_login(
 mod tests {
     use super::*;
     use crate::test_utils::*;
+    use houseflow_types::User;
 
     #[actix_rt::test]
     async fn valid() {
         let state = get_state();
         let user = get_user();
-        state.database.add_user(&user).unwrap();
+        state.database.add_user(&user).await.unwrap();
         let response = on_login(
             Json(Request {
                 email: user.email,
@@ -100,7 +104,7 @@ mod tests {
     async fn invalid_password() {
         let state = get_state();
         let user = get_user();
-        state.database.add_user(&user).unwrap();
+        state.database.add_user(&user).await.unwrap();
         let response = on_login(
             Json(Request {
                 email: user.email,
@@ -116,6 +120,29 @@ mod tests {
         assert_eq!(response, ResponseError::InvalidPassword);
     }
 
+    #[actix_rt::test]
+    async fn empty_password_hash() {
+        let state = get_state();
+        let user = User {
+            password_hash: String::new(),
+            ..get_user()
+        };
+        state.database.add_user(&user).await.unwrap();
+        let response = on_login(
+            Json(Request {
+                email: user.email,
+                password: PASSWORD.into(),
+            }),
+            state.token_store.clone(),
+            state.config.clone(),
+            state.database,
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(response, ResponseError::InvalidPassword);
+    }
+
     #[actix_rt::test]
     async fn not_existing_user() {
         let state = get_state();
@@ -23,7 +23,7 @@ pub async fn on_register(
         email: request.email,
         password_hash,
     };
-    db.add_user(&new_user).map_err(|err| match err {
+    db.add_user(&new_user).await.map_err(|err| match err {
         houseflow_db::Error::AlreadyExists => ResponseError::UserAlreadyExists,
         other => other.into_internal_server_error().into(),
     })?;
@@ -54,6 +54,7 @@ mod tests {
         let db_user = state
             .database
             .get_user_by_email(&request.email)
+            .await
             .unwrap()
             .expect("user not found in database");
 
@@ -62,4 +63,28 @@ mod tests {
         assert_eq!(db_user.email, request.email);
         assert!(argon2::verify_encoded(&db_user.password_hash, PASSWORD.as_bytes()).unwrap());
     }
+
+    #[actix_rt::test]
+    async fn register_taken_email() {
+        let state = get_state();
+        let request = Request {
+            email: String::from("john_smith@example.com"),
+            username: String::from("John Smith"),
+            password: PASSWORD.into(),
+        };
+        on_register(Json(request.clone()), state.database.clone())
+            .await
+            .unwrap();
+        let request = Request {
+            username: String::from("Other John Smith"),
+            ..request
+        };
+        match on_register(Json(request), state.database.clone()).await {
+            Err(ResponseError::UserAlreadyExists) => (),
+            result => panic!(
+                "expected user already exists, received: {:?}",
+                result.map(|_| ())
+            ),
+        }
+    }
 }
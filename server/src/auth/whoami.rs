@@ -15,6 +15,7 @@ pub async fn on_whoami(
         AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
     let user = db
         .get_user(&access_token.sub)
+        .await
         .map_err(houseflow_db::Error::into_internal_server_error)?
         .ok_or(ResponseError::UserNotFound)?;
 
@@ -52,7 +53,7 @@ mod tests {
             },
         );
 
-        state.database.add_user(&user).unwrap();
+        state.database.add_user(&user).await.unwrap();
 
         let request = test::TestRequest::default()
             .append_header((
@@ -91,7 +92,7 @@ mod tests {
             },
         );
 
-        state.database.add_user(&user).unwrap();
+        state.database.add_user(&user).await.unwrap();
         let request = test::TestRequest::default()
             .append_header((
                 http::header::AUTHORIZATION,
@@ -9,10 +9,11 @@ use houseflow_types::{
         self, IntentRequest, IntentRequestInput, IntentResponseBody, IntentResponseError,
     },
     token::AccessToken,
-    DeviceStatus,
+    traits::CommandParams,
+    DeviceID, DeviceStatus,
 };
 
-use crate::Sessions;
+use crate::{token_store::Error as TokenStoreError, Sessions, TokenStore};
 
 pub async fn on_webhook(
     Json(request): Json<IntentRequest>,
@@ -20,10 +21,11 @@ pub async fn on_webhook(
     config: Data<Config>,
     db: web::Data<dyn Database>,
     sessions: web::Data<Sessions>,
+    token_store: web::Data<dyn TokenStore>,
 ) -> Result<web::Json<IntentResponseBody>, IntentResponseError> {
     let access_token =
         AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
-    let input = request.inputs.first().unwrap();
+    let input = merge_inputs(request.inputs)?;
 
     let body: Result<IntentResponseBody, IntentResponseError> = match input {
         IntentRequestInput::Sync => {
@@ -31,53 +33,51 @@ pub async fn on_webhook(
 
             let user_devices = db
                 .get_user_devices(&access_token.sub)
+                .await
                 .map_err(houseflow_db::Error::into_internal_server_error)?;
 
-            let user_devices = user_devices
-                .into_iter()
-                .map(|device| {
-                    let room = db
-                        .get_room(&device.room_id)
-                        .map_err(houseflow_db::Error::into_internal_server_error)?
-                        .ok_or_else(|| {
-                            IntentResponseError::InternalError(
-                                houseflow_types::InternalServerError::Other(
-                                    "couldn't find matching room".to_string(),
-                                ),
-                            )
-                        })?;
-
-                    let payload = sync::response::PayloadDevice {
-                        id: device.id,
-                        device_type: device.device_type,
-                        traits: device.traits,
-                        name: ghome::sync::response::PayloadDeviceName {
-                            default_names: None,
-                            name: device.name,
-                            nicknames: None,
-                        },
-                        will_report_state: device.will_push_state,
-                        notification_supported_by_agent: false, // not sure about that
-                        room_hint: Some(room.name),
-                        device_info: Some(sync::response::PayloadDeviceInfo {
-                            manufacturer: Some("houseflow".to_string()),
-                            model: None,
-                            hw_version: Some(device.hw_version),
-                            sw_version: Some(device.sw_version),
-                        }),
-                        attributes: Some(device.attributes),
-                        custom_data: None,
-                        other_device_ids: None,
-                    };
-
-                    Ok::<_, IntentResponseError>(payload)
-                })
-                .collect::<Result<Vec<_>, _>>()?;
+            let mut devices = Vec::with_capacity(user_devices.len());
+            for device in user_devices {
+                let room = db
+                    .get_room(&device.room_id)
+                    .await
+                    .map_err(houseflow_db::Error::into_internal_server_error)?
+                    .ok_or_else(|| {
+                        IntentResponseError::InternalError(
+                            houseflow_types::InternalServerError::Other(
+                                "couldn't find matching room".to_string(),
+                            ),
+                        )
+                    })?;
+
+                devices.push(sync::response::PayloadDevice {
+                    id: device.id,
+                    device_type: device.device_type,
+                    traits: device.traits,
+                    name: ghome::sync::response::PayloadDeviceName {
+                        default_names: None,
+                        name: device.name,
+                        nicknames: None,
+                    },
+                    will_report_state: device.will_push_state,
+                    notification_supported_by_agent: false, // not sure about that
+                    room_hint: Some(room.name),
+                    device_info: Some(sync::response::PayloadDeviceInfo {
+                        manufacturer: Some("houseflow".to_string()),
+                        model: None,
+                        hw_version: Some(device.hw_version),
+                        sw_version: Some(device.sw_version),
+                    }),
+                    attributes: Some(device.attributes),
+                    custom_data: None,
+                    other_device_ids: None,
+                });
+            }
             let payload = sync::response::Payload {
                 agent_user_id: access_token.sub.clone(),
                 error_code: None,
                 debug_string: None,
-                devices: user_devices,
+                devices,
             };
             Ok(IntentResponseBody::Sync {
                 request_id: request.request_id.clone(),
@@ -92,18 +92,38 @@ pub async fn on_webhook(
             let sessions = &sessions;
 
             let device_responses = payload.devices.iter().map(|device| async move {
-                if !db
-                    .check_user_device_access(&access_token.sub, &device.id)
+                if db
+                    .get_user_device_role(&access_token.sub, &device.id)
+                    .await
                     .map_err(houseflow_db::Error::into_internal_server_error)?
+                    .is_none()
                 {
                     return Err::<query::response::PayloadDevice, IntentResponseError>(
                         IntentResponseError::NoDevicePermission,
                     );
                 }
 
-                let sessions = sessions.lock().unwrap();
-                match sessions.get(&device.id) {
-                    Some(session) => {
+                let will_push_state = db
+                    .get_device(&device.id)
+                    .await
+                    .map_err(houseflow_db::Error::into_internal_server_error)?
+                    .map(|device| device.will_push_state)
+                    .unwrap_or_default();
+                let snapshot = db
+                    .get_device_state(&device.id)
+                    .await
+                    .map_err(houseflow_db::Error::into_internal_server_error)?;
+                let session = sessions.lock().unwrap().get(&device.id).cloned();
+                match (session, snapshot) {
+                    (Some(_), Some(snapshot)) if will_push_state => {
+                        Ok(query::response::PayloadDevice {
+                            online: true,
+                            status: ghome::DeviceStatus::Success,
+                            error_code: None,
+                            state: Some(snapshot.state),
+                        })
+                    }
+                    (Some(session), _) => {
                         let query_frame = houseflow_types::lighthouse::proto::query::Frame {};
                         let response_frame = session
                             .send(crate::lighthouse::aliases::ActorQueryFrame::from(
@@ -120,11 +140,11 @@ pub async fn on_webhook(
                             state: Some(response_frame.state),
                         })
                     }
-                    None => Ok(query::response::PayloadDevice {
+                    (None, snapshot) => Ok(query::response::PayloadDevice {
                         online: false,
                         status: ghome::DeviceStatus::Offline,
                         error_code: None,
-                        state: None,
+                        state: snapshot.map(|snapshot| snapshot.state),
                     }),
                 }
             });
@@ -143,74 +163,333 @@ pub async fn on_webhook(
         IntentRequestInput::Execute(payload) => {
             use ghome::execute;
 
-            let requests = payload
-                .commands
-                .iter()
-                .flat_map(|cmd| cmd.execution.iter().zip(cmd.devices.iter()));
-
             let db = &db;
             let sessions = &sessions;
             let access_token = &access_token;
-            let responses = requests.map(|(exec, device)| async move {
-                if !db
-                    .check_user_device_access(&access_token.sub, &device.id)
-                    .map_err(houseflow_db::Error::into_internal_server_error)?
-                {
-                    return Err::<_, IntentResponseError>(IntentResponseError::NoDevicePermission);
-                }
-                match sessions.lock().unwrap().get(&device.id) {
-                    Some(session) => {
-                        let execute_frame = houseflow_types::lighthouse::proto::execute::Frame {
-                            id: rand::random(),
-                            command: exec.command.clone(),
-                            params: exec.params.clone(),
-                        };
-
-                        let response = session
-                            .send(crate::lighthouse::aliases::ActorExecuteFrame::from(
-                                execute_frame,
-                            ))
-                            .await
-                            .unwrap()?;
-                        let response: houseflow_types::lighthouse::proto::execute_response::Frame =
-                            response.into();
-
-                        Ok(match response.status {
-                            DeviceStatus::Success => execute::response::PayloadCommand {
-                                ids: vec![device.id.clone()],
-                                status: ghome::DeviceStatus::Success,
-                                states: response.state,
-                                error_code: None,
-                            },
-                            DeviceStatus::Error(err) => execute::response::PayloadCommand {
-                                ids: vec![device.id.clone()],
-                                status: ghome::DeviceStatus::Error,
-                                states: response.state,
-                                error_code: Some(err.to_string()),
-                            },
-                        })
+            let responses = payload.commands.iter().flat_map(|command| {
+                command.devices.iter().map(move |device| async move {
+                    // Viewers can only query the state of the device
+                    let role = db
+                        .get_user_device_role(&access_token.sub, &device.id)
+                        .await
+                        .map_err(houseflow_db::Error::into_internal_server_error)?;
+                    if !matches!(role, Some(role) if role.can_execute()) {
+                        return Err::<_, IntentResponseError>(
+                            IntentResponseError::NoDevicePermission,
+                        );
                     }
-                    None => Ok(execute::response::PayloadCommand {
-                        ids: vec![device.id.clone()],
-                        status: ghome::DeviceStatus::Offline,
-                        states: Default::default(),
-                        error_code: None,
-                    }),
-                }
+
+                    execute_device(sessions, &device.id, &command.execution).await
+                })
             });
+            let responses = futures::future::try_join_all(responses).await?;
             let payload = execute::response::Payload {
                 error_code: None,
                 debug_string: None,
-                commands: futures::future::try_join_all(responses).await?,
+                commands: group_execute_responses(responses),
             };
             Ok(IntentResponseBody::Execute {
                 request_id: request.request_id,
                 payload,
             })
         }
-        IntentRequestInput::Disconnect => todo!(),
+        IntentRequestInput::Disconnect => {
+            let revoked = token_store
+                .remove_linked(&access_token.sub)
+                .await
+                .map_err(TokenStoreError::into_internal_server_error)?;
+            db.add_google_home_unlink(&access_token.sub, &chrono::Utc::now())
+                .await
+                .map_err(houseflow_db::Error::into_internal_server_error)?;
+            tracing::info!(user_id = %access_token.sub, revoked, "unlinked Google Home account");
+
+            Ok(IntentResponseBody::Disconnect {})
+        }
     };
     let body = body?;
 
     Ok(web::Json(body))
 }
+
+/// Merges inputs of the request into a single one, so all of them are processed.
+/// Google sends a single input per request, so merging inputs with different intents is not supported.
+fn merge_inputs(
+    inputs: Vec<IntentRequestInput>,
+) -> Result<IntentRequestInput, IntentResponseError> {
+    let mut inputs = inputs.into_iter();
+    let first = inputs
+        .next()
+        .ok_or_else(|| IntentResponseError::InvalidRequest("missing inputs".to_string()))?;
+
+    inputs.try_fold(first, |merged, input| match (merged, input) {
+        (IntentRequestInput::Sync, IntentRequestInput::Sync) => Ok(IntentRequestInput::Sync),
+        (IntentRequestInput::Query(mut merged), IntentRequestInput::Query(payload)) => {
+            merged.devices.extend(payload.devices);
+            Ok(IntentRequestInput::Query(merged))
+        }
+        (IntentRequestInput::Execute(mut merged), IntentRequestInput::Execute(payload)) => {
+            merged.commands.extend(payload.commands);
+            Ok(IntentRequestInput::Execute(merged))
+        }
+        (IntentRequestInput::Disconnect, IntentRequestInput::Disconnect) => {
+            Ok(IntentRequestInput::Disconnect)
+        }
+        _ => Err(IntentResponseError::InvalidRequest(
+            "inputs with different intents".to_string(),
+        )),
+    })
+}
+
+/// Executes the commands on the device one after another, stops at the first one that fails
+async fn execute_device(
+    sessions: &Sessions,
+    device_id: &DeviceID,
+    executions: &[ghome::execute::request::PayloadCommandExecution],
+) -> Result<ghome::execute::response::PayloadCommand, IntentResponseError> {
+    use ghome::execute::response::PayloadCommand;
+
+    let mut response = PayloadCommand {
+        ids: vec![device_id.clone()],
+        status: ghome::DeviceStatus::Success,
+        states: Default::default(),
+        error_code: None,
+    };
+    for execution in executions {
+        let params = match CommandParams::new(execution.command.clone(), execution.params.clone()) {
+            Ok(params) => params,
+            Err(err) => {
+                response.status = ghome::DeviceStatus::Error;
+                response.error_code = Some(err.to_string());
+                break;
+            }
+        };
+
+        let session = sessions.lock().unwrap().get(device_id).cloned();
+        let session = match session {
+            Some(session) => session,
+            None => {
+                response.status = ghome::DeviceStatus::Offline;
+                break;
+            }
+        };
+        let execute_frame = houseflow_types::lighthouse::proto::execute::Frame {
+            id: rand::random(),
+            params,
+        };
+        let execute_response = session
+            .send(crate::lighthouse::aliases::ActorExecuteFrame::from(
+                execute_frame,
+            ))
+            .await
+            .unwrap()?;
+        let execute_response: houseflow_types::lighthouse::proto::execute_response::Frame =
+            execute_response.into();
+        response.states = execute_response.state;
+        if let DeviceStatus::Error(err) = execute_response.status {
+            response.status = ghome::DeviceStatus::Error;
+            response.error_code = Some(err.to_string());
+            break;
+        }
+    }
+
+    Ok(response)
+}
+
+/// Groups responses with the same status, state and error code into a single command, as Google expects
+fn group_execute_responses(
+    responses: Vec<ghome::execute::response::PayloadCommand>,
+) -> Vec<ghome::execute::response::PayloadCommand> {
+    let mut grouped: Vec<ghome::execute::response::PayloadCommand> = Vec::new();
+    for response in responses {
+        let group = grouped.iter_mut().find(|group| {
+            group.status == response.status
+                && group.states == response.states
+                && group.error_code == response.error_code
+        });
+        match group {
+            Some(group) => group.ids.extend(response.ids),
+            None => grouped.push(response),
+        }
+    }
+
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use houseflow_types::{Role, UserStructure};
+
+    #[actix_rt::test]
+    async fn no_inputs() {
+        let state = get_state();
+        let user = get_user();
+        let response = on_webhook(
+            Json(IntentRequest {
+                request_id: String::from("some-request-id"),
+                inputs: vec![],
+            }),
+            get_http_request(&state.config, &user),
+            state.config,
+            state.database,
+            Data::new(Sessions::default()),
+            state.token_store,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(response, IntentResponseError::InvalidRequest(_)));
+    }
+
+    #[actix_rt::test]
+    async fn mixed_inputs() {
+        let state = get_state();
+        let user = get_user();
+        let response = on_webhook(
+            Json(IntentRequest {
+                request_id: String::from("some-request-id"),
+                inputs: vec![IntentRequestInput::Sync, IntentRequestInput::Disconnect],
+            }),
+            get_http_request(&state.config, &user),
+            state.config,
+            state.database,
+            Data::new(Sessions::default()),
+            state.token_store,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(response, IntentResponseError::InvalidRequest(_)));
+    }
+
+    #[actix_rt::test]
+    async fn execute_batch() {
+        let state = get_state();
+        let user = get_user();
+        let structure = get_structure();
+        let room = get_room(&structure);
+        let devices = std::iter::repeat_with(|| get_device(&room))
+            .take(4)
+            .collect::<Vec<_>>();
+        state.database.add_user(&user).await.unwrap();
+        state.database.add_structure(&structure).await.unwrap();
+        state.database.add_room(&room).await.unwrap();
+        for device in &devices {
+            state.database.add_device(device).await.unwrap();
+        }
+        state
+            .database
+            .add_user_structure(&UserStructure {
+                structure_id: structure.id.clone(),
+                user_id: user.id.clone(),
+                role: Role::Operator,
+            })
+            .await
+            .unwrap();
+
+        let request: IntentRequest = serde_json::from_value(serde_json::json!({
+            "requestId": "some-request-id",
+            "inputs": [
+                {
+                    "intent": "action.devices.EXECUTE",
+                    "payload": {
+                        "commands": [{
+                            "devices": [
+                                { "id": devices[0].id },
+                                { "id": devices[1].id },
+                                { "id": devices[2].id },
+                            ],
+                            "execution": [{
+                                "command": "action.devices.commands.OnOff",
+                                "params": { "on": true },
+                            }],
+                        }],
+                    },
+                },
+                {
+                    "intent": "action.devices.EXECUTE",
+                    "payload": {
+                        "commands": [{
+                            "devices": [{ "id": devices[3].id }],
+                            "execution": [{
+                                "command": "action.devices.commands.OnOff",
+                                "params": { "brightness": 50 },
+                            }],
+                        }],
+                    },
+                },
+            ],
+        }))
+        .unwrap();
+        let response = on_webhook(
+            Json(request),
+            get_http_request(&state.config, &user),
+            state.config,
+            state.database,
+            Data::new(Sessions::default()),
+            state.token_store,
+        )
+        .await
+        .unwrap()
+        .into_inner();
+
+        let commands = match response {
+            IntentResponseBody::Execute { payload, .. } => payload.commands,
+            _ => panic!("unexpected response: {:?}", response),
+        };
+        assert_eq!(commands.len(), 2);
+        assert_eq!(
+            commands[0].ids,
+            devices[..3]
+                .iter()
+                .map(|device| device.id.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(commands[0].status, ghome::DeviceStatus::Offline);
+        assert_eq!(commands[1].ids, vec![devices[3].id.clone()]);
+        assert_eq!(commands[1].status, ghome::DeviceStatus::Error);
+    }
+
+    #[actix_rt::test]
+    async fn disconnect() {
+        let state = get_state();
+        let user = get_user();
+        state.database.add_user(&user).await.unwrap();
+
+        let linked_token_id = rand::random();
+        let login_token_id = rand::random();
+        state.token_store.add(&linked_token_id, None).await.unwrap();
+        state
+            .token_store
+            .link(&user.id, &linked_token_id)
+            .await
+            .unwrap();
+        state.token_store.add(&login_token_id, None).await.unwrap();
+
+        let response = on_webhook(
+            Json(IntentRequest {
+                request_id: String::from("some-request-id"),
+                inputs: vec![IntentRequestInput::Disconnect],
+            }),
+            get_http_request(&state.config, &user),
+            state.config,
+            state.database.clone(),
+            Data::new(Sessions::default()),
+            state.token_store.clone(),
+        )
+        .await
+        .unwrap()
+        .into_inner();
+
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            serde_json::json!({})
+        );
+        assert!(!state.token_store.exists(&linked_token_id).await.unwrap());
+        assert!(state.token_store.exists(&login_token_id).await.unwrap());
+        assert!(state
+            .database
+            .check_google_home_unlinked(&user.id)
+            .await
+            .unwrap());
+    }
+}
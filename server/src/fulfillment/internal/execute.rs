@@ -6,7 +6,10 @@ use houseflow_config::server::Config;
 use houseflow_db::Database;
 use houseflow_types::{
     fulfillment::execute::{Request, ResponseBody, ResponseError},
+    lighthouse::proto::{execute, execute_response},
     token::AccessToken,
+    traits::CommandParams,
+    DeviceStatus,
 };
 
 use crate::Sessions;
@@ -20,20 +23,44 @@ pub async fn on_execute(
 ) -> Result<Json<ResponseBody>, ResponseError> {
     let access_token =
         AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
-    if !db
-        .check_user_device_access(&access_token.sub, &execute_request.device_id)
-        .map_err(houseflow_db::Error::into_internal_server_error)?
-    {
+    // Viewers can only query the state of the device
+    let role = db
+        .get_user_device_role(&access_token.sub, &execute_request.device_id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?;
+    if !matches!(role, Some(role) if role.can_execute()) {
         return Err(ResponseError::NoDevicePermission);
     }
 
-    let sessions = sessions.lock().unwrap();
+    let frame_id = rand::random();
+    let params = match CommandParams::new(
+        execute_request.command.clone(),
+        execute_request.params.clone(),
+    ) {
+        Ok(params) => params,
+        Err(err) => {
+            return Ok(Json(ResponseBody {
+                frame: execute_response::Frame {
+                    id: frame_id,
+                    status: DeviceStatus::Error(err),
+                    state: Default::default(),
+                },
+            }))
+        }
+    };
+
     let session = sessions
+        .lock()
+        .unwrap()
         .get(&execute_request.device_id)
+        .cloned()
         .ok_or(ResponseError::DeviceNotConnected)?;
     let response_frame = session
         .send(crate::lighthouse::aliases::ActorExecuteFrame::from(
-            execute_request.frame.clone(),
+            execute::Frame {
+                id: frame_id,
+                params,
+            },
         ))
         .await
         .unwrap()?;
@@ -42,3 +69,136 @@ pub async fn on_execute(
         frame: response_frame.into(),
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use actix_web::{http, test};
+    use chrono::{Duration, Utc};
+    use houseflow_types::{
+        token::{AccessToken, AccessTokenPayload},
+        DeviceCommand, DeviceError, Role, RoleScope, ScopedRole, UserStructure,
+    };
+
+    #[actix_rt::test]
+    async fn execute_invalid_parameters() {
+        let state = get_state();
+        let sessions = Data::new(Sessions::default());
+
+        let user = get_user();
+        let access_token = AccessToken::new(
+            state.config.secrets.access_key.as_bytes(),
+            AccessTokenPayload {
+                sub: user.id.clone(),
+                exp: Utc::now() + Duration::minutes(10),
+            },
+        );
+        state.database.add_user(&user).await.unwrap();
+
+        let structure = get_structure();
+        let room = get_room(&structure);
+        let device = get_device(&room);
+        state.database.add_structure(&structure).await.unwrap();
+        state.database.add_room(&room).await.unwrap();
+        state.database.add_device(&device).await.unwrap();
+        state
+            .database
+            .add_user_structure(&UserStructure {
+                structure_id: structure.id.clone(),
+                user_id: user.id.clone(),
+                role: Role::Operator,
+            })
+            .await
+            .unwrap();
+
+        let request = test::TestRequest::default()
+            .insert_header((
+                http::header::AUTHORIZATION,
+                format!("Bearer {}", access_token),
+            ))
+            .to_http_request();
+        let params = serde_json::json!({ "openPercentt": 100 });
+        let response = on_execute(
+            Json(Request {
+                device_id: device.id.clone(),
+                command: DeviceCommand::OpenClose,
+                params: params.as_object().unwrap().clone(),
+            }),
+            request,
+            state.config,
+            state.database,
+            sessions,
+        )
+        .await
+        .unwrap()
+        .into_inner();
+        assert_eq!(
+            response.frame.status,
+            DeviceStatus::Error(DeviceError::InvalidParameters)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn execute_viewer() {
+        let state = get_state();
+        let sessions = Data::new(Sessions::default());
+
+        let user = get_user();
+        let access_token = AccessToken::new(
+            state.config.secrets.access_key.as_bytes(),
+            AccessTokenPayload {
+                sub: user.id.clone(),
+                exp: Utc::now() + Duration::minutes(10),
+            },
+        );
+        state.database.add_user(&user).await.unwrap();
+
+        let structure = get_structure();
+        let room = get_room(&structure);
+        let device = get_device(&room);
+        state.database.add_structure(&structure).await.unwrap();
+        state.database.add_room(&room).await.unwrap();
+        state.database.add_device(&device).await.unwrap();
+        state
+            .database
+            .add_user_structure(&UserStructure {
+                structure_id: structure.id.clone(),
+                user_id: user.id.clone(),
+                role: Role::Operator,
+            })
+            .await
+            .unwrap();
+        state
+            .database
+            .add_scoped_role(&ScopedRole {
+                user_id: user.id.clone(),
+                scope: RoleScope::Device(device.id.clone()),
+                role: Role::Viewer,
+            })
+            .await
+            .unwrap();
+
+        let request = test::TestRequest::default()
+            .insert_header((
+                http::header::AUTHORIZATION,
+                format!("Bearer {}", access_token),
+            ))
+            .to_http_request();
+        let params = serde_json::json!({ "on": true });
+        let err = on_execute(
+            Json(Request {
+                device_id: device.id.clone(),
+                command: DeviceCommand::OnOff,
+                params: params.as_object().unwrap().clone(),
+            }),
+            request,
+            state.config,
+            state.database,
+            sessions,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, ResponseError::NoDevicePermission));
+    }
+}
@@ -0,0 +1,155 @@
+use actix_web::{
+    web::{Data, Json},
+    HttpRequest,
+};
+use houseflow_config::server::Config;
+use houseflow_db::Database;
+use houseflow_types::{
+    fulfillment::history::{Request, ResponseBody, ResponseError, MAX_LIMIT},
+    token::AccessToken,
+};
+
+pub async fn on_history(
+    request: Json<Request>,
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+) -> Result<Json<ResponseBody>, ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+    if db
+        .get_user_device_role(&access_token.sub, &request.device_id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+        .is_none()
+    {
+        return Err(ResponseError::NoDevicePermission);
+    }
+
+    let states = db
+        .get_device_state_history(
+            &request.device_id,
+            request.since,
+            request.until,
+            request.limit.min(MAX_LIMIT),
+            request.offset,
+        )
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?;
+
+    Ok(Json(ResponseBody { states }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use actix_web::{http, test};
+    use chrono::{Duration, Utc};
+    use houseflow_types::{
+        token::{AccessToken, AccessTokenPayload},
+        traits::{on_off, DeviceState, DeviceStateSnapshot},
+        Role, UserStructure,
+    };
+
+    #[actix_rt::test]
+    async fn history() {
+        let state = get_state();
+
+        let user = get_user();
+        let access_token = AccessToken::new(
+            state.config.secrets.access_key.as_bytes(),
+            AccessTokenPayload {
+                sub: user.id.clone(),
+                exp: Utc::now() + Duration::minutes(10),
+            },
+        );
+        state.database.add_user(&user).await.unwrap();
+
+        let structure_allow = get_structure();
+        let structure_deny = get_structure();
+        let room_allow = get_room(&structure_allow);
+        let room_deny = get_room(&structure_deny);
+        let device_allow = get_device(&room_allow);
+        let device_deny = get_device(&room_deny);
+        state
+            .database
+            .add_structure(&structure_allow)
+            .await
+            .unwrap();
+        state.database.add_structure(&structure_deny).await.unwrap();
+        state.database.add_room(&room_allow).await.unwrap();
+        state.database.add_room(&room_deny).await.unwrap();
+        state.database.add_device(&device_allow).await.unwrap();
+        state.database.add_device(&device_deny).await.unwrap();
+        state
+            .database
+            .add_user_structure(&UserStructure {
+                structure_id: structure_allow.id.clone(),
+                user_id: user.id.clone(),
+                role: Role::Operator,
+            })
+            .await
+            .unwrap();
+
+        let start = Utc::now();
+        let snapshots = (0..5)
+            .map(|i| DeviceStateSnapshot {
+                state: DeviceState {
+                    on_off: Some(on_off::State { on: i % 2 == 0 }),
+                    ..Default::default()
+                },
+                updated_at: start + Duration::minutes(i),
+            })
+            .collect::<Vec<_>>();
+        for snapshot in &snapshots {
+            state
+                .database
+                .add_device_state_history(&device_allow.id, snapshot)
+                .await
+                .unwrap();
+        }
+
+        let get_request = || {
+            test::TestRequest::default()
+                .insert_header((
+                    http::header::AUTHORIZATION,
+                    format!("Bearer {}", access_token),
+                ))
+                .to_http_request()
+        };
+
+        let response = on_history(
+            Json(Request {
+                device_id: device_allow.id.clone(),
+                since: Some(start + Duration::minutes(1)),
+                until: None,
+                limit: 2,
+                offset: 1,
+            }),
+            get_request(),
+            state.config.clone(),
+            state.database.clone(),
+        )
+        .await
+        .unwrap()
+        .into_inner();
+        assert_eq!(response.states, snapshots[2..4]);
+
+        let err = on_history(
+            Json(Request {
+                device_id: device_deny.id.clone(),
+                since: None,
+                until: None,
+                limit: 10,
+                offset: 0,
+            }),
+            get_request(),
+            state.config,
+            state.database,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, ResponseError::NoDevicePermission));
+    }
+}
@@ -1,7 +1,11 @@
 mod execute;
+mod history;
 mod query;
 mod sync;
+mod watch;
 
 pub use execute::on_execute;
+pub use history::on_history;
 pub use query::on_query;
 pub use sync::on_sync;
+pub use watch::on_watch;
@@ -6,6 +6,7 @@ use houseflow_config::server::Config;
 use houseflow_db::Database;
 use houseflow_types::{
     fulfillment::query::{Request, ResponseBody, ResponseError},
+    lighthouse::proto::state,
     token::AccessToken,
 };
 
@@ -20,25 +21,158 @@ pub async fn on_query(
 ) -> Result<Json<ResponseBody>, ResponseError> {
     let access_token =
         AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
-    if !db
-        .check_user_device_access(&access_token.sub, &request.device_id)
+    if db
+        .get_user_device_role(&access_token.sub, &request.device_id)
+        .await
         .map_err(houseflow_db::Error::into_internal_server_error)?
+        .is_none()
     {
         return Err(ResponseError::NoDevicePermission);
     }
 
-    let sessions = sessions.lock().unwrap();
-    let session = sessions
-        .get(&request.device_id)
-        .ok_or(ResponseError::DeviceNotConnected)?;
-    let response_frame = session
-        .send(crate::lighthouse::aliases::ActorQueryFrame::from(
-            request.frame.clone(),
-        ))
+    let device = db
+        .get_device(&request.device_id)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?
+        .ok_or_else(|| {
+            houseflow_types::InternalServerError::Other("couldn't find matching device".to_string())
+        })?;
+    let snapshot = db
+        .get_device_state(&device.id)
         .await
-        .unwrap()?;
+        .map_err(houseflow_db::Error::into_internal_server_error)?;
+    let session = sessions.lock().unwrap().get(&device.id).cloned();
+
+    let response = match (session, snapshot) {
+        (Some(_), Some(snapshot)) if device.will_push_state => ResponseBody {
+            frame: state::Frame {
+                state: snapshot.state,
+            },
+            updated_at: snapshot.updated_at,
+            online: true,
+        },
+        (Some(session), _) => {
+            let response_frame = session
+                .send(crate::lighthouse::aliases::ActorQueryFrame::from(
+                    request.frame.clone(),
+                ))
+                .await
+                .unwrap()?;
+
+            ResponseBody {
+                frame: response_frame.into(),
+                updated_at: chrono::Utc::now(),
+                online: true,
+            }
+        }
+        (None, Some(snapshot)) => ResponseBody {
+            frame: state::Frame {
+                state: snapshot.state,
+            },
+            updated_at: snapshot.updated_at,
+            online: false,
+        },
+        (None, None) => return Err(ResponseError::DeviceNotConnected),
+    };
+
+    Ok(Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use actix_web::{http, test};
+    use chrono::{Duration, Utc};
+    use houseflow_types::{
+        lighthouse::proto::query,
+        token::{AccessToken, AccessTokenPayload},
+        traits::{on_off, DeviceState, DeviceStateSnapshot},
+        Role, UserStructure,
+    };
+
+    #[actix_rt::test]
+    async fn query_offline() {
+        let state = get_state();
+        let sessions = Data::new(Sessions::default());
 
-    Ok(Json(ResponseBody {
-        frame: response_frame.into(),
-    }))
+        let user = get_user();
+        let access_token = AccessToken::new(
+            state.config.secrets.access_key.as_bytes(),
+            AccessTokenPayload {
+                sub: user.id.clone(),
+                exp: Utc::now() + Duration::minutes(10),
+            },
+        );
+        state.database.add_user(&user).await.unwrap();
+
+        let structure = get_structure();
+        let room = get_room(&structure);
+        let device = get_device(&room);
+        state.database.add_structure(&structure).await.unwrap();
+        state.database.add_room(&room).await.unwrap();
+        state.database.add_device(&device).await.unwrap();
+        state
+            .database
+            .add_user_structure(&UserStructure {
+                structure_id: structure.id.clone(),
+                user_id: user.id.clone(),
+                role: Role::Operator,
+            })
+            .await
+            .unwrap();
+
+        let get_request = || {
+            test::TestRequest::default()
+                .insert_header((
+                    http::header::AUTHORIZATION,
+                    format!("Bearer {}", access_token),
+                ))
+                .to_http_request()
+        };
+        let query_request = || {
+            Json(Request {
+                device_id: device.id.clone(),
+                frame: query::Frame {},
+            })
+        };
+
+        let err = on_query(
+            query_request(),
+            get_request(),
+            state.config.clone(),
+            state.database.clone(),
+            sessions.clone(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, ResponseError::DeviceNotConnected));
+
+        let snapshot = DeviceStateSnapshot {
+            state: DeviceState {
+                on_off: Some(on_off::State { on: true }),
+                ..Default::default()
+            },
+            updated_at: Utc::now(),
+        };
+        state
+            .database
+            .set_device_state(&device.id, &snapshot)
+            .await
+            .unwrap();
+
+        let response = on_query(
+            query_request(),
+            get_request(),
+            state.config,
+            state.database,
+            sessions,
+        )
+        .await
+        .unwrap()
+        .into_inner();
+        assert_eq!(response.frame.state, snapshot.state);
+        assert_eq!(response.updated_at, snapshot.updated_at);
+        assert!(!response.online);
+    }
 }
@@ -14,14 +14,29 @@ pub async fn on_sync(
     http_request: HttpRequest,
     config: Data<Config>,
     db: Data<dyn Database>,
+    sessions: Data<crate::Sessions>,
 ) -> Result<Json<ResponseBody>, ResponseError> {
     let access_token =
         AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
 
     let devices = db
         .get_user_devices(&access_token.sub)
+        .await
         .map_err(houseflow_db::Error::into_internal_server_error)?;
-    let response = ResponseBody { devices };
+    let now = chrono::Utc::now();
+    let mut last_seen = std::collections::HashMap::new();
+    for device in &devices {
+        if sessions.lock().unwrap().contains_key(&device.id) {
+            last_seen.insert(device.id.clone(), now);
+        } else if let Some(event) = db
+            .get_last_device_presence_event(&device.id)
+            .await
+            .map_err(houseflow_db::Error::into_internal_server_error)?
+        {
+            last_seen.insert(device.id.clone(), event.occurred_at);
+        }
+    }
+    let response = ResponseBody { devices, last_seen };
 
     Ok(Json(response))
 }
@@ -34,7 +49,7 @@ mod tests {
     use chrono::{Duration, Utc};
     use houseflow_types::{
         token::{AccessToken, AccessTokenPayload},
-        Device, UserStructure,
+        Device, DevicePresenceEvent, Role, UserStructure,
     };
 
     #[actix_rt::test]
@@ -49,16 +64,20 @@ mod tests {
                 exp: Utc::now() + Duration::minutes(10),
             },
         );
-        state.database.add_user(&user).unwrap();
+        state.database.add_user(&user).await.unwrap();
 
         let structure_allow = get_structure();
         let structure_deny = get_structure();
         let room_allow = get_room(&structure_allow);
         let room_deny = get_room(&structure_deny);
-        state.database.add_structure(&structure_allow).unwrap();
-        state.database.add_structure(&structure_deny).unwrap();
-        state.database.add_room(&room_allow).unwrap();
-        state.database.add_room(&room_deny).unwrap();
+        state
+            .database
+            .add_structure(&structure_allow)
+            .await
+            .unwrap();
+        state.database.add_structure(&structure_deny).await.unwrap();
+        state.database.add_room(&room_allow).await.unwrap();
+        state.database.add_room(&room_deny).await.unwrap();
         let devices_allow = std::iter::repeat_with(|| get_device(&room_allow))
             .take(5)
             .collect::<Vec<_>>();
@@ -66,17 +85,34 @@ mod tests {
             .take(5)
             .collect::<Vec<_>>();
 
-        devices_allow
-            .iter()
-            .chain(devices_deny.iter())
-            .for_each(|device| state.database.add_device(&device).unwrap());
+        for device in devices_allow.iter().chain(devices_deny.iter()) {
+            state.database.add_device(device).await.unwrap();
+        }
 
         let user_structure = UserStructure {
             structure_id: structure_allow.id.clone(),
             user_id: user.id.clone(),
-            is_manager: false,
+            role: Role::Operator,
         };
-        state.database.add_user_structure(&user_structure).unwrap();
+        state
+            .database
+            .add_user_structure(&user_structure)
+            .await
+            .unwrap();
+
+        let disconnected_at = Utc::now() - Duration::hours(5);
+        state
+            .database
+            .add_device_presence_event(
+                &devices_allow[0].id,
+                &DevicePresenceEvent {
+                    connected: false,
+                    address: "127.0.0.1:1234".parse().unwrap(),
+                    occurred_at: disconnected_at,
+                },
+            )
+            .await
+            .unwrap();
 
         let request = test::TestRequest::default()
             .insert_header((
@@ -84,14 +120,24 @@ mod tests {
                 format!("Bearer {}", access_token.to_string()),
             ))
             .to_http_request();
-        let response = on_sync(Json(Request {}), request, state.config, state.database)
-            .await
-            .unwrap()
-            .into_inner();
+        let response = on_sync(
+            Json(Request {}),
+            request,
+            state.config,
+            state.database,
+            Data::new(crate::Sessions::default()),
+        )
+        .await
+        .unwrap()
+        .into_inner();
         let sort_devices = |devices: Vec<Device>| {
             devices.clone().sort_by(|a, b| a.id.cmp(&b.id));
             devices
         };
+        assert_eq!(
+            response.last_seen,
+            std::iter::once((devices_allow[0].id.clone(), disconnected_at)).collect()
+        );
         assert_eq!(sort_devices(response.devices), sort_devices(devices_allow));
     }
 }
@@ -0,0 +1,138 @@
+use actix_web::{
+    web::{self, Data},
+    HttpRequest, HttpResponse,
+};
+use futures::Stream;
+use houseflow_config::server::Config;
+use houseflow_db::Database;
+use houseflow_types::{
+    fulfillment::watch::{Event, ResponseError},
+    token::AccessToken,
+    UserID,
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::StateUpdates;
+
+pub async fn on_watch(
+    http_request: HttpRequest,
+    config: Data<Config>,
+    db: Data<dyn Database>,
+    state_updates: Data<StateUpdates>,
+) -> Result<HttpResponse, ResponseError> {
+    let access_token =
+        AccessToken::from_request(config.secrets.access_key.as_bytes(), &http_request)?;
+    let events = user_events(state_updates.subscribe(), db, access_token.sub.clone());
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .streaming(Box::pin(events)))
+}
+
+/// Streams events of devices that the user has access to, in the Server-Sent Events format
+fn user_events(
+    receiver: broadcast::Receiver<Event>,
+    db: Data<dyn Database>,
+    user_id: UserID,
+) -> impl Stream<Item = Result<web::Bytes, std::convert::Infallible>> {
+    futures::stream::unfold(receiver, move |mut receiver| {
+        let db = db.clone();
+        let user_id = user_id.clone();
+        async move {
+            loop {
+                let event = match receiver.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(n)) => {
+                        tracing::warn!("{} state updates skipped for {}", n, user_id);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return None,
+                };
+                match db.get_user_device_role(&user_id, &event.device_id).await {
+                    Ok(Some(_)) => {}
+                    Ok(None) => continue,
+                    Err(err) => {
+                        tracing::error!("failed checking device access: {}", err);
+                        continue;
+                    }
+                }
+                let json = serde_json::to_string(&event).expect("event serialization failed");
+                let bytes = web::Bytes::from(format!("data: {}\n\n", json));
+                return Some((Ok(bytes), receiver));
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use chrono::Utc;
+    use futures::StreamExt;
+    use houseflow_types::{
+        traits::{on_off, DeviceState, DeviceStateSnapshot},
+        DeviceID, Role, UserStructure,
+    };
+
+    #[actix_rt::test]
+    async fn watch() {
+        let state = get_state();
+        let state_updates = StateUpdates::default();
+
+        let user = get_user();
+        state.database.add_user(&user).await.unwrap();
+
+        let structure_allow = get_structure();
+        let structure_deny = get_structure();
+        let room_allow = get_room(&structure_allow);
+        let room_deny = get_room(&structure_deny);
+        let device_allow = get_device(&room_allow);
+        let device_deny = get_device(&room_deny);
+        state
+            .database
+            .add_structure(&structure_allow)
+            .await
+            .unwrap();
+        state.database.add_structure(&structure_deny).await.unwrap();
+        state.database.add_room(&room_allow).await.unwrap();
+        state.database.add_room(&room_deny).await.unwrap();
+        state.database.add_device(&device_allow).await.unwrap();
+        state.database.add_device(&device_deny).await.unwrap();
+        state
+            .database
+            .add_user_structure(&UserStructure {
+                structure_id: structure_allow.id.clone(),
+                user_id: user.id.clone(),
+                role: Role::Operator,
+            })
+            .await
+            .unwrap();
+
+        let events = user_events(state_updates.subscribe(), state.database, user.id);
+        futures::pin_mut!(events);
+
+        let get_event = |device_id: &DeviceID| Event {
+            device_id: device_id.clone(),
+            snapshot: DeviceStateSnapshot {
+                state: DeviceState {
+                    on_off: Some(on_off::State { on: true }),
+                    ..Default::default()
+                },
+                updated_at: Utc::now(),
+            },
+        };
+        let event = get_event(&device_allow.id);
+        state_updates.send(get_event(&device_deny.id));
+        state_updates.send(event.clone());
+
+        let bytes = events.next().await.unwrap().unwrap();
+        let text = std::str::from_utf8(&bytes).unwrap();
+        let json = text
+            .strip_prefix("data: ")
+            .unwrap()
+            .strip_suffix("\n\n")
+            .unwrap();
+        assert_eq!(serde_json::from_str::<Event>(json).unwrap(), event);
+    }
+}
@@ -0,0 +1,334 @@
+//! Reconciles the database with the declarative [Home], either on start of the server or with `houseflow admin apply`
+
+use houseflow_db::Database;
+use houseflow_types::{
+    DeviceTrait, Home, HomeChange, HomeItem, HomePlan, StructureID, UserID, UserStructure,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("database error: {0}")]
+    DatabaseError(#[from] houseflow_db::Error),
+
+    #[error("{0} is declared more than once")]
+    Duplicate(String),
+
+    #[error("user with email {0} doesn't exist")]
+    UserNotFound(String),
+
+    #[error("database has been changed since the plan was made")]
+    StalePlan,
+}
+
+/// Returns a change which turns the existing item into the desired one, if they differ
+fn change<T: PartialEq>(
+    existing: Option<T>,
+    desired: T,
+    item: impl Fn(T) -> HomeItem,
+) -> Option<HomeChange> {
+    match existing {
+        None => Some(HomeChange::Create {
+            item: item(desired),
+        }),
+        Some(existing) if existing != desired => Some(HomeChange::Update {
+            before: item(existing),
+            after: item(desired),
+        }),
+        Some(_) => None,
+    }
+}
+
+/// Order of the traits isn't preserved by the database
+fn same_traits(a: &[DeviceTrait], b: &[DeviceTrait]) -> bool {
+    a.len() == b.len() && a.iter().all(|device_trait| b.contains(device_trait))
+}
+
+/// Compares the home with the database. Items which aren't declared in the home are deleted only with `prune`,
+/// items of the deleted structures and rooms are left out of the plan, as they're deleted along with them.
+pub async fn plan(db: &dyn Database, home: &Home, prune: bool) -> Result<HomePlan, Error> {
+    let users = db.get_users().await?;
+    let user_ids = users
+        .iter()
+        .map(|user| (user.email.as_str(), &user.id))
+        .collect::<HashMap<_, _>>();
+    let mut structures = db
+        .get_structures()
+        .await?
+        .into_iter()
+        .map(|structure| (structure.id.clone(), structure))
+        .collect::<BTreeMap<_, _>>();
+    let mut rooms = db
+        .get_rooms()
+        .await?
+        .into_iter()
+        .map(|room| (room.id.clone(), room))
+        .collect::<BTreeMap<_, _>>();
+    let mut devices = db
+        .get_devices()
+        .await?
+        .into_iter()
+        .map(|device| (device.id.clone(), device))
+        .collect::<BTreeMap<_, _>>();
+    let mut user_structures = db
+        .get_user_structures()
+        .await?
+        .into_iter()
+        .map(|user_structure| {
+            let key = (
+                user_structure.structure_id.clone(),
+                user_structure.user_id.clone(),
+            );
+            (key, user_structure)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let (mut structure_changes, mut room_changes, mut device_changes, mut member_changes) =
+        (vec![], vec![], vec![], vec![]);
+    let (mut declared_structures, mut declared_members) = (HashSet::new(), HashSet::new());
+    let (mut declared_rooms, mut declared_devices) = (HashSet::new(), HashSet::new());
+    for structure in &home.structures {
+        if !declared_structures.insert(&structure.id) {
+            return Err(Error::Duplicate(format!("structure {}", structure.id)));
+        }
+        structure_changes.extend(change(
+            structures.remove(&structure.id),
+            structure.to_structure(),
+            HomeItem::Structure,
+        ));
+
+        for member in &structure.members {
+            let user_id = user_ids
+                .get(member.email.as_str())
+                .ok_or_else(|| Error::UserNotFound(member.email.clone()))?;
+            if !declared_members.insert((&structure.id, *user_id)) {
+                return Err(Error::Duplicate(format!(
+                    "member {} of structure {}",
+                    member.email, structure.id
+                )));
+            }
+            let existing = user_structures.remove(&(structure.id.clone(), (*user_id).clone()));
+            let desired = UserStructure {
+                structure_id: structure.id.clone(),
+                user_id: (*user_id).clone(),
+                role: member.role,
+            };
+            member_changes.extend(change(existing, desired, |user_structure| {
+                HomeItem::Member {
+                    email: member.email.clone(),
+                    user_structure,
+                }
+            }));
+        }
+
+        for room in &structure.rooms {
+            if !declared_rooms.insert(&room.id) {
+                return Err(Error::Duplicate(format!("room {}", room.id)));
+            }
+            room_changes.extend(change(
+                rooms.remove(&room.id),
+                room.to_room(&structure.id),
+                HomeItem::Room,
+            ));
+
+            for device in &room.devices {
+                if !declared_devices.insert(&device.id) {
+                    return Err(Error::Duplicate(format!("device {}", device.id)));
+                }
+                let mut desired = device.to_device(&room.id);
+                let existing = devices.remove(&device.id).map(|mut existing| {
+                    existing.password_hash.clear();
+                    existing
+                });
+                if let Some(existing) = &existing {
+                    if same_traits(&existing.traits, &desired.traits) {
+                        desired.traits = existing.traits.clone();
+                    }
+                }
+                device_changes.extend(change(existing, desired, |device| {
+                    HomeItem::Device(Box::new(device))
+                }));
+            }
+        }
+    }
+
+    let mut changes = structure_changes;
+    changes.append(&mut room_changes);
+    changes.append(&mut device_changes);
+    changes.append(&mut member_changes);
+    if !prune {
+        return Ok(HomePlan { changes });
+    }
+
+    // Remaining items aren't declared, deletes go from the children, so the moved ones are updated before
+    let emails = users
+        .iter()
+        .map(|user| (&user.id, user.email.as_str()))
+        .collect::<HashMap<&UserID, _>>();
+    let deleted_structure = |structure_id: &StructureID| structures.contains_key(structure_id);
+    for ((structure_id, user_id), user_structure) in &user_structures {
+        if !deleted_structure(structure_id) {
+            changes.push(HomeChange::Delete {
+                item: HomeItem::Member {
+                    email: emails[user_id].to_string(),
+                    user_structure: user_structure.clone(),
+                },
+            });
+        }
+    }
+    for mut device in devices.into_values() {
+        if !rooms.contains_key(&device.room_id) {
+            device.password_hash.clear();
+            changes.push(HomeChange::Delete {
+                item: HomeItem::Device(Box::new(device)),
+            });
+        }
+    }
+    for room in rooms.values() {
+        if !deleted_structure(&room.structure_id) {
+            changes.push(HomeChange::Delete {
+                item: HomeItem::Room(room.clone()),
+            });
+        }
+    }
+    for structure in structures.values() {
+        changes.push(HomeChange::Delete {
+            item: HomeItem::Structure(structure.clone()),
+        });
+    }
+
+    Ok(HomePlan { changes })
+}
+
+/// Applies the changes in a single transaction, nothing is changed if any of them fails
+pub async fn apply(db: &dyn Database, plan: &HomePlan) -> Result<(), Error> {
+    db.apply_home(plan).await.map_err(|err| match err {
+        // Updated item has been deleted since the plan was made
+        houseflow_db::Error::NotModified => Error::StalePlan,
+        err => err.into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use houseflow_types::{DeviceType, HomeDevice, HomeMember, HomeRoom, HomeStructure, Role};
+    use semver::Version;
+
+    fn get_home(email: &str) -> Home {
+        Home {
+            structures: vec![HomeStructure {
+                id: rand::random(),
+                name: "Home".to_string(),
+                members: vec![HomeMember {
+                    email: email.to_string(),
+                    role: Role::Manager,
+                }],
+                rooms: vec![HomeRoom {
+                    id: rand::random(),
+                    name: "Kitchen".to_string(),
+                    devices: vec![HomeDevice {
+                        id: rand::random(),
+                        name: "Lamp".to_string(),
+                        device_type: DeviceType::Light,
+                        traits: vec![DeviceTrait::OnOff, DeviceTrait::Brightness],
+                        will_push_state: true,
+                        model: "lamp".to_string(),
+                        hw_version: Version::new(1, 0, 0),
+                        sw_version: Version::new(1, 2, 0),
+                        attributes: Default::default(),
+                    }],
+                }],
+            }],
+        }
+    }
+
+    #[actix_rt::test]
+    async fn create_update() {
+        let db = get_database();
+        let user = get_user();
+        db.add_user(&user).await.unwrap();
+        let mut home = get_home(&user.email);
+
+        let created = plan(&**db, &home, false).await.unwrap();
+        assert_eq!(created.summary(), (4, 0, 0));
+        apply(&**db, &created).await.unwrap();
+        assert!(plan(&**db, &home, true).await.unwrap().is_empty());
+
+        let structure = &mut home.structures[0];
+        structure.members[0].role = Role::Viewer;
+        structure.rooms[0].name = "Dining room".to_string();
+        structure.rooms[0].devices[0].traits.reverse();
+        let room_id = structure.rooms[0].id.clone();
+        let updated = plan(&**db, &home, false).await.unwrap();
+        assert_eq!(updated.summary(), (0, 2, 0));
+        apply(&**db, &updated).await.unwrap();
+        assert!(plan(&**db, &home, true).await.unwrap().is_empty());
+        assert_eq!(
+            db.get_room(&room_id).await.unwrap().unwrap().name,
+            "Dining room"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn prune() {
+        let db = get_database();
+        let user = get_user();
+        db.add_user(&user).await.unwrap();
+        let home = get_home(&user.email);
+        apply(&**db, &plan(&**db, &home, false).await.unwrap())
+            .await
+            .unwrap();
+
+        let undeclared = get_structure();
+        let undeclared_room = get_room(&undeclared);
+        let room = get_room(&home.structures[0].to_structure());
+        let device = get_device(&room);
+        db.add_structure(&undeclared).await.unwrap();
+        db.add_room(&undeclared_room).await.unwrap();
+        db.add_room(&room).await.unwrap();
+        db.add_device(&device).await.unwrap();
+
+        assert!(plan(&**db, &home, false).await.unwrap().is_empty());
+        let pruned = plan(&**db, &home, true).await.unwrap();
+        let deleted = pruned
+            .changes
+            .iter()
+            .map(|change| match change {
+                HomeChange::Delete { item } => item.clone(),
+                change => panic!("expected only deletes, received: {}", change),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            deleted,
+            vec![
+                HomeItem::Room(room.clone()),
+                HomeItem::Structure(undeclared)
+            ]
+        );
+        apply(&**db, &pruned).await.unwrap();
+        assert!(plan(&**db, &home, true).await.unwrap().is_empty());
+        assert_eq!(db.get_device(&device.id).await.unwrap(), None);
+    }
+
+    #[actix_rt::test]
+    async fn invalid() {
+        let db = get_database();
+        let user = get_user();
+        db.add_user(&user).await.unwrap();
+
+        match plan(&**db, &get_home("nobody@example.com"), false).await {
+            Err(Error::UserNotFound(email)) => assert_eq!(email, "nobody@example.com"),
+            result => panic!("expected user not found, received: {:?}", result),
+        }
+
+        let mut home = get_home(&user.email);
+        let room = home.structures[0].rooms[0].clone();
+        home.structures[0].rooms.push(room);
+        match plan(&**db, &home, false).await {
+            Err(Error::Duplicate(_)) => (),
+            result => panic!("expected duplicate, received: {:?}", result),
+        }
+    }
+}
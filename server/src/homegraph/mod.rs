@@ -0,0 +1,500 @@
+//! Client of the Google HomeGraph API, used to notify Google Home about state changes
+//! of devices and changes of the device list.
+
+use houseflow_config::server::google;
+use houseflow_db::Database;
+use houseflow_types::{fulfillment::watch, traits::DeviceState, DeviceID, User, UserID};
+use ring::signature::{RsaKeyPair, RSA_PKCS1_SHA256};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, Mutex};
+use url::Url;
+
+const SCOPE: &str = "https://www.googleapis.com/auth/homegraph";
+const GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:jwt-bearer";
+const ASSERTION_DURATION: Duration = Duration::from_secs(3600);
+
+/// Access token is refreshed this long before it expires
+const ACCESS_TOKEN_MARGIN: Duration = Duration::from_secs(60);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("io error: {0}")]
+    IOError(#[from] std::io::Error),
+
+    #[error("invalid service account key: {0}")]
+    InvalidKey(String),
+
+    #[error("request failed: {0}")]
+    RequestError(#[from] reqwest::Error),
+
+    #[error("json error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("HomeGraph API responded with {status}: {body}")]
+    ApiError {
+        status: reqwest::StatusCode,
+        body: String,
+    },
+}
+
+/// Service account key, as downloaded from the Google Cloud Console
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServiceAccountKey {
+    pub client_email: String,
+    pub private_key: String,
+    pub token_uri: Url,
+}
+
+#[derive(Debug, Serialize)]
+struct Claims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: u64,
+    exp: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccessTokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Debug)]
+struct AccessToken {
+    token: String,
+    expires_at: Instant,
+}
+
+pub struct HomeGraph {
+    client: reqwest::Client,
+    url: Url,
+    key: ServiceAccountKey,
+    key_pair: RsaKeyPair,
+    access_token: Mutex<Option<AccessToken>>,
+}
+
+impl HomeGraph {
+    /// Creates the client if the service account key is configured
+    pub fn from_config(config: &google::Config) -> Result<Option<Self>, Error> {
+        let path = match &config.service_account_key_path {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let key = serde_json::from_slice(&std::fs::read(path)?)?;
+
+        Self::new(key, config.homegraph_url.clone()).map(Some)
+    }
+
+    pub fn new(key: ServiceAccountKey, url: Url) -> Result<Self, Error> {
+        let der = key
+            .private_key
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect::<String>();
+        let der = base64::decode(der).map_err(|err| Error::InvalidKey(err.to_string()))?;
+        let key_pair =
+            RsaKeyPair::from_pkcs8(&der).map_err(|err| Error::InvalidKey(err.to_string()))?;
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            url,
+            key,
+            key_pair,
+            access_token: Mutex::new(None),
+        })
+    }
+
+    fn sign_assertion(&self) -> String {
+        let encode = |bytes: &[u8]| base64::encode_config(bytes, base64::URL_SAFE_NO_PAD);
+
+        let iat = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let claims = Claims {
+            iss: &self.key.client_email,
+            scope: SCOPE,
+            aud: self.key.token_uri.as_str(),
+            iat,
+            exp: iat + ASSERTION_DURATION.as_secs(),
+        };
+        let header = encode(br#"{"alg":"RS256","typ":"JWT"}"#);
+        let payload = encode(&serde_json::to_vec(&claims).unwrap());
+        let message = format!("{}.{}", header, payload);
+
+        let mut signature = vec![0; self.key_pair.public_modulus_len()];
+        self.key_pair
+            .sign(
+                &RSA_PKCS1_SHA256,
+                &ring::rand::SystemRandom::new(),
+                message.as_bytes(),
+                &mut signature,
+            )
+            .expect("signing failed");
+
+        format!("{}.{}", message, encode(&signature))
+    }
+
+    async fn access_token(&self) -> Result<String, Error> {
+        let mut access_token = self.access_token.lock().await;
+        if let Some(access_token) = &*access_token {
+            if access_token.expires_at > Instant::now() {
+                return Ok(access_token.token.clone());
+            }
+        }
+
+        let response = self
+            .client
+            .post(self.key.token_uri.clone())
+            .form(&[
+                ("grant_type", GRANT_TYPE),
+                ("assertion", &self.sign_assertion()),
+            ])
+            .send()
+            .await?;
+        let response: AccessTokenResponse = Self::check_response(response).await?.json().await?;
+        let expires_in =
+            Duration::from_secs(response.expires_in).saturating_sub(ACCESS_TOKEN_MARGIN);
+        *access_token = Some(AccessToken {
+            token: response.access_token.clone(),
+            expires_at: Instant::now() + expires_in,
+        });
+
+        Ok(response.access_token)
+    }
+
+    async fn check_response(response: reqwest::Response) -> Result<reqwest::Response, Error> {
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err(Error::ApiError {
+                status: response.status(),
+                body: response.text().await?,
+            })
+        }
+    }
+
+    async fn post(&self, method: &str, body: serde_json::Value) -> Result<(), Error> {
+        let url = self.url.join(&format!("v1/devices:{}", method)).unwrap();
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(self.access_token().await?)
+            .json(&body)
+            .send()
+            .await?;
+        Self::check_response(response).await?;
+
+        Ok(())
+    }
+
+    /// Reports current state of the device to the HomeGraph of the user
+    pub async fn report_state(
+        &self,
+        agent_user_id: &UserID,
+        device_id: &DeviceID,
+        state: &DeviceState,
+    ) -> Result<(), Error> {
+        let mut states = serde_json::Map::new();
+        states.insert(device_id.to_string(), serde_json::to_value(state)?);
+        let body = serde_json::json!({
+            "requestId": request_id(),
+            "agentUserId": agent_user_id,
+            "payload": {
+                "devices": {
+                    "states": states,
+                },
+            },
+        });
+
+        self.post("reportStateAndNotification", body).await
+    }
+
+    /// Asks Google to send a SYNC intent for the user, must be called when list of user devices changes
+    pub async fn request_sync(&self, agent_user_id: &UserID) -> Result<(), Error> {
+        let body = serde_json::json!({
+            "agentUserId": agent_user_id,
+            "async": true,
+        });
+
+        self.post("requestSync", body).await
+    }
+}
+
+fn request_id() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+/// Checks if the user hasn't unlinked their Google Home account, errors are only logged
+async fn is_linked(db: &dyn Database, user_id: &UserID) -> bool {
+    match db.check_google_home_unlinked(user_id).await {
+        Ok(unlinked) => !unlinked,
+        Err(err) => {
+            tracing::error!(user_id = %user_id, "checking account link failed: {}", err);
+            false
+        }
+    }
+}
+
+/// Requests sync for each of the users which haven't unlinked their account, errors are only logged
+pub async fn request_sync(homegraph: Option<&HomeGraph>, db: &dyn Database, users: &[User]) {
+    let homegraph = match homegraph {
+        Some(homegraph) => homegraph,
+        None => return,
+    };
+
+    for user in users {
+        if !is_linked(db, &user.id).await {
+            continue;
+        }
+        if let Err(err) = homegraph.request_sync(&user.id).await {
+            tracing::error!(user_id = %user.id, "request sync failed: {}", err);
+        }
+    }
+}
+
+/// Reports state updates of the devices which push their state to HomeGraph of every linked user with access to the device,
+/// runs until the channel is closed
+pub async fn report_state_updates(
+    homegraph: actix_web::web::Data<HomeGraph>,
+    database: actix_web::web::Data<dyn Database>,
+    mut receiver: broadcast::Receiver<watch::Event>,
+) {
+    loop {
+        let event = match receiver.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::warn!("skipped reporting {} state updates", skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        // Google is told in SYNC that the other devices don't report their state
+        match database.get_device(&event.device_id).await {
+            Ok(Some(device)) if device.will_push_state => (),
+            Ok(_) => continue,
+            Err(err) => {
+                tracing::error!(device_id = %event.device_id, "fetching device failed: {}", err);
+                continue;
+            }
+        }
+
+        let users = match database.get_device_users(&event.device_id).await {
+            Ok(users) => users,
+            Err(err) => {
+                tracing::error!(device_id = %event.device_id, "fetching device users failed: {}", err);
+                continue;
+            }
+        };
+
+        for user in users {
+            if !is_linked(&**database, &user.id).await {
+                continue;
+            }
+            if let Err(err) = homegraph
+                .report_state(&user.id, &event.device_id, &event.snapshot.state)
+                .await
+            {
+                tracing::error!(device_id = %event.device_id, user_id = %user.id, "report state failed: {}", err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use actix_web::{web, App, HttpResponse, HttpServer};
+    use houseflow_types::{
+        traits::{on_off, DeviceStateSnapshot},
+        Device, Role, UserStructure,
+    };
+    use std::sync::{Arc, Mutex as StdMutex};
+
+    const TEST_KEY: &str = include_str!("test_key.pem");
+
+    /// Requests received by the mock server, as pairs of path and body
+    pub type Requests = Arc<StdMutex<Vec<(String, String)>>>;
+
+    /// Records the request and responds like Google would, requiring the token issued by the token endpoint
+    async fn mock_request(
+        request: web::HttpRequest,
+        body: String,
+        requests: web::Data<Requests>,
+    ) -> HttpResponse {
+        requests
+            .lock()
+            .unwrap()
+            .push((request.uri().to_string(), body));
+        if request.path() == "/token" {
+            HttpResponse::Ok().json(serde_json::json!({
+                "access_token": "some-access-token",
+                "expires_in": 3600,
+                "token_type": "Bearer",
+            }))
+        } else if request
+            .headers()
+            .get("Authorization")
+            .map(|value| value == "Bearer some-access-token")
+            .unwrap_or(false)
+        {
+            HttpResponse::Ok().json(serde_json::json!({}))
+        } else {
+            HttpResponse::Unauthorized().finish()
+        }
+    }
+
+    /// Starts a mock of the token endpoint and the HomeGraph API, returns client pointing to it
+    pub fn get_homegraph() -> (HomeGraph, Requests) {
+        let requests = Requests::default();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+
+        let server_requests = web::Data::new(requests.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(server_requests.clone())
+                .default_service(web::to(mock_request))
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_rt::spawn(async move { server.await.unwrap() });
+
+        let key = ServiceAccountKey {
+            client_email: "houseflow@some-project-id.iam.gserviceaccount.com".to_string(),
+            private_key: TEST_KEY.to_string(),
+            token_uri: url.join("token").unwrap(),
+        };
+
+        (HomeGraph::new(key, url).unwrap(), requests)
+    }
+
+    fn find_request(requests: &Requests, path: &str) -> Vec<serde_json::Value> {
+        requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(request_path, _)| request_path == path)
+            .map(|(_, body)| serde_json::from_str(body).unwrap())
+            .collect()
+    }
+
+    #[actix_rt::test]
+    async fn request_sync() {
+        let (homegraph, requests) = get_homegraph();
+        let user_id: UserID = rand::random();
+        homegraph.request_sync(&user_id).await.unwrap();
+        homegraph.request_sync(&user_id).await.unwrap();
+
+        let token_requests = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(path, _)| path == "/token")
+            .map(|(_, body)| body.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(token_requests.len(), 1, "access token should be cached");
+
+        let form: std::collections::HashMap<String, String> =
+            url::form_urlencoded::parse(token_requests[0].as_bytes())
+                .into_owned()
+                .collect();
+        assert_eq!(form["grant_type"], GRANT_TYPE);
+        let assertion = form["assertion"].split('.').collect::<Vec<_>>();
+        let public_key = ring::signature::UnparsedPublicKey::new(
+            &ring::signature::RSA_PKCS1_2048_8192_SHA256,
+            ring::signature::KeyPair::public_key(&homegraph.key_pair)
+                .as_ref()
+                .to_vec(),
+        );
+        public_key
+            .verify(
+                format!("{}.{}", assertion[0], assertion[1]).as_bytes(),
+                &base64::decode_config(assertion[2], base64::URL_SAFE_NO_PAD).unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            find_request(&requests, "/v1/devices:requestSync"),
+            vec![serde_json::json!({ "agentUserId": user_id, "async": true }); 2]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn report_state_updates() {
+        let state = get_state();
+        let user = get_user();
+        let structure = get_structure();
+        let room = get_room(&structure);
+        let device = get_device(&room);
+        state.database.add_user(&user).await.unwrap();
+        state.database.add_structure(&structure).await.unwrap();
+        state.database.add_room(&room).await.unwrap();
+        state.database.add_device(&device).await.unwrap();
+        let polled_device = Device {
+            will_push_state: false,
+            ..get_device(&room)
+        };
+        state.database.add_device(&polled_device).await.unwrap();
+        let unlinked_user = get_user();
+        state.database.add_user(&unlinked_user).await.unwrap();
+        state
+            .database
+            .add_google_home_unlink(&unlinked_user.id, &chrono::Utc::now())
+            .await
+            .unwrap();
+        for user_id in [&user.id, &unlinked_user.id].iter() {
+            state
+                .database
+                .add_user_structure(&UserStructure {
+                    structure_id: structure.id.clone(),
+                    user_id: (*user_id).clone(),
+                    role: Role::Operator,
+                })
+                .await
+                .unwrap();
+        }
+
+        let (homegraph, requests) = get_homegraph();
+        let state_updates = crate::StateUpdates::default();
+        let reporter = super::report_state_updates(
+            web::Data::new(homegraph),
+            state.database.clone(),
+            state_updates.subscribe(),
+        );
+        let device_state = DeviceState {
+            on_off: Some(on_off::State { on: true }),
+            ..Default::default()
+        };
+        state_updates.send(watch::Event {
+            device_id: device.id.clone(),
+            snapshot: DeviceStateSnapshot {
+                state: device_state.clone(),
+                updated_at: chrono::Utc::now(),
+            },
+        });
+        state_updates.send(watch::Event {
+            device_id: polled_device.id.clone(),
+            snapshot: DeviceStateSnapshot {
+                state: device_state.clone(),
+                updated_at: chrono::Utc::now(),
+            },
+        });
+        drop(state_updates);
+        reporter.await;
+
+        let reports = find_request(&requests, "/v1/devices:reportStateAndNotification");
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0]["agentUserId"], serde_json::json!(user.id));
+        assert_eq!(
+            reports[0]["payload"]["devices"]["states"][device.id.to_string()],
+            serde_json::json!({ "on": true })
+        );
+    }
+}
@@ -1,6 +1,8 @@
 mod admin;
 mod auth;
 mod fulfillment;
+pub mod home;
+pub mod homegraph;
 mod lighthouse;
 mod oauth;
 mod token_store;
@@ -14,6 +16,31 @@ use houseflow_db::Database;
 use {houseflow_types::DeviceID, lighthouse::Session, std::collections::HashMap, std::sync::Mutex};
 pub type Sessions = Mutex<HashMap<DeviceID, actix::Addr<Session>>>;
 
+use houseflow_types::fulfillment::watch;
+use tokio::sync::broadcast;
+
+const STATE_UPDATES_CHANNEL_SIZE: usize = 32;
+
+/// Broadcasts state changes of all connected devices
+pub struct StateUpdates(broadcast::Sender<watch::Event>);
+
+impl Default for StateUpdates {
+    fn default() -> Self {
+        Self(broadcast::channel(STATE_UPDATES_CHANNEL_SIZE).0)
+    }
+}
+
+impl StateUpdates {
+    pub fn send(&self, event: watch::Event) {
+        // Fails only if nobody is subscribed, which is fine
+        let _ = self.0.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<watch::Event> {
+        self.0.subscribe()
+    }
+}
+
 pub(crate) fn get_password_salt() -> [u8; 16] {
     rand::random()
 }
@@ -28,22 +55,80 @@ pub fn configure(
     database: web::Data<dyn Database>,
     config: web::Data<Config>,
     sessions: web::Data<Sessions>,
+    state_updates: web::Data<StateUpdates>,
+    homegraph: Option<web::Data<homegraph::HomeGraph>>,
 ) {
+    if let Some(homegraph) = homegraph {
+        cfg.app_data(homegraph);
+    }
     cfg.app_data(config)
         .app_data(token_store)
         .app_data(sessions)
+        .app_data(state_updates)
         .app_data(database)
         .route("/health_check", web::get().to(health_check))
         .service(
             web::scope("/admin")
-                .service(web::scope("/device").route("/add", web::put().to(admin::device::on_add)))
-                .service(web::scope("/room").route("/add", web::put().to(admin::room::on_add)))
+                .route("/apply", web::post().to(admin::apply::on_apply))
+                .service(
+                    web::scope("/backup")
+                        .route("/export", web::get().to(admin::backup::on_export))
+                        .route("/import", web::post().to(admin::backup::on_import)),
+                )
                 .service(
-                    web::scope("/structure").route("/add", web::put().to(admin::structure::on_add)),
+                    web::scope("/device")
+                        .route("/add", web::put().to(admin::device::on_add))
+                        .route("/connected", web::get().to(admin::device::on_connected))
+                        .route(
+                            "/rotate_token",
+                            web::post().to(admin::device::on_rotate_token),
+                        )
+                        .route(
+                            "/rotate_password",
+                            web::post().to(admin::device::on_rotate_password),
+                        )
+                        .route("/revoke", web::post().to(admin::device::on_revoke))
+                        .route("/list", web::get().to(admin::device::on_list))
+                        .route("/get", web::get().to(admin::device::on_get))
+                        .route("/update", web::post().to(admin::device::on_update))
+                        .route("/delete", web::delete().to(admin::device::on_delete)),
+                )
+                .service(
+                    web::scope("/role")
+                        .route("/add", web::put().to(admin::role::on_add))
+                        .route("/list", web::get().to(admin::role::on_list))
+                        .route("/delete", web::delete().to(admin::role::on_delete)),
+                )
+                .service(
+                    web::scope("/room")
+                        .route("/add", web::put().to(admin::room::on_add))
+                        .route("/list", web::get().to(admin::room::on_list))
+                        .route("/get", web::get().to(admin::room::on_get))
+                        .route("/update", web::post().to(admin::room::on_update))
+                        .route("/delete", web::delete().to(admin::room::on_delete)),
+                )
+                .service(
+                    web::scope("/structure")
+                        .route("/add", web::put().to(admin::structure::on_add))
+                        .route("/list", web::get().to(admin::structure::on_list))
+                        .route("/get", web::get().to(admin::structure::on_get))
+                        .route("/update", web::post().to(admin::structure::on_update))
+                        .route("/delete", web::delete().to(admin::structure::on_delete)),
+                )
+                .service(
+                    web::scope("/user")
+                        .route("/list", web::get().to(admin::user::on_list))
+                        .route("/get", web::get().to(admin::user::on_get))
+                        .route("/update", web::post().to(admin::user::on_update))
+                        .route("/delete", web::delete().to(admin::user::on_delete)),
                 )
                 .service(
                     web::scope("/user_structure")
-                        .route("/add", web::put().to(admin::user_structure::on_add)),
+                        .route("/add", web::put().to(admin::user_structure::on_add))
+                        .route("/list", web::get().to(admin::user_structure::on_list))
+                        .route("/get", web::get().to(admin::user_structure::on_get))
+                        .route("/update", web::post().to(admin::user_structure::on_update))
+                        .route("/delete", web::delete().to(admin::user_structure::on_delete)),
                 ),
         )
         .service(
@@ -74,6 +159,8 @@ pub fn configure(
                             web::post().to(fulfillment::internal::on_execute),
                         )
                         .route("/query", web::get().to(fulfillment::internal::on_query))
+                        .route("/history", web::get().to(fulfillment::internal::on_history))
+                        .route("/watch", web::get().to(fulfillment::internal::on_watch))
                         .route("/sync", web::get().to(fulfillment::internal::on_sync)),
                 )
                 .service(
@@ -107,9 +194,12 @@ mod test_utils {
     use super::Config;
     use crate::{token_store, TokenStore};
     use houseflow_db::{sqlite::Database as SqliteDatabase, Database};
-    use houseflow_types::{Device, DeviceType, Room, Structure, User, UserID};
+    use houseflow_types::{
+        token::{AccessToken, AccessTokenPayload},
+        Device, DeviceType, Room, Structure, User, UserID,
+    };
 
-    use actix_web::web::Data;
+    use actix_web::{http, test, web::Data, HttpRequest};
     use std::sync::Arc;
 
     pub const PASSWORD: &str = "SomePassword";
@@ -135,6 +225,7 @@ mod test_utils {
 
         Data::from(Arc::new(Config {
             hostname: defaults::server_hostname(),
+            database: Default::default(),
             database_path: std::path::PathBuf::new(),
             tokens_path: std::path::PathBuf::new(),
             tls: None,
@@ -143,6 +234,8 @@ mod test_utils {
                 client_id: "some-client-id".to_string(),
                 client_secret: "some-client-secret".to_string(),
                 project_id: "some-project-id".to_string(),
+                service_account_key_path: None,
+                homegraph_url: defaults::homegraph_url(),
             }),
         }))
     }
@@ -170,6 +263,23 @@ mod test_utils {
         }
     }
 
+    /// Request authorized with an access token of the user
+    pub fn get_http_request(config: &Config, user: &User) -> HttpRequest {
+        let access_token = AccessToken::new(
+            config.secrets.access_key.as_bytes(),
+            AccessTokenPayload {
+                sub: user.id.clone(),
+                exp: chrono::Utc::now() + chrono::Duration::minutes(10),
+            },
+        );
+        test::TestRequest::default()
+            .insert_header((
+                http::header::AUTHORIZATION,
+                format!("Bearer {}", access_token),
+            ))
+            .to_http_request()
+    }
+
     pub fn get_structure() -> Structure {
         Structure {
             id: rand::random(),
@@ -1,13 +1,27 @@
-use super::Session;
-use crate::Sessions;
+use super::{session::Replace, Session};
+use crate::{Sessions, StateUpdates};
 use actix_web::{http, web, HttpRequest, HttpResponse};
 use actix_web_actors::ws;
+use houseflow_config::server::Config;
 use houseflow_db::Database;
-use houseflow_types::{lighthouse::ConnectResponseError, DeviceID, DevicePassword};
-use itertools::Itertools;
+use houseflow_types::{
+    lighthouse::{proto, proto::Encoding, ConnectResponseError},
+    token::{DecodeError, DeviceToken},
+    DeviceID, DevicePassword,
+};
 use std::str::FromStr;
 
-fn parse_authorization_header(req: &HttpRequest) -> Result<(DeviceID, DevicePassword), String> {
+/// Credentials sent by the device in the `Authorization` header
+#[derive(Debug, Clone, PartialEq)]
+enum Credentials {
+    /// `Basic` scheme with the device ID as user-id, as described in RFC 7617
+    Password(DeviceID, DevicePassword),
+
+    /// `Bearer` scheme with a device token
+    Token(String),
+}
+
+fn parse_authorization_header(req: &HttpRequest) -> Result<Credentials, String> {
     let header = req
         .headers()
         .get(http::header::AUTHORIZATION)
@@ -15,21 +29,35 @@ fn parse_authorization_header(req: &HttpRequest) -> Result<(DeviceID, DevicePass
         .to_str()
         .map_err(|err| format!("Invalid string `Authorization` header, error: `{}`", err))?;
 
-    let mut iter = header.split_whitespace();
-    let auth_type = iter
-        .next()
-        .ok_or("Missing auth type in `Authorization` header")?;
-    if auth_type != "Basic" {
-        return Err(format!("Invalid auth type: {}", auth_type));
-    }
-    let credentials = iter
-        .next()
+    let (scheme, credentials) = header
+        .trim()
+        .split_once(' ')
         .ok_or("Missing credentials in `Authorization` header")?;
+    let credentials = credentials.trim();
+    if scheme.eq_ignore_ascii_case("Basic") {
+        let (device_id, device_password) = parse_basic_credentials(credentials)?;
+        Ok(Credentials::Password(device_id, device_password))
+    } else if scheme.eq_ignore_ascii_case("Bearer") {
+        Ok(Credentials::Token(credentials.to_string()))
+    } else {
+        Err(format!("Invalid auth type: {}", scheme))
+    }
+}
+
+fn parse_basic_credentials(credentials: &str) -> Result<(DeviceID, DevicePassword), String> {
+    let credentials = match base64::decode(credentials) {
+        Ok(credentials) => String::from_utf8(credentials)
+            .map_err(|err| format!("Invalid UTF-8 in credentials: {}", err))?,
+        // Firmware released before the credentials were encoded
+        Err(_) if credentials.contains(':') => {
+            tracing::warn!("Device has sent credentials without base64 encoding, it is deprecated");
+            credentials.to_string()
+        }
+        Err(err) => return Err(format!("Invalid base64 in credentials: {}", err)),
+    };
 
     let (device_id, device_password) = credentials
-        .split_terminator(':')
-        .take(2)
-        .next_tuple()
+        .split_once(':')
         .ok_or("Missing ID/Password in `Authorization` header")?;
 
     Ok((
@@ -38,33 +66,862 @@ fn parse_authorization_header(req: &HttpRequest) -> Result<(DeviceID, DevicePass
     ))
 }
 
+/// Verifies the credentials, returns ID of the device they belong to
+async fn authenticate(
+    credentials: Credentials,
+    database: &dyn Database,
+    config: &Config,
+) -> Result<DeviceID, ConnectResponseError> {
+    match credentials {
+        Credentials::Password(device_id, device_password) => {
+            let device = database
+                .get_device(&device_id)
+                .await
+                .map_err(|err| ConnectResponseError::InternalError(err.to_string()))?
+                .ok_or(ConnectResponseError::InvalidCredentials)?;
+            // Password authentication of the device is revoked when the hash is empty
+            if device.password_hash.is_empty()
+                || !argon2::verify_encoded(&device.password_hash, device_password.as_bytes())
+                    .unwrap()
+            {
+                return Err(ConnectResponseError::InvalidCredentials);
+            }
+            Ok(device_id)
+        }
+        Credentials::Token(token) => {
+            // Tokens are disabled when the key isn't configured
+            let device_key = config
+                .secrets
+                .device_key
+                .as_ref()
+                .ok_or(ConnectResponseError::InvalidCredentials)?;
+            let token =
+                DeviceToken::decode(device_key.as_bytes(), &token).map_err(|err| match err {
+                    DecodeError::InvalidSignature | DecodeError::ValidationError(_) => {
+                        ConnectResponseError::InvalidCredentials
+                    }
+                    err => ConnectResponseError::InvalidAuthorizationHeader(err.to_string()),
+                })?;
+            // Only the last issued token is valid, which also makes sure the device still exists
+            let token_id = database
+                .get_device_token_id(&token.sub)
+                .await
+                .map_err(|err| ConnectResponseError::InternalError(err.to_string()))?;
+            if token_id.as_ref() != Some(&token.tid) {
+                return Err(ConnectResponseError::InvalidCredentials);
+            }
+            Ok(token.sub.clone())
+        }
+    }
+}
+
+/// Devices which do not advertise the protocol version are assumed to speak the legacy one
+fn negotiate_protocol_version(req: &HttpRequest) -> Result<semver::Version, ConnectResponseError> {
+    let version = match req.headers().get(proto::VERSION_HEADER) {
+        Some(header) => header
+            .to_str()
+            .ok()
+            .and_then(|version| semver::Version::parse(version).ok())
+            .ok_or_else(|| {
+                ConnectResponseError::UnsupportedProtocolVersion(format!(
+                    "invalid `{}` header: {:?}",
+                    proto::VERSION_HEADER,
+                    header
+                ))
+            })?,
+        None => proto::LEGACY_VERSION,
+    };
+    proto::negotiate_version(&version).ok_or_else(|| {
+        ConnectResponseError::UnsupportedProtocolVersion(format!(
+            "{}, server supports {}",
+            version,
+            proto::VERSION
+        ))
+    })
+}
+
 pub async fn on_websocket(
     req: HttpRequest,
     stream: web::Payload,
     sessions: web::Data<Sessions>,
     database: web::Data<dyn Database>,
+    state_updates: web::Data<StateUpdates>,
+    config: web::Data<Config>,
 ) -> Result<HttpResponse, ConnectResponseError> {
     let address = req.peer_addr().unwrap();
-    let (device_id, device_password) = parse_authorization_header(&req)
+    let protocol_version = negotiate_protocol_version(&req)?;
+    let credentials = parse_authorization_header(&req)
         .map_err(ConnectResponseError::InvalidAuthorizationHeader)?;
+    let device_id = authenticate(credentials, &**database, &config).await?;
+
+    // Device connects again only if it has lost the previous connection, which might not have been detected yet
+    let stale_session = sessions.lock().unwrap().get(&device_id).cloned();
+    if let Some(stale_session) = stale_session {
+        // Fails only if the session has been stopped in the meantime
+        let _ = stale_session.send(Replace).await;
+    }
+
+    let encoding = req
+        .headers()
+        .get(http::header::SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|protocols| protocols.to_str().ok())
+        .map(Encoding::negotiate)
+        .unwrap_or_default();
+    let session = Session::new(
+        device_id.clone(),
+        address,
+        encoding,
+        protocol_version.clone(),
+        sessions.clone().into_inner(),
+        database.clone(),
+        state_updates.clone(),
+    );
+    let mut response = ws::handshake_with_protocols(&req, &[encoding.protocol()])
+        .map_err(|err| ConnectResponseError::HandshakeError(err.to_string()))?;
+    response.insert_header((proto::VERSION_HEADER, protocol_version.to_string()));
+    let (address, stream) = ws::WebsocketContext::create_with_addr(session, stream);
+    sessions.lock().unwrap().insert(device_id, address);
+
+    Ok(response.streaming(stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::aliases::ActorQueryFrame;
+    use super::{
+        authenticate, parse_authorization_header, Config, ConnectResponseError, Credentials,
+    };
+    use crate::test_utils::*;
+    use crate::{Sessions, StateUpdates};
+    use actix_web::{
+        http,
+        web::{Data, Json},
+        App, HttpServer,
+    };
+    use chrono::Utc;
+    use futures::{SinkExt, StreamExt};
+    use houseflow_types::admin::device::{delete, revoke, rotate_password, rotate_token};
+    use houseflow_types::lighthouse::proto::{self, execute_response, query, Encoding, Frame};
+    use houseflow_types::token::{
+        AccessToken, AccessTokenPayload, DeviceToken, DeviceTokenPayload,
+    };
+    use houseflow_types::traits::{on_off, CommandParams, DeviceState};
+    use houseflow_types::{Device, DeviceID, DeviceStatus, Role, UserStructure};
+    use std::time::{Duration, Instant};
+    use tokio_tungstenite::tungstenite::{self, client::IntoClientRequest};
+
+    type Stream = tokio_tungstenite::WebSocketStream<tokio::net::TcpStream>;
 
-    let device = database
-        .get_device(&device_id)
-        .map_err(|err| ConnectResponseError::InternalError(err.to_string()))?
-        .ok_or(ConnectResponseError::InvalidCredentials)?;
+    /// Starts the server with a single device, returns its address
+    async fn start_server(sessions: Data<Sessions>) -> (std::net::SocketAddr, Device, State) {
+        let state = get_state();
+        let user = get_user();
+        let structure = get_structure();
+        let room = get_room(&structure);
+        let device = get_device(&room);
+        state.database.add_user(&user).await.unwrap();
+        state.database.add_structure(&structure).await.unwrap();
+        state.database.add_room(&room).await.unwrap();
+        state.database.add_device(&device).await.unwrap();
 
-    if !argon2::verify_encoded(&device.password_hash, device_password.as_bytes()).unwrap() {
-        return Err(ConnectResponseError::InvalidCredentials);
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let state_updates = Data::new(StateUpdates::default());
+        let (token_store, database, config) = (
+            state.token_store.clone(),
+            state.database.clone(),
+            state.config.clone(),
+        );
+        let server = HttpServer::new(move || {
+            App::new().configure(|cfg| {
+                crate::configure(
+                    cfg,
+                    token_store.clone(),
+                    database.clone(),
+                    config.clone(),
+                    sessions.clone(),
+                    state_updates.clone(),
+                    None,
+                )
+            })
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_rt::spawn(async move { server.await.unwrap() });
+
+        (address, device, state)
     }
 
-    if sessions.lock().unwrap().contains_key(&device_id) {
-        return Err(ConnectResponseError::AlreadyConnected);
+    /// Performs handshake as the device, with additional headers
+    async fn handshake(
+        address: std::net::SocketAddr,
+        device_id: &DeviceID,
+        headers: &[(&'static str, &str)],
+    ) -> Result<(Stream, tungstenite::handshake::client::Response), tungstenite::Error> {
+        let mut request = format!("ws://{}/lighthouse/ws", address)
+            .into_client_request()
+            .unwrap();
+        request.headers_mut().insert(
+            tungstenite::http::header::AUTHORIZATION,
+            format!(
+                "Basic {}",
+                base64::encode(format!("{}:{}", device_id, PASSWORD))
+            )
+            .parse()
+            .unwrap(),
+        );
+        for (name, value) in headers {
+            request.headers_mut().insert(*name, value.parse().unwrap());
+        }
+        let stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        tokio_tungstenite::client_async(request, stream).await
     }
 
-    let session = Session::new(device_id.clone(), address, sessions.clone().into_inner());
-    let (address, response) = ws::start_with_addr(session, &req, stream)
-        .map_err(|err| ConnectResponseError::HandshakeError(err.to_string()))?;
-    sessions.lock().unwrap().insert(device_id, address);
+    async fn connect(address: std::net::SocketAddr, device_id: &DeviceID) -> Stream {
+        handshake(address, device_id, &[]).await.unwrap().0
+    }
+
+    /// Waits until the condition is met, panics after a second
+    async fn wait_until(condition: impl Fn() -> bool) {
+        wait_until_async(|| std::future::ready(condition())).await
+    }
+
+    async fn wait_until_async<F: std::future::Future<Output = bool>>(condition: impl Fn() -> F) {
+        let start = Instant::now();
+        while !condition().await {
+            assert!(start.elapsed() < Duration::from_secs(1), "timed out");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[actix_rt::test]
+    async fn replace_session() {
+        let sessions = Data::new(Sessions::default());
+        let (address, device, _) = start_server(sessions.clone()).await;
+
+        let mut stale = connect(address, &device.id).await;
+        wait_until(|| sessions.lock().unwrap().contains_key(&device.id)).await;
+        let stale_session = sessions.lock().unwrap()[&device.id].clone();
+
+        let _stream = connect(address, &device.id).await;
+        wait_until(|| sessions.lock().unwrap()[&device.id] != stale_session).await;
+        match stale.next().await {
+            Some(Ok(tungstenite::Message::Close(_))) => (),
+            message => panic!("expected close frame, received: {:?}", message),
+        }
+        wait_until(|| !stale_session.connected()).await;
+        assert!(sessions.lock().unwrap().contains_key(&device.id));
+    }
+
+    #[actix_rt::test]
+    async fn heartbeat() {
+        let sessions = Data::new(Sessions::default());
+        let (address, device, _) = start_server(sessions.clone()).await;
+
+        let mut stream = connect(address, &device.id).await;
+        wait_until(|| sessions.lock().unwrap().contains_key(&device.id)).await;
+        // Reading from the stream responds to the pings
+        let read = async {
+            while let Some(message) = stream.next().await {
+                message.unwrap();
+            }
+        };
+        let _ = tokio::time::timeout(Duration::from_millis(600), read).await;
+        assert!(sessions.lock().unwrap().contains_key(&device.id));
+
+        // Not reading from the stream anymore, so pings are left without response
+        wait_until(|| !sessions.lock().unwrap().contains_key(&device.id)).await;
+    }
+
+    struct Light {
+        on: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl houseflow_device::devices::Device for Light {
+        async fn on_execute(&mut self, _params: CommandParams) -> anyhow::Result<DeviceStatus> {
+            Ok(DeviceStatus::Success)
+        }
+
+        fn state(&self) -> DeviceState {
+            DeviceState {
+                on_off: Some(on_off::State { on: self.on }),
+                ..Default::default()
+            }
+        }
+    }
+
+    /// Runs the device session, then pushes the state and queries it back
+    async fn push_and_query_state(encoding: Encoding) {
+        let sessions = Data::new(Sessions::default());
+        let (address, device, state) = start_server(sessions.clone()).await;
+        let database = state.database;
+        let state = |on| DeviceState {
+            on_off: Some(on_off::State { on }),
+            ..Default::default()
+        };
+        let get_state = || async {
+            database
+                .get_device_state(&device.id)
+                .await
+                .unwrap()
+                .map(|snapshot| snapshot.state)
+        };
 
-    Ok(response)
+        let session = houseflow_device::Session::new(houseflow_config::device::Config {
+            device_id: device.id.clone(),
+            device_password: PASSWORD.into(),
+            device_token: None,
+            server_hostname: url::Host::Ipv4(std::net::Ipv4Addr::LOCALHOST),
+            server_port: Some(address.port()),
+            use_tls: false,
+            encoding,
+        });
+        let pusher = session.state_pusher();
+        actix_rt::spawn(async move {
+            session.run(Light { on: false }).await.unwrap();
+        });
+        wait_until_async(|| async { get_state().await == Some(state(false)) }).await;
+
+        pusher.push(state(true)).unwrap();
+        wait_until_async(|| async { get_state().await == Some(state(true)) }).await;
+
+        let session = sessions.lock().unwrap()[&device.id].clone();
+        let response = session
+            .send(ActorQueryFrame::from(query::Frame {}))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.inner.state, state(false));
+    }
+
+    #[actix_rt::test]
+    async fn push_state_json() {
+        push_and_query_state(Encoding::Json).await;
+    }
+
+    #[actix_rt::test]
+    async fn push_state_cbor() {
+        push_and_query_state(Encoding::Cbor).await;
+    }
+
+    #[actix_rt::test]
+    async fn negotiate_encoding() {
+        let sessions = Data::new(Sessions::default());
+        let (address, device, _) = start_server(sessions.clone()).await;
+
+        for (offered, negotiated) in [
+            ("houseflow-cbor, houseflow-json", Some("houseflow-cbor")),
+            ("chat, houseflow-json", Some("houseflow-json")),
+            ("chat", None),
+        ]
+        .iter()
+        {
+            let headers = [("sec-websocket-protocol", *offered)];
+            let (_, response) = handshake(address, &device.id, &headers).await.unwrap();
+            let protocol = response
+                .headers()
+                .get(tungstenite::http::header::SEC_WEBSOCKET_PROTOCOL)
+                .map(|protocol| protocol.to_str().unwrap());
+            assert_eq!(protocol, *negotiated);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn negotiate_protocol_version() {
+        let sessions = Data::new(Sessions::default());
+        let (address, device, _) = start_server(sessions.clone()).await;
+        let newer_minor = semver::Version::new(proto::VERSION.major, proto::VERSION.minor + 1, 0);
+        let newer_major = semver::Version::new(proto::VERSION.major + 1, 0, 0);
+
+        for (advertised, negotiated) in [
+            (Some(proto::VERSION.to_string()), Some(proto::VERSION)),
+            (Some(newer_minor.to_string()), Some(proto::VERSION)),
+            (None, Some(proto::LEGACY_VERSION)),
+            (Some(newer_major.to_string()), None),
+            (Some(String::from("invalid")), None),
+        ]
+        .iter()
+        {
+            let headers: Vec<_> = advertised
+                .iter()
+                .map(|version| (proto::VERSION_HEADER, version.as_str()))
+                .collect();
+            let result = handshake(address, &device.id, &headers).await;
+            let negotiated = match negotiated {
+                Some(negotiated) => negotiated,
+                None => {
+                    match result {
+                        Err(tungstenite::Error::Http(response)) => {
+                            assert_eq!(response.status(), 400)
+                        }
+                        result => panic!("expected bad request, received: {:?}", result),
+                    }
+                    continue;
+                }
+            };
+            let (_stream, response) = result.unwrap();
+            assert_eq!(
+                response.headers().get(proto::VERSION_HEADER).unwrap(),
+                negotiated.to_string().as_str()
+            );
+            wait_until(|| sessions.lock().unwrap().contains_key(&device.id)).await;
+            let session = sessions.lock().unwrap()[&device.id].clone();
+            let info = session
+                .send(crate::lighthouse::GetConnectionInfo)
+                .await
+                .unwrap();
+            assert_eq!(&info.protocol_version, negotiated);
+            sessions.lock().unwrap().clear();
+        }
+    }
+
+    /// Reads the next message, skipping the pings
+    async fn next_message(stream: &mut Stream) -> tungstenite::Message {
+        loop {
+            match stream.next().await.unwrap().unwrap() {
+                tungstenite::Message::Ping(_) => continue,
+                message => return message,
+            }
+        }
+    }
+
+    /// Reads messages until the close frame, fails if the socket has been torn down before it could be read
+    async fn next_close(
+        stream: &mut Stream,
+    ) -> Result<Option<tungstenite::protocol::CloseFrame<'static>>, std::io::Error> {
+        loop {
+            match stream
+                .next()
+                .await
+                .expect("connection ended without close frame")
+            {
+                Ok(tungstenite::Message::Ping(_)) => continue,
+                Ok(tungstenite::Message::Close(frame)) => return Ok(frame),
+                Err(tungstenite::Error::Io(err))
+                    if matches!(
+                        err.kind(),
+                        std::io::ErrorKind::BrokenPipe | std::io::ErrorKind::ConnectionReset
+                    ) =>
+                {
+                    return Err(err)
+                }
+                message => panic!("expected close frame, received: {:?}", message),
+            }
+        }
+    }
+
+    /// Asserts that the server closed the connection because of the policy.
+    /// Pong replying to a ping can hit the socket closed by the server, so the client never reads the close frame,
+    /// then the session must be gone.
+    async fn assert_disconnected(mut stream: Stream, sessions: &Sessions, device_id: &DeviceID) {
+        match next_close(&mut stream).await {
+            Ok(frame) => assert_eq!(
+                frame.map(|frame| frame.code),
+                Some(tungstenite::protocol::frame::coding::CloseCode::Policy)
+            ),
+            Err(_) => wait_until(|| !sessions.lock().unwrap().contains_key(device_id)).await,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn error_frame() {
+        let sessions = Data::new(Sessions::default());
+        let (address, device, _) = start_server(sessions.clone()).await;
+        let version = proto::VERSION.to_string();
+        let headers = [(proto::VERSION_HEADER, version.as_str())];
+        let (mut stream, _) = handshake(address, &device.id, &headers).await.unwrap();
+
+        let unexpected = Encoding::Json
+            .encode(&Frame::ExecuteResponse(execute_response::Frame {
+                id: 8,
+                status: DeviceStatus::Success,
+                state: DeviceState::default(),
+            }))
+            .unwrap();
+        let cases = [
+            (
+                br#"{"type": "ExecuteResponse", "id": 7}"#.to_vec(),
+                Some(7),
+                "invalid_frame",
+            ),
+            (unexpected, Some(8), "response_without_request"),
+            (
+                Encoding::Json
+                    .encode(&Frame::Query(query::Frame {}))
+                    .unwrap(),
+                None,
+                "unexpected_frame",
+            ),
+        ];
+        for (bytes, id, error) in cases.iter() {
+            let text = String::from_utf8(bytes.clone()).unwrap();
+            stream.send(tungstenite::Message::Text(text)).await.unwrap();
+            let frame = match next_message(&mut stream).await {
+                tungstenite::Message::Text(text) => Encoding::Json.decode(text.as_bytes()).unwrap(),
+                message => panic!("expected text message, received: {:?}", message),
+            };
+            match frame {
+                Frame::Error(frame) => {
+                    assert_eq!(frame.id, *id);
+                    let json = serde_json::to_value(&frame.error).unwrap();
+                    assert_eq!(json["error"], *error);
+                }
+                frame => panic!("expected error frame, received: {:?}", frame),
+            }
+        }
+        assert!(sessions.lock().unwrap().contains_key(&device.id));
+
+        // Devices speaking older protocol versions don't understand error frames
+        let mut stream = connect(address, &device.id).await;
+        let text = String::from("invalid");
+        stream.send(tungstenite::Message::Text(text)).await.unwrap();
+        match next_message(&mut stream).await {
+            tungstenite::Message::Close(Some(frame)) => assert_eq!(u16::from(frame.code), 4000),
+            message => panic!("expected close frame, received: {:?}", message),
+        }
+    }
+
+    #[test]
+    fn parse_authorization() {
+        let device_id: DeviceID = rand::random();
+        let parse = |header: &str| {
+            let request = actix_web::test::TestRequest::default()
+                .insert_header((http::header::AUTHORIZATION, header))
+                .to_http_request();
+            parse_authorization_header(&request)
+        };
+        let password = Credentials::Password(device_id.clone(), String::from("pass:word"));
+        let encoded = base64::encode(format!("{}:pass:word", device_id));
+
+        assert_eq!(parse(&format!("Basic {}", encoded)), Ok(password.clone()));
+        assert_eq!(parse(&format!("basic  {}", encoded)), Ok(password.clone()));
+        assert_eq!(
+            parse(&format!("Basic {}:pass:word", device_id)),
+            Ok(password)
+        );
+        assert_eq!(
+            parse("Bearer some.device.token"),
+            Ok(Credentials::Token(String::from("some.device.token")))
+        );
+        assert!(parse(&format!("Digest {}", encoded)).is_err());
+        assert!(parse("Basic").is_err());
+        assert!(parse("Basic !invalid!").is_err());
+        assert!(parse(&format!("Basic {}", base64::encode(device_id.to_string()))).is_err());
+    }
+
+    #[actix_rt::test]
+    async fn token_authentication() {
+        let sessions = Data::new(Sessions::default());
+        let (address, device, state) = start_server(sessions.clone()).await;
+        let admin = get_user();
+        state.database.add_user(&admin).await.unwrap();
+        state.database.add_admin(&admin.id).await.unwrap();
+        let access_token = AccessToken::new(
+            state.config.secrets.access_key.as_bytes(),
+            AccessTokenPayload {
+                sub: admin.id.clone(),
+                exp: Utc::now() + chrono::Duration::minutes(10),
+            },
+        );
+        let rotate_token = || async {
+            let request = actix_web::test::TestRequest::default()
+                .insert_header((
+                    http::header::AUTHORIZATION,
+                    format!("Bearer {}", access_token),
+                ))
+                .to_http_request();
+            crate::admin::device::on_rotate_token(
+                Json(rotate_token::Request {
+                    device_id: device.id.clone(),
+                }),
+                request,
+                state.config.clone(),
+                state.database.clone(),
+            )
+            .await
+            .unwrap()
+            .into_inner()
+            .device_token
+        };
+        let connect_with_token = |token: String| {
+            let device_id = device.id.clone();
+            async move {
+                let header = format!("Bearer {}", token);
+                let headers = [("authorization", header.as_str())];
+                handshake(address, &device_id, &headers).await
+            }
+        };
+
+        let revoked = rotate_token().await;
+        let token = rotate_token().await;
+        let _stream = connect_with_token(token).await.unwrap();
+        wait_until(|| sessions.lock().unwrap().contains_key(&device.id)).await;
+
+        match connect_with_token(revoked).await {
+            Err(tungstenite::Error::Http(response)) => assert_eq!(response.status(), 401),
+            result => panic!("expected unauthorized, received: {:?}", result),
+        }
+        let forged = DeviceToken::new(
+            b"some-other-key",
+            DeviceTokenPayload {
+                tid: rand::random(),
+                sub: device.id.clone(),
+                exp: None,
+            },
+        );
+        match connect_with_token(forged.encode()).await {
+            Err(tungstenite::Error::Http(response)) => assert_eq!(response.status(), 401),
+            result => panic!("expected unauthorized, received: {:?}", result),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn token_without_device_key() {
+        let state = get_state();
+        let admin = get_user();
+        let device = get_device(&get_room(&get_structure()));
+        state.database.add_user(&admin).await.unwrap();
+        state.database.add_admin(&admin.id).await.unwrap();
+        let mut config = Config::clone(&state.config);
+        config.secrets.device_key = None;
+
+        let token = DeviceToken::new(
+            b"some-key",
+            DeviceTokenPayload {
+                tid: rand::random(),
+                sub: device.id.clone(),
+                exp: None,
+            },
+        );
+        match authenticate(
+            Credentials::Token(token.encode()),
+            &**state.database,
+            &config,
+        )
+        .await
+        {
+            Err(ConnectResponseError::InvalidCredentials) => (),
+            result => panic!("expected invalid credentials, received: {:?}", result),
+        }
+
+        let access_token = AccessToken::new(
+            config.secrets.access_key.as_bytes(),
+            AccessTokenPayload {
+                sub: admin.id.clone(),
+                exp: Utc::now() + chrono::Duration::minutes(10),
+            },
+        );
+        let request = actix_web::test::TestRequest::default()
+            .insert_header((
+                http::header::AUTHORIZATION,
+                format!("Bearer {}", access_token),
+            ))
+            .to_http_request();
+        let result = crate::admin::device::on_rotate_token(
+            Json(rotate_token::Request {
+                device_id: device.id,
+            }),
+            request,
+            Data::new(config),
+            state.database.clone(),
+        )
+        .await;
+        match result {
+            Err(rotate_token::ResponseError::DeviceKeyNotConfigured) => (),
+            result => panic!(
+                "expected device key not configured, received: {:?}",
+                result.map(|_| ())
+            ),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn rotate_and_revoke_password() {
+        let sessions = Data::new(Sessions::default());
+        let (address, device, state) = start_server(sessions.clone()).await;
+        let admin = get_user();
+        state.database.add_user(&admin).await.unwrap();
+        state.database.add_admin(&admin.id).await.unwrap();
+        let access_token = AccessToken::new(
+            state.config.secrets.access_key.as_bytes(),
+            AccessTokenPayload {
+                sub: admin.id.clone(),
+                exp: Utc::now() + chrono::Duration::minutes(10),
+            },
+        );
+        let http_request = || {
+            actix_web::test::TestRequest::default()
+                .insert_header((
+                    http::header::AUTHORIZATION,
+                    format!("Bearer {}", access_token),
+                ))
+                .to_http_request()
+        };
+        let connect_with_password = |password: &str| {
+            let device_id = device.id.clone();
+            let header = format!(
+                "Basic {}",
+                base64::encode(format!("{}:{}", device_id, password))
+            );
+            async move {
+                let headers = [("authorization", header.as_str())];
+                handshake(address, &device_id, &headers).await
+            }
+        };
+        let assert_unauthorized = |result: Result<_, tungstenite::Error>| match result {
+            Err(tungstenite::Error::Http(response)) => assert_eq!(response.status(), 401),
+            result => panic!("expected unauthorized, received: {:?}", result),
+        };
+
+        let stream = connect(address, &device.id).await;
+        wait_until(|| sessions.lock().unwrap().contains_key(&device.id)).await;
+        let response = crate::admin::device::on_rotate_password(
+            Json(rotate_password::Request {
+                device_id: device.id.clone(),
+                password: String::from("new-device-password"),
+            }),
+            http_request(),
+            state.config.clone(),
+            state.database.clone(),
+            sessions.clone(),
+        )
+        .await
+        .unwrap();
+        assert!(response.disconnected);
+        assert_disconnected(stream, &sessions, &device.id).await;
+        wait_until(|| !sessions.lock().unwrap().contains_key(&device.id)).await;
+        assert_unauthorized(connect_with_password(PASSWORD).await);
+
+        let (stream, _) = connect_with_password("new-device-password").await.unwrap();
+        wait_until(|| sessions.lock().unwrap().contains_key(&device.id)).await;
+        let response = crate::admin::device::on_revoke(
+            Json(revoke::Request {
+                device_id: device.id.clone(),
+            }),
+            http_request(),
+            state.config.clone(),
+            state.database.clone(),
+            sessions.clone(),
+        )
+        .await
+        .unwrap();
+        assert!(response.disconnected);
+        assert_disconnected(stream, &sessions, &device.id).await;
+        assert_unauthorized(connect_with_password("new-device-password").await);
+    }
+
+    #[actix_rt::test]
+    async fn delete_device() {
+        let sessions = Data::new(Sessions::default());
+        let (address, device, state) = start_server(sessions.clone()).await;
+        let admin = get_user();
+        state.database.add_user(&admin).await.unwrap();
+        state.database.add_admin(&admin.id).await.unwrap();
+        let access_token = AccessToken::new(
+            state.config.secrets.access_key.as_bytes(),
+            AccessTokenPayload {
+                sub: admin.id.clone(),
+                exp: Utc::now() + chrono::Duration::minutes(10),
+            },
+        );
+        let delete = || {
+            let http_request = actix_web::test::TestRequest::default()
+                .insert_header((
+                    http::header::AUTHORIZATION,
+                    format!("Bearer {}", access_token),
+                ))
+                .to_http_request();
+            crate::admin::device::on_delete(
+                Json(delete::Request {
+                    device_id: device.id.clone(),
+                }),
+                http_request,
+                state.config.clone(),
+                state.database.clone(),
+                sessions.clone(),
+                None,
+            )
+        };
+
+        let stream = connect(address, &device.id).await;
+        wait_until(|| sessions.lock().unwrap().contains_key(&device.id)).await;
+        delete().await.unwrap();
+        assert_disconnected(stream, &sessions, &device.id).await;
+        assert_eq!(state.database.get_device(&device.id).await.unwrap(), None);
+        match handshake(address, &device.id, &[]).await {
+            Err(tungstenite::Error::Http(response)) => assert_eq!(response.status(), 401),
+            result => panic!("expected unauthorized, received: {:?}", result),
+        }
+        match delete().await {
+            Err(delete::ResponseError::NotFound) => (),
+            result => panic!("expected not found, received: {:?}", result.map(|_| ())),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn delete_structure() {
+        let sessions = Data::new(Sessions::default());
+        let (address, device, state) = start_server(sessions.clone()).await;
+        let admin = get_user();
+        state.database.add_user(&admin).await.unwrap();
+        state.database.add_admin(&admin.id).await.unwrap();
+        let room = state
+            .database
+            .get_room(&device.room_id)
+            .await
+            .unwrap()
+            .unwrap();
+        state
+            .database
+            .add_user_structure(&UserStructure {
+                structure_id: room.structure_id.clone(),
+                user_id: admin.id.clone(),
+                role: Role::Viewer,
+            })
+            .await
+            .unwrap();
+        let access_token = AccessToken::new(
+            state.config.secrets.access_key.as_bytes(),
+            AccessTokenPayload {
+                sub: admin.id.clone(),
+                exp: Utc::now() + chrono::Duration::minutes(10),
+            },
+        );
+        let http_request = actix_web::test::TestRequest::default()
+            .insert_header((
+                http::header::AUTHORIZATION,
+                format!("Bearer {}", access_token),
+            ))
+            .to_http_request();
+        let (homegraph, requests) = crate::homegraph::tests::get_homegraph();
+
+        let stream = connect(address, &device.id).await;
+        wait_until(|| sessions.lock().unwrap().contains_key(&device.id)).await;
+        crate::admin::structure::on_delete(
+            Json(houseflow_types::admin::structure::delete::Request {
+                structure_id: room.structure_id.clone(),
+            }),
+            http_request,
+            state.config.clone(),
+            state.database.clone(),
+            sessions.clone(),
+            Some(Data::new(homegraph)),
+        )
+        .await
+        .unwrap();
+        assert_disconnected(stream, &sessions, &device.id).await;
+        assert_eq!(state.database.get_device(&device.id).await.unwrap(), None);
+        let synced = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(path, _)| path == "/v1/devices:requestSync")
+            .map(|(_, body)| serde_json::from_str::<serde_json::Value>(body).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(synced.len(), 1);
+        assert_eq!(synced[0]["agentUserId"], admin.id.to_string());
+    }
 }
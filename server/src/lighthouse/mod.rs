@@ -4,3 +4,4 @@ mod session;
 
 pub use connect::on_websocket;
 pub use session::Session;
+pub(crate) use session::{Disconnect, GetConnectionInfo};
@@ -1,16 +1,25 @@
 use actix::prelude::*;
 use actix::{Actor, ActorContext, Handler, StreamHandler};
 use actix_web_actors::ws;
+use chrono::{DateTime, Utc};
+use houseflow_db::Database;
 use houseflow_types::lighthouse::{
-    proto::{execute, execute_response, query, state, Frame, FrameID},
+    proto::{
+        self, error, execute, execute_response, query, state, Encoding, EncodingError, Frame,
+        FrameID,
+    },
     DeviceCommunicationError,
 };
-use houseflow_types::{DeviceID, DeviceStatus};
+use houseflow_types::{
+    fulfillment::watch,
+    traits::{DeviceState, DeviceStateSnapshot},
+    DeviceID, DevicePresenceEvent, DeviceStatus,
+};
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::time::{Duration, Instant};
 use thiserror::Error;
-use tokio::sync::{broadcast, oneshot};
+use tokio::sync::{broadcast, mpsc, oneshot};
 use tracing::Level;
 
 use super::aliases::*;
@@ -19,13 +28,22 @@ const EXECUTE_TIMEOUT: Duration = Duration::from_secs(10);
 const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
 const STATE_CHANNEL_SIZE: usize = 4;
 
+/// How often pings are sent to the device
+#[cfg(not(test))]
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+#[cfg(test)]
+const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Session is closed if nothing was received from the device for that long
+#[cfg(not(test))]
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(30);
+#[cfg(test)]
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_millis(300);
+
 #[derive(Debug, Error)]
 pub enum SessionError {
-    #[error("client sent invalid json {0}")]
-    JsonError(#[from] serde_json::Error),
-
-    #[error("send state over channel failed {0}")]
-    SendStateError(#[from] tokio::sync::broadcast::error::SendError<state::Frame>),
+    #[error("client sent invalid frame {0}")]
+    DecodeError(#[from] EncodingError),
 
     #[error("send execute response over channel failed")]
     SendExecuteResponseError,
@@ -37,48 +55,294 @@ pub enum SessionError {
     ResponseWithoutRequest,
 }
 
+impl SessionError {
+    fn to_frame_error(&self) -> error::Error {
+        match self {
+            Self::DecodeError(err) => error::Error::InvalidFrame(err.to_string()),
+            Self::UnexpectedFrame { frame_name } => {
+                error::Error::UnexpectedFrame(frame_name.to_string())
+            }
+            // Request has timed out in the meantime
+            Self::SendExecuteResponseError | Self::ResponseWithoutRequest => {
+                error::Error::ResponseWithoutRequest
+            }
+        }
+    }
+}
+
 use std::sync::Arc;
 
+/// Database writes of the session, they're made in order by [write_loop], so the actor doesn't wait for them
+enum Write {
+    State(DeviceStateSnapshot),
+    Presence(DevicePresenceEvent),
+    /// Notifies once the writes sent before are done
+    Flush(oneshot::Sender<()>),
+}
+
+async fn write_loop(
+    device_id: DeviceID,
+    database: actix_web::web::Data<dyn Database>,
+    state_updates: actix_web::web::Data<crate::StateUpdates>,
+    mut writes: mpsc::UnboundedReceiver<Write>,
+) {
+    while let Some(write) = writes.recv().await {
+        match write {
+            Write::State(snapshot) => {
+                save_state(&device_id, &**database, &state_updates, snapshot).await
+            }
+            Write::Presence(event) => {
+                if let Err(err) = database.add_device_presence_event(&device_id, &event).await {
+                    tracing::error!("failed saving presence of {}: {}", device_id, err);
+                }
+            }
+            Write::Flush(sender) => {
+                let _ = sender.send(());
+            }
+        }
+    }
+}
+
+/// Saves the state as the last known one, so it can be served while the device is offline,
+/// and if it has changed, records it in the history and notifies the subscribers
+async fn save_state(
+    device_id: &DeviceID,
+    database: &dyn Database,
+    state_updates: &crate::StateUpdates,
+    snapshot: DeviceStateSnapshot,
+) {
+    let previous = match database.get_device_state(device_id).await {
+        Ok(previous) => previous,
+        Err(err) => {
+            tracing::error!("failed reading state of {}: {}", device_id, err);
+            None
+        }
+    };
+    if let Err(err) = database.set_device_state(device_id, &snapshot).await {
+        tracing::error!("failed saving state of {}: {}", device_id, err);
+    }
+    if previous.map(|previous| previous.state).as_ref() != Some(&snapshot.state) {
+        if let Err(err) = database
+            .add_device_state_history(device_id, &snapshot)
+            .await
+        {
+            tracing::error!("failed saving state history of {}: {}", device_id, err);
+        }
+        state_updates.send(watch::Event {
+            device_id: device_id.clone(),
+            snapshot,
+        });
+    }
+}
+
 pub struct Session {
     sessions: Arc<crate::Sessions>,
+    writes: mpsc::UnboundedSender<Write>,
     device_id: DeviceID,
     address: SocketAddr,
-    pub execute_channels: HashMap<FrameID, oneshot::Sender<execute_response::Frame>>,
+    encoding: Encoding,
+    protocol_version: semver::Version,
+    connected_at: DateTime<Utc>,
+    last_heartbeat: Instant,
+    replaced: bool,
+    pub execute_channels:
+        HashMap<FrameID, oneshot::Sender<Result<execute_response::Frame, error::Error>>>,
     pub state_channel: broadcast::Sender<state::Frame>,
 }
 
 impl Session {
-    pub fn new(device_id: DeviceID, address: SocketAddr, sessions: Arc<crate::Sessions>) -> Self {
+    pub fn new(
+        device_id: DeviceID,
+        address: SocketAddr,
+        encoding: Encoding,
+        protocol_version: semver::Version,
+        sessions: Arc<crate::Sessions>,
+        database: actix_web::web::Data<dyn Database>,
+        state_updates: actix_web::web::Data<crate::StateUpdates>,
+    ) -> Self {
         let (state_channel, _) = broadcast::channel(STATE_CHANNEL_SIZE);
+        let (writes, receiver) = mpsc::unbounded_channel();
+        actix_rt::spawn(write_loop(
+            device_id.clone(),
+            database,
+            state_updates,
+            receiver,
+        ));
 
         Self {
             sessions,
+            writes,
             device_id,
             address,
+            encoding,
+            protocol_version,
+            connected_at: Utc::now(),
+            last_heartbeat: Instant::now(),
+            replaced: false,
             state_channel,
             execute_channels: Default::default(),
         }
     }
+
+    /// Sends the frame using the encoding negotiated at handshake
+    fn send_frame(
+        &self,
+        frame: &Frame,
+        ctx: &mut <Self as Actor>::Context,
+    ) -> Result<(), EncodingError> {
+        let bytes = self.encoding.encode(frame)?;
+        if self.encoding.is_binary() {
+            ctx.binary(bytes);
+        } else {
+            ctx.text(String::from_utf8(bytes).expect("JSON is valid UTF-8"));
+        }
+        Ok(())
+    }
+
+    fn write(&self, write: Write) {
+        // Fails only if the write loop has panicked
+        if self.writes.send(write).is_err() {
+            tracing::error!("write loop of {} is not running", self.device_id);
+        }
+    }
+
+    fn save_state(&self, state: &DeviceState) {
+        self.write(Write::State(DeviceStateSnapshot {
+            state: state.clone(),
+            updated_at: chrono::Utc::now(),
+        }));
+    }
+
+    fn save_presence(&self, connected: bool) {
+        self.write(Write::Presence(DevicePresenceEvent {
+            connected,
+            address: self.address,
+            occurred_at: Utc::now(),
+        }));
+    }
+
+    /// Returns future which resolves once the writes made so far are done
+    fn flush(&self) -> impl std::future::Future<Output = ()> {
+        let (sender, receiver) = oneshot::channel();
+        self.write(Write::Flush(sender));
+        async move {
+            let _ = receiver.await;
+        }
+    }
+}
+
+/// Requests information about the connection of the device
+pub struct GetConnectionInfo;
+
+#[derive(MessageResponse)]
+pub struct ConnectionInfo {
+    pub address: SocketAddr,
+    pub protocol_version: semver::Version,
+    pub connected_at: DateTime<Utc>,
+}
+
+/// Closes the session, because the device has connected again
+pub struct Replace;
+
+impl Message for Replace {
+    type Result = ();
+}
+
+impl Handler<Replace> for Session {
+    type Result = ResponseFuture<()>;
+
+    fn handle(&mut self, _: Replace, ctx: &mut Self::Context) -> Self::Result {
+        tracing::info!(
+            "Device {} connected again, closing old session.",
+            self.device_id
+        );
+        // Disconnection is saved before responding, so it's not recorded after the connection of the new session
+        self.save_presence(false);
+        self.replaced = true;
+        ctx.close(Some(ws::CloseReason {
+            code: ws::CloseCode::Policy,
+            description: Some(String::from("replaced by a new connection")),
+        }));
+        ctx.stop();
+        Box::pin(self.flush())
+    }
+}
+
+/// Closes the session, because credentials of the device have been changed
+pub struct Disconnect {
+    pub reason: String,
+}
+
+impl Message for Disconnect {
+    type Result = ();
+}
+
+impl Handler<Disconnect> for Session {
+    type Result = ();
+
+    fn handle(&mut self, msg: Disconnect, ctx: &mut Self::Context) -> Self::Result {
+        tracing::info!(
+            "Disconnecting device {}, reason: {}.",
+            self.device_id,
+            msg.reason
+        );
+        ctx.close(Some(ws::CloseReason {
+            code: ws::CloseCode::Policy,
+            description: Some(msg.reason),
+        }));
+        ctx.stop();
+    }
+}
+
+impl Message for GetConnectionInfo {
+    type Result = ConnectionInfo;
+}
+
+impl Handler<GetConnectionInfo> for Session {
+    type Result = ConnectionInfo;
+
+    fn handle(&mut self, _: GetConnectionInfo, _ctx: &mut Self::Context) -> Self::Result {
+        ConnectionInfo {
+            address: self.address,
+            protocol_version: self.protocol_version.clone(),
+            connected_at: self.connected_at,
+        }
+    }
 }
 
 impl Actor for Session {
     type Context = ws::WebsocketContext<Self>;
 
-    fn started(&mut self, _ctx: &mut Self::Context) {
+    fn started(&mut self, ctx: &mut Self::Context) {
         tracing::info!(
-            "New device connected from {} as {}.",
+            "New device connected from {} as {}, using protocol version {}.",
             self.address,
-            self.device_id
+            self.device_id,
+            self.protocol_version,
         );
+        self.save_presence(true);
+        ctx.run_interval(HEARTBEAT_INTERVAL, |session, ctx| {
+            if session.last_heartbeat.elapsed() > HEARTBEAT_TIMEOUT {
+                tracing::warn!(
+                    "Device {} missed heartbeats, closing session.",
+                    session.device_id
+                );
+                ctx.stop();
+            } else {
+                ctx.ping(b"");
+            }
+        });
     }
-    fn stopped(&mut self, _ctx: &mut Self::Context) {
+    fn stopped(&mut self, ctx: &mut Self::Context) {
         tracing::info!("Device {} disconnected.", self.device_id);
-        assert!(self
-            .sessions
-            .lock()
-            .unwrap()
-            .remove(&self.device_id)
-            .is_some());
+        if !self.replaced {
+            self.save_presence(false);
+        }
+        // Session might have been already replaced by a new one
+        let mut sessions = self.sessions.lock().unwrap();
+        if sessions.get(&self.device_id) == Some(&ctx.address()) {
+            sessions.remove(&self.device_id);
+        }
     }
 }
 
@@ -101,11 +365,10 @@ impl Handler<ActorQueryFrame> for Session {
         let frame = Frame::Query(frame);
 
         let mut rx = self.state_channel.subscribe();
-        let json = match serde_json::to_string(&frame) {
-            Ok(json) => json,
-            Err(err) => return Box::pin(async move { Err(err.into()) }.into_actor(self)),
-        };
-        ctx.text(json);
+        if let Err(err) = self.send_frame(&frame, ctx) {
+            let err = DeviceCommunicationError::InternalError(err.to_string());
+            return Box::pin(async move { Err(err) }.into_actor(self));
+        }
         let send_time = Instant::now();
         tracing::event!(Level::INFO, "Sent Query to the device");
 
@@ -144,7 +407,7 @@ impl Handler<ActorExecuteFrame> for Session {
         fields(
             device = %self.device_id,
             id = frame.inner.id,
-            command = %frame.inner.command,
+            command = %frame.inner.params.command(),
             params = ?frame.inner.params,
         )
     )]
@@ -155,14 +418,12 @@ impl Handler<ActorExecuteFrame> for Session {
         let frame_id = frame.id;
         let frame = Frame::Execute(frame);
 
-        let json = match serde_json::to_string(&frame) {
-            Ok(json) => json,
-            Err(err) => return Box::pin(async move { Err(err.into()) }.into_actor(self)),
-        };
-
+        if let Err(err) = self.send_frame(&frame, ctx) {
+            let err = DeviceCommunicationError::InternalError(err.to_string());
+            return Box::pin(async move { Err(err) }.into_actor(self));
+        }
         let (tx, rx) = oneshot::channel();
         self.execute_channels.insert(frame_id, tx);
-        ctx.text(json);
         let send_time = Instant::now();
         tracing::event!(Level::INFO, "Sent Execute to the device");
 
@@ -170,7 +431,8 @@ impl Handler<ActorExecuteFrame> for Session {
             let resp = tokio::time::timeout(EXECUTE_TIMEOUT, rx)
                 .await
                 .map_err(|_| DeviceCommunicationError::Timeout)?
-                .map_err(|err| DeviceCommunicationError::InternalError(err.to_string()))?;
+                .map_err(|err| DeviceCommunicationError::InternalError(err.to_string()))?
+                .map_err(DeviceCommunicationError::FrameRejected)?;
 
             let span = tracing::span!(
                 Level::INFO,
@@ -205,6 +467,79 @@ impl Handler<ActorExecuteFrame> for Session {
     }
 }
 
+impl Session {
+    /// Handles the frame, errors are reported back to the device if it supports it, otherwise the connection is closed
+    fn handle_message(
+        &mut self,
+        encoding: Encoding,
+        bytes: &[u8],
+        ctx: &mut <Self as Actor>::Context,
+    ) {
+        let result = encoding
+            .decode(bytes)
+            .map_err(SessionError::from)
+            .and_then(|frame| self.handle_frame(frame));
+        let err = match result {
+            Ok(()) => return,
+            Err(err) => err,
+        };
+        tracing::warn!("Invalid frame from {}: {}", self.device_id, err);
+
+        if self.protocol_version < proto::ERROR_FRAME_VERSION {
+            ctx.close(Some(ws::CloseReason {
+                code: ws::CloseCode::Other(4000),
+                description: Some(err.to_string()),
+            }));
+            ctx.stop();
+            return;
+        }
+        let frame = Frame::Error(error::Frame {
+            id: encoding.decode_id(bytes),
+            error: err.to_frame_error(),
+        });
+        if let Err(err) = self.send_frame(&frame, ctx) {
+            tracing::error!("failed sending error frame: {}", err);
+        }
+    }
+
+    fn handle_frame(&mut self, frame: Frame) -> Result<(), SessionError> {
+        match frame {
+            Frame::State(frame) => {
+                self.save_state(&frame.state);
+                // Sending fails if there is no pending query, which is the case when device pushes the state by itself
+                let _ = self.state_channel.send(frame);
+            }
+            Frame::ExecuteResponse(frame) => {
+                self.save_state(&frame.state);
+                self.execute_channels
+                    .remove(&frame.id)
+                    .ok_or(SessionError::ResponseWithoutRequest)?
+                    .send(Ok(frame))
+                    .map_err(|_| SessionError::SendExecuteResponseError)?;
+            }
+            Frame::Error(frame) => {
+                tracing::warn!(
+                    "Device {} rejected frame {:?}: {}",
+                    self.device_id,
+                    frame.id,
+                    frame.error
+                );
+                let channel = frame.id.and_then(|id| self.execute_channels.remove(&id));
+                if let Some(channel) = channel {
+                    // Fails only if the request has timed out in the meantime
+                    let _ = channel.send(Err(frame.error));
+                }
+            }
+            frame => {
+                return Err(SessionError::UnexpectedFrame {
+                    frame_name: frame.name(),
+                })
+            }
+        }
+        Ok(())
+    }
+}
+
 impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Session {
     fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
         let msg = match msg {
@@ -216,61 +551,32 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Session {
             }
         };
 
-        let result = (|| {
-            match msg {
-                ws::Message::Text(text) => {
-                    let frame = serde_json::from_str(&text)?;
-                    match frame {
-                        Frame::State(frame) => {
-                            self.state_channel.send(frame)?;
-                        }
-                        Frame::ExecuteResponse(frame) => {
-                            self.execute_channels
-                                .remove(&frame.id)
-                                .ok_or(SessionError::ResponseWithoutRequest)?
-                                .send(frame)
-                                .map_err(|_| SessionError::SendExecuteResponseError)?;
-                        }
-                        frame => {
-                            return Err(SessionError::UnexpectedFrame {
-                                frame_name: frame.name(),
-                            })
-                        }
-                    }
-                }
-                ws::Message::Binary(bytes) => {
-                    tracing::debug!("Received binary: {:?}", bytes);
-                }
-                ws::Message::Continuation(item) => {
-                    tracing::debug!("Received continuation: {:?}", item);
-                }
-                ws::Message::Ping(bytes) => {
-                    tracing::debug!("Received ping: {:?}", bytes);
-                    ctx.pong(b"");
-                }
-                ws::Message::Pong(bytes) => {
-                    tracing::debug!("Received pong: {:?}", bytes);
-                }
-                ws::Message::Close(reason) => {
-                    tracing::debug!("Connection closed, reason: {:?}", reason);
-                    ctx.close(reason);
-                    ctx.stop();
-                }
-                ws::Message::Nop => {
-                    tracing::debug!("Received no operation");
-                }
-            };
-            Ok(())
-        })();
-        match result {
-            Ok(_) => {}
-            Err(err) => {
-                ctx.close(Some(ws::CloseReason {
-                    code: ws::CloseCode::Other(4000),
-                    description: Some(err.to_string()),
-                }));
+        self.last_heartbeat = Instant::now();
+        match msg {
+            ws::Message::Text(text) => {
+                self.handle_message(Encoding::Json, text.as_bytes(), ctx);
+            }
+            ws::Message::Binary(bytes) => {
+                self.handle_message(Encoding::Cbor, &bytes, ctx);
+            }
+            ws::Message::Continuation(item) => {
+                tracing::debug!("Received continuation: {:?}", item);
+            }
+            ws::Message::Ping(bytes) => {
+                tracing::debug!("Received ping: {:?}", bytes);
+                ctx.pong(b"");
+            }
+            ws::Message::Pong(bytes) => {
+                tracing::debug!("Received pong: {:?}", bytes);
+            }
+            ws::Message::Close(reason) => {
+                tracing::debug!("Connection closed, reason: {:?}", reason);
+                ctx.close(reason);
                 ctx.stop();
             }
-        }
+            ws::Message::Nop => {
+                tracing::debug!("Received no operation");
+            }
+        };
     }
 }
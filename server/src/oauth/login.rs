@@ -13,9 +13,10 @@ use houseflow_types::{
 use super::{verify_redirect_uri, AuthorizationRequestQuery, AuthorizationResponseError};
 
 fn verify_password(hash: &str, password: &str) -> Result<(), ResponseError> {
-    match argon2::verify_encoded(hash, password.as_bytes()).unwrap() {
-        true => Ok(()),
-        false => Err(ResponseError::InvalidPassword),
+    // Hash is empty for the users which didn't exist before a backup without secrets was restored
+    match argon2::verify_encoded(hash, password.as_bytes()) {
+        Ok(true) => Ok(()),
+        Ok(false) | Err(_) => Err(ResponseError::InvalidPassword),
     }
 }
 
@@ -60,6 +61,7 @@ pub async fn on_login(
     validator::Validate::validate(&request).map_err(houseflow_types::ValidationError::from)?;
     let user = db
         .get_user_by_email(&request.email)
+        .await
         .map_err(houseflow_db::Error::into_internal_server_error)?
         .ok_or(ResponseError::UserNotFound)?;
 
@@ -2,6 +2,7 @@ use crate::{token_store::Error as TokenStoreError, TokenStore};
 use actix_web::web::{Data, Form, FormConfig, Json};
 use chrono::{Duration, Utc};
 use houseflow_config::server::Config;
+use houseflow_db::Database;
 use houseflow_types::token::{
     AccessToken, AccessTokenPayload, AuthorizationCode, RefreshToken, RefreshTokenPayload,
 };
@@ -159,6 +160,7 @@ async fn on_refresh_token_grant(
 async fn on_authorization_code_grant(
     token_store: Data<dyn TokenStore>,
     config: Data<Config>,
+    db: Data<dyn Database>,
     code: String,
 ) -> Response {
     let code = AuthorizationCode::decode(config.secrets.authorization_code_key.as_bytes(), &code)
@@ -190,6 +192,13 @@ async fn on_authorization_code_grant(
         .add(&refresh_token.tid, refresh_token.exp.as_ref())
         .await
         .map_err(TokenStoreError::into_internal_server_error)?;
+    token_store
+        .link(&refresh_token.sub, &refresh_token.tid)
+        .await
+        .map_err(TokenStoreError::into_internal_server_error)?;
+    db.remove_google_home_unlink(&refresh_token.sub)
+        .await
+        .map_err(houseflow_db::Error::into_internal_server_error)?;
 
     Ok(ResponseBody {
         access_token: access_token.to_string(),
@@ -203,6 +212,7 @@ pub async fn on_token_grant(
     Form(request): Form<Request>,
     token_store: Data<dyn TokenStore>,
     config: Data<Config>,
+    db: Data<dyn Database>,
 ) -> Result<Json<ResponseBody>, ResponseError> {
     let verify_client = |client_id, client_secret| {
         if client_id != config.google.as_ref().unwrap().client_id
@@ -231,7 +241,7 @@ pub async fn on_token_grant(
             ..
         } => {
             verify_client(client_id, client_secret)?;
-            on_authorization_code_grant(token_store, config, code).await
+            on_authorization_code_grant(token_store, config, db, code).await
         }
     }
     .map(Json)
@@ -265,6 +275,7 @@ mod tests {
             }),
             state.token_store.clone(),
             state.config.clone(),
+            state.database.clone(),
         )
         .await
         .unwrap()
@@ -280,6 +291,7 @@ mod tests {
             }),
             state.token_store.clone(),
             state.config.clone(),
+            state.database.clone(),
         )
         .await
         .unwrap()
@@ -322,6 +334,7 @@ mod tests {
                 }),
                 state.token_store.clone(),
                 state.config.clone(),
+                state.database.clone(),
             )
             .await
             .unwrap()
@@ -346,6 +359,7 @@ mod tests {
                 }),
                 state.token_store,
                 state.config,
+                state.database,
             )
             .await
             .unwrap_err();
@@ -378,6 +392,7 @@ mod tests {
                 }),
                 state.token_store.clone(),
                 state.config.clone(),
+                state.database.clone(),
             )
             .await
             .unwrap()
@@ -407,6 +422,7 @@ mod tests {
                 }),
                 state.token_store,
                 state.config,
+                state.database,
             )
             .await
             .unwrap_err();
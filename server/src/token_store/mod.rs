@@ -2,7 +2,7 @@ pub mod sled;
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use houseflow_types::token::RefreshTokenID;
+use houseflow_types::{token::RefreshTokenID, UserID};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -24,6 +24,12 @@ pub trait TokenStore: Send + Sync {
         id: &RefreshTokenID,
         expire_at: Option<&DateTime<Utc>>,
     ) -> Result<(), Error>;
+
+    /// Associates the token with the user whose account it was issued for during Google Home account linking
+    async fn link(&self, user_id: &UserID, id: &RefreshTokenID) -> Result<(), Error>;
+
+    /// Removes every token linked to the user, returns number of removed tokens
+    async fn remove_linked(&self, user_id: &UserID) -> Result<usize, Error>;
 }
 
 impl From<Error> for houseflow_types::InternalServerError {
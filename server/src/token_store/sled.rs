@@ -2,25 +2,32 @@ use super::Error;
 use async_trait::async_trait;
 use bytes::{Buf, BufMut, BytesMut};
 use chrono::{DateTime, Utc};
-use houseflow_types::token::RefreshTokenID;
+use houseflow_types::{token::RefreshTokenID, UserID};
 
 #[derive(Clone)]
 pub struct TokenStore {
     database: sled::Db,
+
+    /// Keys are made of user ID followed by ID of the linked token
+    linked: sled::Tree,
 }
 
 impl TokenStore {
     pub fn new(path: impl AsRef<std::path::Path>) -> Result<Self, Error> {
         let config = sled::Config::new().path(path);
-        Ok(Self {
-            database: config.open()?,
-        })
+        Self::open(config)
     }
 
     pub fn new_temporary(path: impl AsRef<std::path::Path>) -> Result<Self, Error> {
         let config = sled::Config::new().path(path).temporary(true);
+        Self::open(config)
+    }
+
+    fn open(config: sled::Config) -> Result<Self, Error> {
+        let database = config.open()?;
         Ok(Self {
-            database: config.open()?,
+            linked: database.open_tree("linked")?,
+            database,
         })
     }
 }
@@ -85,6 +92,30 @@ impl crate::TokenStore for TokenStore {
         self.database.flush_async().await?;
         Ok(())
     }
+
+    async fn link(&self, user_id: &UserID, id: &RefreshTokenID) -> Result<(), Error> {
+        let key = [user_id.as_ref(), id.as_ref()].concat();
+        self.linked.insert(key, &[])?;
+        self.database.flush_async().await?;
+        Ok(())
+    }
+
+    async fn remove_linked(&self, user_id: &UserID) -> Result<usize, Error> {
+        let mut removed = 0;
+        for key in self.linked.scan_prefix(user_id).keys() {
+            let key = key?;
+            if self
+                .database
+                .remove(&key[user_id.as_ref().len()..])?
+                .is_some()
+            {
+                removed += 1;
+            }
+            self.linked.remove(key)?;
+        }
+        self.database.flush_async().await?;
+        Ok(removed)
+    }
 }
 
 #[cfg(test)]
@@ -136,4 +167,30 @@ mod tests {
             .unwrap();
         assert_eq!(token_store.exists(&token_id).await.unwrap(), false);
     }
+
+    #[tokio::test]
+    async fn remove_linked() {
+        let token_store = get_token_store();
+        let (user_id, other_user_id) = (random(), random());
+        let linked_token_ids = [random(), random()];
+        let (unlinked_token_id, other_user_token_id) = (random(), random());
+        for token_id in linked_token_ids.iter() {
+            token_store.add(token_id, None).await.unwrap();
+            token_store.link(&user_id, token_id).await.unwrap();
+        }
+        token_store.add(&unlinked_token_id, None).await.unwrap();
+        token_store.add(&other_user_token_id, None).await.unwrap();
+        token_store
+            .link(&other_user_id, &other_user_token_id)
+            .await
+            .unwrap();
+
+        assert_eq!(token_store.remove_linked(&user_id).await.unwrap(), 2);
+        for token_id in linked_token_ids.iter() {
+            assert!(!token_store.exists(token_id).await.unwrap());
+        }
+        assert!(token_store.exists(&unlinked_token_id).await.unwrap());
+        assert!(token_store.exists(&other_user_token_id).await.unwrap());
+        assert_eq!(token_store.remove_linked(&user_id).await.unwrap(), 0);
+    }
 }
@@ -0,0 +1,66 @@
+use crate::{token, Home, HomePlan};
+use serde::{Deserialize, Serialize};
+
+/// Reconciles the database with the home
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Request {
+    pub home: Home,
+
+    /// Delete the structures, rooms, devices and members which aren't declared in the home
+    #[serde(default)]
+    pub prune: bool,
+
+    /// Only return the plan, without applying it
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+pub type Response = Result<ResponseBody, ResponseError>;
+
+#[derive(Debug, Clone, Deserialize, Serialize, thiserror::Error)]
+#[serde(
+    tag = "error",
+    content = "error_description",
+    rename_all = "snake_case"
+)]
+pub enum ResponseError {
+    #[error("internal error: {0}")]
+    InternalError(#[from] crate::InternalServerError),
+
+    #[error("token error: {0}")]
+    TokenError(#[from] token::Error),
+
+    #[error("User is not admin")]
+    UserNotAdmin,
+
+    #[error("Invalid home: {0}")]
+    InvalidHome(String),
+
+    #[error("Home has been changed while applying, try again")]
+    StalePlan,
+}
+
+#[cfg(feature = "actix")]
+impl actix_web::ResponseError for ResponseError {
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        use actix_web::http::StatusCode;
+
+        match self {
+            Self::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::TokenError(err) => err.status_code(),
+            Self::UserNotAdmin => StatusCode::FORBIDDEN,
+            Self::InvalidHome(_) => StatusCode::BAD_REQUEST,
+            Self::StalePlan => StatusCode::CONFLICT,
+        }
+    }
+
+    fn error_response(&self) -> actix_web::HttpResponse {
+        crate::json_error_response(self.status_code(), self)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ResponseBody {
+    /// Changes made to the database, or the ones which would be made on a dry run
+    pub plan: HomePlan,
+}
@@ -0,0 +1,88 @@
+use super::ListResponseError;
+
+pub mod export {
+    use super::ListResponseError;
+    use crate::Backup;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {
+        /// Leaves out the password hashes of the users and devices
+        #[serde(default)]
+        pub without_secrets: bool,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ListResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {
+        pub backup: Backup,
+    }
+}
+
+pub mod import {
+    use crate::{token, Backup};
+    use serde::{Deserialize, Serialize};
+
+    /// Replaces the whole home configuration with the backup
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {
+        pub backup: Backup,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+
+    #[derive(Debug, Clone, Deserialize, Serialize, thiserror::Error)]
+    #[serde(
+        tag = "error",
+        content = "error_description",
+        rename_all = "snake_case"
+    )]
+    pub enum ResponseError {
+        #[error("internal error: {0}")]
+        InternalError(#[from] crate::InternalServerError),
+
+        #[error("token error: {0}")]
+        TokenError(#[from] token::Error),
+
+        #[error("User is not admin")]
+        UserNotAdmin,
+
+        #[error("Backup version {0} is not supported, expected {}", Backup::VERSION)]
+        UnsupportedVersion(u32),
+
+        #[error("Backup contains duplicated items")]
+        AlreadyExists,
+
+        #[error("Item referred by the backup doesn't exist")]
+        ReferenceNotFound,
+
+        #[error("None of the admins would be able to log in after restoring the backup")]
+        NoAdminPassword,
+    }
+
+    #[cfg(feature = "actix")]
+    impl actix_web::ResponseError for ResponseError {
+        fn status_code(&self) -> actix_web::http::StatusCode {
+            use actix_web::http::StatusCode;
+
+            match self {
+                Self::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                Self::TokenError(err) => err.status_code(),
+                Self::UserNotAdmin => StatusCode::FORBIDDEN,
+                Self::UnsupportedVersion(_) => StatusCode::BAD_REQUEST,
+                Self::AlreadyExists => StatusCode::BAD_REQUEST,
+                Self::ReferenceNotFound => StatusCode::BAD_REQUEST,
+                Self::NoAdminPassword => StatusCode::BAD_REQUEST,
+            }
+        }
+
+        fn error_response(&self) -> actix_web::HttpResponse {
+            crate::json_error_response(self.status_code(), self)
+        }
+    }
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {}
+}
@@ -1,4 +1,4 @@
-use super::AddResponseError;
+use super::{AddResponseError, ItemResponseError, ListResponseError};
 
 pub mod add {
     use super::AddResponseError;
@@ -31,3 +31,333 @@ pub mod add {
         pub device_id: DeviceID,
     }
 }
+
+pub mod connected {
+    use crate::{token, DeviceID};
+    use chrono::{DateTime, Utc};
+    use semver::Version;
+    use serde::{Deserialize, Serialize};
+    use std::net::SocketAddr;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {}
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+
+    #[derive(Debug, Clone, Deserialize, Serialize, thiserror::Error)]
+    #[serde(
+        tag = "error",
+        content = "error_description",
+        rename_all = "snake_case"
+    )]
+    pub enum ResponseError {
+        #[error("internal error: {0}")]
+        InternalError(#[from] crate::InternalServerError),
+
+        #[error("token error: {0}")]
+        TokenError(#[from] token::Error),
+
+        #[error("User is not admin")]
+        UserNotAdmin,
+    }
+
+    #[cfg(feature = "actix")]
+    impl actix_web::ResponseError for ResponseError {
+        fn status_code(&self) -> actix_web::http::StatusCode {
+            use actix_web::http::StatusCode;
+
+            match self {
+                Self::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                Self::TokenError(err) => err.status_code(),
+                Self::UserNotAdmin => StatusCode::FORBIDDEN,
+            }
+        }
+
+        fn error_response(&self) -> actix_web::HttpResponse {
+            crate::json_error_response(self.status_code(), self)
+        }
+    }
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {
+        pub devices: Vec<ConnectedDevice>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+    pub struct ConnectedDevice {
+        pub device_id: DeviceID,
+
+        /// Address the device has connected from
+        pub address: SocketAddr,
+
+        /// Time when the device has connected
+        pub connected_at: DateTime<Utc>,
+
+        /// Time elapsed since the device has connected, in seconds
+        pub uptime: u64,
+
+        /// Version of the lighthouse protocol negotiated with the device
+        pub protocol_version: Version,
+    }
+}
+
+pub mod rotate_token {
+    use crate::{token, DeviceID};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {
+        pub device_id: DeviceID,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+
+    #[derive(Debug, Clone, Deserialize, Serialize, thiserror::Error)]
+    #[serde(
+        tag = "error",
+        content = "error_description",
+        rename_all = "snake_case"
+    )]
+    pub enum ResponseError {
+        #[error("internal error: {0}")]
+        InternalError(#[from] crate::InternalServerError),
+
+        #[error("token error: {0}")]
+        TokenError(#[from] token::Error),
+
+        #[error("User is not admin")]
+        UserNotAdmin,
+
+        #[error("Device not found")]
+        DeviceNotFound,
+
+        #[error("Device tokens are disabled, `device_key` is missing in the server configuration")]
+        DeviceKeyNotConfigured,
+    }
+
+    #[cfg(feature = "actix")]
+    impl actix_web::ResponseError for ResponseError {
+        fn status_code(&self) -> actix_web::http::StatusCode {
+            use actix_web::http::StatusCode;
+
+            match self {
+                Self::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                Self::TokenError(err) => err.status_code(),
+                Self::UserNotAdmin => StatusCode::FORBIDDEN,
+                Self::DeviceNotFound => StatusCode::NOT_FOUND,
+                Self::DeviceKeyNotConfigured => StatusCode::NOT_IMPLEMENTED,
+            }
+        }
+
+        fn error_response(&self) -> actix_web::HttpResponse {
+            crate::json_error_response(self.status_code(), self)
+        }
+    }
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {
+        /// Token which the device can use instead of the password, previously issued token is revoked
+        pub device_token: String,
+    }
+}
+
+pub mod rotate_password {
+    use crate::{token, DeviceID};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    #[derive(Debug, Clone, Deserialize, Serialize, Validate)]
+    pub struct Request {
+        pub device_id: DeviceID,
+
+        #[validate(length(min = 8))]
+        pub password: String,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+
+    #[derive(Debug, Clone, Deserialize, Serialize, thiserror::Error)]
+    #[serde(
+        tag = "error",
+        content = "error_description",
+        rename_all = "snake_case"
+    )]
+    pub enum ResponseError {
+        #[error("internal error: {0}")]
+        InternalError(#[from] crate::InternalServerError),
+
+        #[error("validation error: {0}")]
+        ValidationError(#[from] crate::ValidationError),
+
+        #[error("token error: {0}")]
+        TokenError(#[from] token::Error),
+
+        #[error("User is not admin")]
+        UserNotAdmin,
+
+        #[error("Device not found")]
+        DeviceNotFound,
+    }
+
+    #[cfg(feature = "actix")]
+    impl actix_web::ResponseError for ResponseError {
+        fn status_code(&self) -> actix_web::http::StatusCode {
+            use actix_web::http::StatusCode;
+
+            match self {
+                Self::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                Self::ValidationError(_) => StatusCode::BAD_REQUEST,
+                Self::TokenError(err) => err.status_code(),
+                Self::UserNotAdmin => StatusCode::FORBIDDEN,
+                Self::DeviceNotFound => StatusCode::NOT_FOUND,
+            }
+        }
+
+        fn error_response(&self) -> actix_web::HttpResponse {
+            crate::json_error_response(self.status_code(), self)
+        }
+    }
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {
+        /// Whether the device was connected with the old password, and has been disconnected
+        pub disconnected: bool,
+    }
+}
+
+pub mod revoke {
+    use crate::{token, DeviceID};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {
+        pub device_id: DeviceID,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+
+    #[derive(Debug, Clone, Deserialize, Serialize, thiserror::Error)]
+    #[serde(
+        tag = "error",
+        content = "error_description",
+        rename_all = "snake_case"
+    )]
+    pub enum ResponseError {
+        #[error("internal error: {0}")]
+        InternalError(#[from] crate::InternalServerError),
+
+        #[error("token error: {0}")]
+        TokenError(#[from] token::Error),
+
+        #[error("User is not admin")]
+        UserNotAdmin,
+
+        #[error("Device not found")]
+        DeviceNotFound,
+    }
+
+    #[cfg(feature = "actix")]
+    impl actix_web::ResponseError for ResponseError {
+        fn status_code(&self) -> actix_web::http::StatusCode {
+            use actix_web::http::StatusCode;
+
+            match self {
+                Self::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                Self::TokenError(err) => err.status_code(),
+                Self::UserNotAdmin => StatusCode::FORBIDDEN,
+                Self::DeviceNotFound => StatusCode::NOT_FOUND,
+            }
+        }
+
+        fn error_response(&self) -> actix_web::HttpResponse {
+            crate::json_error_response(self.status_code(), self)
+        }
+    }
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {
+        /// Whether the device was connected, and has been disconnected
+        pub disconnected: bool,
+    }
+}
+
+pub mod list {
+    use super::ListResponseError;
+    use crate::Device;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {}
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ListResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {
+        pub devices: Vec<Device>,
+    }
+}
+
+pub mod get {
+    use super::ItemResponseError;
+    use crate::{Device, DeviceID};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {
+        pub device_id: DeviceID,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ItemResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {
+        pub device: Device,
+    }
+}
+
+pub mod update {
+    use super::ItemResponseError;
+    use crate::{DeviceID, DeviceTrait, DeviceType, RoomID};
+    use semver::Version;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    #[derive(Debug, Clone, Deserialize, Serialize, Validate)]
+    pub struct Request {
+        pub device_id: DeviceID,
+        pub room_id: RoomID,
+        pub device_type: DeviceType,
+        pub traits: Vec<DeviceTrait>,
+        pub name: String,
+        pub will_push_state: bool,
+        pub model: String,
+        pub hw_version: Version,
+        pub sw_version: Version,
+        pub attributes: serde_json::Map<String, serde_json::Value>,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ItemResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {}
+}
+
+pub mod delete {
+    use super::ItemResponseError;
+    use crate::DeviceID;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {
+        pub device_id: DeviceID,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ItemResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {}
+}
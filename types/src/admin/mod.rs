@@ -1,6 +1,10 @@
+pub mod apply;
+pub mod backup;
 pub mod device;
+pub mod role;
 pub mod room;
 pub mod structure;
+pub mod user;
 pub mod user_structure;
 
 use crate::token;
@@ -22,11 +26,17 @@ pub enum AddResponseError {
     #[error("token error: {0}")]
     TokenError(#[from] token::Error),
 
-    #[error("Device already exists")]
-    DeviceAlreadyExists,
+    #[error("Item already exists")]
+    AlreadyExists,
+
+    #[error("Item referred by the request doesn't exist")]
+    ReferenceNotFound,
 
     #[error("User is not admin")]
     UserNotAdmin,
+
+    #[error("User is neither admin nor manager of the structure")]
+    UserNotManager,
 }
 
 #[cfg(feature = "actix")]
@@ -36,10 +46,101 @@ impl actix_web::ResponseError for AddResponseError {
 
         match self {
             Self::TokenError(err) => err.status_code(),
-            Self::DeviceAlreadyExists => StatusCode::BAD_REQUEST,
+            Self::AlreadyExists => StatusCode::CONFLICT,
+            Self::ReferenceNotFound => StatusCode::BAD_REQUEST,
+            Self::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::ValidationError(_) => StatusCode::BAD_REQUEST,
+            Self::UserNotAdmin => StatusCode::FORBIDDEN,
+            Self::UserNotManager => StatusCode::FORBIDDEN,
+        }
+    }
+
+    fn error_response(&self) -> actix_web::HttpResponse {
+        crate::json_error_response(self.status_code(), self)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, thiserror::Error)]
+#[serde(
+    tag = "error",
+    content = "error_description",
+    rename_all = "snake_case"
+)]
+pub enum ListResponseError {
+    #[error("internal error: {0}")]
+    InternalError(#[from] crate::InternalServerError),
+
+    #[error("token error: {0}")]
+    TokenError(#[from] token::Error),
+
+    #[error("User is not admin")]
+    UserNotAdmin,
+}
+
+#[cfg(feature = "actix")]
+impl actix_web::ResponseError for ListResponseError {
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        use actix_web::http::StatusCode;
+
+        match self {
+            Self::TokenError(err) => err.status_code(),
+            Self::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::UserNotAdmin => StatusCode::FORBIDDEN,
+        }
+    }
+
+    fn error_response(&self) -> actix_web::HttpResponse {
+        crate::json_error_response(self.status_code(), self)
+    }
+}
+
+/// Error of the request which gets, updates or deletes a single item
+#[derive(Debug, Clone, Deserialize, Serialize, thiserror::Error)]
+#[serde(
+    tag = "error",
+    content = "error_description",
+    rename_all = "snake_case"
+)]
+pub enum ItemResponseError {
+    #[error("internal error: {0}")]
+    InternalError(#[from] crate::InternalServerError),
+
+    #[error("validation error: {0}")]
+    ValidationError(#[from] crate::ValidationError),
+
+    #[error("token error: {0}")]
+    TokenError(#[from] token::Error),
+
+    #[error("User is not admin")]
+    UserNotAdmin,
+
+    #[error("User is neither admin nor manager of the structure")]
+    UserNotManager,
+
+    #[error("Item not found")]
+    NotFound,
+
+    #[error("Item already exists")]
+    AlreadyExists,
+
+    #[error("Item referred by the request doesn't exist")]
+    ReferenceNotFound,
+}
+
+#[cfg(feature = "actix")]
+impl actix_web::ResponseError for ItemResponseError {
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        use actix_web::http::StatusCode;
+
+        match self {
+            Self::TokenError(err) => err.status_code(),
             Self::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::ValidationError(_) => StatusCode::BAD_REQUEST,
             Self::UserNotAdmin => StatusCode::FORBIDDEN,
+            Self::UserNotManager => StatusCode::FORBIDDEN,
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::AlreadyExists => StatusCode::CONFLICT,
+            Self::ReferenceNotFound => StatusCode::BAD_REQUEST,
         }
     }
 
@@ -0,0 +1,56 @@
+use super::{AddResponseError, ItemResponseError, ListResponseError};
+
+pub mod add {
+    use super::AddResponseError;
+    use crate::{Role, RoleScope, UserID};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    #[derive(Debug, Clone, Deserialize, Serialize, Validate)]
+    pub struct Request {
+        pub user_id: UserID,
+        pub scope: RoleScope,
+        pub role: Role,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = AddResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {}
+}
+
+pub mod list {
+    use super::ListResponseError;
+    use crate::ScopedRole;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {}
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ListResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {
+        pub roles: Vec<ScopedRole>,
+    }
+}
+
+pub mod delete {
+    use super::ItemResponseError;
+    use crate::{RoleScope, UserID};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {
+        pub user_id: UserID,
+        pub scope: RoleScope,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ItemResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {}
+}
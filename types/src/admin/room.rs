@@ -1,4 +1,4 @@
-use super::AddResponseError;
+use super::{AddResponseError, ItemResponseError, ListResponseError};
 
 pub mod add {
     use super::AddResponseError;
@@ -20,3 +20,76 @@ pub mod add {
         pub room_id: RoomID,
     }
 }
+
+pub mod list {
+    use super::ListResponseError;
+    use crate::Room;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {}
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ListResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {
+        pub rooms: Vec<Room>,
+    }
+}
+
+pub mod get {
+    use super::ItemResponseError;
+    use crate::{Room, RoomID};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {
+        pub room_id: RoomID,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ItemResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {
+        pub room: Room,
+    }
+}
+
+pub mod update {
+    use super::ItemResponseError;
+    use crate::{RoomID, StructureID};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    #[derive(Debug, Clone, Deserialize, Serialize, Validate)]
+    pub struct Request {
+        pub room_id: RoomID,
+        pub structure_id: StructureID,
+        pub room_name: String,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ItemResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {}
+}
+
+pub mod delete {
+    use super::ItemResponseError;
+    use crate::RoomID;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {
+        pub room_id: RoomID,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ItemResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {}
+}
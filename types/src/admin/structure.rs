@@ -1,4 +1,4 @@
-use super::AddResponseError;
+use super::{AddResponseError, ItemResponseError, ListResponseError};
 
 pub mod add {
     use super::AddResponseError;
@@ -19,3 +19,75 @@ pub mod add {
         pub structure_id: StructureID,
     }
 }
+
+pub mod list {
+    use super::ListResponseError;
+    use crate::Structure;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {}
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ListResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {
+        pub structures: Vec<Structure>,
+    }
+}
+
+pub mod get {
+    use super::ItemResponseError;
+    use crate::{Structure, StructureID};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {
+        pub structure_id: StructureID,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ItemResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {
+        pub structure: Structure,
+    }
+}
+
+pub mod update {
+    use super::ItemResponseError;
+    use crate::StructureID;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    #[derive(Debug, Clone, Deserialize, Serialize, Validate)]
+    pub struct Request {
+        pub structure_id: StructureID,
+        pub structure_name: String,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ItemResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {}
+}
+
+pub mod delete {
+    use super::ItemResponseError;
+    use crate::StructureID;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {
+        pub structure_id: StructureID,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ItemResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {}
+}
@@ -0,0 +1,76 @@
+use super::{ItemResponseError, ListResponseError};
+
+pub mod list {
+    use super::ListResponseError;
+    use crate::User;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {}
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ListResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {
+        pub users: Vec<User>,
+    }
+}
+
+pub mod get {
+    use super::ItemResponseError;
+    use crate::{User, UserID};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {
+        pub user_id: UserID,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ItemResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {
+        pub user: User,
+    }
+}
+
+pub mod update {
+    use super::ItemResponseError;
+    use crate::UserID;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    #[derive(Debug, Clone, Deserialize, Serialize, Validate)]
+    pub struct Request {
+        pub user_id: UserID,
+        pub username: String,
+
+        #[validate(email)]
+        pub email: String,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ItemResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {}
+}
+
+pub mod delete {
+    use super::ItemResponseError;
+    use crate::UserID;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {
+        pub user_id: UserID,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ItemResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {}
+}
@@ -1,8 +1,8 @@
-use super::AddResponseError;
+use super::{AddResponseError, ItemResponseError, ListResponseError};
 
 pub mod add {
     use super::AddResponseError;
-    use crate::{StructureID, UserID};
+    use crate::{Role, StructureID, UserID};
     use serde::{Deserialize, Serialize};
     use validator::Validate;
 
@@ -10,7 +10,7 @@ pub mod add {
     pub struct Request {
         pub structure_id: StructureID,
         pub user_id: UserID,
-        pub is_manager: bool,
+        pub role: Role,
     }
 
     pub type Response = Result<ResponseBody, ResponseError>;
@@ -19,3 +19,78 @@ pub mod add {
     #[derive(Debug, Clone, Deserialize, Serialize)]
     pub struct ResponseBody {}
 }
+
+pub mod list {
+    use super::ListResponseError;
+    use crate::UserStructure;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {}
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ListResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {
+        pub user_structures: Vec<UserStructure>,
+    }
+}
+
+pub mod get {
+    use super::ItemResponseError;
+    use crate::{StructureID, UserID, UserStructure};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {
+        pub structure_id: StructureID,
+        pub user_id: UserID,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ItemResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {
+        pub user_structure: UserStructure,
+    }
+}
+
+pub mod update {
+    use super::ItemResponseError;
+    use crate::{Role, StructureID, UserID};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    #[derive(Debug, Clone, Deserialize, Serialize, Validate)]
+    pub struct Request {
+        pub structure_id: StructureID,
+        pub user_id: UserID,
+        pub role: Role,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ItemResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {}
+}
+
+pub mod delete {
+    use super::ItemResponseError;
+    use crate::{StructureID, UserID};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct Request {
+        pub structure_id: StructureID,
+        pub user_id: UserID,
+    }
+
+    pub type Response = Result<ResponseBody, ResponseError>;
+    pub type ResponseError = ItemResponseError;
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ResponseBody {}
+}
@@ -45,7 +45,7 @@ impl actix_web::ResponseError for ResponseError {
         match self {
             Self::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::ValidationError(_) => StatusCode::BAD_REQUEST,
-            Self::UserAlreadyExists => StatusCode::BAD_REQUEST,
+            Self::UserAlreadyExists => StatusCode::CONFLICT,
         }
     }
 
@@ -0,0 +1,264 @@
+use crate::{
+    Device, DeviceID, DeviceTokenID, Room, ScopedRole, Structure, User, UserID, UserStructure,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Home configuration exported from the database, it can be saved both as JSON and TOML.
+///
+/// TOML requires the plain values to be placed before the tables, keep that in mind when adding fields.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Backup {
+    /// Version of the document format, see [Backup::VERSION]
+    pub version: u32,
+
+    /// Time when the backup has been created
+    pub created_at: DateTime<Utc>,
+
+    /// False if the password hashes of the users and devices have been left out
+    pub with_secrets: bool,
+
+    /// IDs of the users with admin privileges
+    pub admins: Vec<UserID>,
+
+    // Empty lists are skipped, TOML would emit them as values after the tables
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub structures: Vec<Structure>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rooms: Vec<Room>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub devices: Vec<Device>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<User>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub user_structures: Vec<UserStructure>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty", with = "scoped_roles")]
+    pub scoped_roles: Vec<ScopedRole>,
+    /// Left out along with the password hashes, devices must get new tokens then
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub device_tokens: Vec<IssuedDeviceToken>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub google_home_unlinks: Vec<GoogleHomeUnlink>,
+}
+
+/// ID of the only valid token of the device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssuedDeviceToken {
+    pub device_id: DeviceID,
+    pub token_id: DeviceTokenID,
+    pub issued_at: DateTime<Utc>,
+}
+
+/// User who unlinked their Google Home account, the server doesn't report their devices until they link it again
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoogleHomeUnlink {
+    pub user_id: UserID,
+    pub unlinked_at: DateTime<Utc>,
+}
+
+impl Backup {
+    /// Version of the document format created by this build, bumped when the format changes
+    pub const VERSION: u32 = 1;
+
+    /// Clears the password hashes and IDs of the device tokens.
+    /// Restoring it keeps the passwords of the users which already exist, the other ones can't log in,
+    /// and devices must get new passwords before they can connect.
+    pub fn without_secrets(mut self) -> Self {
+        for user in &mut self.users {
+            user.password_hash.clear();
+        }
+        for device in &mut self.devices {
+            device.password_hash.clear();
+        }
+        self.device_tokens.clear();
+        self.with_secrets = false;
+        self
+    }
+}
+
+/// TOML can't represent the newtype variants of [crate::RoleScope],
+/// so the scope is stored as either `room_id` or `device_id` of the role.
+mod scoped_roles {
+    use crate::{DeviceID, Role, RoleScope, RoomID, ScopedRole, UserID};
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct FlatScopedRole {
+        user_id: UserID,
+        role: Role,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        room_id: Option<RoomID>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        device_id: Option<DeviceID>,
+    }
+
+    pub fn serialize<S: Serializer>(
+        scoped_roles: &[ScopedRole],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let scoped_roles = scoped_roles
+            .iter()
+            .map(|scoped_role| {
+                let (room_id, device_id) = match &scoped_role.scope {
+                    RoleScope::Room(room_id) => (Some(room_id.clone()), None),
+                    RoleScope::Device(device_id) => (None, Some(device_id.clone())),
+                };
+                FlatScopedRole {
+                    user_id: scoped_role.user_id.clone(),
+                    role: scoped_role.role,
+                    room_id,
+                    device_id,
+                }
+            })
+            .collect::<Vec<_>>();
+        scoped_roles.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<ScopedRole>, D::Error> {
+        Vec::<FlatScopedRole>::deserialize(deserializer)?
+            .into_iter()
+            .map(|flat| {
+                let scope = match (flat.room_id, flat.device_id) {
+                    (Some(room_id), None) => RoleScope::Room(room_id),
+                    (None, Some(device_id)) => RoleScope::Device(device_id),
+                    _ => {
+                        return Err(D::Error::custom(
+                            "scoped role must have either room_id or device_id",
+                        ))
+                    }
+                };
+                Ok(ScopedRole {
+                    user_id: flat.user_id,
+                    scope,
+                    role: flat.role,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeviceTrait, DeviceType, Role, RoleScope};
+    use semver::Version;
+
+    fn backup() -> Backup {
+        let structure = Structure {
+            id: rand::random(),
+            name: "Home".to_string(),
+        };
+        let room = Room {
+            id: rand::random(),
+            structure_id: structure.id.clone(),
+            name: "Bedroom".to_string(),
+        };
+        let device = Device {
+            id: rand::random(),
+            room_id: room.id.clone(),
+            password_hash: "SomeDevicePasswordHash".to_string(),
+            device_type: DeviceType::Light,
+            traits: vec![DeviceTrait::OnOff, DeviceTrait::Brightness],
+            name: "Night lamp".to_string(),
+            will_push_state: true,
+            model: "lamp".to_string(),
+            hw_version: Version::new(1, 0, 0),
+            sw_version: Version::new(1, 2, 0),
+            attributes: serde_json::json!({ "colorModel": "rgb" })
+                .as_object()
+                .unwrap()
+                .clone(),
+        };
+        let user = User {
+            id: rand::random(),
+            username: "John".to_string(),
+            email: "john@example.com".to_string(),
+            password_hash: "SomeUserPasswordHash".to_string(),
+        };
+        Backup {
+            version: Backup::VERSION,
+            created_at: Utc::now(),
+            with_secrets: true,
+            admins: vec![user.id.clone()],
+            user_structures: vec![UserStructure {
+                structure_id: structure.id.clone(),
+                user_id: user.id.clone(),
+                role: Role::Viewer,
+            }],
+            scoped_roles: vec![
+                ScopedRole {
+                    user_id: user.id.clone(),
+                    scope: RoleScope::Room(room.id.clone()),
+                    role: Role::Operator,
+                },
+                ScopedRole {
+                    user_id: user.id.clone(),
+                    scope: RoleScope::Device(device.id.clone()),
+                    role: Role::Manager,
+                },
+            ],
+            device_tokens: vec![IssuedDeviceToken {
+                device_id: device.id.clone(),
+                token_id: rand::random(),
+                issued_at: Utc::now(),
+            }],
+            google_home_unlinks: vec![GoogleHomeUnlink {
+                user_id: user.id.clone(),
+                unlinked_at: Utc::now(),
+            }],
+            structures: vec![structure],
+            rooms: vec![room],
+            devices: vec![device],
+            users: vec![user],
+        }
+    }
+
+    #[test]
+    fn json() {
+        let backup = backup();
+        let json = serde_json::to_string(&backup).unwrap();
+        assert_eq!(serde_json::from_str::<Backup>(&json).unwrap(), backup);
+    }
+
+    #[test]
+    fn toml() {
+        let backup = backup();
+        let toml = toml::to_string_pretty(&backup).unwrap();
+        assert_eq!(toml::from_str::<Backup>(&toml).unwrap(), backup);
+    }
+
+    #[test]
+    fn toml_empty_lists() {
+        let backup = Backup {
+            rooms: vec![],
+            devices: vec![],
+            ..backup()
+        };
+        let toml = toml::to_string_pretty(&backup).unwrap();
+        assert_eq!(toml::from_str::<Backup>(&toml).unwrap(), backup);
+    }
+
+    #[test]
+    fn scoped_role_without_scope() {
+        let json = serde_json::json!([{ "user_id": UserID::default(), "role": "viewer" }]);
+        scoped_roles::deserialize(json).unwrap_err();
+    }
+
+    #[test]
+    fn without_secrets() {
+        let backup = backup().without_secrets();
+        assert!(!backup.with_secrets);
+        assert!(backup
+            .users
+            .iter()
+            .all(|user| user.password_hash.is_empty()));
+        assert!(backup
+            .devices
+            .iter()
+            .all(|device| device.password_hash.is_empty()));
+        assert!(backup.device_tokens.is_empty());
+        assert!(!backup.google_home_unlinks.is_empty());
+    }
+}
@@ -182,6 +182,39 @@ impl<const N: usize> rusqlite::types::FromSql for Credential<N> {
     }
 }
 
+#[cfg(feature = "postgres-types")]
+impl<const N: usize> postgres_types::ToSql for Credential<N> {
+    fn to_sql(
+        &self,
+        ty: &postgres_types::Type,
+        out: &mut bytes::BytesMut,
+    ) -> Result<postgres_types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        self.to_string().to_sql(ty, out)
+    }
+
+    fn accepts(ty: &postgres_types::Type) -> bool {
+        <String as postgres_types::ToSql>::accepts(ty)
+    }
+
+    postgres_types::to_sql_checked!();
+}
+
+#[cfg(feature = "postgres-types")]
+impl<'a, const N: usize> postgres_types::FromSql<'a> for Credential<N> {
+    fn from_sql(
+        ty: &postgres_types::Type,
+        raw: &'a [u8],
+    ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(Self::from_str(
+            <&str as postgres_types::FromSql>::from_sql(ty, raw)?,
+        )?)
+    }
+
+    fn accepts(ty: &postgres_types::Type) -> bool {
+        <&str as postgres_types::FromSql>::accepts(ty)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -5,6 +5,7 @@ use strum::{EnumIter, IntoEnumIterator};
 
 pub type DeviceID = Credential<16>;
 pub type DevicePassword = String;
+pub type DeviceTokenID = Credential<16>;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Device {
@@ -42,6 +43,19 @@ pub struct Device {
     pub attributes: serde_json::Map<String, serde_json::Value>,
 }
 
+/// Device connecting to or disconnecting from the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DevicePresenceEvent {
+    /// True if the device has connected, false if it has disconnected
+    pub connected: bool,
+
+    /// Address the device has connected from
+    pub address: std::net::SocketAddr,
+
+    /// Time when the event has occurred
+    pub occurred_at: chrono::DateTime<chrono::Utc>,
+}
+
 pub type StructureID = Credential<16>;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -63,7 +77,61 @@ pub struct Room {
 pub struct UserStructure {
     pub structure_id: StructureID,
     pub user_id: crate::UserID,
-    pub is_manager: bool,
+    pub role: Role,
+}
+
+/// Role of the user, each role has the permissions of the lower ones
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    strum::Display,
+    strum::EnumString,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Can see the devices and query their state
+    Viewer,
+
+    /// Can also execute commands on the devices
+    Operator,
+
+    /// Can also add and remove rooms, devices and users
+    Manager,
+}
+
+impl Role {
+    pub fn can_execute(&self) -> bool {
+        *self >= Self::Operator
+    }
+
+    pub fn can_manage(&self) -> bool {
+        *self >= Self::Manager
+    }
+}
+
+/// Part of the structure which the scoped role applies to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoleScope {
+    Room(RoomID),
+    Device(DeviceID),
+}
+
+/// Role of the user for the single room or device, it takes precedence over the role in the structure
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScopedRole {
+    pub user_id: crate::UserID,
+    pub scope: RoleScope,
+    pub role: Role,
 }
 
 use strum::EnumString;
@@ -74,6 +142,9 @@ use strum::EnumString;
 pub enum DeviceTrait {
     OnOff,
     OpenClose,
+    Brightness,
+    ColorSetting,
+    TemperatureSetting,
 }
 
 impl DeviceTrait {
@@ -81,17 +152,29 @@ impl DeviceTrait {
         match *self {
             Self::OnOff => vec![DeviceCommand::OnOff],
             Self::OpenClose => vec![DeviceCommand::OpenClose],
+            Self::Brightness => vec![
+                DeviceCommand::BrightnessAbsolute,
+                DeviceCommand::BrightnessRelative,
+            ],
+            Self::ColorSetting => vec![DeviceCommand::ColorAbsolute],
+            Self::TemperatureSetting => vec![
+                DeviceCommand::ThermostatTemperatureSetpoint,
+                DeviceCommand::ThermostatTemperatureSetRange,
+                DeviceCommand::ThermostatSetMode,
+            ],
         }
     }
 }
 
 /// Type of the device
 #[derive(Debug, Clone, PartialEq, Eq, strum::Display, EnumString, Serialize, Deserialize)]
+#[strum(ascii_case_insensitive)]
 #[non_exhaustive]
 pub enum DeviceType {
     Gate,
     Garage,
     Light,
+    Thermostat,
 }
 
 impl DeviceType {
@@ -100,6 +183,7 @@ impl DeviceType {
             Self::Gate => vec![DeviceTrait::OpenClose],
             Self::Garage => vec![DeviceTrait::OpenClose],
             Self::Light => vec![DeviceTrait::OnOff],
+            Self::Thermostat => vec![DeviceTrait::TemperatureSetting],
         }
     }
 }
@@ -111,6 +195,12 @@ impl DeviceType {
 pub enum DeviceCommand {
     OnOff,
     OpenClose,
+    BrightnessAbsolute,
+    BrightnessRelative,
+    ColorAbsolute,
+    ThermostatTemperatureSetpoint,
+    ThermostatTemperatureSetRange,
+    ThermostatSetMode,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, strum::Display, EnumIter)]
@@ -168,6 +258,15 @@ impl rusqlite::ToSql for DeviceTrait {
     }
 }
 
+#[cfg(feature = "rusqlite")]
+impl rusqlite::ToSql for Role {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        Ok(rusqlite::types::ToSqlOutput::Owned(
+            rusqlite::types::Value::Text(self.to_string()),
+        ))
+    }
+}
+
 #[cfg(feature = "rusqlite")]
 use std::str::FromStr;
 
@@ -186,3 +285,53 @@ impl rusqlite::types::FromSql for DeviceTrait {
             .map_err(|err| rusqlite::types::FromSqlError::Other(Box::new(err)))
     }
 }
+
+#[cfg(feature = "rusqlite")]
+impl rusqlite::types::FromSql for Role {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        Self::from_str(value.as_str()?)
+            .map_err(|err| rusqlite::types::FromSqlError::Other(Box::new(err)))
+    }
+}
+
+/// Device types, traits and roles are stored as text, the same as in SQLite
+#[cfg(feature = "postgres-types")]
+macro_rules! impl_postgres_text {
+    ($($ty:ty),*) => {
+        $(
+            impl postgres_types::ToSql for $ty {
+                fn to_sql(
+                    &self,
+                    ty: &postgres_types::Type,
+                    out: &mut bytes::BytesMut,
+                ) -> Result<postgres_types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
+                    self.to_string().to_sql(ty, out)
+                }
+
+                fn accepts(ty: &postgres_types::Type) -> bool {
+                    <String as postgres_types::ToSql>::accepts(ty)
+                }
+
+                postgres_types::to_sql_checked!();
+            }
+
+            impl<'a> postgres_types::FromSql<'a> for $ty {
+                fn from_sql(
+                    ty: &postgres_types::Type,
+                    raw: &'a [u8],
+                ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+                    Ok(<$ty as std::str::FromStr>::from_str(
+                        <&str as postgres_types::FromSql>::from_sql(ty, raw)?,
+                    )?)
+                }
+
+                fn accepts(ty: &postgres_types::Type) -> bool {
+                    <&str as postgres_types::FromSql>::accepts(ty)
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "postgres-types")]
+impl_postgres_text!(DeviceType, DeviceTrait, Role);
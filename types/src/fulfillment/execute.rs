@@ -1,12 +1,13 @@
-use crate::{lighthouse, token, DeviceID};
+use crate::{lighthouse, token, DeviceCommand, DeviceID};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Request {
     pub device_id: DeviceID,
+    pub command: DeviceCommand,
 
-    #[serde(flatten)]
-    pub frame: lighthouse::proto::execute::Frame,
+    #[serde(default)]
+    pub params: serde_json::Map<String, serde_json::Value>,
 }
 
 pub type Response = Result<ResponseBody, ResponseError>;
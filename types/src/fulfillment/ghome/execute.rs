@@ -1,5 +1,5 @@
 use super::DeviceStatus;
-use crate::{DeviceCommand, DeviceID};
+use crate::{traits::DeviceState, DeviceCommand, DeviceID};
 use serde::{Deserialize, Serialize};
 
 pub mod request {
@@ -128,9 +128,54 @@ pub mod response {
 
         /// Aligned with per-trait states described in each trait schema reference.
         /// These are the states after execution, if available.
-        pub states: serde_json::Map<String, serde_json::Value>,
+        pub states: DeviceState,
 
         /// Expanding ERROR state if needed from the preset error codes, which will map to the errors presented to users.
         pub error_code: Option<String>,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::{brightness, temperature_setting};
+
+    #[test]
+    fn request_commands() {
+        let payload: request::Payload = serde_json::from_value(serde_json::json!({
+            "commands": [{
+                "devices": [{ "id": "6f2fe3bbda3f4c1c9fd11d7a1b4c9a7e" }],
+                "execution": [
+                    {
+                        "command": "action.devices.commands.BrightnessAbsolute",
+                        "params": { "brightness": 65 }
+                    },
+                    {
+                        "command": "action.devices.commands.ThermostatSetMode",
+                        "params": { "thermostatMode": "heatcool" }
+                    }
+                ]
+            }]
+        }))
+        .unwrap();
+        let execution = &payload.commands[0].execution;
+        assert_eq!(execution[0].command, DeviceCommand::BrightnessAbsolute);
+        assert_eq!(
+            serde_json::from_value::<brightness::BrightnessAbsoluteParams>(
+                serde_json::Value::Object(execution[0].params.clone())
+            )
+            .unwrap(),
+            brightness::BrightnessAbsoluteParams { brightness: 65 }
+        );
+        assert_eq!(execution[1].command, DeviceCommand::ThermostatSetMode);
+        assert_eq!(
+            serde_json::from_value::<temperature_setting::ThermostatSetModeParams>(
+                serde_json::Value::Object(execution[1].params.clone())
+            )
+            .unwrap(),
+            temperature_setting::ThermostatSetModeParams {
+                thermostat_mode: temperature_setting::ThermostatMode::Heatcool
+            }
+        );
+    }
+}
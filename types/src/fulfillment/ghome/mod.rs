@@ -65,7 +65,8 @@ pub enum IntentResponseBody {
         request_id: String,
         payload: execute::response::Payload,
     },
-    Disconnect,
+    /// Serialized as an empty object, which is what Google expects in response to DISCONNECT
+    Disconnect {},
 }
 
 use crate::{lighthouse, token};
@@ -83,6 +84,9 @@ pub enum IntentResponseError {
     #[error("token error: {0}")]
     TokenError(#[from] token::Error),
 
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+
     #[error("no device permission")]
     NoDevicePermission,
 
@@ -98,6 +102,7 @@ impl actix_web::ResponseError for IntentResponseError {
         match self {
             Self::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::TokenError(_) => StatusCode::UNAUTHORIZED,
+            Self::InvalidRequest(_) => StatusCode::BAD_REQUEST,
             Self::NoDevicePermission => StatusCode::UNAUTHORIZED,
             Self::DeviceCommunicationError(_) => StatusCode::BAD_GATEWAY,
         }
@@ -1,5 +1,5 @@
 use super::DeviceStatus;
-use crate::DeviceID;
+use crate::{traits::DeviceState, DeviceID};
 use serde::{Deserialize, Serialize};
 
 pub mod request {
@@ -58,6 +58,6 @@ pub mod response {
 
         /// Device state
         #[serde(flatten)]
-        pub state: Option<serde_json::Map<String, serde_json::Value>>,
+        pub state: Option<DeviceState>,
     }
 }
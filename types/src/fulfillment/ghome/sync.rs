@@ -123,3 +123,40 @@ pub mod response {
         pub device_id: DeviceID,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::response::*;
+    use crate::{DeviceTrait, DeviceType};
+
+    #[test]
+    fn payload_device() {
+        let device = PayloadDevice {
+            id: rand::random(),
+            device_type: DeviceType::Thermostat,
+            traits: vec![DeviceTrait::TemperatureSetting],
+            name: PayloadDeviceName {
+                default_names: None,
+                name: "Living room thermostat".to_string(),
+                nicknames: None,
+            },
+            will_report_state: false,
+            notification_supported_by_agent: false,
+            room_hint: None,
+            device_info: None,
+            attributes: None,
+            custom_data: None,
+            other_device_ids: None,
+        };
+        let json = serde_json::to_value(&device).unwrap();
+        assert_eq!(json["type"], "action.devices.types.THERMOSTAT");
+        assert_eq!(
+            json["traits"],
+            serde_json::json!(["action.devices.traits.TemperatureSetting"])
+        );
+
+        let parsed: PayloadDevice = serde_json::from_str(&json.to_string()).unwrap();
+        assert_eq!(parsed.device_type, DeviceType::Thermostat);
+        assert_eq!(parsed.traits, vec![DeviceTrait::TemperatureSetting]);
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{token, traits::DeviceStateSnapshot, DeviceID};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Amount of entries returned if the limit is not specified
+pub const DEFAULT_LIMIT: u32 = 100;
+
+/// Maximum amount of entries that can be returned at once
+pub const MAX_LIMIT: u32 = 1000;
+
+fn default_limit() -> u32 {
+    DEFAULT_LIMIT
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Request {
+    pub device_id: DeviceID,
+
+    /// Return only the states reported at or after this time
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Return only the states reported before this time
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+
+    /// Maximum amount of entries to return, capped at `MAX_LIMIT`
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+
+    /// Amount of entries to skip
+    #[serde(default)]
+    pub offset: u32,
+}
+
+pub type Response = Result<ResponseBody, ResponseError>;
+
+#[derive(Debug, Clone, Deserialize, Serialize, thiserror::Error)]
+#[serde(
+    tag = "error",
+    content = "error_description",
+    rename_all = "snake_case"
+)]
+pub enum ResponseError {
+    #[error("internal error: {0}")]
+    InternalError(#[from] crate::InternalServerError),
+
+    #[error("token error: {0}")]
+    TokenError(#[from] token::Error),
+
+    #[error("no device permission")]
+    NoDevicePermission,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ResponseBody {
+    /// States of the device, from the oldest to the newest
+    pub states: Vec<DeviceStateSnapshot>,
+}
+
+#[cfg(feature = "actix")]
+impl actix_web::ResponseError for ResponseError {
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        use actix_web::http::StatusCode;
+
+        match self {
+            Self::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::TokenError(_) => StatusCode::UNAUTHORIZED,
+            Self::NoDevicePermission => StatusCode::UNAUTHORIZED,
+        }
+    }
+
+    fn error_response(&self) -> actix_web::HttpResponse {
+        crate::json_error_response(self.status_code(), self)
+    }
+}
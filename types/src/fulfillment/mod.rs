@@ -1,5 +1,7 @@
 pub mod execute;
+pub mod history;
 pub mod query;
 pub mod sync;
+pub mod watch;
 
 pub mod ghome;
@@ -1,4 +1,5 @@
 use crate::{lighthouse, token, DeviceID};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -37,6 +38,12 @@ pub enum ResponseError {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ResponseBody {
     pub frame: lighthouse::proto::state::Frame,
+
+    /// Time when the state was reported by the device
+    pub updated_at: DateTime<Utc>,
+
+    /// False if the device is disconnected and the state is the last known one
+    pub online: bool,
 }
 
 #[cfg(feature = "actix")]
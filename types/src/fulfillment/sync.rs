@@ -1,4 +1,5 @@
-use crate::{token, Device};
+use crate::{token, Device, DeviceID};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -23,6 +24,10 @@ pub enum ResponseError {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ResponseBody {
     pub devices: Vec<Device>,
+
+    /// Time when each of the devices was last connected, devices which have never connected are missing
+    #[serde(default)]
+    pub last_seen: std::collections::HashMap<DeviceID, DateTime<Utc>>,
 }
 
 #[cfg(feature = "actix")]
@@ -0,0 +1,303 @@
+use crate::{
+    Device, DeviceID, DeviceTrait, DeviceType, Role, Room, RoomID, Structure, StructureID,
+    UserStructure,
+};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+/// Declarative description of the structures, rooms, devices and members of the home, usually kept in `home.toml`.
+/// Items are matched with the database by their IDs, so renaming or moving them updates the existing ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Home {
+    #[serde(default)]
+    pub structures: Vec<HomeStructure>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HomeStructure {
+    pub id: StructureID,
+    pub name: String,
+
+    #[serde(default)]
+    pub members: Vec<HomeMember>,
+
+    #[serde(default)]
+    pub rooms: Vec<HomeRoom>,
+}
+
+/// User with a role in the structure, the user must have registered already
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HomeMember {
+    pub email: String,
+    pub role: Role,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HomeRoom {
+    pub id: RoomID,
+    pub name: String,
+
+    #[serde(default)]
+    pub devices: Vec<HomeDevice>,
+}
+
+/// Device without the password, new devices get one with `houseflow admin device rotate-password`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HomeDevice {
+    pub id: DeviceID,
+    pub name: String,
+    pub device_type: DeviceType,
+    pub traits: Vec<DeviceTrait>,
+    pub will_push_state: bool,
+    pub model: String,
+    pub hw_version: Version,
+    pub sw_version: Version,
+
+    #[serde(default)]
+    pub attributes: serde_json::Map<String, serde_json::Value>,
+}
+
+impl HomeStructure {
+    pub fn to_structure(&self) -> Structure {
+        Structure {
+            id: self.id.clone(),
+            name: self.name.clone(),
+        }
+    }
+}
+
+impl HomeRoom {
+    pub fn to_room(&self, structure_id: &StructureID) -> Room {
+        Room {
+            id: self.id.clone(),
+            structure_id: structure_id.clone(),
+            name: self.name.clone(),
+        }
+    }
+}
+
+impl HomeDevice {
+    /// Password hash is left empty, so the device can't connect until it gets a password
+    pub fn to_device(&self, room_id: &RoomID) -> Device {
+        Device {
+            id: self.id.clone(),
+            room_id: room_id.clone(),
+            password_hash: String::new(),
+            device_type: self.device_type.clone(),
+            traits: self.traits.clone(),
+            name: self.name.clone(),
+            will_push_state: self.will_push_state,
+            model: self.model.clone(),
+            hw_version: self.hw_version.clone(),
+            sw_version: self.sw_version.clone(),
+            attributes: self.attributes.clone(),
+        }
+    }
+}
+
+/// Item of the database managed by [Home], password hashes of the devices are always left out
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HomeItem {
+    Structure(Structure),
+    Room(Room),
+    Device(Box<Device>),
+    Member {
+        email: String,
+        user_structure: UserStructure,
+    },
+}
+
+impl HomeItem {
+    /// Fields of the item, used to show what an update changes
+    fn fields(&self) -> serde_json::Map<String, serde_json::Value> {
+        let value = match self {
+            Self::Structure(structure) => serde_json::to_value(structure),
+            Self::Room(room) => serde_json::to_value(room),
+            Self::Device(device) => serde_json::to_value(device),
+            Self::Member { user_structure, .. } => serde_json::to_value(user_structure),
+        };
+        match value {
+            Ok(serde_json::Value::Object(fields)) => fields,
+            _ => unreachable!("items are serialized as objects"),
+        }
+    }
+}
+
+impl std::fmt::Display for HomeItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Structure(structure) => {
+                write!(f, "structure {:?} ({})", structure.name, structure.id)
+            }
+            Self::Room(room) => write!(f, "room {:?} ({})", room.name, room.id),
+            Self::Device(device) => write!(f, "device {:?} ({})", device.name, device.id),
+            Self::Member {
+                email,
+                user_structure,
+            } => write!(
+                f,
+                "member {} of structure {}",
+                email, user_structure.structure_id
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum HomeChange {
+    Create { item: HomeItem },
+    Update { before: HomeItem, after: HomeItem },
+    Delete { item: HomeItem },
+}
+
+/// Shown like `+ room "Kitchen" (...)`, updates are followed by the changed fields
+impl std::fmt::Display for HomeChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Create { item } => write!(f, "+ {}", item),
+            Self::Delete { item } => write!(f, "- {}", item),
+            Self::Update { before, after } => {
+                write!(f, "~ {}", after)?;
+                let before = before.fields();
+                for (field, value) in after.fields() {
+                    match before.get(&field) {
+                        Some(previous) if *previous == value => (),
+                        Some(previous) => write!(f, "\n    {}: {} -> {}", field, previous, value)?,
+                        None => write!(f, "\n    {}: {}", field, value)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Changes which make the database match the [Home], in the order they must be applied
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HomePlan {
+    pub changes: Vec<HomeChange>,
+}
+
+impl HomePlan {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Numbers of the items to create, update and delete
+    pub fn summary(&self) -> (usize, usize, usize) {
+        self.changes
+            .iter()
+            .fold((0, 0, 0), |(create, update, delete), change| match change {
+                HomeChange::Create { .. } => (create + 1, update, delete),
+                HomeChange::Update { .. } => (create, update + 1, delete),
+                HomeChange::Delete { .. } => (create, update, delete + 1),
+            })
+    }
+}
+
+impl std::fmt::Display for HomePlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        let (create, update, delete) = self.summary();
+        write!(
+            f,
+            "Plan: {} to create, {} to update, {} to delete",
+            create, update, delete
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOME: &str = r#"
+        [[structures]]
+        id = "aaf04931e9f14f0da3a59d3e51375e6e"
+        name = "Home"
+
+        [[structures.members]]
+        email = "john@example.com"
+        role = "manager"
+
+        [[structures.rooms]]
+        id = "baf04931e9f14f0da3a59d3e51375e6e"
+        name = "Kitchen"
+
+        [[structures.rooms.devices]]
+        id = "caf04931e9f14f0da3a59d3e51375e6e"
+        name = "Lamp"
+        device_type = "Light"
+        traits = ["OnOff", "Brightness"]
+        will_push_state = true
+        model = "lamp"
+        hw_version = "1.0.0"
+        sw_version = "1.2.0"
+
+        [structures.rooms.devices.attributes]
+        colorModel = "rgb"
+    "#;
+
+    #[test]
+    fn parse() {
+        let home: Home = toml::from_str(HOME).unwrap();
+        let structure = &home.structures[0];
+        assert_eq!(structure.name, "Home");
+        assert_eq!(structure.members[0].role, Role::Manager);
+        let room = &structure.rooms[0];
+        assert_eq!(room.name, "Kitchen");
+        let device = room.devices[0].to_device(&room.id);
+        assert_eq!(device.room_id, room.id);
+        assert_eq!(
+            device.traits,
+            vec![DeviceTrait::OnOff, DeviceTrait::Brightness]
+        );
+        assert_eq!(device.attributes["colorModel"], "rgb");
+        assert_eq!(device.password_hash, "");
+    }
+
+    #[test]
+    fn display() {
+        let structure = Structure {
+            id: StructureID::default(),
+            name: "Home".to_string(),
+        };
+        let room = Room {
+            id: RoomID::default(),
+            structure_id: structure.id.clone(),
+            name: "Kitchen".to_string(),
+        };
+        let plan = HomePlan {
+            changes: vec![
+                HomeChange::Create {
+                    item: HomeItem::Structure(structure),
+                },
+                HomeChange::Update {
+                    before: HomeItem::Room(room.clone()),
+                    after: HomeItem::Room(Room {
+                        name: "Dining room".to_string(),
+                        ..room.clone()
+                    }),
+                },
+                HomeChange::Delete {
+                    item: HomeItem::Room(room),
+                },
+            ],
+        };
+        let id = StructureID::default();
+        assert_eq!(
+            plan.to_string(),
+            format!(
+                "+ structure \"Home\" ({id})\n\
+                 ~ room \"Dining room\" ({id})\n    name: \"Kitchen\" -> \"Dining room\"\n\
+                 - room \"Kitchen\" ({id})\n\
+                 Plan: 1 to create, 1 to update, 1 to delete",
+                id = id
+            )
+        );
+    }
+}
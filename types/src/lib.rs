@@ -1,7 +1,11 @@
+mod backup;
 mod common;
 mod device;
+mod home;
 mod user;
 
+pub mod traits;
+
 #[cfg(feature = "admin")]
 pub mod admin;
 
@@ -17,8 +21,10 @@ pub mod lighthouse;
 #[cfg(feature = "token")]
 pub mod token;
 
+pub use backup::*;
 pub use common::*;
 pub use device::*;
+pub use home::*;
 pub use user::*;
 
 #[cfg(feature = "actix")]
@@ -17,6 +17,9 @@ pub enum DeviceCommunicationError {
 
     #[error("invalid JSON input")]
     InvalidJSON(String),
+
+    #[error("device rejected the frame: {0}")]
+    FrameRejected(proto::error::Error),
 }
 
 impl From<serde_json::Error> for DeviceCommunicationError {
@@ -44,8 +47,8 @@ pub enum ConnectResponseError {
     #[error("invalid credentials")]
     InvalidCredentials,
 
-    #[error("there is an existing connection with device with the specified ID")]
-    AlreadyConnected,
+    #[error("unsupported protocol version: {0}")]
+    UnsupportedProtocolVersion(String),
 }
 
 #[cfg(feature = "actix")]
@@ -57,8 +60,8 @@ impl actix_web::ResponseError for ConnectResponseError {
             Self::InvalidAuthorizationHeader(_) => StatusCode::BAD_REQUEST,
             Self::HandshakeError(_) => StatusCode::BAD_REQUEST,
             Self::InvalidCredentials => StatusCode::UNAUTHORIZED,
+            Self::UnsupportedProtocolVersion(_) => StatusCode::BAD_REQUEST,
             Self::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            Self::AlreadyConnected => StatusCode::NOT_ACCEPTABLE,
         }
     }
 
@@ -0,0 +1,31 @@
+use crate::lighthouse::proto::FrameID;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(
+    tag = "error",
+    content = "error_description",
+    rename_all = "snake_case"
+)]
+pub enum Error {
+    /// Frame could not be decoded
+    #[error("invalid frame: {0}")]
+    InvalidFrame(String),
+
+    /// Frame is valid, but the receiver does not expect it
+    #[error("unexpected {0} frame")]
+    UnexpectedFrame(String),
+
+    /// Response has been received, but there is no pending request with the same ID
+    #[error("response has been received without corresponding request")]
+    ResponseWithoutRequest,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Frame {
+    /// ID of the frame which has caused the error, if it could be determined
+    pub id: Option<FrameID>,
+
+    #[serde(flatten)]
+    pub error: Error,
+}
@@ -1,10 +1,39 @@
 use crate::lighthouse::proto::FrameID;
-use crate::DeviceCommand;
+use crate::traits::CommandParams;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Frame {
     pub id: FrameID,
-    pub command: DeviceCommand,
-    pub params: serde_json::Map<String, serde_json::Value>,
+
+    #[serde(flatten)]
+    pub params: CommandParams,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lighthouse::proto::Frame as LighthouseFrame;
+    use crate::traits::on_off::OnOffParams;
+
+    #[test]
+    fn frame() {
+        let frame = LighthouseFrame::Execute(Frame {
+            id: 7,
+            params: CommandParams::OnOff(OnOffParams { on: true }),
+        });
+        let json = serde_json::to_value(&frame).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "Execute",
+                "id": 7,
+                "command": "OnOff",
+                "params": { "on": true },
+            })
+        );
+
+        let parsed: LighthouseFrame = serde_json::from_str(&json.to_string()).unwrap();
+        assert_eq!(parsed, frame);
+    }
 }
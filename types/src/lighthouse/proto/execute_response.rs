@@ -1,12 +1,13 @@
 use crate::lighthouse::proto::FrameID;
+use crate::traits::DeviceState;
 use crate::DeviceStatus;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Frame {
     pub id: FrameID,
 
     #[serde(flatten)]
     pub status: DeviceStatus,
-    pub state: serde_json::Map<String, serde_json::Value>,
+    pub state: DeviceState,
 }
@@ -1,3 +1,4 @@
+pub mod error;
 pub mod execute;
 pub mod execute_response;
 
@@ -6,9 +7,11 @@ pub mod state;
 
 pub type FrameID = u16;
 
+use semver::Version;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[non_exhaustive]
 #[serde(tag = "type")]
 #[repr(u8)]
@@ -24,6 +27,9 @@ pub enum Frame {
 
     /// Packet which will be send as a response to Execute request from server
     ExecuteResponse(execute_response::Frame),
+
+    /// Packet which will be send by either side if a received frame can't be handled, supported since [`ERROR_FRAME_VERSION`]
+    Error(error::Frame),
 }
 
 impl Frame {
@@ -33,6 +39,215 @@ impl Frame {
             Self::Query(_) => "QUERY",
             Self::Execute(_) => "EXECUTE",
             Self::ExecuteResponse(_) => "EXECUTE_RESPONSE",
+            Self::Error(_) => "ERROR",
+        }
+    }
+}
+
+/// Version of the protocol, major version must be bumped on every breaking change of the frames
+pub const VERSION: Version = Version::new(1, 1, 0);
+
+/// Version assumed for devices which do not advertise it
+pub const LEGACY_VERSION: Version = Version::new(1, 0, 0);
+
+/// First version which supports [`Frame::Error`], peers speaking older versions close the connection instead
+pub const ERROR_FRAME_VERSION: Version = Version::new(1, 1, 0);
+
+/// Header used at handshake, device sends its own version and server responds with the negotiated one
+pub const VERSION_HEADER: &str = "x-lighthouse-version";
+
+/// Returns version which will be used to talk with a device that speaks the given one,
+/// that is the lower version if major versions match
+pub fn negotiate_version(version: &Version) -> Option<Version> {
+    if version.major == VERSION.major {
+        Some(std::cmp::min(version, &VERSION).clone())
+    } else {
+        None
+    }
+}
+
+/// Encoding of the frames, negotiated using the `Sec-WebSocket-Protocol` header at handshake
+///
+/// JSON frames are sent as text messages, binary ones as binary messages.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    #[default]
+    Json,
+    Cbor,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EncodingError {
+    #[error("json: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("cbor: {0}")]
+    Cbor(#[from] serde_cbor::Error),
+}
+
+impl Encoding {
+    /// Name of the websocket subprotocol
+    pub fn protocol(&self) -> &'static str {
+        match self {
+            Self::Json => "houseflow-json",
+            Self::Cbor => "houseflow-cbor",
         }
     }
+
+    /// Picks the first known encoding from comma-separated subprotocols, JSON is used if there is none
+    pub fn negotiate(protocols: &str) -> Self {
+        protocols
+            .split(',')
+            .find_map(|protocol| protocol.trim().parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether frames are sent as binary messages
+    pub fn is_binary(&self) -> bool {
+        !matches!(self, Self::Json)
+    }
+
+    pub fn encode(&self, frame: &Frame) -> Result<Vec<u8>, EncodingError> {
+        Ok(match self {
+            Self::Json => serde_json::to_vec(frame)?,
+            Self::Cbor => serde_cbor::to_vec(frame)?,
+        })
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> Result<Frame, EncodingError> {
+        Ok(match self {
+            Self::Json => serde_json::from_slice(bytes)?,
+            Self::Cbor => serde_cbor::from_slice(bytes)?,
+        })
+    }
+
+    /// Extracts only the ID of the frame, so errors can reference frames which failed to decode
+    pub fn decode_id(&self, bytes: &[u8]) -> Option<FrameID> {
+        #[derive(Deserialize)]
+        struct Header {
+            id: Option<FrameID>,
+        }
+
+        let header: Header = match self {
+            Self::Json => serde_json::from_slice(bytes).ok()?,
+            Self::Cbor => serde_cbor::from_slice(bytes).ok()?,
+        };
+        header.id
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(protocol: &str) -> Result<Self, Self::Err> {
+        [Self::Json, Self::Cbor]
+            .iter()
+            .find(|encoding| encoding.protocol() == protocol)
+            .copied()
+            .ok_or_else(|| format!("unknown protocol: {}", protocol))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::{brightness::BrightnessAbsoluteParams, on_off, CommandParams, DeviceState};
+    use crate::{DeviceError, DeviceStatus};
+
+    fn frames() -> Vec<Frame> {
+        let state = DeviceState {
+            on_off: Some(on_off::State { on: true }),
+            ..Default::default()
+        };
+        vec![
+            Frame::State(state::Frame {
+                state: state.clone(),
+            }),
+            Frame::Query(query::Frame {}),
+            Frame::Execute(execute::Frame {
+                id: 1,
+                params: CommandParams::BrightnessAbsolute(BrightnessAbsoluteParams {
+                    brightness: 50,
+                }),
+            }),
+            Frame::ExecuteResponse(execute_response::Frame {
+                id: 2,
+                status: DeviceStatus::Success,
+                state: state.clone(),
+            }),
+            Frame::ExecuteResponse(execute_response::Frame {
+                id: u16::MAX,
+                status: DeviceStatus::Error(DeviceError::InvalidParameters),
+                state,
+            }),
+            Frame::Error(error::Frame {
+                id: Some(3),
+                error: error::Error::InvalidFrame(String::from("missing field `params`")),
+            }),
+            Frame::Error(error::Frame {
+                id: None,
+                error: error::Error::ResponseWithoutRequest,
+            }),
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        for encoding in [Encoding::Json, Encoding::Cbor].iter() {
+            for frame in frames() {
+                let bytes = encoding.encode(&frame).unwrap();
+                assert_eq!(encoding.decode(&bytes).unwrap(), frame, "{:?}", encoding);
+            }
+        }
+    }
+
+    #[test]
+    fn decode_id() {
+        for encoding in [Encoding::Json, Encoding::Cbor].iter() {
+            let frame = Frame::Execute(execute::Frame {
+                id: 5,
+                params: CommandParams::BrightnessAbsolute(BrightnessAbsoluteParams {
+                    brightness: 50,
+                }),
+            });
+            let bytes = encoding.encode(&frame).unwrap();
+            assert_eq!(encoding.decode_id(&bytes), Some(5));
+            let bytes = encoding.encode(&Frame::Query(query::Frame {})).unwrap();
+            assert_eq!(encoding.decode_id(&bytes), None);
+        }
+        let json = br#"{"type": "ExecuteResponse", "id": 9, "unknown": true}"#;
+        assert_eq!(Encoding::Json.decode(json).ok(), None);
+        assert_eq!(Encoding::Json.decode_id(json), Some(9));
+    }
+
+    #[test]
+    fn cbor_is_compact() {
+        for frame in frames() {
+            let json = Encoding::Json.encode(&frame).unwrap();
+            let cbor = Encoding::Cbor.encode(&frame).unwrap();
+            assert!(cbor.len() <= json.len(), "{:?}", frame);
+        }
+    }
+
+    #[test]
+    fn version() {
+        assert_eq!(negotiate_version(&VERSION), Some(VERSION));
+        assert_eq!(negotiate_version(&LEGACY_VERSION), Some(LEGACY_VERSION));
+        let newer_minor = Version::new(VERSION.major, VERSION.minor + 1, 0);
+        assert_eq!(negotiate_version(&newer_minor), Some(VERSION));
+        let newer_major = Version::new(VERSION.major + 1, 0, 0);
+        assert_eq!(negotiate_version(&newer_major), None);
+    }
+
+    #[test]
+    fn negotiate() {
+        assert_eq!(
+            Encoding::negotiate("houseflow-cbor, houseflow-json"),
+            Encoding::Cbor
+        );
+        assert_eq!(Encoding::negotiate("chat,houseflow-json"), Encoding::Json);
+        assert_eq!(Encoding::negotiate("chat"), Encoding::Json);
+        assert_eq!(Encoding::negotiate(""), Encoding::Json);
+    }
 }
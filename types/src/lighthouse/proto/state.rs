@@ -1,6 +1,7 @@
+use crate::traits::DeviceState;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Frame {
-    pub state: serde_json::Map<String, serde_json::Value>,
+    pub state: DeviceState,
 }
@@ -1,4 +1,4 @@
-use crate::{Credential, UserID};
+use crate::{Credential, DeviceID, DeviceTokenID, UserID};
 use chrono::{DateTime, Utc};
 use serde::{de, ser, Deserialize, Serialize};
 
@@ -107,6 +107,7 @@ impl<P: ser::Serialize + de::DeserializeOwned> std::ops::Deref for Token<P> {
 pub type AccessToken = Token<AccessTokenPayload>;
 pub type RefreshToken = Token<RefreshTokenPayload>;
 pub type AuthorizationCode = Token<AuthorizationCodePayload>;
+pub type DeviceToken = Token<DeviceTokenPayload>;
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AccessTokenPayload {
@@ -133,6 +134,16 @@ pub struct RefreshTokenPayload {
     pub exp: Option<DateTime<Utc>>,
 }
 
+/// Token used by devices to authenticate to the lighthouse, only the last issued one is valid
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceTokenPayload {
+    pub tid: DeviceTokenID,
+    pub sub: DeviceID,
+
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    pub exp: Option<DateTime<Utc>>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct BasePayload {
     #[serde(with = "chrono::serde::ts_seconds_option")]
@@ -434,4 +445,35 @@ mod tests {
             assert_eq!(err, DecodeError::InvalidSignature);
         }
     }
+
+    mod dt {
+        use super::*;
+
+        #[test]
+        fn valid() {
+            let key = get_key();
+            let payload = DeviceTokenPayload {
+                tid: random(),
+                sub: random(),
+                exp: None,
+            };
+            let token = DeviceToken::new(&key, payload);
+            let encoded = token.encode();
+            let decoded = DeviceToken::decode(&key, &encoded).unwrap();
+            assert_eq!(token, decoded);
+        }
+
+        #[test]
+        fn invalid_signature() {
+            let payload = DeviceTokenPayload {
+                tid: random(),
+                sub: random(),
+                exp: None,
+            };
+            let token = DeviceToken::new(&get_key(), payload);
+            let encoded = token.encode();
+            let err = DeviceToken::decode(&get_key(), &encoded).unwrap_err();
+            assert_eq!(err, DecodeError::InvalidSignature);
+        }
+    }
 }
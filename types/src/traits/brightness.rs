@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+/// Attributes of the device with Brightness trait.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attributes {
+    /// Indicates if the device supports using one-way (true) or two-way (false) communication.
+    #[serde(default)]
+    pub command_only_brightness: bool,
+}
+
+/// State of the device with Brightness trait.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct State {
+    /// Current brightness level of the device, in range from 0 to 100.
+    pub brightness: u8,
+}
+
+/// Parameters of the BrightnessAbsolute command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrightnessAbsoluteParams {
+    /// New absolute brightness percentage.
+    pub brightness: u8,
+}
+
+/// Parameters of the BrightnessRelative command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BrightnessRelativeParams {
+    /// The exact percentage of brightness to change.
+    Percent {
+        #[serde(rename = "brightnessRelativePercent")]
+        brightness_relative_percent: i8,
+    },
+
+    /// Indicates the amount of ambiguous brightness change, from small amount to large amount.
+    Weight {
+        #[serde(rename = "brightnessRelativeWeight")]
+        brightness_relative_weight: i8,
+    },
+}
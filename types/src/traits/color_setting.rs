@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+
+/// Color model supported by the device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorModel {
+    /// Full spectrum color model, in RGB
+    Rgb,
+
+    /// Full spectrum color model, in HSV
+    Hsv,
+}
+
+/// Supported color temperature range, in Kelvin.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorTemperatureRange {
+    /// Minimum supported color temperature in Kelvin.
+    pub temperature_min_k: u32,
+
+    /// Maximum supported color temperature in Kelvin.
+    pub temperature_max_k: u32,
+}
+
+/// Attributes of the device with ColorSetting trait.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attributes {
+    /// Full spectrum color model supported by the device, None if only color temperature is supported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_model: Option<ColorModel>,
+
+    /// Supported color temperature range, None if color temperature is not supported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_temperature_range: Option<ColorTemperatureRange>,
+
+    /// Indicates if the device supports using one-way (true) or two-way (false) communication.
+    #[serde(default)]
+    pub command_only_color_setting: bool,
+}
+
+/// Color in the HSV color model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpectrumHsv {
+    /// Hue in degrees, in range from 0 to 360.
+    pub hue: f64,
+
+    /// Saturation, in range from 0 to 1.
+    pub saturation: f64,
+
+    /// Value, in range from 0 to 1.
+    pub value: f64,
+}
+
+/// Current color of the device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Color {
+    /// Spectrum value in RGB, as an integer number, e.g 0xFF0000 for red.
+    SpectrumRgb(u32),
+
+    /// Color temperature in Kelvin.
+    TemperatureK(u32),
+
+    /// Spectrum value in HSV.
+    SpectrumHsv(SpectrumHsv),
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Self::SpectrumRgb(0xFFFFFF)
+    }
+}
+
+/// State of the device with ColorSetting trait.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct State {
+    pub color: Color,
+}
+
+/// Color value sent with the ColorAbsolute command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ColorAbsoluteValue {
+    /// Spectrum value in RGB, as an integer number.
+    #[serde(rename = "spectrumRGB")]
+    SpectrumRgb(u32),
+
+    /// Color temperature in Kelvin.
+    #[serde(rename = "temperature")]
+    Temperature(u32),
+
+    /// Spectrum value in HSV.
+    #[serde(rename = "spectrumHSV")]
+    SpectrumHsv(SpectrumHsv),
+}
+
+impl From<ColorAbsoluteValue> for Color {
+    fn from(val: ColorAbsoluteValue) -> Self {
+        match val {
+            ColorAbsoluteValue::SpectrumRgb(rgb) => Self::SpectrumRgb(rgb),
+            ColorAbsoluteValue::Temperature(temperature) => Self::TemperatureK(temperature),
+            ColorAbsoluteValue::SpectrumHsv(hsv) => Self::SpectrumHsv(hsv),
+        }
+    }
+}
+
+/// Color sent with the ColorAbsolute command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorAbsoluteColor {
+    /// Color name, e.g "magenta", as provided by the user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(flatten)]
+    pub value: ColorAbsoluteValue,
+}
+
+/// Parameters of the ColorAbsolute command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorAbsoluteParams {
+    /// Color to set.
+    pub color: ColorAbsoluteColor,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_absolute_params() {
+        let params: ColorAbsoluteParams =
+            serde_json::from_str(r#"{ "color": { "name": "magenta", "spectrumRGB": 16711935 } }"#)
+                .unwrap();
+        assert_eq!(params.color.name.as_deref(), Some("magenta"));
+        assert_eq!(
+            Color::from(params.color.value),
+            Color::SpectrumRgb(0xFF00FF)
+        );
+
+        let params: ColorAbsoluteParams =
+            serde_json::from_str(r#"{ "color": { "temperature": 2700 } }"#).unwrap();
+        assert_eq!(params.color.value, ColorAbsoluteValue::Temperature(2700));
+    }
+
+    #[test]
+    fn state() {
+        let state = State {
+            color: Color::TemperatureK(2700),
+        };
+        assert_eq!(
+            serde_json::to_value(&state).unwrap(),
+            serde_json::json!({ "color": { "temperatureK": 2700 } })
+        );
+    }
+}
@@ -0,0 +1,198 @@
+pub mod brightness;
+pub mod color_setting;
+pub mod on_off;
+pub mod open_close;
+pub mod temperature_setting;
+
+use crate::{DeviceCommand, DeviceError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Parameters of a command, tagged with the command they belong to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", content = "params")]
+#[non_exhaustive]
+pub enum CommandParams {
+    OnOff(on_off::OnOffParams),
+    OpenClose(open_close::OpenCloseParams),
+    BrightnessAbsolute(brightness::BrightnessAbsoluteParams),
+    BrightnessRelative(brightness::BrightnessRelativeParams),
+    ColorAbsolute(color_setting::ColorAbsoluteParams),
+    ThermostatTemperatureSetpoint(temperature_setting::ThermostatTemperatureSetpointParams),
+    ThermostatTemperatureSetRange(temperature_setting::ThermostatTemperatureSetRangeParams),
+    ThermostatSetMode(temperature_setting::ThermostatSetModeParams),
+}
+
+impl CommandParams {
+    /// Parses and validates untyped parameters of the command.
+    pub fn new(
+        command: DeviceCommand,
+        params: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<Self, DeviceError> {
+        let value = serde_json::json!({
+            "command": command,
+            "params": params,
+        });
+        let params: Self =
+            serde_json::from_value(value).map_err(|_| DeviceError::InvalidParameters)?;
+        params.validate()?;
+
+        Ok(params)
+    }
+
+    pub fn command(&self) -> DeviceCommand {
+        match self {
+            Self::OnOff(_) => DeviceCommand::OnOff,
+            Self::OpenClose(_) => DeviceCommand::OpenClose,
+            Self::BrightnessAbsolute(_) => DeviceCommand::BrightnessAbsolute,
+            Self::BrightnessRelative(_) => DeviceCommand::BrightnessRelative,
+            Self::ColorAbsolute(_) => DeviceCommand::ColorAbsolute,
+            Self::ThermostatTemperatureSetpoint(_) => DeviceCommand::ThermostatTemperatureSetpoint,
+            Self::ThermostatTemperatureSetRange(_) => DeviceCommand::ThermostatTemperatureSetRange,
+            Self::ThermostatSetMode(_) => DeviceCommand::ThermostatSetMode,
+        }
+    }
+
+    /// Checks if values of the parameters are in the ranges defined by the trait schemas.
+    pub fn validate(&self) -> Result<(), DeviceError> {
+        use brightness::BrightnessRelativeParams;
+        use color_setting::ColorAbsoluteValue;
+
+        let is_valid = match self {
+            Self::OnOff(_) | Self::ThermostatSetMode(_) => true,
+            Self::OpenClose(params) => params.open_percent <= 100,
+            Self::BrightnessAbsolute(params) => params.brightness <= 100,
+            Self::BrightnessRelative(BrightnessRelativeParams::Percent {
+                brightness_relative_percent,
+            }) => (-100..=100).contains(brightness_relative_percent),
+            Self::BrightnessRelative(BrightnessRelativeParams::Weight { .. }) => true,
+            Self::ColorAbsolute(params) => match &params.color.value {
+                ColorAbsoluteValue::SpectrumRgb(rgb) => *rgb <= 0xFFFFFF,
+                ColorAbsoluteValue::Temperature(_) => true,
+                ColorAbsoluteValue::SpectrumHsv(hsv) => {
+                    (0.0..=360.0).contains(&hsv.hue)
+                        && (0.0..=1.0).contains(&hsv.saturation)
+                        && (0.0..=1.0).contains(&hsv.value)
+                }
+            },
+            Self::ThermostatTemperatureSetpoint(params) => {
+                params.thermostat_temperature_setpoint.is_finite()
+            }
+            Self::ThermostatTemperatureSetRange(params) => {
+                params.thermostat_temperature_setpoint_low
+                    <= params.thermostat_temperature_setpoint_high
+            }
+        };
+
+        if is_valid {
+            Ok(())
+        } else {
+            Err(DeviceError::InvalidParameters)
+        }
+    }
+}
+
+/// State of the device, made of states of each trait it supports.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceState {
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub on_off: Option<on_off::State>,
+
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub open_close: Option<open_close::State>,
+
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub brightness: Option<brightness::State>,
+
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub color_setting: Option<color_setting::State>,
+
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub temperature_setting: Option<temperature_setting::State>,
+}
+
+/// State of the device along with the time it was reported at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceStateSnapshot {
+    pub state: DeviceState,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn command_params() {
+        let params = CommandParams::new(
+            DeviceCommand::OnOff,
+            object(serde_json::json!({ "on": true })),
+        )
+        .unwrap();
+        assert_eq!(
+            params,
+            CommandParams::OnOff(on_off::OnOffParams { on: true })
+        );
+        assert_eq!(params.command(), DeviceCommand::OnOff);
+
+        let params = CommandParams::new(
+            DeviceCommand::ThermostatSetMode,
+            object(serde_json::json!({ "thermostatMode": "heat" })),
+        )
+        .unwrap();
+        assert_eq!(params.command(), DeviceCommand::ThermostatSetMode);
+    }
+
+    #[test]
+    fn command_params_invalid() {
+        let invalid = [
+            (DeviceCommand::OnOff, serde_json::json!({ "onn": true })),
+            (
+                DeviceCommand::OnOff,
+                serde_json::json!({ "brightness": 50 }),
+            ),
+            (
+                DeviceCommand::BrightnessAbsolute,
+                serde_json::json!({ "brightness": 120 }),
+            ),
+            (
+                DeviceCommand::OpenClose,
+                serde_json::json!({ "openPercent": -5 }),
+            ),
+            (
+                DeviceCommand::ThermostatTemperatureSetRange,
+                serde_json::json!({
+                    "thermostatTemperatureSetpointLow": 25.0,
+                    "thermostatTemperatureSetpointHigh": 20.0,
+                }),
+            ),
+        ];
+        for (command, params) in invalid.iter() {
+            assert_eq!(
+                CommandParams::new(command.clone(), object(params.clone())),
+                Err(DeviceError::InvalidParameters),
+                "command: {}, params: {}",
+                command,
+                params
+            );
+        }
+    }
+
+    #[test]
+    fn device_state() {
+        let state = DeviceState {
+            on_off: Some(on_off::State { on: true }),
+            brightness: Some(brightness::State { brightness: 40 }),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json, serde_json::json!({ "on": true, "brightness": 40 }));
+
+        let parsed: DeviceState = serde_json::from_str(&json.to_string()).unwrap();
+        assert_eq!(parsed, state);
+    }
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+/// Attributes of the device with OnOff trait.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attributes {
+    /// Indicates if the device supports using one-way (true) or two-way (false) communication.
+    #[serde(default)]
+    pub command_only_on_off: bool,
+
+    /// Indicates if the device supports querying the state, but not changing it.
+    #[serde(default)]
+    pub query_only_on_off: bool,
+}
+
+/// State of the device with OnOff trait.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct State {
+    /// Whether the device is currently on.
+    pub on: bool,
+}
+
+/// Parameters of the OnOff command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OnOffParams {
+    /// Whether to turn the device on or off.
+    pub on: bool,
+}
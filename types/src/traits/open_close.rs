@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+/// Attributes of the device with OpenClose trait.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attributes {
+    /// Indicates if the device can only be fully opened or closed, without intermediate states.
+    #[serde(default)]
+    pub discrete_only_open_close: bool,
+
+    /// Indicates if the device supports using one-way (true) or two-way (false) communication.
+    #[serde(default)]
+    pub command_only_open_close: bool,
+
+    /// Indicates if the device supports querying the state, but not changing it.
+    #[serde(default)]
+    pub query_only_open_close: bool,
+}
+
+/// State of the device with OpenClose trait.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct State {
+    /// Current open percentage, 0 means closed and 100 means fully open.
+    pub open_percent: u8,
+}
+
+/// Parameters of the OpenClose command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenCloseParams {
+    /// Target open percentage, 0 means closed and 100 means fully open.
+    pub open_percent: u8,
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+
+/// Mode of the thermostat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum::Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum ThermostatMode {
+    Off,
+    Heat,
+    Cool,
+    On,
+    Heatcool,
+    Auto,
+    #[serde(rename = "fan-only")]
+    #[strum(serialize = "fan-only")]
+    FanOnly,
+    Purifier,
+    Eco,
+    Dry,
+}
+
+/// Unit of the temperature displayed on the device.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TemperatureUnit {
+    #[default]
+    #[serde(rename = "C")]
+    Celsius,
+
+    #[serde(rename = "F")]
+    Fahrenheit,
+}
+
+/// Supported temperature range of the thermostat, in degrees Celsius.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThermostatTemperatureRange {
+    pub min_threshold_celsius: f64,
+    pub max_threshold_celsius: f64,
+}
+
+/// Attributes of the device with TemperatureSetting trait.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attributes {
+    /// Modes supported by the device.
+    pub available_thermostat_modes: Vec<ThermostatMode>,
+
+    /// Supported temperature range, None if the default range should be used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thermostat_temperature_range: Option<ThermostatTemperatureRange>,
+
+    /// Unit the device displays the temperature in.
+    #[serde(default)]
+    pub thermostat_temperature_unit: TemperatureUnit,
+
+    /// Minimum offset between heat-cool setpoints in Celsius, if heatcool mode is supported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buffer_range_celsius: Option<f64>,
+
+    /// Indicates if the device supports using one-way (true) or two-way (false) communication.
+    #[serde(default)]
+    pub command_only_temperature_setting: bool,
+
+    /// Indicates if the device supports querying the state, but not changing it.
+    #[serde(default)]
+    pub query_only_temperature_setting: bool,
+}
+
+/// State of the device with TemperatureSetting trait.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct State {
+    /// Currently active mode of the device.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_thermostat_mode: Option<ThermostatMode>,
+
+    /// Current mode of the device.
+    pub thermostat_mode: ThermostatMode,
+
+    /// Current observed temperature, in degrees Celsius.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thermostat_temperature_ambient: Option<f64>,
+
+    /// Current temperature setpoint, in degrees Celsius. Used outside of heatcool mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thermostat_temperature_setpoint: Option<f64>,
+
+    /// Current high point of the setpoint range, in degrees Celsius. Used in heatcool mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thermostat_temperature_setpoint_high: Option<f64>,
+
+    /// Current low point of the setpoint range, in degrees Celsius. Used in heatcool mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thermostat_temperature_setpoint_low: Option<f64>,
+
+    /// Current observed relative humidity, in percent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thermostat_humidity_ambient: Option<f64>,
+}
+
+/// Parameters of the ThermostatTemperatureSetpoint command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThermostatTemperatureSetpointParams {
+    /// Target temperature setpoint, in degrees Celsius.
+    pub thermostat_temperature_setpoint: f64,
+}
+
+/// Parameters of the ThermostatTemperatureSetRange command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThermostatTemperatureSetRangeParams {
+    /// High point of the target range, in degrees Celsius.
+    pub thermostat_temperature_setpoint_high: f64,
+
+    /// Low point of the target range, in degrees Celsius.
+    pub thermostat_temperature_setpoint_low: f64,
+}
+
+/// Parameters of the ThermostatSetMode command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThermostatSetModeParams {
+    /// Mode to switch to.
+    pub thermostat_mode: ThermostatMode,
+}